@@ -0,0 +1,458 @@
+// Derives for bencode-rs' `ToBencode` and `FromBencode`, re-exported from
+// there behind the `derive` feature. Structs with named fields map to dicts
+// keyed by field name. Field attributes:
+//
+//     #[bencode(rename = "creation date")]  use a different dict key
+//     #[bencode(alias = "name.utf-8")]      also read this key, after the
+//                                           ones before it; repeatable
+//     #[bencode(default)]                   Default::default() when missing
+//     #[bencode(default = "path::to::fn")]  fn() -> T when missing
+//     #[bencode(flatten)]                   collect keys no other field claims
+//
+// A flattened field is a map such as `HashMap<String, BValue>`. Decoding
+// fills it with the unrecognized entries and encoding merges them back in
+// key order, so vendor-specific keys survive a round trip.
+//
+// Aliases only affect decoding, which looks keys up through an `Aliases`
+// built once per type; encoding always writes the primary key.
+//
+// `Option<T>` fields are left out of the output when `None` and read as
+// `None` when absent. The input is parsed by hand from the token stream,
+// which keeps the macro free of dependencies.
+
+use proc_macro::{Delimiter, TokenStream, TokenTree};
+
+#[proc_macro_derive(ToBencode, attributes(bencode))]
+pub fn derive_to_bencode(input: TokenStream) -> TokenStream {
+    expand(input, to_bencode)
+}
+
+#[proc_macro_derive(FromBencode, attributes(bencode))]
+pub fn derive_from_bencode(input: TokenStream) -> TokenStream {
+    expand(input, from_bencode)
+}
+
+struct Struct {
+    name: String,
+    fields: Vec<Field>,
+}
+
+struct Field {
+    ident: String,
+    key: String,
+    aliases: Vec<String>,
+    optional: bool,
+    default: Option<Fallback>,
+    flatten: bool,
+}
+
+impl Field {
+    /// The primary key, then the aliases in order.
+    fn keys(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.key.as_str()).chain(self.aliases.iter().map(String::as_str))
+    }
+}
+
+enum Fallback {
+    Trait,
+    Fn(String),
+}
+
+fn expand(input: TokenStream, generate: fn(&Struct) -> String) -> TokenStream {
+    let code = match parse_struct(input) {
+        Ok(s) => generate(&s),
+        Err(msg) => format!("::core::compile_error!({:?});", msg),
+    };
+    code.parse().expect("generated code is valid Rust")
+}
+
+fn to_bencode(s: &Struct) -> String {
+    if s.fields.iter().any(|f| f.flatten) {
+        return to_bencode_merged(s);
+    }
+
+    let mut fields: Vec<&Field> = s.fields.iter().collect();
+    fields.sort_by(|a, b| a.key.as_bytes().cmp(b.key.as_bytes()));
+
+    let mut body = String::new();
+    for f in fields {
+        if f.optional {
+            body += &format!(
+                "if let ::core::option::Option::Some(v) = &self.{ident} {{ \
+                     enc.key({key:?}.as_bytes())?; \
+                     ::bencode_rs::ToBencode::write_bencode(v, enc)?; \
+                 }}",
+                ident = f.ident,
+                key = f.key,
+            );
+        } else {
+            body += &format!(
+                "enc.key({key:?}.as_bytes())?; \
+                 ::bencode_rs::ToBencode::write_bencode(&self.{ident}, enc)?;",
+                ident = f.ident,
+                key = f.key,
+            );
+        }
+    }
+
+    format!(
+        "impl ::bencode_rs::ToBencode for {name} {{ \
+             fn write_bencode<W: ::std::io::Write>(&self, enc: &mut ::bencode_rs::Encoder<W>) \
+                 -> ::std::io::Result<()> {{ \
+                 enc.begin_dict()?; \
+                 {body} \
+                 enc.end()?; \
+                 ::core::result::Result::Ok(()) \
+             }} \
+         }}",
+        name = s.name,
+    )
+}
+
+// With a flattened map the key order is only known at runtime, so every
+// entry becomes a writer closure and the list is sorted before encoding.
+fn to_bencode_merged(s: &Struct) -> String {
+    let mut body = String::new();
+    for f in s.fields.iter().filter(|f| !f.flatten) {
+        let push = format!(
+            "entries.push(({key:?}.as_bytes(), ::std::boxed::Box::new(move |enc: &mut ::bencode_rs::Encoder<W>| \
+                 ::bencode_rs::ToBencode::write_bencode(v, enc))));",
+            key = f.key,
+        );
+        if f.optional {
+            body += &format!(
+                "if let ::core::option::Option::Some(v) = &self.{} {{ {} }}",
+                f.ident, push
+            );
+        } else {
+            body += &format!("{{ let v = &self.{}; {} }}", f.ident, push);
+        }
+    }
+    for f in s.fields.iter().filter(|f| f.flatten) {
+        body += &format!(
+            "for (k, v) in &self.{ident} {{ \
+                 if !KNOWN.contains(&k.as_str()) {{ \
+                     entries.push((k.as_bytes(), ::std::boxed::Box::new(move |enc: &mut ::bencode_rs::Encoder<W>| \
+                         ::bencode_rs::ToBencode::write_bencode(v, enc)))); \
+                 }} \
+             }}",
+            ident = f.ident,
+        );
+    }
+
+    format!(
+        "impl ::bencode_rs::ToBencode for {name} {{ \
+             fn write_bencode<W: ::std::io::Write>(&self, enc: &mut ::bencode_rs::Encoder<W>) \
+                 -> ::std::io::Result<()> {{ \
+                 {known} \
+                 let mut entries: ::std::vec::Vec<(&[u8], ::std::boxed::Box< \
+                     dyn Fn(&mut ::bencode_rs::Encoder<W>) -> ::std::io::Result<()> + '_>)> = \
+                     ::std::vec::Vec::new(); \
+                 {body} \
+                 entries.sort_by(|a, b| a.0.cmp(b.0)); \
+                 enc.begin_dict()?; \
+                 for (key, write) in entries {{ \
+                     enc.key(key)?; \
+                     write(enc)?; \
+                 }} \
+                 enc.end()?; \
+                 ::core::result::Result::Ok(()) \
+             }} \
+         }}",
+        name = s.name,
+        known = known_keys(s),
+    )
+}
+
+fn known_keys(s: &Struct) -> String {
+    let keys: Vec<String> = s
+        .fields
+        .iter()
+        .filter(|f| !f.flatten)
+        .flat_map(|f| f.keys())
+        .map(|key| format!("{:?}", key))
+        .collect();
+    format!("const KNOWN: &[&str] = &[{}];", keys.join(", "))
+}
+
+fn aliases(s: &Struct) -> String {
+    let mut aliases = String::from("::bencode_rs::Aliases::new()");
+    for f in s.fields.iter().filter(|f| !f.aliases.is_empty()) {
+        let keys: Vec<String> = f.keys().map(|key| format!("{:?}", key)).collect();
+        aliases += &format!(".alias({:?}, &[{}])", f.key, keys.join(", "));
+    }
+    format!(
+        "static ALIASES: ::std::sync::LazyLock<::bencode_rs::Aliases> = \
+             ::std::sync::LazyLock::new(|| {});",
+        aliases
+    )
+}
+
+fn from_bencode(s: &Struct) -> String {
+    let mut fields = String::new();
+    for f in s.fields.iter().filter(|f| f.flatten) {
+        fields += &format!(
+            "{ident}: value \
+                 .entries() \
+                 .filter(|(k, _)| !KNOWN.iter().any(|known| known.as_bytes() == *k)) \
+                 .map(|(k, v)| ::core::result::Result::Ok(( \
+                     ::std::string::String::from_utf8(k.to_vec()) \
+                         .map_err(|_e| ::bencode_rs::FromBencodeError::InvalidUtf8)?, \
+                     ::bencode_rs::FromBencode::from_bencode(v)?, \
+                 ))) \
+                 .collect::<::core::result::Result<_, ::bencode_rs::FromBencodeError>>()?,",
+            ident = f.ident,
+        );
+    }
+    for f in s.fields.iter().filter(|f| !f.flatten) {
+        let present = if f.optional {
+            "::core::option::Option::Some(::bencode_rs::FromBencode::from_bencode(v)?)"
+        } else {
+            "::bencode_rs::FromBencode::from_bencode(v)?"
+        };
+        let missing = match &f.default {
+            Some(Fallback::Trait) => "::core::default::Default::default()".to_string(),
+            Some(Fallback::Fn(path)) => format!("{}()", path),
+            None if f.optional => "::core::option::Option::None".to_string(),
+            None => format!(
+                "return ::core::result::Result::Err(\
+                     ::bencode_rs::FromBencodeError::MissingKey(::std::string::String::from({:?})))",
+                f.key
+            ),
+        };
+        fields += &format!(
+            "{ident}: match ALIASES.get_lazy(value, {key:?}) {{ \
+                 ::core::option::Option::Some(v) => {present}, \
+                 ::core::option::Option::None => {missing}, \
+             }},",
+            ident = f.ident,
+            key = f.key,
+        );
+    }
+
+    format!(
+        "impl ::bencode_rs::FromBencode for {name} {{ \
+             fn from_bencode(value: ::bencode_rs::LazyValue<'_>) \
+                 -> ::core::result::Result<Self, ::bencode_rs::FromBencodeError> {{ \
+                 if value.kind() != ::bencode_rs::Kind::Dict {{ \
+                     return ::core::result::Result::Err(::bencode_rs::FromBencodeError::WrongKind {{ \
+                         expected: ::bencode_rs::Kind::Dict, \
+                         found: value.kind(), \
+                     }}); \
+                 }} \
+                 {known} \
+                 {aliases} \
+                 ::core::result::Result::Ok({name} {{ {fields} }}) \
+             }} \
+         }}",
+        name = s.name,
+        known = known_keys(s),
+        aliases = aliases(s),
+    )
+}
+
+fn parse_struct(input: TokenStream) -> Result<Struct, String> {
+    let tokens: Vec<TokenTree> = input.into_iter().collect();
+    let mut i = skip_attrs_and_vis(&tokens, 0);
+
+    match tokens.get(i) {
+        Some(TokenTree::Ident(kw)) if kw.to_string() == "struct" => i += 1,
+        _ => return Err("bencode derives support only structs with named fields".into()),
+    }
+    let name = match tokens.get(i) {
+        Some(TokenTree::Ident(name)) => name.to_string(),
+        _ => return Err("expected a struct name".into()),
+    };
+    i += 1;
+
+    let body = match tokens.get(i) {
+        Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Brace => g.stream(),
+        Some(TokenTree::Punct(p)) if p.as_char() == '<' => {
+            return Err("bencode derives do not support generic structs".into());
+        }
+        _ => return Err("bencode derives support only structs with named fields".into()),
+    };
+
+    let mut fields = Vec::new();
+    for chunk in split_fields(body) {
+        fields.push(parse_field(&chunk)?);
+    }
+    for (n, f) in fields.iter().enumerate() {
+        if f.flatten && fields[..n].iter().any(|g| g.flatten) {
+            return Err("only one field can be #[bencode(flatten)]".into());
+        }
+        if f.flatten {
+            continue;
+        }
+        let mut earlier = fields[..n].iter().filter(|g| !g.flatten).flat_map(Field::keys);
+        if let Some(key) = earlier.find(|key| f.keys().any(|k| k == *key)) {
+            return Err(format!("duplicate bencode key {:?}", key));
+        }
+    }
+    Ok(Struct { name, fields })
+}
+
+/// Skips outer attributes and a visibility qualifier starting at `i`.
+fn skip_attrs_and_vis(tokens: &[TokenTree], mut i: usize) -> usize {
+    while matches!(tokens.get(i), Some(TokenTree::Punct(p)) if p.as_char() == '#') {
+        i += 2;
+    }
+    if matches!(tokens.get(i), Some(TokenTree::Ident(id)) if id.to_string() == "pub") {
+        i += 1;
+        if matches!(tokens.get(i), Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis)
+        {
+            i += 1;
+        }
+    }
+    i
+}
+
+/// Splits a struct body at top-level commas. Commas inside generic
+/// arguments are not separated by a group, so angle brackets are counted.
+fn split_fields(body: TokenStream) -> Vec<Vec<TokenTree>> {
+    let mut fields = vec![Vec::new()];
+    let mut depth = 0usize;
+    let mut prev_dash = false;
+    for token in body {
+        if let TokenTree::Punct(p) = &token {
+            match p.as_char() {
+                '<' => depth += 1,
+                // Not the arrow of a fn pointer type.
+                '>' if !prev_dash => depth = depth.saturating_sub(1),
+                ',' if depth == 0 => {
+                    fields.push(Vec::new());
+                    continue;
+                }
+                _ => {}
+            }
+            prev_dash = p.as_char() == '-';
+        } else {
+            prev_dash = false;
+        }
+        if let Some(field) = fields.last_mut() {
+            field.push(token);
+        }
+    }
+    fields.retain(|f| !f.is_empty());
+    fields
+}
+
+fn parse_field(tokens: &[TokenTree]) -> Result<Field, String> {
+    let mut key = None;
+    let mut aliases = Vec::new();
+    let mut default = None;
+    let mut flatten = false;
+
+    let mut i = 0;
+    while let (Some(TokenTree::Punct(p)), Some(TokenTree::Group(attr))) = (tokens.get(i), tokens.get(i + 1)) {
+        if p.as_char() != '#' {
+            break;
+        }
+        let attr: Vec<TokenTree> = attr.stream().into_iter().collect();
+        if let [TokenTree::Ident(name), TokenTree::Group(args)] = &attr[..]
+            && name.to_string() == "bencode"
+        {
+            parse_args(
+                args.stream(),
+                &mut key,
+                &mut aliases,
+                &mut default,
+                &mut flatten,
+            )?;
+        }
+        i += 2;
+    }
+    i = skip_attrs_and_vis(tokens, i);
+
+    let ident = match tokens.get(i) {
+        Some(TokenTree::Ident(ident)) => ident.to_string(),
+        _ => return Err("expected a field name".into()),
+    };
+    if !matches!(tokens.get(i + 1), Some(TokenTree::Punct(p)) if p.as_char() == ':') {
+        return Err(format!("expected `:` after field `{}`", ident));
+    }
+
+    // `Option<T>`, `std::option::Option<T>` and the like.
+    let ty = &tokens[i + 2..];
+    let end = ty
+        .iter()
+        .position(|t| matches!(t, TokenTree::Punct(p) if p.as_char() == '<'))
+        .unwrap_or(ty.len());
+    let optional = matches!(ty[..end].last(), Some(TokenTree::Ident(id)) if id.to_string() == "Option");
+
+    let key = key.unwrap_or_else(|| ident.trim_start_matches("r#").to_string());
+    Ok(Field {
+        ident,
+        key,
+        aliases,
+        optional,
+        default,
+        flatten,
+    })
+}
+
+fn parse_args(
+    args: TokenStream,
+    key: &mut Option<String>,
+    aliases: &mut Vec<String>,
+    default: &mut Option<Fallback>,
+    flatten: &mut bool,
+) -> Result<(), String> {
+    let args: Vec<TokenTree> = args.into_iter().collect();
+    for arg in args.split(|t| matches!(t, TokenTree::Punct(p) if p.as_char() == ',')) {
+        match arg {
+            [] => {}
+            [TokenTree::Ident(name)] if name.to_string() == "default" => {
+                *default = Some(Fallback::Trait);
+            }
+            [TokenTree::Ident(name)] if name.to_string() == "flatten" => *flatten = true,
+            [TokenTree::Ident(name), TokenTree::Punct(eq), TokenTree::Literal(lit)] if eq.as_char() == '=' => {
+                let value = string_literal(&lit.to_string())?;
+                match name.to_string().as_str() {
+                    "rename" => *key = Some(value),
+                    "alias" => aliases.push(value),
+                    "default" => *default = Some(Fallback::Fn(value)),
+                    other => return Err(format!("unknown bencode attribute `{}`", other)),
+                }
+            }
+            _ => {
+                return Err(
+                    "expected `rename = \"...\"`, `alias = \"...\"`, `default`, \
+                     `default = \"...\"` or `flatten`"
+                        .into(),
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Contents of a plain or raw string literal.
+fn string_literal(lit: &str) -> Result<String, String> {
+    if let Some(raw) = lit.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        return Ok(raw[hashes + 1..raw.len() - hashes - 1].to_string());
+    }
+    let inner = lit
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .ok_or_else(|| format!("expected a string literal, found {}", lit))?;
+
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('0') => out.push('\0'),
+            Some(c @ ('\\' | '"' | '\'')) => out.push(c),
+            _ => return Err(format!("unsupported escape in {}", lit)),
+        }
+    }
+    Ok(out)
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use crate::{BValue, LazyValue};
+
+// Dict key alias resolution. Real-world torrents spell some keys several
+// ways (`publisher-url` vs `publisher.url`, `name.utf-8`), so lookups go
+// through a configurable map instead of hand-rolled fallback chains.
+// Some producers also vary the case (`Name`, `CREATED BY`); matching that
+// is opt-in, since keys are case-sensitive in the format.
+
+#[derive(Debug, Clone, Default)]
+pub struct Aliases {
+    map: HashMap<String, Vec<String>>,
+    case_insensitive: bool,
+}
+
+impl Aliases {
+    /// No aliases; lookups only match the exact key.
+    pub fn new() -> Self {
+        Aliases::default()
+    }
+
+    /// Aliases commonly seen in `.torrent` files. UTF-8 variants take
+    /// precedence over the plain key when both are present.
+    pub fn torrent() -> Self {
+        Aliases::new()
+            .alias("name", &["name.utf-8", "name"])
+            .alias("path", &["path.utf-8", "path"])
+            .alias("comment", &["comment.utf-8", "comment"])
+            .alias("publisher", &["publisher.utf-8", "publisher"])
+            .alias("publisher-url", &["publisher-url.utf-8", "publisher-url", "publisher.url"])
+            .alias("created by", &["created by.utf-8", "created by", "created-by"])
+    }
+
+    /// Looks up `key` through `candidates`, tried in order. `key` itself is
+    /// tried last unless it is listed.
+    pub fn alias(mut self, key: &str, candidates: &[&str]) -> Self {
+        let mut list: Vec<String> = candidates.iter().map(|c| c.to_string()).collect();
+        if !candidates.contains(&key) {
+            list.push(key.to_string());
+        }
+        self.map.insert(key.to_string(), list);
+        self
+    }
+
+    /// Also matches keys that differ from a candidate only in ASCII case.
+    /// Candidates are still tried in order, and for each one a key of the
+    /// exact case wins over a folded match.
+    pub fn case_insensitive(mut self) -> Self {
+        self.case_insensitive = true;
+        self
+    }
+
+    /// The keys tried for `key`, in order; just `key` if it has no aliases.
+    pub fn candidates<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> {
+        let list = self.map.get(key);
+        list.into_iter()
+            .flatten()
+            .map(String::as_str)
+            .chain(list.is_none().then_some(key))
+    }
+
+    /// Value of the first candidate for `key` present in `dict`. `None` if
+    /// none is present or `dict` is not a dict.
+    pub fn get<'a>(&self, dict: &'a BValue, key: &str) -> Option<&'a BValue> {
+        let BValue::Dict(map) = dict else {
+            return None;
+        };
+        self.candidates(key).find_map(|c| {
+            map.get(c)
+                .or_else(|| self.folded(map.iter().map(|(k, v)| (k.as_bytes(), v)), c))
+        })
+    }
+
+    /// `get` for a dict that has not been decoded, as read by
+    /// `FromBencode`. Keys need not be UTF-8.
+    pub fn get_lazy<'a>(&self, dict: LazyValue<'a>, key: &str) -> Option<LazyValue<'a>> {
+        self.candidates(key)
+            .find_map(|c| dict.get(c).or_else(|| self.folded(dict.entries(), c)))
+    }
+
+    /// The first entry whose key equals `candidate` up to ASCII case, if
+    /// that is enabled.
+    fn folded<'k, V>(
+        &self,
+        mut entries: impl Iterator<Item = (&'k [u8], V)>,
+        candidate: &str,
+    ) -> Option<V> {
+        if !self.case_insensitive {
+            return None;
+        }
+        entries
+            .find(|(k, _)| k.eq_ignore_ascii_case(candidate.as_bytes()))
+            .map(|(_, v)| v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode;
+
+    #[test]
+    fn test_resolves_in_candidate_order() {
+        let aliases = Aliases::torrent();
+
+        let (info, _) = decode(b"d4:name3:foo10:name.utf-83:bare").unwrap();
+        assert_eq!(aliases.get(&info, "name"), Some(&BValue::Str("bar".to_string())));
+
+        let (info, _) = decode(b"d4:name3:fooe").unwrap();
+        assert_eq!(aliases.get(&info, "name"), Some(&BValue::Str("foo".to_string())));
+
+        let (torrent, _) = decode(b"d13:publisher.url3:urle").unwrap();
+        assert_eq!(
+            aliases.get(&torrent, "publisher-url"),
+            Some(&BValue::Str("url".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_unaliased_keys_match_exactly() {
+        let aliases = Aliases::new().alias("a", &["b"]);
+        let (dict, _) = decode(b"d1:ai1e1:ci3ee").unwrap();
+        assert_eq!(aliases.get(&dict, "a"), Some(&BValue::Int(1)));
+        assert_eq!(aliases.get(&dict, "c"), Some(&BValue::Int(3)));
+        assert_eq!(aliases.get(&dict, "d"), None);
+        assert_eq!(aliases.get(&BValue::Int(1), "a"), None);
+        assert!(aliases.candidates("a").eq(["b", "a"]));
+        assert!(aliases.candidates("c").eq(["c"]));
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        let input = b"d7:Comment3:old10:CREATED BY3:foo13:comment.UTF-83:newe";
+        let (dict, _) = decode(b"d10:CREATED BY3:fooe").unwrap();
+        let (lazy, _) = LazyValue::parse(input).unwrap();
+
+        let exact = Aliases::torrent();
+        assert_eq!(exact.get(&dict, "created by"), None);
+        assert_eq!(exact.get_lazy(lazy, "comment"), None);
+
+        let folded = Aliases::torrent().case_insensitive();
+        assert_eq!(
+            folded.get(&dict, "created by"),
+            Some(&BValue::Str("foo".to_string()))
+        );
+        // `comment.utf-8` comes first among the candidates.
+        let comment = folded.get_lazy(lazy, "comment").unwrap();
+        assert_eq!(comment.as_bytes(), Some(&b"new"[..]));
+        assert_eq!(
+            folded.get_lazy(lazy, "created by").unwrap().as_str(),
+            Some("foo")
+        );
+    }
+}
@@ -0,0 +1,381 @@
+use std::cmp::Ordering;
+
+use crate::{BValue, DecodeError, Kind};
+
+// Archived form of a decoded tree that can be written to disk and read back
+// (e.g. memory-mapped) without deserializing. Dict keys are stored sorted so
+// lookups binary search in place.
+//
+// Layout, all integers little-endian:
+//   header: b"BNCA" | version: u8 | root offset: u32
+//   None:   0   (also stands in for externals, which are opaque)
+//   Int:    1 | i16
+//   Str:    2 | len: u32 | bytes
+//   List:   3 | count: u32 | count x child offset: u32
+//   Dict:   4 | count: u32 | count x (key offset: u32, value offset: u32)
+// Children are written before their parent, so every offset points backwards.
+// A node's subtree is one contiguous run: its children's subtrees in order,
+// then the node itself, with the root last. Reading insists on exactly that,
+// so no node can be shared and checking is linear in the archive's size.
+
+const MAGIC: &[u8; 4] = b"BNCA";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 9;
+
+const TAG_NONE: u8 = 0;
+const TAG_INT: u8 = 1;
+const TAG_STR: u8 = 2;
+const TAG_LIST: u8 = 3;
+const TAG_DICT: u8 = 4;
+
+/// Serializes `value` into the archive format.
+pub fn archive(value: &BValue) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(HEADER_LEN);
+    buf.extend_from_slice(MAGIC);
+    buf.push(VERSION);
+    buf.extend_from_slice(&[0; 4]);
+
+    let root = write_node(value, &mut buf);
+    buf[5..HEADER_LEN].copy_from_slice(&root.to_le_bytes());
+    buf
+}
+
+fn write_node(value: &BValue, buf: &mut Vec<u8>) -> u32 {
+    match value {
+        BValue::None | BValue::External(_) => push_node(buf, |buf| buf.push(TAG_NONE)),
+        BValue::Int(n) => push_node(buf, |buf| {
+            buf.push(TAG_INT);
+            buf.extend_from_slice(&n.to_le_bytes());
+        }),
+        BValue::Str(s) => write_str(s, buf),
+        BValue::List(list) => {
+            let children: Vec<u32> = list.iter().map(|v| write_node(v, buf)).collect();
+            push_node(buf, |buf| {
+                buf.push(TAG_LIST);
+                buf.extend_from_slice(&(children.len() as u32).to_le_bytes());
+                for child in children {
+                    buf.extend_from_slice(&child.to_le_bytes());
+                }
+            })
+        }
+        BValue::Dict(dict) => {
+            let mut entries: Vec<(&String, &BValue)> = dict.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            let children: Vec<(u32, u32)> = entries
+                .into_iter()
+                .map(|(k, v)| (write_str(k, buf), write_node(v, buf)))
+                .collect();
+            push_node(buf, |buf| {
+                buf.push(TAG_DICT);
+                buf.extend_from_slice(&(children.len() as u32).to_le_bytes());
+                for (key, value) in children {
+                    buf.extend_from_slice(&key.to_le_bytes());
+                    buf.extend_from_slice(&value.to_le_bytes());
+                }
+            })
+        }
+    }
+}
+
+fn write_str(s: &str, buf: &mut Vec<u8>) -> u32 {
+    push_node(buf, |buf| {
+        buf.push(TAG_STR);
+        buf.extend_from_slice(&(s.len() as u32).to_le_bytes());
+        buf.extend_from_slice(s.as_bytes());
+    })
+}
+
+fn push_node(buf: &mut Vec<u8>, write: impl FnOnce(&mut Vec<u8>)) -> u32 {
+    let offset = buf.len() as u32;
+    write(buf);
+    offset
+}
+
+/// Read-only view of a node inside an archive buffer.
+#[derive(Debug, Clone, Copy)]
+pub struct ArchivedValue<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ArchivedValue<'a> {
+    /// Checks the whole archive once and returns a view of its root. After
+    /// this, accessors read the buffer directly.
+    pub fn from_bytes(buf: &'a [u8]) -> Result<ArchivedValue<'a>, DecodeError> {
+        if buf.len() < HEADER_LEN {
+            return Err(DecodeError::UnexpectedEof { offset: buf.len() });
+        }
+        if &buf[..4] != MAGIC || buf[4] != VERSION {
+            return Err(DecodeError::UnexpectedByte {
+                offset: 0,
+                byte: buf[0],
+            });
+        }
+
+        let root = read_u32(buf, 5) as usize;
+        let end = check_node(buf, root, HEADER_LEN, buf.len())?;
+        if end != buf.len() {
+            return Err(DecodeError::TrailingData { offset: end });
+        }
+        Ok(ArchivedValue { buf, pos: root })
+    }
+
+    pub fn kind(&self) -> Kind {
+        match self.buf[self.pos] {
+            TAG_INT => Kind::Int,
+            TAG_STR => Kind::Str,
+            TAG_LIST => Kind::List,
+            TAG_DICT => Kind::Dict,
+            _ => Kind::None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i16> {
+        if self.kind() != Kind::Int {
+            return None;
+        }
+        Some(i16::from_le_bytes([self.buf[self.pos + 1], self.buf[self.pos + 2]]))
+    }
+
+    pub fn as_str(&self) -> Option<&'a str> {
+        if self.kind() != Kind::Str {
+            return None;
+        }
+        let len = read_u32(self.buf, self.pos + 1) as usize;
+        let start = self.pos + 5;
+        std::str::from_utf8(&self.buf[start..start + len]).ok()
+    }
+
+    /// Number of list elements or dict entries; 0 for other kinds.
+    pub fn len(&self) -> usize {
+        match self.kind() {
+            Kind::List | Kind::Dict => read_u32(self.buf, self.pos + 1) as usize,
+            _ => 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn index(&self, i: usize) -> Option<ArchivedValue<'a>> {
+        if self.kind() != Kind::List || i >= self.len() {
+            return None;
+        }
+        Some(self.at(read_u32(self.buf, self.pos + 5 + i * 4)))
+    }
+
+    /// Binary searches the sorted keys of a dict.
+    pub fn get(&self, key: &str) -> Option<ArchivedValue<'a>> {
+        if self.kind() != Kind::Dict {
+            return None;
+        }
+
+        let (mut lo, mut hi) = (0, self.len());
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            let (k, v) = self.entry(mid);
+            match k.as_str()?.cmp(key) {
+                Ordering::Less => lo = mid + 1,
+                Ordering::Greater => hi = mid,
+                Ordering::Equal => return Some(v),
+            }
+        }
+        None
+    }
+
+    /// Dict entries in key order; empty for non-dicts.
+    pub fn entries(&self) -> impl Iterator<Item = (&'a str, ArchivedValue<'a>)> + '_ {
+        let count = if self.kind() == Kind::Dict { self.len() } else { 0 };
+        (0..count).filter_map(|i| {
+            let (k, v) = self.entry(i);
+            Some((k.as_str()?, v))
+        })
+    }
+
+    /// Copies this node back into an owned tree.
+    pub fn to_value(&self) -> BValue {
+        match self.kind() {
+            Kind::Int => BValue::Int(self.as_int().unwrap_or_default()),
+            Kind::Str => BValue::Str(self.as_str().unwrap_or_default().to_string()),
+            Kind::List => BValue::List(
+                (0..self.len())
+                    .filter_map(|i| self.index(i))
+                    .map(|v| v.to_value())
+                    .collect(),
+            ),
+            Kind::Dict => BValue::Dict(
+                self.entries()
+                    .map(|(k, v)| (k.to_string(), v.to_value()))
+                    .collect(),
+            ),
+            Kind::None | Kind::External => BValue::None,
+        }
+    }
+
+    fn entry(&self, i: usize) -> (ArchivedValue<'a>, ArchivedValue<'a>) {
+        let at = self.pos + 5 + i * 8;
+        (
+            self.at(read_u32(self.buf, at)),
+            self.at(read_u32(self.buf, at + 4)),
+        )
+    }
+
+    fn at(&self, pos: u32) -> ArchivedValue<'a> {
+        ArchivedValue {
+            buf: self.buf,
+            pos: pos as usize,
+        }
+    }
+}
+
+fn read_u32(buf: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([buf[at], buf[at + 1], buf[at + 2], buf[at + 3]])
+}
+
+// Bounds-checks a node and everything below it and returns where the node
+// ends. Its subtree must start at `start`, and the node must sit before
+// `limit` (its parent), which also rules out cycles. Shared children would
+// let a few hundred bytes stand for an exponentially large tree.
+fn check_node(buf: &[u8], pos: usize, start: usize, limit: usize) -> Result<usize, DecodeError> {
+    let eof = DecodeError::UnexpectedEof { offset: buf.len() };
+    if pos < HEADER_LEN || pos >= limit {
+        return Err(eof);
+    }
+    let misplaced = DecodeError::UnexpectedByte {
+        offset: pos,
+        byte: buf[pos],
+    };
+    let header = |len: usize| {
+        if pos + len <= buf.len() {
+            Ok(())
+        } else {
+            Err(DecodeError::UnexpectedEof { offset: buf.len() })
+        }
+    };
+
+    match buf[pos] {
+        TAG_LIST | TAG_DICT => {}
+        _ if pos != start => return Err(misplaced),
+        _ => {}
+    }
+
+    match buf[pos] {
+        TAG_NONE => Ok(pos + 1),
+        TAG_INT => header(3).map(|_| pos + 3),
+        TAG_STR => {
+            header(5)?;
+            let len = read_u32(buf, pos + 1) as usize;
+            let bytes = buf.get(pos + 5..pos + 5 + len).ok_or(eof)?;
+            std::str::from_utf8(bytes)
+                .map(|_| pos + 5 + len)
+                .map_err(|_e| DecodeError::InvalidUtf8 { offset: pos + 5 })
+        }
+        TAG_LIST | TAG_DICT => {
+            header(5)?;
+            let count = read_u32(buf, pos + 1) as usize;
+            let width = if buf[pos] == TAG_LIST { 1 } else { 2 };
+            let len = count
+                .checked_mul(width * 4)
+                .and_then(|n| n.checked_add(5))
+                .ok_or(eof)?;
+            header(len)?;
+
+            let mut next = start;
+            for i in 0..count * width {
+                let child = read_u32(buf, pos + 5 + i * 4) as usize;
+                next = check_node(buf, child, next, pos)?;
+                if width == 2 && i % 2 == 0 && buf[child] != TAG_STR {
+                    return Err(DecodeError::NonStringKey { offset: child });
+                }
+            }
+            if next != pos {
+                return Err(misplaced);
+            }
+            Ok(pos + len)
+        }
+        byte => Err(DecodeError::UnexpectedByte { offset: pos, byte }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode;
+
+    #[test]
+    fn test_round_trip() {
+        let (value, _) =
+            decode(b"d8:announce3:url4:infod6:lengthi42e4:pathl1:a1:bee1:zle1:ai-7ee").unwrap();
+        let buf = archive(&value);
+        let archived = ArchivedValue::from_bytes(&buf).unwrap();
+        assert_eq!(archived.to_value(), value);
+    }
+
+    #[test]
+    fn test_reads_in_place() {
+        let (value, _) = decode(b"d4:infod6:lengthi42e4:pathl1:a1:bee4:name3:fooe").unwrap();
+        let buf = archive(&value);
+        let root = ArchivedValue::from_bytes(&buf).unwrap();
+
+        assert_eq!(root.len(), 2);
+        assert_eq!(root.get("name").unwrap().as_str(), Some("foo"));
+        let info = root.get("info").unwrap();
+        assert_eq!(info.get("length").unwrap().as_int(), Some(42));
+        assert_eq!(info.get("path").unwrap().index(1).unwrap().as_str(), Some("b"));
+        assert!(root.get("missing").is_none());
+    }
+
+    #[test]
+    fn test_rejects_corrupt_archives() {
+        let buf = archive(&BValue::List(vec![BValue::Int(1)]));
+        assert!(ArchivedValue::from_bytes(&buf[..buf.len() - 1]).is_err());
+        assert!(ArchivedValue::from_bytes(b"XXXX\x01\x09\x00\x00\x00\x00").is_err());
+
+        // Child offset pointing at its own parent.
+        let mut cyclic = buf.clone();
+        let root = read_u32(&buf, 5) as usize;
+        cyclic[root + 5..root + 9].copy_from_slice(&(root as u32).to_le_bytes());
+        assert!(ArchivedValue::from_bytes(&cyclic).is_err());
+
+        let mut trailing = buf.clone();
+        trailing.push(TAG_NONE);
+        assert_eq!(
+            ArchivedValue::from_bytes(&trailing).unwrap_err(),
+            DecodeError::TrailingData { offset: buf.len() }
+        );
+    }
+
+    #[test]
+    fn test_rejects_shared_children() {
+        // Each list holds the one before it twice: 2^40 leaves as a tree.
+        let mut buf = MAGIC.to_vec();
+        buf.push(VERSION);
+        buf.extend_from_slice(&[0; 4]);
+        let mut child = buf.len() as u32;
+        buf.push(TAG_NONE);
+        for _ in 0..40 {
+            let pos = buf.len() as u32;
+            buf.push(TAG_LIST);
+            buf.extend_from_slice(&2u32.to_le_bytes());
+            buf.extend_from_slice(&child.to_le_bytes());
+            buf.extend_from_slice(&child.to_le_bytes());
+            child = pos;
+        }
+        buf[5..HEADER_LEN].copy_from_slice(&child.to_le_bytes());
+        assert_eq!(
+            ArchivedValue::from_bytes(&buf).unwrap_err(),
+            DecodeError::UnexpectedByte {
+                offset: HEADER_LEN,
+                byte: TAG_NONE,
+            }
+        );
+
+        // Children out of order are rejected the same way.
+        let (value, _) = decode(b"l1:a1:be").unwrap();
+        let mut swapped = archive(&value);
+        let root = read_u32(&swapped, 5) as usize;
+        swapped[root + 5..root + 13].rotate_left(4);
+        assert!(ArchivedValue::from_bytes(&swapped).is_err());
+    }
+}
@@ -0,0 +1,28 @@
+// Prints the JSON fixture manifest for a bencoded file.
+//
+//     bencode-fixture path/to/file.torrent > file.json
+
+use std::{env, fs, process};
+
+fn main() {
+    let Some(path) = env::args().nth(1) else {
+        eprintln!("usage: bencode-fixture <file>");
+        process::exit(2);
+    };
+
+    let input = match fs::read(&path) {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            process::exit(1);
+        }
+    };
+
+    match bencode_rs::export_fixture(&input) {
+        Ok(manifest) => print!("{}", manifest),
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddrV4, SocketAddrV6};
+
+// Big-endian readers for binary data carried in byte-string fields
+// (`peers`, `nodes`, `ip`, `port` and friends), so byte-order handling
+// lives in one place. They work on raw bytes, as `Event::Str` or a decode
+// hook provides them; a `BValue::Str` must be UTF-8 and so cannot hold most
+// binary values.
+
+/// Length of a compact IPv4 peer: 4 address bytes and a 2 byte port.
+pub const COMPACT_V4_LEN: usize = 6;
+/// Length of a compact IPv6 peer: 16 address bytes and a 2 byte port.
+pub const COMPACT_V6_LEN: usize = 18;
+
+pub fn read_be_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(array_at(bytes, offset)?))
+}
+
+pub fn read_be_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(array_at(bytes, offset)?))
+}
+
+pub fn read_be_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(array_at(bytes, offset)?))
+}
+
+pub fn read_ipv4(bytes: &[u8], offset: usize) -> Option<Ipv4Addr> {
+    Some(Ipv4Addr::from(array_at::<4>(bytes, offset)?))
+}
+
+pub fn read_ipv6(bytes: &[u8], offset: usize) -> Option<Ipv6Addr> {
+    Some(Ipv6Addr::from(array_at::<16>(bytes, offset)?))
+}
+
+/// Splits a compact IPv4 peer list. `None` unless the length is a
+/// multiple of `COMPACT_V4_LEN`.
+pub fn compact_peers_v4(bytes: &[u8]) -> Option<Vec<SocketAddrV4>> {
+    if !bytes.len().is_multiple_of(COMPACT_V4_LEN) {
+        return None;
+    }
+    bytes
+        .chunks_exact(COMPACT_V4_LEN)
+        .map(|c| Some(SocketAddrV4::new(read_ipv4(c, 0)?, read_be_u16(c, 4)?)))
+        .collect()
+}
+
+/// Splits a compact IPv6 peer list. `None` unless the length is a
+/// multiple of `COMPACT_V6_LEN`.
+pub fn compact_peers_v6(bytes: &[u8]) -> Option<Vec<SocketAddrV6>> {
+    if !bytes.len().is_multiple_of(COMPACT_V6_LEN) {
+        return None;
+    }
+    bytes
+        .chunks_exact(COMPACT_V6_LEN)
+        .map(|c| Some(SocketAddrV6::new(read_ipv6(c, 0)?, read_be_u16(c, 16)?, 0, 0)))
+        .collect()
+}
+
+/// Packs peers into the compact IPv4 form read by `compact_peers_v4`.
+pub fn encode_compact_peers_v4(addrs: &[SocketAddrV4]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(addrs.len() * COMPACT_V4_LEN);
+    for addr in addrs {
+        out.extend(addr.ip().octets());
+        out.extend(addr.port().to_be_bytes());
+    }
+    out
+}
+
+pub fn encode_compact_peers_v6(addrs: &[SocketAddrV6]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(addrs.len() * COMPACT_V6_LEN);
+    for addr in addrs {
+        out.extend(addr.ip().octets());
+        out.extend(addr.port().to_be_bytes());
+    }
+    out
+}
+
+fn array_at<const N: usize>(bytes: &[u8], offset: usize) -> Option<[u8; N]> {
+    bytes.get(offset..offset.checked_add(N)?)?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_width_reads() {
+        let bytes = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09];
+        assert_eq!(read_be_u16(&bytes, 1), Some(0x0203));
+        assert_eq!(read_be_u32(&bytes, 0), Some(0x01020304));
+        assert_eq!(read_be_u64(&bytes, 1), Some(0x0203040506070809));
+        assert_eq!(read_be_u32(&bytes, 6), None);
+        assert_eq!(read_be_u16(&bytes, usize::MAX), None);
+    }
+
+    #[test]
+    fn test_compact_peers() {
+        let bytes = [127, 0, 0, 1, 0x1a, 0x21, 10, 0, 0, 2, 0, 80];
+        assert_eq!(
+            compact_peers_v4(&bytes),
+            Some(vec![
+                SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 6689),
+                SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 2), 80),
+            ])
+        );
+        assert_eq!(compact_peers_v4(&bytes[..7]), None);
+
+        let mut v6 = [0u8; COMPACT_V6_LEN];
+        v6[15] = 1;
+        v6[17] = 80;
+        assert_eq!(
+            compact_peers_v6(&v6),
+            Some(vec![SocketAddrV6::new(Ipv6Addr::LOCALHOST, 80, 0, 0)])
+        );
+    }
+
+    #[test]
+    fn test_encode_compact_peers() {
+        let v4: [SocketAddrV4; 2] = ["1.2.3.4:258".parse().unwrap(), "0.0.0.0:0".parse().unwrap()];
+        let packed = encode_compact_peers_v4(&v4);
+        assert_eq!(packed, b"\x01\x02\x03\x04\x01\x02\0\0\0\0\0\0");
+        assert_eq!(compact_peers_v4(&packed), Some(v4.to_vec()));
+        assert_eq!(compact_peers_v4(b""), Some(vec![]));
+
+        let v6: [SocketAddrV6; 1] = ["[2001:db8::1]:6881".parse().unwrap()];
+        let packed = encode_compact_peers_v6(&v6);
+        assert_eq!(packed.len(), COMPACT_V6_LEN);
+        assert_eq!(&packed[16..], b"\x1a\xe1");
+        assert_eq!(compact_peers_v6(&packed), Some(v6.to_vec()));
+    }
+}
@@ -0,0 +1,234 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use crate::{BValue, Encoder};
+
+// Chained construction of values, for tracker responses, KRPC replies and
+// the like:
+//
+//     let reply = DictBuilder::new()
+//         .str("y", "r")
+//         .dict("r", |r| r.bytes("id", &node_id))
+//         .list("nodes", |l| l.str("a").str("b"))
+//         .encode()?;
+//
+// Setting a key twice keeps the last value. Integers are `i64` and `bytes`
+// takes binary strings, which `encode` writes as-is; `build` only succeeds
+// when everything also fits a `BValue`.
+
+#[derive(Debug, Clone)]
+enum Node {
+    Int(i64),
+    Bytes(Vec<u8>),
+    List(Vec<Node>),
+    Dict(BTreeMap<String, Node>),
+    Value(BValue),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DictBuilder {
+    entries: BTreeMap<String, Node>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ListBuilder {
+    items: Vec<Node>,
+}
+
+impl DictBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn node(mut self, key: &str, node: Node) -> Self {
+        self.entries.insert(key.to_string(), node);
+        self
+    }
+
+    pub fn value(self, key: &str, value: BValue) -> Self {
+        self.node(key, Node::Value(value))
+    }
+
+    pub fn str(self, key: &str, value: &str) -> Self {
+        self.bytes(key, value.as_bytes())
+    }
+
+    pub fn bytes(self, key: &str, value: &[u8]) -> Self {
+        self.node(key, Node::Bytes(value.to_vec()))
+    }
+
+    pub fn int(self, key: &str, value: i64) -> Self {
+        self.node(key, Node::Int(value))
+    }
+
+    pub fn list(self, key: &str, build: impl FnOnce(ListBuilder) -> ListBuilder) -> Self {
+        self.node(key, Node::List(build(ListBuilder::new()).items))
+    }
+
+    pub fn dict(self, key: &str, build: impl FnOnce(DictBuilder) -> DictBuilder) -> Self {
+        self.node(key, Node::Dict(build(DictBuilder::new()).entries))
+    }
+
+    /// The built dict as a tree. Fails with `InvalidData` on an integer
+    /// outside `i16` or a string that is not UTF-8; `encode` takes both.
+    pub fn build(self) -> io::Result<BValue> {
+        Node::Dict(self.entries).into_value()
+    }
+
+    /// Builds and encodes in one step. Fails only if a `BValue::None` or
+    /// `BValue::External` was added with `value`.
+    pub fn encode(self) -> io::Result<Vec<u8>> {
+        let node = Node::Dict(self.entries);
+        Encoder::to_vec(|enc| node.write(enc))
+    }
+}
+
+impl ListBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn node(mut self, node: Node) -> Self {
+        self.items.push(node);
+        self
+    }
+
+    pub fn value(self, value: BValue) -> Self {
+        self.node(Node::Value(value))
+    }
+
+    pub fn str(self, value: &str) -> Self {
+        self.bytes(value.as_bytes())
+    }
+
+    pub fn bytes(self, value: &[u8]) -> Self {
+        self.node(Node::Bytes(value.to_vec()))
+    }
+
+    pub fn int(self, value: i64) -> Self {
+        self.node(Node::Int(value))
+    }
+
+    pub fn list(self, build: impl FnOnce(ListBuilder) -> ListBuilder) -> Self {
+        self.node(Node::List(build(ListBuilder::new()).items))
+    }
+
+    pub fn dict(self, build: impl FnOnce(DictBuilder) -> DictBuilder) -> Self {
+        self.node(Node::Dict(build(DictBuilder::new()).entries))
+    }
+
+    pub fn build(self) -> io::Result<BValue> {
+        Node::List(self.items).into_value()
+    }
+
+    pub fn encode(self) -> io::Result<Vec<u8>> {
+        let node = Node::List(self.items);
+        Encoder::to_vec(|enc| node.write(enc))
+    }
+}
+
+impl Node {
+    // Dict entries are already in key order, as the encoder requires.
+    fn write<W: Write>(&self, enc: &mut Encoder<W>) -> io::Result<()> {
+        match self {
+            Node::Int(n) => enc.int(*n).map(|_| ()),
+            Node::Bytes(bytes) => enc.bytes(bytes).map(|_| ()),
+            Node::List(items) => {
+                enc.begin_list()?;
+                for item in items {
+                    item.write(enc)?;
+                }
+                enc.end().map(|_| ())
+            }
+            Node::Dict(entries) => {
+                enc.begin_dict()?;
+                for (key, value) in entries {
+                    enc.key(key.as_bytes())?;
+                    value.write(enc)?;
+                }
+                enc.end().map(|_| ())
+            }
+            Node::Value(value) => enc.value(value).map(|_| ()),
+        }
+    }
+
+    fn into_value(self) -> io::Result<BValue> {
+        Ok(match self {
+            Node::Int(n) => BValue::Int(
+                i16::try_from(n).map_err(|_e| invalid_data("integer out of range for BValue"))?,
+            ),
+            Node::Bytes(bytes) => BValue::Str(
+                String::from_utf8(bytes).map_err(|_e| invalid_data("string is not valid UTF-8"))?,
+            ),
+            Node::List(items) => {
+                BValue::List(items.into_iter().map(Node::into_value).collect::<io::Result<_>>()?)
+            }
+            Node::Dict(entries) => BValue::Dict(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| Ok((key, value.into_value()?)))
+                    .collect::<io::Result<_>>()?,
+            ),
+            Node::Value(value) => value,
+        })
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_tracker_response() {
+        let response = DictBuilder::new()
+            .int("interval", 1800)
+            .list("peers", |l| {
+                l.dict(|p| p.str("ip", "10.0.0.1").int("port", 6881))
+                    .dict(|p| p.str("ip", "10.0.0.2").int("port", 6882))
+            })
+            .int("complete", 3)
+            .int("complete", 2)
+            .encode()
+            .unwrap();
+        assert_eq!(
+            response,
+            b"d8:completei2e8:intervali1800e5:peersld2:ip8:10.0.0.14:porti6881eed2:ip8:10.0.0.24:porti6882eeee"
+        );
+    }
+
+    #[test]
+    fn test_build_values() {
+        let value = ListBuilder::new()
+            .int(-1)
+            .list(|l| l)
+            .dict(|d| d.dict("r", |r| r.str("id", "x")))
+            .build()
+            .unwrap();
+        assert_eq!(value[2]["r"]["id"], "x");
+        assert_eq!(value[1], BValue::List(vec![]));
+
+        assert!(DictBuilder::new().value("a", BValue::None).encode().is_err());
+    }
+
+    #[test]
+    fn test_build_binary_and_large_values() {
+        let builder = DictBuilder::new()
+            .bytes("id", b"\xff\x00")
+            .int("length", 1 << 40)
+            .list("l", |l| l.bytes(b"\x01").int(i64::MIN));
+        assert_eq!(
+            builder.clone().encode().unwrap(),
+            b"d2:id2:\xff\x001:ll1:\x01i-9223372036854775808ee6:lengthi1099511627776ee"
+        );
+        let err = builder.build().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let value = DictBuilder::new().bytes("s", b"ok").int("n", 7).build().unwrap();
+        assert_eq!(value["s"], "ok");
+        assert_eq!(value["n"], BValue::Int(7));
+    }
+}
@@ -0,0 +1,273 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::ops::Deref;
+
+use crate::convert::wrong_kind;
+use crate::{BValue, Encoder, FromBencode, FromBencodeError, Kind, LazyValue, ToBencode};
+
+// Textual forms of binary strings: lowercase hex, and RFC 4648 base32 as
+// used in magnet links (`urn:btih:` with 32 characters for a 20-byte
+// infohash). Base32 is written unpadded; both decoders ignore case.
+// Percent-encoding is for putting them in URL query strings.
+
+const BASE32: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for &b in bytes {
+        out.push(DIGITS[usize::from(b >> 4)] as char);
+        out.push(DIGITS[usize::from(b & 0xf)] as char);
+    }
+    out
+}
+
+/// `None` on an odd length or a character that is not a hex digit.
+pub fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    s.as_bytes()
+        .chunks(2)
+        .map(|pair| Some((hex_digit(pair[0])? << 4) | hex_digit(pair[1])?))
+        .collect()
+}
+
+fn hex_digit(c: u8) -> Option<u8> {
+    (c as char).to_digit(16).map(|d| d as u8)
+}
+
+pub fn to_base32(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let (mut buffer, mut bits) = (0u16, 0);
+    for &b in bytes {
+        buffer = (buffer << 8) | u16::from(b);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32[usize::from((buffer >> bits) & 0x1f)] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32[usize::from((buffer << (5 - bits)) & 0x1f)] as char);
+    }
+    out
+}
+
+/// Accepts padded or unpadded input. `None` on a character outside the
+/// alphabet or a length no encoder produces.
+pub fn from_base32(s: &str) -> Option<Vec<u8>> {
+    let s = s.trim_end_matches('=');
+    if matches!(s.len() % 8, 1 | 3 | 6) {
+        return None;
+    }
+    let mut out = Vec::with_capacity(s.len() * 5 / 8);
+    let (mut buffer, mut bits) = (0u16, 0);
+    for c in s.bytes() {
+        let value = BASE32.iter().position(|&a| a == c.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | value as u16;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+/// URL query encoding: every byte outside the RFC 3986 unreserved set
+/// becomes `%XX`.
+pub(crate) fn percent_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len());
+    for &b in bytes {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+/// The inverse of `percent_encode`, also reading `+` as a space. `None` on
+/// a malformed escape.
+pub(crate) fn percent_decode(s: &str) -> Option<Vec<u8>> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = bytes.get(i + 1..i + 3)?;
+                out.push(hex_digit(hex[0])? << 4 | hex_digit(hex[1])?);
+                i += 3;
+            }
+            b'+' => {
+                out.push(b' ');
+                i += 1;
+            }
+            b => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    Some(out)
+}
+
+impl BValue {
+    /// The string's bytes in hex; `None` for non-strings.
+    pub fn as_hex(&self) -> Option<String> {
+        self.as_bytes().map(to_hex)
+    }
+
+    pub fn as_base32(&self) -> Option<String> {
+        self.as_bytes().map(to_base32)
+    }
+}
+
+/// An owned byte string for binary values such as infohashes and peer
+/// IDs. Unlike `BValue::Str` it need not be UTF-8, and it encodes through
+/// `ToBencode` as a plain byte string. Without a serde layer there is no
+/// `serde_bytes` mapping yet; this type fills that role for now.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Bytes(pub Vec<u8>);
+
+impl Bytes {
+    pub fn from_hex(s: &str) -> Option<Self> {
+        from_hex(s).map(Bytes)
+    }
+
+    pub fn from_base32(s: &str) -> Option<Self> {
+        from_base32(s).map(Bytes)
+    }
+
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.0)
+    }
+
+    pub fn to_base32(&self) -> String {
+        to_base32(&self.0)
+    }
+}
+
+impl Deref for Bytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Bytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Bytes(bytes)
+    }
+}
+
+impl From<&[u8]> for Bytes {
+    fn from(bytes: &[u8]) -> Self {
+        Bytes(bytes.to_vec())
+    }
+}
+
+/// Shown as hex.
+impl fmt::Display for Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl ToBencode for Bytes {
+    fn write_bencode<W: Write>(&self, enc: &mut Encoder<W>) -> io::Result<()> {
+        enc.bytes(&self.0).map(|_| ())
+    }
+}
+
+impl FromBencode for Bytes {
+    fn from_bencode(value: LazyValue<'_>) -> Result<Self, FromBencodeError> {
+        match value.as_bytes() {
+            Some(bytes) => Ok(Bytes::from(bytes)),
+            None => Err(wrong_kind(Kind::Str, value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(to_hex(&[0x00, 0x9f, 0xff]), "009fff");
+        assert_eq!(from_hex("009FfF"), Some(vec![0x00, 0x9f, 0xff]));
+        assert_eq!(from_hex(""), Some(vec![]));
+        assert_eq!(from_hex("abc"), None);
+        assert_eq!(from_hex("zz"), None);
+        assert_eq!(from_hex("+1"), None);
+    }
+
+    #[test]
+    fn test_base32_rfc4648_vectors() {
+        let vectors = [
+            ("", ""),
+            ("f", "MY"),
+            ("fo", "MZXQ"),
+            ("foo", "MZXW6"),
+            ("foob", "MZXW6YQ"),
+            ("fooba", "MZXW6YTB"),
+            ("foobar", "MZXW6YTBOI"),
+        ];
+        for (plain, encoded) in vectors {
+            assert_eq!(to_base32(plain.as_bytes()), encoded);
+            assert_eq!(from_base32(encoded), Some(plain.as_bytes().to_vec()));
+        }
+        assert_eq!(from_base32("mzxw6ytboi======"), Some(b"foobar".to_vec()));
+        assert_eq!(from_base32("MZXW6YT1"), None);
+        assert_eq!(from_base32("M"), None);
+    }
+
+    #[test]
+    fn test_infohash_forms() {
+        let hash = Bytes::from_hex("c12fe1c06bba254a9dc9f519b335aa7c1367a88a").unwrap();
+        assert_eq!(hash.len(), 20);
+        assert_eq!(hash.to_base32(), "YEX6DQDLXISUVHOJ6UM3GNNKPQJWPKEK");
+        assert_eq!(Bytes::from_base32(&hash.to_base32()), Some(hash.clone()));
+        assert_eq!(hash.to_string(), "c12fe1c06bba254a9dc9f519b335aa7c1367a88a");
+
+        let mut encoded = b"20:".to_vec();
+        encoded.extend_from_slice(&hash);
+        assert_eq!(hash.to_bencode().unwrap(), encoded);
+    }
+
+    #[test]
+    fn test_percent_encoding() {
+        let encoded = percent_encode(b"udp://t:80/a b~\xff");
+        assert_eq!(encoded, "udp%3A%2F%2Ft%3A80%2Fa%20b~%FF");
+        assert_eq!(percent_encode(b"AZaz09-._~"), "AZaz09-._~");
+        assert_eq!(
+            percent_decode(&encoded),
+            Some(b"udp://t:80/a b~\xff".to_vec())
+        );
+        assert_eq!(percent_decode("a+b"), Some(b"a b".to_vec()));
+        assert_eq!(percent_decode("%4"), None);
+        assert_eq!(percent_decode("%zz"), None);
+        assert_eq!(percent_decode("%+f"), None);
+        assert_eq!(percent_decode("%-1"), None);
+        assert_eq!(percent_decode("%fF"), Some(vec![0xff]));
+    }
+
+    #[test]
+    fn test_value_helpers() {
+        let id = BValue::Str("-qB4500-".to_string());
+        assert_eq!(id.as_hex().as_deref(), Some("2d7142343530302d"));
+        assert_eq!(id.as_base32().as_deref(), Some("FVYUENBVGAYC2"));
+        assert_eq!(BValue::Int(1).as_hex(), None);
+        assert_eq!(id.decode_as::<Bytes>(), Ok(Bytes(b"-qB4500-".to_vec())));
+
+        assert_eq!(
+            crate::decode_into::<Bytes>(b"2:\xff\xfe"),
+            Ok(Bytes(b"\xff\xfe".to_vec()))
+        );
+    }
+}
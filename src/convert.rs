@@ -0,0 +1,515 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::{BValue, DecodeError, Encoder, Kind, LazyValue};
+
+// Lightweight conversions between user types and bencode, without serde.
+// `ToBencode` writes through an `Encoder`, so integers keep their full
+// range and nothing is built in memory; `FromBencode` reads a `LazyValue`
+// view, so integers are `i64` and strings are bytes until a type asks for
+// something narrower.
+//
+// No serde `Serializer` or `Deserializer` is provided yet, so serde types
+// need a hand-written (or derived) impl of these traits to go through
+// bencode.
+
+/// Writes a value as bencode. This is the crate's stand-in for
+/// `serde::Serialize` until a serde feature exists.
+pub trait ToBencode {
+    fn write_bencode<W: Write>(&self, enc: &mut Encoder<W>) -> io::Result<()>;
+
+    fn to_bencode(&self) -> io::Result<Vec<u8>> {
+        let mut enc = Encoder::new(Vec::new());
+        self.write_bencode(&mut enc)?;
+        enc.finish()
+    }
+}
+
+/// Reads a value from bencode. Serde's `Deserialize` is not supported yet;
+/// `#[derive(FromBencode)]`, behind the `derive` feature, covers typed
+/// structs in the meantime.
+pub trait FromBencode: Sized {
+    fn from_bencode(value: LazyValue<'_>) -> Result<Self, FromBencodeError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FromBencodeError {
+    WrongKind { expected: Kind, found: Kind },
+    /// An integer that does not fit the target type.
+    OutOfRange,
+    /// A required dict entry is absent.
+    MissingKey(String),
+    /// A string or dict key read into a `String` is not UTF-8.
+    InvalidUtf8,
+    /// `decode_as` was called on a tree holding `BValue::None` or
+    /// `BValue::External`, which have no encoded form.
+    Unencodable,
+    /// The input was not valid bencode; see `decode_into`.
+    Decode(DecodeError),
+}
+
+impl fmt::Display for FromBencodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FromBencodeError::WrongKind { expected, found } => {
+                write!(f, "expected {:?}, found {:?}", expected, found)
+            }
+            FromBencodeError::OutOfRange => write!(f, "integer out of range"),
+            FromBencodeError::MissingKey(key) => write!(f, "missing key {:?}", key),
+            FromBencodeError::InvalidUtf8 => write!(f, "string is not valid UTF-8"),
+            FromBencodeError::Unencodable => write!(f, "value has no encoded form"),
+            FromBencodeError::Decode(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for FromBencodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FromBencodeError::Decode(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<DecodeError> for FromBencodeError {
+    fn from(e: DecodeError) -> Self {
+        FromBencodeError::Decode(e)
+    }
+}
+
+/// Decodes `input` as exactly one value and converts it into `T`, with
+/// decoding and conversion failures reported through the same error.
+pub fn decode_into<T: FromBencode>(input: &[u8]) -> Result<T, FromBencodeError> {
+    let (value, rest) = LazyValue::parse(input)?;
+    if !rest.is_empty() {
+        return Err(DecodeError::TrailingData {
+            offset: input.len() - rest.len(),
+        }
+        .into());
+    }
+    T::from_bencode(value)
+}
+
+impl BValue {
+    /// Converts this value into `T` by way of its encoding.
+    pub fn decode_as<T: FromBencode>(&self) -> Result<T, FromBencodeError> {
+        let bytes = crate::encode(self).map_err(|_e| FromBencodeError::Unencodable)?;
+        decode_into(&bytes)
+    }
+}
+
+pub(crate) fn wrong_kind(expected: Kind, value: LazyValue<'_>) -> FromBencodeError {
+    FromBencodeError::WrongKind {
+        expected,
+        found: value.kind(),
+    }
+}
+
+impl ToBencode for BValue {
+    fn write_bencode<W: Write>(&self, enc: &mut Encoder<W>) -> io::Result<()> {
+        enc.value(self).map(|_| ())
+    }
+}
+
+/// Fails where `decode` would, on non-UTF-8 strings and integers outside
+/// `i16`.
+impl FromBencode for BValue {
+    fn from_bencode(value: LazyValue<'_>) -> Result<Self, FromBencodeError> {
+        Ok(value.to_value()?)
+    }
+}
+
+macro_rules! integer_impls {
+    ($($t:ty),*) => {$(
+        impl ToBencode for $t {
+            fn write_bencode<W: Write>(&self, enc: &mut Encoder<W>) -> io::Result<()> {
+                let n = i64::try_from(*self).map_err(|_e| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "integer out of range")
+                })?;
+                enc.int(n).map(|_| ())
+            }
+        }
+
+        impl FromBencode for $t {
+            fn from_bencode(value: LazyValue<'_>) -> Result<Self, FromBencodeError> {
+                match value.as_int() {
+                    Some(n) => <$t>::try_from(n).map_err(|_e| FromBencodeError::OutOfRange),
+                    None => Err(wrong_kind(Kind::Int, value)),
+                }
+            }
+        }
+    )*};
+}
+
+integer_impls!(i16, i32, i64, u8, u16, u32, u64, usize);
+
+impl ToBencode for str {
+    fn write_bencode<W: Write>(&self, enc: &mut Encoder<W>) -> io::Result<()> {
+        enc.str(self).map(|_| ())
+    }
+}
+
+impl ToBencode for String {
+    fn write_bencode<W: Write>(&self, enc: &mut Encoder<W>) -> io::Result<()> {
+        self.as_str().write_bencode(enc)
+    }
+}
+
+impl FromBencode for String {
+    fn from_bencode(value: LazyValue<'_>) -> Result<Self, FromBencodeError> {
+        match value.as_bytes() {
+            Some(bytes) => utf8_key(bytes),
+            None => Err(wrong_kind(Kind::Str, value)),
+        }
+    }
+}
+
+fn utf8_key(bytes: &[u8]) -> Result<String, FromBencodeError> {
+    String::from_utf8(bytes.to_vec()).map_err(|_e| FromBencodeError::InvalidUtf8)
+}
+
+impl<T: ToBencode + ?Sized> ToBencode for &T {
+    fn write_bencode<W: Write>(&self, enc: &mut Encoder<W>) -> io::Result<()> {
+        (**self).write_bencode(enc)
+    }
+}
+
+impl<T: ToBencode> ToBencode for [T] {
+    fn write_bencode<W: Write>(&self, enc: &mut Encoder<W>) -> io::Result<()> {
+        enc.begin_list()?;
+        for item in self {
+            item.write_bencode(enc)?;
+        }
+        enc.end().map(|_| ())
+    }
+}
+
+impl<T: ToBencode> ToBencode for Vec<T> {
+    fn write_bencode<W: Write>(&self, enc: &mut Encoder<W>) -> io::Result<()> {
+        self.as_slice().write_bencode(enc)
+    }
+}
+
+impl<T: FromBencode> FromBencode for Vec<T> {
+    fn from_bencode(value: LazyValue<'_>) -> Result<Self, FromBencodeError> {
+        match value.kind() {
+            Kind::List => value.items().map(T::from_bencode).collect(),
+            _ => Err(wrong_kind(Kind::List, value)),
+        }
+    }
+}
+
+// Entries are written in sorted key order, as the encoder requires.
+fn write_entries<'a, W, T, I>(entries: I, enc: &mut Encoder<W>) -> io::Result<()>
+where
+    W: Write,
+    T: ToBencode + 'a,
+    I: Iterator<Item = (&'a String, &'a T)>,
+{
+    enc.begin_dict()?;
+    for (key, value) in entries {
+        enc.key(key.as_bytes())?;
+        value.write_bencode(enc)?;
+    }
+    enc.end().map(|_| ())
+}
+
+impl<T: ToBencode> ToBencode for BTreeMap<String, T> {
+    fn write_bencode<W: Write>(&self, enc: &mut Encoder<W>) -> io::Result<()> {
+        write_entries(self.iter(), enc)
+    }
+}
+
+impl<T: ToBencode> ToBencode for HashMap<String, T> {
+    fn write_bencode<W: Write>(&self, enc: &mut Encoder<W>) -> io::Result<()> {
+        let mut entries: Vec<_> = self.iter().collect();
+        entries.sort_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+        write_entries(entries.into_iter(), enc)
+    }
+}
+
+// Dict keys become `String`s, so they must be UTF-8.
+fn read_entries<T, C>(value: LazyValue<'_>) -> Result<C, FromBencodeError>
+where
+    T: FromBencode,
+    C: FromIterator<(String, T)>,
+{
+    match value.kind() {
+        Kind::Dict => value
+            .entries()
+            .map(|(k, v)| Ok((utf8_key(k)?, T::from_bencode(v)?)))
+            .collect(),
+        _ => Err(wrong_kind(Kind::Dict, value)),
+    }
+}
+
+impl<T: FromBencode> FromBencode for BTreeMap<String, T> {
+    fn from_bencode(value: LazyValue<'_>) -> Result<Self, FromBencodeError> {
+        read_entries(value)
+    }
+}
+
+impl<T: FromBencode> FromBencode for HashMap<String, T> {
+    fn from_bencode(value: LazyValue<'_>) -> Result<Self, FromBencodeError> {
+        read_entries(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode_exact;
+
+    #[derive(Debug, PartialEq)]
+    struct File {
+        length: u32,
+        path: Vec<String>,
+    }
+
+    impl ToBencode for File {
+        fn write_bencode<W: Write>(&self, enc: &mut Encoder<W>) -> io::Result<()> {
+            enc.begin_dict()?.key(b"length")?;
+            self.length.write_bencode(enc)?;
+            enc.key(b"path")?;
+            self.path.write_bencode(enc)?;
+            enc.end().map(|_| ())
+        }
+    }
+
+    impl FromBencode for File {
+        fn from_bencode(value: LazyValue<'_>) -> Result<Self, FromBencodeError> {
+            if value.kind() != Kind::Dict {
+                return Err(wrong_kind(Kind::Dict, value));
+            }
+            let field = |key: &str| {
+                value
+                    .get(key)
+                    .ok_or_else(|| FromBencodeError::MissingKey(key.to_string()))
+            };
+            Ok(File {
+                length: FromBencode::from_bencode(field("length")?)?,
+                path: FromBencode::from_bencode(field("path")?)?,
+            })
+        }
+    }
+
+    #[test]
+    fn test_user_type_round_trip() {
+        let files = vec![
+            File {
+                length: 42,
+                path: vec!["a".to_string(), "b".to_string()],
+            },
+            File {
+                length: 7,
+                path: vec![],
+            },
+        ];
+        let bytes = files.to_bencode().unwrap();
+        assert_eq!(bytes, b"ld6:lengthi42e4:pathl1:a1:beed6:lengthi7e4:pathleee");
+        assert_eq!(decode_exact(&bytes).unwrap().decode_as::<Vec<File>>(), Ok(files));
+
+        let value = decode_exact(b"d6:lengthi1ee").unwrap();
+        assert_eq!(
+            value.decode_as::<File>(),
+            Err(FromBencodeError::MissingKey("path".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_decode_into() {
+        assert_eq!(decode_into::<Vec<u8>>(b"li1ei2ee"), Ok(vec![1, 2]));
+        assert_eq!(
+            decode_into::<Vec<u8>>(b"li1e"),
+            Err(FromBencodeError::Decode(DecodeError::UnexpectedEof { offset: 4 }))
+        );
+        assert_eq!(
+            decode_into::<Vec<u8>>(b"li-1ee"),
+            Err(FromBencodeError::OutOfRange)
+        );
+        assert_eq!(
+            decode_into::<u8>(b"i1e1:x"),
+            Err(FromBencodeError::Decode(DecodeError::TrailingData { offset: 3 }))
+        );
+    }
+
+    #[test]
+    fn test_large_ints_and_binary_strings() {
+        // Neither fits the decoded tree (`i16`, UTF-8 strings).
+        assert_eq!(decode_into::<u64>(b"i100000e"), Ok(100_000));
+        assert_eq!(decode_into::<i64>(b"i-9223372036854775808e"), Ok(i64::MIN));
+        assert_eq!(
+            decode_into::<BTreeMap<String, u64>>(b"d6:lengthi4294967296ee"),
+            Ok([("length".to_string(), 1 << 32)].into_iter().collect())
+        );
+        assert_eq!(decode_into::<String>(b"2:\xff\xfe"), Err(FromBencodeError::InvalidUtf8));
+        assert_eq!(
+            decode_into::<BTreeMap<String, u8>>(b"d1:\xffi1ee"),
+            Err(FromBencodeError::InvalidUtf8)
+        );
+
+        let value = BValue::List(vec![BValue::None]);
+        assert_eq!(value.decode_as::<Vec<u8>>(), Err(FromBencodeError::Unencodable));
+    }
+
+    #[test]
+    fn test_primitives_and_maps() {
+        let mut map = HashMap::new();
+        map.insert("b".to_string(), -1i64);
+        map.insert("a".to_string(), i64::MAX);
+        assert_eq!(
+            map.to_bencode().unwrap(),
+            b"d1:ai9223372036854775807e1:bi-1ee"
+        );
+        assert!(u64::MAX.to_bencode().is_err());
+
+        let value = decode_exact(b"d1:ai-1ee").unwrap();
+        let map: BTreeMap<String, i32> = value.decode_as().unwrap();
+        assert_eq!(map["a"], -1);
+        assert_eq!(
+            value.decode_as::<BTreeMap<String, u8>>(),
+            Err(FromBencodeError::OutOfRange)
+        );
+        assert_eq!(
+            value.decode_as::<String>(),
+            Err(FromBencodeError::WrongKind {
+                expected: Kind::Str,
+                found: Kind::Dict
+            })
+        );
+        assert_eq!(BValue::Int(3).to_bencode().unwrap(), b"i3e");
+    }
+    #[cfg(feature = "derive")]
+    mod derive {
+        use crate::{Bytes, FromBencode, ToBencode, decode_exact, decode_into};
+
+        #[derive(Debug, PartialEq, ToBencode, FromBencode)]
+        struct Torrent {
+            announce: String,
+            #[bencode(rename = "creation date")]
+            creation_date: Option<i64>,
+            /// Doc comments and other attributes are ignored.
+            #[bencode(default)]
+            private: u8,
+            #[bencode(default = "default_tiers")]
+            tiers: Vec<Vec<String>>,
+            r#type: std::collections::BTreeMap<String, i32>,
+        }
+
+        #[derive(Debug, PartialEq, ToBencode, FromBencode)]
+        struct Info {
+            name: String,
+            #[bencode(rename = "piece length")]
+            piece_length: Option<u32>,
+            #[bencode(flatten)]
+            extra: std::collections::HashMap<String, crate::BValue>,
+        }
+
+        #[derive(Debug, PartialEq, ToBencode, FromBencode)]
+        struct File {
+            length: u64,
+            #[bencode(rename = "creation date")]
+            creation_date: Option<i64>,
+            pieces: Bytes,
+        }
+
+        #[derive(Debug, PartialEq, ToBencode, FromBencode)]
+        struct Named {
+            #[bencode(rename = "name.utf-8", alias = "name", alias = "Name")]
+            name: String,
+            #[bencode(flatten)]
+            extra: std::collections::HashMap<String, crate::BValue>,
+        }
+
+        fn default_tiers() -> Vec<Vec<String>> {
+            vec![vec!["udp://fallback".to_string()]]
+        }
+
+        #[test]
+        fn test_derived_round_trip() {
+            let torrent = Torrent {
+                announce: "http://t".to_string(),
+                creation_date: Some(7),
+                private: 1,
+                tiers: vec![],
+                r#type: [("a".to_string(), -1)].into_iter().collect(),
+            };
+            let bytes = torrent.to_bencode().unwrap();
+            assert_eq!(
+                bytes,
+                b"d8:announce8:http://t13:creation datei7e7:privatei1e5:tiersle4:typed1:ai-1eee"
+            );
+            assert_eq!(decode_exact(&bytes).unwrap().decode_as::<Torrent>(), Ok(torrent));
+        }
+
+        #[test]
+        fn test_derived_defaults_and_missing_keys() {
+            let torrent: Torrent = decode_into(b"d8:announce1:x4:typedee").unwrap();
+            assert_eq!(torrent.creation_date, None);
+            assert_eq!(torrent.private, 0);
+            assert_eq!(torrent.tiers, default_tiers());
+            assert_eq!(torrent.to_bencode().unwrap(), b"d8:announce1:x7:privatei0e5:tiersll14:udp://fallbackee4:typedee");
+
+            let value = decode_exact(b"d8:announce1:xe").unwrap();
+            assert_eq!(
+                value.decode_as::<Torrent>(),
+                Err(crate::FromBencodeError::MissingKey("type".to_string()))
+            );
+        }
+
+        #[test]
+        fn test_derived_large_and_binary_fields() {
+            let input: &[u8] =
+                b"d13:creation datei1700000000e6:lengthi8589934592e6:pieces2:\xff\x00e";
+            let file: File = decode_into(input).unwrap();
+            assert_eq!(
+                file,
+                File {
+                    length: 1 << 33,
+                    creation_date: Some(1_700_000_000),
+                    pieces: Bytes::from(&b"\xff\x00"[..]),
+                }
+            );
+            assert_eq!(file.to_bencode().unwrap(), input);
+        }
+
+        #[test]
+        fn test_aliases_are_read_in_order() {
+            let named: Named = decode_into(b"d4:Name1:c4:name1:b10:name.utf-81:ae").unwrap();
+            assert_eq!(named.name, "a");
+            // Aliases are known keys, so they are not collected as extras.
+            assert!(named.extra.is_empty());
+            assert_eq!(named.to_bencode().unwrap(), b"d10:name.utf-81:ae");
+
+            let named: Named = decode_into(b"d4:Name1:c4:name1:be").unwrap();
+            assert_eq!(named.name, "b");
+            let named: Named = decode_into(b"d4:Name1:ce").unwrap();
+            assert_eq!(named.name, "c");
+            assert_eq!(
+                decode_into::<Named>(b"de"),
+                Err(crate::FromBencodeError::MissingKey("name.utf-8".to_string()))
+            );
+        }
+
+        #[test]
+        fn test_flatten_preserves_unknown_keys() {
+            let input = b"d1:ai1e4:name1:n12:piece lengthi16e12:x_cross_seed2:abe";
+            let info: Info = decode_exact(input).unwrap().decode_as().unwrap();
+            assert_eq!(info.piece_length, Some(16));
+            assert_eq!(info.extra.len(), 2);
+            assert_eq!(info.extra["x_cross_seed"], crate::BValue::Str("ab".to_string()));
+            assert_eq!(info.to_bencode().unwrap(), input);
+
+            // Known fields take precedence over a clashing extra entry.
+            let mut info = info;
+            info.piece_length = None;
+            info.extra.insert("name".to_string(), crate::BValue::Int(0));
+            assert_eq!(
+                info.to_bencode().unwrap(),
+                b"d1:ai1e4:name1:n12:x_cross_seed2:abe"
+            );
+        }
+    }
+}
@@ -0,0 +1,696 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::hashing::PieceHasher;
+use crate::{Digest, Encoder, Sha256, TorrentVersion};
+
+// Creating .torrent files from disk. v1 pieces run across file boundaries
+// as BEP 3 requires, so files are streamed through one hasher in torrent
+// order. v2 (BEP 52) hashes each file on its own: SHA-256 of every 16 KiB
+// block, combined into a merkle tree whose root goes in the `file tree`
+// and whose piece-sized layer goes in `piece layers`. A hybrid carries
+// both, with BEP 47 padding files so every v1 file starts on a piece
+// boundary. Everything is read once, feeding all the hashers in the same
+// pass. The result is written with `Encoder` rather than built as a
+// `BValue`: hashes are binary and lengths do not fit in `i16`.
+
+const MIN_PIECE_LENGTH: u64 = 16 * 1024;
+const MAX_PIECE_LENGTH: u64 = 16 * 1024 * 1024;
+const BLOCK_SIZE: u64 = 16 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct TorrentBuilder {
+    path: PathBuf,
+    name: Option<String>,
+    piece_length: Option<u64>,
+    version: TorrentVersion,
+    tiers: Vec<Vec<String>>,
+    comment: Option<String>,
+    created_by: Option<String>,
+    creation_date: Option<SystemTime>,
+    private: bool,
+}
+
+/// A file to hash: its path inside the torrent and on disk.
+struct Source {
+    path: Vec<String>,
+    disk: PathBuf,
+}
+
+/// What hashing the sources produced.
+#[derive(Default)]
+struct Hashes {
+    /// Concatenated SHA-1 piece hashes; empty for v2 only.
+    pieces: Vec<u8>,
+    /// Bytes read per file.
+    lengths: Vec<u64>,
+    /// Zeros hashed after each file to align the next one; hybrid only.
+    padding: Vec<u64>,
+    /// Merkle root per file, `None` for empty files; v2 and hybrid only.
+    roots: Vec<Option<[u8; 32]>>,
+    /// Piece layers of files longer than one piece, keyed by root.
+    layers: BTreeMap<[u8; 32], Vec<u8>>,
+}
+
+/// A directory level of the v2 `file tree`, indexing into the sources.
+#[derive(Default)]
+struct Tree<'a> {
+    dirs: BTreeMap<&'a str, Tree<'a>>,
+    files: BTreeMap<&'a str, usize>,
+}
+
+impl TorrentBuilder {
+    /// A torrent of the file or directory at `path`. A directory becomes a
+    /// multi-file torrent of every regular file below it, in sorted order;
+    /// symlinks are skipped.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        TorrentBuilder {
+            path: path.into(),
+            name: None,
+            piece_length: None,
+            version: TorrentVersion::V1,
+            tiers: Vec::new(),
+            comment: None,
+            created_by: None,
+            creation_date: None,
+            private: false,
+        }
+    }
+
+    /// Overrides the name, which defaults to the last component of the
+    /// canonical path, so `.` is named after the current directory.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Fixes the piece length instead of choosing it from the total size.
+    /// v2 and hybrid torrents need a power of two of at least 16 KiB.
+    pub fn piece_length(mut self, piece_length: u64) -> Self {
+        self.piece_length = Some(piece_length);
+        self
+    }
+
+    /// Which layouts to write; v1 by default. v2 and hybrid hash with
+    /// SHA-256, so this needs the `sha256` feature.
+    #[cfg(feature = "sha256")]
+    pub fn version(mut self, version: TorrentVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Adds a tier holding one tracker.
+    pub fn tracker(self, url: impl Into<String>) -> Self {
+        self.tier(vec![url.into()])
+    }
+
+    /// Adds a tier of trackers tried after those already added (BEP 12).
+    pub fn tier(mut self, urls: Vec<String>) -> Self {
+        if !urls.is_empty() {
+            self.tiers.push(urls);
+        }
+        self
+    }
+
+    pub fn comment(mut self, comment: impl Into<String>) -> Self {
+        self.comment = Some(comment.into());
+        self
+    }
+
+    pub fn created_by(mut self, created_by: impl Into<String>) -> Self {
+        self.created_by = Some(created_by.into());
+        self
+    }
+
+    pub fn creation_date(mut self, date: SystemTime) -> Self {
+        self.creation_date = Some(date);
+        self
+    }
+
+    /// Sets the BEP 27 `private` flag.
+    pub fn private(mut self, private: bool) -> Self {
+        self.private = private;
+        self
+    }
+
+    /// A power of two between 16 KiB and 16 MiB giving at most about 1500
+    /// pieces.
+    pub fn auto_piece_length(total_length: u64) -> u64 {
+        total_length
+            .div_ceil(1500)
+            .next_power_of_two()
+            .clamp(MIN_PIECE_LENGTH, MAX_PIECE_LENGTH)
+    }
+
+    /// Reads and hashes the files and returns the encoded torrent.
+    pub fn build(&self) -> io::Result<Vec<u8>> {
+        let single = fs::metadata(&self.path)?.is_file();
+        let name = match &self.name {
+            Some(name) => name.clone(),
+            None => {
+                let path = fs::canonicalize(&self.path)?;
+                let name = path.file_name().ok_or_else(|| invalid("path has no name to use"))?;
+                utf8(name)?
+            }
+        };
+        if name.is_empty() {
+            return Err(invalid("name must not be empty"));
+        }
+        let sources = if single {
+            vec![Source {
+                path: vec![name.clone()],
+                disk: self.path.clone(),
+            }]
+        } else {
+            let mut sources = Vec::new();
+            collect(&self.path, &mut Vec::new(), &mut sources)?;
+            sources
+        };
+        if sources.is_empty() {
+            return Err(invalid("no files to add"));
+        }
+
+        let total = sources
+            .iter()
+            .map(|source| fs::metadata(&source.disk).map(|m| m.len()))
+            .sum::<io::Result<u64>>()?;
+        let piece_length = self
+            .piece_length
+            .unwrap_or_else(|| Self::auto_piece_length(total));
+        if piece_length == 0 {
+            return Err(invalid("piece length must be positive"));
+        }
+        let (v1, v2) = match self.version {
+            TorrentVersion::V1 => (true, false),
+            TorrentVersion::V2 => (false, true),
+            TorrentVersion::Hybrid => (true, true),
+        };
+        if v2 && !(piece_length >= BLOCK_SIZE && piece_length.is_power_of_two()) {
+            return Err(invalid(
+                "v2 piece length must be a power of two of at least 16 KiB",
+            ));
+        }
+        let hashes = hash_files(&sources, piece_length, v1, v2)?;
+
+        let mut enc = Encoder::new(Vec::new());
+        enc.begin_dict()?;
+        if let Some(url) = self.tiers.first().and_then(|tier| tier.first()) {
+            enc.key(b"announce")?.str(url)?;
+        }
+        if self.tiers.iter().map(Vec::len).sum::<usize>() > 1 {
+            enc.key(b"announce-list")?.begin_list()?;
+            for tier in &self.tiers {
+                enc.begin_list()?;
+                for url in tier {
+                    enc.str(url)?;
+                }
+                enc.end()?;
+            }
+            enc.end()?;
+        }
+        if let Some(comment) = &self.comment {
+            enc.key(b"comment")?.str(comment)?;
+        }
+        if let Some(created_by) = &self.created_by {
+            enc.key(b"created by")?.str(created_by)?;
+        }
+        if let Some(date) = self.creation_date {
+            let secs = match date.duration_since(UNIX_EPOCH) {
+                Ok(after) => int(after.as_secs())?,
+                Err(before) => -int(before.duration().as_secs())?,
+            };
+            enc.key(b"creation date")?.int(secs)?;
+        }
+
+        enc.key(b"info")?.begin_dict()?;
+        if v2 {
+            let mut tree = Tree::default();
+            for (index, source) in sources.iter().enumerate() {
+                tree.insert(&source.path, index);
+            }
+            enc.key(b"file tree")?;
+            tree.write(&mut enc, &hashes)?;
+        }
+        if v1 && single {
+            enc.key(b"length")?.int(int(hashes.lengths[0])?)?;
+        } else if v1 {
+            enc.key(b"files")?.begin_list()?;
+            for (i, source) in sources.iter().enumerate() {
+                enc.begin_dict()?
+                    .key(b"length")?
+                    .int(int(hashes.lengths[i])?)?;
+                enc.key(b"path")?.begin_list()?;
+                for part in &source.path {
+                    enc.str(part)?;
+                }
+                enc.end()?.end()?;
+                let pad = hashes.padding.get(i).copied().unwrap_or(0);
+                if pad > 0 {
+                    enc.begin_dict()?.key(b"attr")?.str("p")?;
+                    enc.key(b"length")?.int(int(pad)?)?;
+                    enc.key(b"path")?
+                        .begin_list()?
+                        .str(".pad")?
+                        .str(&pad.to_string())?;
+                    enc.end()?.end()?;
+                }
+            }
+            enc.end()?;
+        }
+        if v2 {
+            enc.key(b"meta version")?.int(2)?;
+        }
+        enc.key(b"name")?.str(&name)?;
+        enc.key(b"piece length")?.int(int(piece_length)?)?;
+        if v1 {
+            enc.key(b"pieces")?.bytes(&hashes.pieces)?;
+        }
+        if self.private {
+            enc.key(b"private")?.int(1)?;
+        }
+        enc.end()?;
+
+        if !hashes.layers.is_empty() {
+            enc.key(b"piece layers")?.begin_dict()?;
+            for (root, layer) in &hashes.layers {
+                enc.key(root)?.bytes(layer)?;
+            }
+            enc.end()?;
+        }
+        enc.end()?;
+        enc.finish()
+    }
+}
+
+impl<'a> Tree<'a> {
+    fn insert(&mut self, path: &'a [String], index: usize) {
+        match path {
+            [file] => {
+                self.files.insert(file, index);
+            }
+            [dir, rest @ ..] => self.dirs.entry(dir).or_default().insert(rest, index),
+            [] => {}
+        }
+    }
+
+    /// Writes this level as a dict; a file is a dict holding one entry
+    /// under the empty key.
+    fn write<W: Write>(&self, enc: &mut Encoder<W>, hashes: &Hashes) -> io::Result<()> {
+        let mut names: Vec<&str> = self.dirs.keys().chain(self.files.keys()).copied().collect();
+        names.sort_unstable();
+        enc.begin_dict()?;
+        for name in names {
+            enc.key(name.as_bytes())?;
+            if let Some(dir) = self.dirs.get(name) {
+                dir.write(enc, hashes)?;
+                continue;
+            }
+            let index = self.files[name];
+            enc.begin_dict()?.key(b"")?.begin_dict()?;
+            enc.key(b"length")?.int(int(hashes.lengths[index])?)?;
+            if let Some(root) = &hashes.roots[index] {
+                enc.key(b"pieces root")?.bytes(root)?;
+            }
+            enc.end()?.end()?;
+        }
+        enc.end().map(|_| ())
+    }
+}
+
+/// Adds the regular files below `dir` in sorted order.
+fn collect(dir: &Path, prefix: &mut Vec<String>, out: &mut Vec<Source>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let kind = entry.file_type()?;
+        prefix.push(utf8(&entry.file_name())?);
+        if kind.is_dir() {
+            collect(&entry.path(), prefix, out)?;
+        } else if kind.is_file() {
+            out.push(Source {
+                path: prefix.clone(),
+                disk: entry.path(),
+            });
+        }
+        prefix.pop();
+    }
+    Ok(())
+}
+
+/// Reads every file once, hashing v1 pieces across files and v2 blocks
+/// per file as asked.
+fn hash_files(sources: &[Source], piece_length: u64, v1: bool, v2: bool) -> io::Result<Hashes> {
+    let mut hashes = Hashes::default();
+    let mut pieces = PieceHasher::new(piece_length);
+    let mut buf = vec![0; 64 * 1024];
+
+    for (i, source) in sources.iter().enumerate() {
+        let mut file = File::open(&source.disk)?;
+        let mut blocks = Blocks::default();
+        let mut length = 0;
+        loop {
+            let n = file.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            length += n as u64;
+            if v1 {
+                hash_pieces(&mut pieces, &buf[..n], &mut hashes.pieces);
+            }
+            if v2 {
+                blocks.feed(&buf[..n]);
+            }
+        }
+        hashes.lengths.push(length);
+
+        if v2 {
+            let leaves = blocks.finish();
+            let root = (!leaves.is_empty())
+                .then(|| merkle_root(leaves.clone(), leaves.len().next_power_of_two()));
+            if let Some(root) = root
+                && length > piece_length
+            {
+                let per_piece = (piece_length / BLOCK_SIZE) as usize;
+                let layer = leaves
+                    .chunks(per_piece)
+                    .flat_map(|piece| merkle_root(piece.to_vec(), per_piece))
+                    .collect();
+                hashes.layers.insert(root, layer);
+            }
+            hashes.roots.push(root);
+        }
+        // In a hybrid every file but the last is padded out to a piece.
+        if v1 && v2 && i + 1 < sources.len() && pieces.in_piece() > 0 {
+            let pad = piece_length - pieces.in_piece();
+            buf.fill(0);
+            let mut left = pad;
+            while left > 0 {
+                let take = left.min(buf.len() as u64) as usize;
+                hash_pieces(&mut pieces, &buf[..take], &mut hashes.pieces);
+                left -= take as u64;
+            }
+            hashes.padding.push(pad);
+        } else {
+            hashes.padding.push(0);
+        }
+    }
+    hashes.pieces.extend(pieces.finish().into_iter().flatten());
+    Ok(hashes)
+}
+
+fn hash_pieces(pieces: &mut PieceHasher, mut data: &[u8], out: &mut Vec<u8>) {
+    while !data.is_empty() {
+        let (taken, done) = pieces.update(data);
+        data = &data[taken..];
+        out.extend(done.into_iter().flatten());
+    }
+}
+
+/// SHA-256 of each 16 KiB block of one file; the last may be short.
+#[derive(Default)]
+struct Blocks {
+    hasher: Sha256,
+    in_block: u64,
+    out: Vec<[u8; 32]>,
+}
+
+impl Blocks {
+    fn feed(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            let take = data.len().min((BLOCK_SIZE - self.in_block) as usize);
+            self.hasher.update(&data[..take]);
+            self.in_block += take as u64;
+            data = &data[take..];
+            if self.in_block == BLOCK_SIZE {
+                self.out.push(std::mem::take(&mut self.hasher).finish());
+                self.in_block = 0;
+            }
+        }
+    }
+
+    fn finish(mut self) -> Vec<[u8; 32]> {
+        if self.in_block > 0 {
+            self.out.push(self.hasher.finish());
+        }
+        self.out
+    }
+}
+
+/// Root of a merkle tree over `width` leaves, a power of two, of which
+/// those past `leaves` are zero.
+fn merkle_root(mut layer: Vec<[u8; 32]>, mut width: usize) -> [u8; 32] {
+    let mut pad = [0; 32];
+    while width > 1 {
+        if layer.len() % 2 == 1 {
+            layer.push(pad);
+        }
+        layer = layer
+            .chunks(2)
+            .map(|pair| hash_pair(&pair[0], &pair[1]))
+            .collect();
+        pad = hash_pair(&pad, &pad);
+        width /= 2;
+    }
+    layer.first().copied().unwrap_or(pad)
+}
+
+fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finish()
+}
+
+fn utf8(name: &std::ffi::OsStr) -> io::Result<String> {
+    name.to_str()
+        .map(str::to_string)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "file name is not UTF-8"))
+}
+
+fn int(n: u64) -> io::Result<i64> {
+    i64::try_from(n).map_err(|_| invalid("integer does not fit in i64"))
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::temp_path;
+    use crate::{FileEntry, Files, MetaInfo, sha1};
+    #[cfg(feature = "sha256")]
+    use crate::{TreeEntry, sha256};
+
+    #[test]
+    fn test_multi_file_pieces_span_files() {
+        let root = temp_path("create-multi");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("a"), b"abcdef").unwrap();
+        fs::write(root.join("sub").join("b"), b"gh").unwrap();
+
+        let built = TorrentBuilder::new(&root)
+            .name("root")
+            .piece_length(4)
+            .tracker("udp://t/1")
+            .tier(vec!["udp://t/2".to_string(), "udp://t/3".to_string()])
+            .comment("test")
+            .creation_date(UNIX_EPOCH + std::time::Duration::from_secs(1))
+            .private(true)
+            .build();
+        fs::remove_dir_all(&root).unwrap();
+
+        let meta = MetaInfo::from_bytes(&built.unwrap()).unwrap();
+        assert_eq!(meta.announce.as_deref(), Some("udp://t/1"));
+        assert_eq!(
+            meta.tiers(),
+            [vec!["udp://t/1"], vec!["udp://t/2", "udp://t/3"]]
+        );
+        assert_eq!(meta.comment.as_deref(), Some("test"));
+        assert!(meta.is_private());
+        assert_eq!(meta.info.name, "root");
+        assert_eq!(
+            meta.info.files,
+            Some(Files::Multi(vec![
+                FileEntry {
+                    length: 6,
+                    path: vec!["a".to_string()],
+                    attr: None,
+                    sha1: None,
+                    symlink_path: None,
+                },
+                FileEntry {
+                    length: 2,
+                    path: vec!["sub".to_string(), "b".to_string()],
+                    attr: None,
+                    sha1: None,
+                    symlink_path: None,
+                },
+            ]))
+        );
+        assert_eq!(meta.info.pieces, [sha1(b"abcd"), sha1(b"efgh")]);
+    }
+
+    #[test]
+    fn test_single_file() {
+        let path = temp_path("create-single.bin");
+        fs::write(&path, vec![7; 20000]).unwrap();
+        let built = TorrentBuilder::new(&path).build();
+        fs::remove_file(&path).unwrap();
+
+        let meta = MetaInfo::from_bytes(&built.unwrap()).unwrap();
+        assert_eq!(meta.announce, None);
+        assert_eq!(meta.info.name, path.file_name().unwrap().to_str().unwrap());
+        assert_eq!(meta.info.files, Some(Files::Single { length: 20000 }));
+        assert_eq!(meta.info.piece_length, 16384);
+        assert_eq!(
+            meta.info.pieces,
+            [sha1(&[7; 16384]), sha1(&[7; 20000 - 16384])]
+        );
+    }
+
+    #[cfg(feature = "sha256")]
+    #[test]
+    fn test_hybrid() {
+        let root = temp_path("create-hybrid");
+        fs::create_dir_all(&root).unwrap();
+        let data: Vec<u8> = (0..40000).map(|i| (i % 251) as u8).collect();
+        fs::write(root.join("a"), &data).unwrap();
+        fs::write(root.join("b"), b"12345").unwrap();
+        fs::write(root.join("c"), b"").unwrap();
+
+        let built = TorrentBuilder::new(&root)
+            .name("root")
+            .piece_length(16384)
+            .version(TorrentVersion::Hybrid)
+            .build();
+        fs::remove_dir_all(&root).unwrap();
+
+        let meta = MetaInfo::from_bytes(&built.unwrap()).unwrap();
+        assert_eq!(meta.info.version(), TorrentVersion::Hybrid);
+        assert_eq!(meta.validate(), Ok(()));
+
+        // One block per piece, so the piece layer holds the block hashes.
+        let blocks: Vec<[u8; 32]> = data.chunks(16384).map(sha256).collect();
+        let a_root = hash_pair(
+            &hash_pair(&blocks[0], &blocks[1]),
+            &hash_pair(&blocks[2], &[0; 32]),
+        );
+        assert_eq!(
+            meta.info.file_tree,
+            [
+                TreeEntry {
+                    path: vec!["a".to_string()],
+                    length: 40000,
+                    pieces_root: Some(a_root),
+                },
+                TreeEntry {
+                    path: vec!["b".to_string()],
+                    length: 5,
+                    pieces_root: Some(sha256(b"12345")),
+                },
+                TreeEntry {
+                    path: vec!["c".to_string()],
+                    length: 0,
+                    pieces_root: None,
+                },
+            ]
+        );
+        assert_eq!(meta.piece_layers[&a_root], blocks);
+
+        // v1 files are padded to piece boundaries, except the last.
+        let Some(Files::Multi(files)) = &meta.info.files else {
+            panic!()
+        };
+        let lengths: Vec<_> = files.iter().map(|f| (f.length, f.is_padding())).collect();
+        assert_eq!(
+            lengths,
+            [
+                (40000, false),
+                (9152, true),
+                (5, false),
+                (16379, true),
+                (0, false)
+            ]
+        );
+        let mut last = b"12345".to_vec();
+        last.resize(16384, 0);
+        assert_eq!(meta.info.pieces.len(), 4);
+        assert_eq!(meta.info.pieces[0], sha1(&data[..16384]));
+        assert_eq!(meta.info.pieces[3], sha1(&last));
+    }
+
+    #[cfg(feature = "sha256")]
+    #[test]
+    fn test_v2_only() {
+        let path = temp_path("create-v2.bin");
+        let data: Vec<u8> = (0..40000).map(|i| (i % 7) as u8).collect();
+        fs::write(&path, &data).unwrap();
+        let built = TorrentBuilder::new(&path)
+            .name("f")
+            .piece_length(32768)
+            .version(TorrentVersion::V2)
+            .build();
+        let err = TorrentBuilder::new(&path)
+            .piece_length(20000)
+            .version(TorrentVersion::V2)
+            .build()
+            .unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        let meta = MetaInfo::from_bytes(&built.unwrap()).unwrap();
+        assert_eq!(meta.info.version(), TorrentVersion::V2);
+        assert_eq!(meta.validate(), Ok(()));
+        assert!(meta.info.pieces.is_empty());
+
+        // Two blocks per piece; the second piece is padded with a zero leaf.
+        let blocks: Vec<[u8; 32]> = data.chunks(16384).map(sha256).collect();
+        let layer = vec![
+            hash_pair(&blocks[0], &blocks[1]),
+            hash_pair(&blocks[2], &[0; 32]),
+        ];
+        let root = hash_pair(&layer[0], &layer[1]);
+        assert_eq!(meta.info.file_tree[0].path, ["f"]);
+        assert_eq!(meta.info.file_tree[0].pieces_root, Some(root));
+        assert_eq!(meta.piece_layers[&root], layer);
+    }
+
+    #[test]
+    fn test_auto_piece_length_and_errors() {
+        assert_eq!(TorrentBuilder::auto_piece_length(0), 16 * 1024);
+        assert_eq!(TorrentBuilder::auto_piece_length(1 << 30), 1 << 20);
+        assert_eq!(TorrentBuilder::auto_piece_length(u64::MAX / 2), 16 << 20);
+
+        let root = temp_path("create-empty");
+        fs::create_dir_all(&root).unwrap();
+        let err = TorrentBuilder::new(&root).build().unwrap_err();
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(
+            TorrentBuilder::new("/nonexistent/bencode-rs")
+                .build()
+                .is_err()
+        );
+        let err = TorrentBuilder::new("/").build().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let err = TorrentBuilder::new("/").name("").build().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_name_of_a_relative_path() {
+        let root = temp_path("create-parent");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("a"), b"abc").unwrap();
+        let built = TorrentBuilder::new(root.join("sub").join("..")).build();
+        fs::remove_dir_all(&root).unwrap();
+
+        let meta = MetaInfo::from_bytes(&built.unwrap()).unwrap();
+        assert_eq!(Some(meta.info.name.as_ref()), root.file_name());
+    }
+}
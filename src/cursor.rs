@@ -0,0 +1,187 @@
+use crate::{DecodeError, Event, Events};
+
+// Pull parser over the event stream for hand-written typed decoders: look
+// at the next token, read it as the expected type, or skip a whole
+// sub-tree.
+
+pub struct Cursor<'a> {
+    input: &'a [u8],
+    events: Events<'a>,
+    /// Next event and the offset it starts at, if already pulled.
+    peeked: Option<(usize, Event<'a>)>,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        Cursor {
+            input,
+            events: Events::new(input),
+            peeked: None,
+        }
+    }
+
+    /// Offset of the next unread token.
+    pub fn offset(&self) -> usize {
+        match self.peeked {
+            Some((offset, _)) => offset,
+            None => self.events.offset(),
+        }
+    }
+
+    /// The next event without consuming it. `None` at the end of input.
+    pub fn peek(&mut self) -> Result<Option<Event<'a>>, DecodeError> {
+        if self.peeked.is_none() {
+            let offset = self.events.offset();
+            if let Some(event) = self.events.next().transpose()? {
+                self.peeked = Some((offset, event));
+            }
+        }
+        Ok(self.peeked.map(|(_, event)| event))
+    }
+
+    pub fn next_event(&mut self) -> Result<Option<Event<'a>>, DecodeError> {
+        self.peek()?;
+        Ok(self.peeked.take().map(|(_, event)| event))
+    }
+
+    /// Consumes the next value, including everything nested inside it.
+    pub fn skip_value(&mut self) -> Result<(), DecodeError> {
+        let mut depth = 0usize;
+        loop {
+            let offset = self.offset();
+            match self.next_event()? {
+                None => return Err(DecodeError::UnexpectedEof { offset }),
+                Some(Event::ListStart | Event::DictStart) => depth += 1,
+                Some(Event::End) if depth == 0 => {
+                    return Err(DecodeError::UnexpectedEnd { offset });
+                }
+                Some(Event::End) => depth -= 1,
+                Some(_) => {}
+            }
+            if depth == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    pub fn read_int(&mut self) -> Result<i64, DecodeError> {
+        self.take(|e| match e {
+            Event::Int(n) => Some(n),
+            _ => None,
+        })
+    }
+
+    pub fn read_str(&mut self) -> Result<&'a [u8], DecodeError> {
+        self.take(|e| match e {
+            Event::Str(s) => Some(s),
+            _ => None,
+        })
+    }
+
+    /// Consumes the start of a dict.
+    pub fn begin_dict(&mut self) -> Result<(), DecodeError> {
+        self.take(|e| (e == Event::DictStart).then_some(()))
+    }
+
+    /// Consumes the start of a list.
+    pub fn begin_list(&mut self) -> Result<(), DecodeError> {
+        self.take(|e| (e == Event::ListStart).then_some(()))
+    }
+
+    /// Next key of the current dict, or `None` (consuming the end marker)
+    /// once the dict is finished.
+    pub fn read_key(&mut self) -> Result<Option<&'a [u8]>, DecodeError> {
+        self.take(|e| match e {
+            Event::Key(k) => Some(Some(k)),
+            Event::End => Some(None),
+            _ => None,
+        })
+    }
+
+    /// Whether the current list or dict is finished, consuming the end
+    /// marker if so.
+    pub fn at_end(&mut self) -> Result<bool, DecodeError> {
+        if self.expect()? == Event::End {
+            self.peeked = None;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Consumes the next token if `read` accepts it. A token of the wrong
+    /// type stays unread.
+    fn take<T>(&mut self, read: impl FnOnce(Event<'a>) -> Option<T>) -> Result<T, DecodeError> {
+        match read(self.expect()?) {
+            Some(value) => {
+                self.peeked = None;
+                Ok(value)
+            }
+            None => Err(self.mismatch()),
+        }
+    }
+
+    fn expect(&mut self) -> Result<Event<'a>, DecodeError> {
+        self.peek()?.ok_or(DecodeError::UnexpectedEof {
+            offset: self.input.len(),
+        })
+    }
+
+    fn mismatch(&self) -> DecodeError {
+        let offset = self.offset();
+        DecodeError::UnexpectedByte {
+            offset,
+            byte: self.input[offset],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typed_reads() {
+        let input = b"d6:lengthi42e4:infod1:xle1:yi1ee4:name3:fooe";
+        let mut cursor = Cursor::new(input);
+        let (mut length, mut name) = (None, None);
+
+        cursor.begin_dict().unwrap();
+        while let Some(key) = cursor.read_key().unwrap() {
+            match key {
+                b"length" => length = Some(cursor.read_int().unwrap()),
+                b"name" => name = Some(cursor.read_str().unwrap()),
+                _ => cursor.skip_value().unwrap(),
+            }
+        }
+
+        assert_eq!(length, Some(42));
+        assert_eq!(name, Some(&b"foo"[..]));
+        assert_eq!(cursor.peek(), Ok(None));
+    }
+
+    #[test]
+    fn test_peek_does_not_consume() {
+        let mut cursor = Cursor::new(b"li1ei2ee");
+        cursor.begin_list().unwrap();
+        assert_eq!(cursor.peek(), Ok(Some(Event::Int(1))));
+        assert_eq!(cursor.offset(), 1);
+        assert_eq!(cursor.read_int(), Ok(1));
+        assert_eq!(cursor.read_int(), Ok(2));
+        assert_eq!(cursor.at_end(), Ok(true));
+    }
+
+    #[test]
+    fn test_type_mismatch_leaves_token_unread() {
+        let mut cursor = Cursor::new(b"l3:abce");
+        cursor.begin_list().unwrap();
+        assert_eq!(
+            cursor.read_int(),
+            Err(DecodeError::UnexpectedByte { offset: 1, byte: b'3' })
+        );
+        assert_eq!(cursor.read_str(), Ok(&b"abc"[..]));
+        assert_eq!(
+            cursor.skip_value(),
+            Err(DecodeError::UnexpectedEnd { offset: 6 })
+        );
+    }
+}
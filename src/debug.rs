@@ -0,0 +1,128 @@
+use std::fmt;
+
+use crate::BValue;
+
+// `Debug` for `BValue` in the shape `derive` would give, but readable on real
+// torrents: long strings are cut to a prefix plus their length, strings that
+// are not text show a hex preview, and only the first items of large lists
+// and dicts are listed. Dict entries are in sorted key order.
+
+/// Bytes of a string shown before it is truncated.
+const STR_PREVIEW: usize = 64;
+/// Items of a list or dict shown before the rest are counted instead.
+const ITEM_PREVIEW: usize = 16;
+
+impl fmt::Debug for BValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BValue::Str(s) => f.debug_tuple("Str").field(&Preview(s)).finish(),
+            BValue::Int(n) => f.debug_tuple("Int").field(n).finish(),
+            BValue::List(list) => f.debug_tuple("List").field(&ListPreview(list)).finish(),
+            BValue::Dict(_) => f.debug_tuple("Dict").field(&DictPreview(self)).finish(),
+            BValue::None => f.write_str("None"),
+            BValue::External(external) => f.debug_tuple("External").field(external).finish(),
+        }
+    }
+}
+
+struct ListPreview<'a>(&'a [BValue]);
+
+impl fmt::Debug for ListPreview<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut items = f.debug_list();
+        items.entries(self.0.iter().take(ITEM_PREVIEW));
+        if self.0.len() > ITEM_PREVIEW {
+            items.entry(&More(self.0.len() - ITEM_PREVIEW));
+        }
+        items.finish()
+    }
+}
+
+struct DictPreview<'a>(&'a BValue);
+
+impl fmt::Debug for DictPreview<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut entries = f.debug_map();
+        let sorted = self.0.dict_iter_sorted();
+        let len = sorted.len();
+        for (key, value) in sorted.take(ITEM_PREVIEW) {
+            entries.entry(&Preview(key), value);
+        }
+        if len > ITEM_PREVIEW {
+            entries.entry(&More(len - ITEM_PREVIEW), &More(0));
+        }
+        entries.finish()
+    }
+}
+
+struct Preview<'a>(&'a str);
+
+impl fmt::Debug for Preview<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = self.0;
+        if s.chars().any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t')) {
+            write!(f, "<{} bytes:", s.len())?;
+            for b in s.bytes().take(8) {
+                write!(f, " {:02x}", b)?;
+            }
+            return f.write_str(if s.len() > 8 { " ...>" } else { ">" });
+        }
+        if s.len() <= STR_PREVIEW {
+            return write!(f, "{:?}", s);
+        }
+        let mut end = STR_PREVIEW;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        write!(f, "{:?}... ({} bytes)", &s[..end], s.len())
+    }
+}
+
+/// Placeholder for items left out of a preview; `More(0)` is a bare `...`.
+struct More(usize);
+
+impl fmt::Debug for More {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            0 => f.write_str("..."),
+            n => write!(f, "... {} more", n),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode_exact;
+
+    #[test]
+    fn test_small_values_match_derive_shape() {
+        let value = decode_exact(b"d1:bli1e1:xe1:ad0:i-2eee").unwrap();
+        assert_eq!(
+            format!("{:?}", value),
+            r#"Dict({"a": Dict({"": Int(-2)}), "b": List([Int(1), Str("x")])})"#
+        );
+        assert_eq!(format!("{:?}", BValue::None), "None");
+    }
+
+    #[test]
+    fn test_truncates_large_values() {
+        let long = "a".repeat(100);
+        assert_eq!(
+            format!("{:?}", BValue::Str(long)),
+            format!("Str({:?}... (100 bytes))", "a".repeat(64))
+        );
+        assert_eq!(
+            format!("{:?}", BValue::Str("\u{0}\u{1}".to_string())),
+            "Str(<2 bytes: 00 01>)"
+        );
+
+        let list = BValue::List((0..20).map(BValue::Int).collect());
+        let rendered = format!("{:?}", list);
+        assert!(rendered.ends_with("Int(15), ... 4 more])"), "{}", rendered);
+
+        let dict = BValue::Dict((0..20).map(|i| (format!("k{:02}", i), BValue::Int(i))).collect());
+        let rendered = format!("{:?}", dict);
+        assert!(rendered.ends_with(r#""k15": Int(15), ... 4 more: ...})"#), "{}", rendered);
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use crate::BValue;
+
+// Path-based structural diff of two values, for readable test failures on
+// nested structures. Each line names the path where the values part ways.
+
+/// Renders the differences between `left` and `right`, one per line, e.g.
+/// `/info/files/0/length: Int(42) != Int(43)`. Empty when they are equal.
+pub fn render_diff(left: &BValue, right: &BValue) -> String {
+    let mut out = String::new();
+    diff_at(left, right, &mut String::new(), &mut out);
+    out
+}
+
+fn diff_at(left: &BValue, right: &BValue, path: &mut String, out: &mut String) {
+    match (left, right) {
+        (BValue::List(l), BValue::List(r)) => {
+            for i in 0..l.len().max(r.len()) {
+                let len = path.len();
+                let _ = write!(path, "/{}", i);
+                match (l.get(i), r.get(i)) {
+                    (Some(a), Some(b)) => diff_at(a, b, path, out),
+                    (Some(a), None) => line(out, path, &summary(a), "<missing>"),
+                    (None, Some(b)) => line(out, path, "<missing>", &summary(b)),
+                    (None, None) => {}
+                }
+                path.truncate(len);
+            }
+        }
+        (BValue::Dict(l), BValue::Dict(r)) => {
+            let keys: BTreeSet<&String> = l.keys().chain(r.keys()).collect();
+            for key in keys {
+                let len = path.len();
+                let _ = write!(path, "/{}", key);
+                match (l.get(key), r.get(key)) {
+                    (Some(a), Some(b)) => diff_at(a, b, path, out),
+                    (Some(a), None) => line(out, path, &summary(a), "<missing>"),
+                    (None, Some(b)) => line(out, path, "<missing>", &summary(b)),
+                    (None, None) => {}
+                }
+                path.truncate(len);
+            }
+        }
+        (a, b) if a != b => line(out, path, &summary(a), &summary(b)),
+        _ => {}
+    }
+}
+
+fn line(out: &mut String, path: &str, left: &str, right: &str) {
+    let path = if path.is_empty() { "/" } else { path };
+    let _ = writeln!(out, "{}: {} != {}", path, left, right);
+}
+
+// Containers are summarized so one mismatch does not dump a whole subtree.
+fn summary(value: &BValue) -> String {
+    match value {
+        BValue::List(l) => format!("List(len {})", l.len()),
+        BValue::Dict(d) => format!("Dict(len {})", d.len()),
+        v => format!("{:?}", v),
+    }
+}
+
+/// Like `assert_eq!` for `BValue`s, but prints a path-based structural
+/// diff on mismatch instead of two full `Debug` dumps.
+#[macro_export]
+macro_rules! assert_bvalue_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if left != right {
+                    panic!(
+                        "assertion `left == right` failed\n{}",
+                        $crate::render_diff(left, right)
+                    );
+                }
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode;
+
+    #[test]
+    fn test_diff_reports_paths() {
+        let (left, _) = decode(b"d4:infod5:filesld6:lengthi42eee4:name1:aee").unwrap();
+        let (right, _) = decode(b"d4:infod5:filesld6:lengthi43eed6:lengthi1eee5:extrai1eee").unwrap();
+
+        assert_eq!(
+            render_diff(&left, &right),
+            "/info/extra: <missing> != Int(1)\n\
+             /info/files/0/length: Int(42) != Int(43)\n\
+             /info/files/1: <missing> != Dict(len 1)\n\
+             /info/name: Str(\"a\") != <missing>\n"
+        );
+        assert_eq!(render_diff(&left, &left), "");
+    }
+
+    #[test]
+    #[should_panic(expected = "/1: Int(2) != Int(3)")]
+    fn test_assert_macro_panics_with_diff() {
+        assert_bvalue_eq!(decode(b"li1ei2ee").unwrap().0, decode(b"li1ei3ee").unwrap().0);
+    }
+}
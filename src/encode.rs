@@ -0,0 +1,342 @@
+use std::io::{self, Write};
+
+use crate::{
+    BValue, COLON_DELIM, DELIM_END, DICT_DELIM_BEGIN, Event, Events, INT_DELIM_BEGIN,
+    LIST_DELIM_BEGIN,
+};
+
+// Canonical encoding: dict keys are written in sorted byte order, so equal
+// trees always encode to the same bytes (and the same infohash).
+
+/// Encodes `value` straight into `w`, without building the output in
+/// memory first. Wrap unbuffered sinks in a `BufWriter`. There is no
+/// `AsyncWrite` version yet.
+///
+/// `BValue::None` is a decoding placeholder and `BValue::External` an
+/// opaque hook result; neither has an encoded form and both fail with
+/// `InvalidInput`.
+pub fn encode_to_writer<W: Write>(value: &BValue, mut w: W) -> io::Result<()> {
+    write_value(value, &mut w)
+}
+
+pub fn encode(value: &BValue) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    encode_to_writer(value, &mut out)?;
+    Ok(out)
+}
+
+/// Same as `encode`, for call sites that depend on the output being
+/// canonical: values that compare equal always produce identical bytes, so
+/// a re-encoded info dict hashes like the original whenever the original
+/// was canonical (see `is_canonical`).
+pub fn encode_canonical(value: &BValue) -> io::Result<Vec<u8>> {
+    encode(value)
+}
+
+/// Whether `input` is exactly one value in canonical form: dict keys
+/// strictly increasing as raw bytes, and integers and string lengths
+/// without a sign, leading zeros or `-0` where the shortest form has none.
+/// Any bytes or integer fit, so this holds for real info dicts, which
+/// `BValue` cannot represent.
+pub fn is_canonical(input: &[u8]) -> bool {
+    let mut events = Events::new(input);
+    // The last key of each open dict; `None` for a list.
+    let mut open: Vec<Option<Option<&[u8]>>> = Vec::new();
+    loop {
+        let start = events.offset();
+        let Some(Ok(event)) = events.next() else {
+            return false;
+        };
+        // Every non-canonical spelling of a number is longer than its
+        // shortest form.
+        let len = events.offset() - start;
+        match event {
+            Event::DictStart => open.push(Some(None)),
+            Event::ListStart => open.push(None),
+            Event::End => {
+                open.pop();
+            }
+            Event::Int(n) if len != int_len(n) => return false,
+            Event::Int(_) => {}
+            Event::Str(bytes) if len != bytes_len(bytes.len()) => return false,
+            Event::Str(_) => {}
+            Event::Key(key) => {
+                if len != bytes_len(key.len()) {
+                    return false;
+                }
+                let Some(Some(last)) = open.last_mut() else {
+                    return false;
+                };
+                if last.is_some_and(|last| last >= key) {
+                    return false;
+                }
+                *last = Some(key);
+            }
+        }
+        if open.is_empty() {
+            return events.next().is_none();
+        }
+    }
+}
+
+/// Exact number of bytes `encode` would produce for `value`.
+pub fn encoded_len(value: &BValue) -> io::Result<usize> {
+    Ok(match value {
+        BValue::Str(s) => bytes_len(s.len()),
+        BValue::Int(n) => int_len(*n as i64),
+        BValue::List(list) => {
+            let mut len = 2;
+            for item in list {
+                len += encoded_len(item)?;
+            }
+            len
+        }
+        BValue::Dict(dict) => {
+            let mut len = 2;
+            for (key, value) in dict {
+                len += bytes_len(key.len()) + encoded_len(value)?;
+            }
+            len
+        }
+        BValue::None | BValue::External(_) => return Err(unencodable(value)),
+    })
+}
+
+/// Encodes `value` into the front of `buf` and returns the number of bytes
+/// written. A buffer shorter than `encoded_len` is a `WriteZero` error and
+/// is left untouched.
+pub fn encode_into(value: &BValue, buf: &mut [u8]) -> io::Result<usize> {
+    let len = encoded_len(value)?;
+    if buf.len() < len {
+        return Err(io::Error::new(
+            io::ErrorKind::WriteZero,
+            format!("buffer of {} bytes is too small for {}", buf.len(), len),
+        ));
+    }
+    write_value(value, &mut &mut buf[..len])?;
+    Ok(len)
+}
+
+/// Appends the encoding of `value` to `out`, growing it at most once.
+pub fn encode_into_vec(value: &BValue, out: &mut Vec<u8>) -> io::Result<()> {
+    out.reserve_exact(encoded_len(value)?);
+    write_value(value, out)
+}
+
+pub(crate) fn write_value<W: Write>(value: &BValue, w: &mut W) -> io::Result<()> {
+    match value {
+        BValue::Str(s) => write_str(s, w),
+        BValue::Int(n) => write_int(w, *n as i64),
+        BValue::List(list) => {
+            w.write_all(&[LIST_DELIM_BEGIN])?;
+            for item in list {
+                write_value(item, w)?;
+            }
+            w.write_all(&[DELIM_END])
+        }
+        BValue::Dict(dict) => {
+            let mut entries: Vec<(&String, &BValue)> = dict.iter().collect();
+            entries.sort_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+
+            w.write_all(&[DICT_DELIM_BEGIN])?;
+            for (key, value) in entries {
+                write_str(key, w)?;
+                write_value(value, w)?;
+            }
+            w.write_all(&[DELIM_END])
+        }
+        BValue::None | BValue::External(_) => Err(unencodable(value)),
+    }
+}
+
+fn unencodable(value: &BValue) -> io::Error {
+    let name = match value {
+        BValue::External(_) => "BValue::External",
+        _ => "BValue::None",
+    };
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("{} cannot be encoded", name),
+    )
+}
+
+fn write_str<W: Write>(s: &str, w: &mut W) -> io::Result<()> {
+    write_bytes(w, s.as_bytes())
+}
+
+pub(crate) fn write_int<W: Write + ?Sized>(w: &mut W, n: i64) -> io::Result<()> {
+    let mut buf = [0u8; 22];
+    let mut start = format_u64(&mut buf, n.unsigned_abs());
+    if n < 0 {
+        start -= 1;
+        buf[start] = b'-';
+    }
+    start -= 1;
+    buf[start] = INT_DELIM_BEGIN;
+    buf[buf.len() - 1] = DELIM_END;
+    w.write_all(&buf[start..])
+}
+
+pub(crate) fn write_bytes<W: Write + ?Sized>(w: &mut W, bytes: &[u8]) -> io::Result<()> {
+    let mut buf = [0u8; 21];
+    let start = format_u64(&mut buf, bytes.len() as u64);
+    buf[buf.len() - 1] = COLON_DELIM;
+    w.write_all(&buf[start..])?;
+    w.write_all(bytes)
+}
+
+fn int_len(n: i64) -> usize {
+    2 + usize::from(n < 0) + digits(n.unsigned_abs())
+}
+
+fn bytes_len(len: usize) -> usize {
+    digits(len as u64) + 1 + len
+}
+
+fn digits(mut n: u64) -> usize {
+    let mut count = 1;
+    while n >= 10 {
+        n /= 10;
+        count += 1;
+    }
+    count
+}
+
+// Writes the decimal digits of `n` so they end just before the last byte of
+// `buf` (left free for a delimiter) and returns where they start. Plain
+// ASCII digits, no locale or formatting machinery involved.
+fn format_u64(buf: &mut [u8], mut n: u64) -> usize {
+    let mut pos = buf.len() - 1;
+    loop {
+        pos -= 1;
+        buf[pos] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            return pos;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_round_trip_is_canonical() {
+        let input = b"d4:infod6:lengthi42e4:pathl1:a1:bee4:name3:fooe";
+        let (value, _) = decode(input).unwrap();
+        assert_eq!(encode(&value).unwrap(), input);
+
+        // Keys come out sorted regardless of input order.
+        let (value, _) = decode(b"d1:bi-1e1:ai0ee").unwrap();
+        assert_eq!(encode(&value).unwrap(), b"d1:ai0e1:bi-1ee");
+    }
+
+    #[test]
+    fn test_canonical_encoding() {
+        let mut a = HashMap::new();
+        let mut b = HashMap::new();
+        for i in 0..64 {
+            a.insert(format!("k{}", i), BValue::Int(i));
+            b.insert(format!("k{}", 63 - i), BValue::Int(63 - i));
+        }
+        let (a, b) = (BValue::Dict(a), BValue::Dict(b));
+        assert_eq!(a, b);
+        assert_eq!(encode_canonical(&a).unwrap(), encode_canonical(&b).unwrap());
+
+        assert!(is_canonical(b"d1:ai0e1:bl0:ee"));
+        assert!(!is_canonical(b"d1:bi0e1:ai0ee"));
+        assert!(!is_canonical(b"d1:ai0e1:ai1ee"));
+        assert!(!is_canonical(b"i1ei2e"));
+        assert!(!is_canonical(b"d1:ai0e"));
+        assert!(!is_canonical(b"i1ex"));
+    }
+
+    #[test]
+    fn test_canonical_binary_and_large_values() {
+        assert!(is_canonical(b"i5000000000e"));
+        assert!(is_canonical(b"i-9223372036854775808e"));
+        assert!(is_canonical(b"2:\xff\xfe"));
+        assert!(is_canonical(b"d2:\xff\x001:a2:\xff\x01i0ee"));
+        assert!(!is_canonical(b"d2:\xff\x01i0e2:\xff\x001:ae"));
+
+        let mut info = b"d6:lengthi70000e4:name1:a12:piece lengthi65536e6:pieces40:".to_vec();
+        info.extend((0..40).map(|i| 0xff - i as u8));
+        info.push(b'e');
+        assert!(is_canonical(&info));
+
+        for input in [
+            &b"i+5e"[..],
+            b"i05e",
+            b"i-0e",
+            b"i-05e",
+            b"01:a",
+            b"+1:a",
+            b"d01:ai0ee",
+        ] {
+            assert!(!is_canonical(input), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_integer_formatting() {
+        let int = |n: i64| {
+            let mut out = Vec::new();
+            write_int(&mut out, n).unwrap();
+            out
+        };
+        assert_eq!(int(0), b"i0e");
+        assert_eq!(int(-7), b"i-7e");
+        assert_eq!(int(1234567890), b"i1234567890e");
+        assert_eq!(int(i64::MAX), b"i9223372036854775807e");
+        assert_eq!(int(i64::MIN), b"i-9223372036854775808e");
+
+        let mut out = Vec::new();
+        write_bytes(&mut out, &[b'x'; 12]).unwrap();
+        assert_eq!(out, b"12:xxxxxxxxxxxx");
+    }
+
+    #[test]
+    fn test_encoded_len_matches_output() {
+        let (value, _) = decode(b"d4:infod6:lengthi-42e4:pathl0:10:abcdefghijee1:zi0ee").unwrap();
+        let encoded = encode(&value).unwrap();
+        assert_eq!(encoded_len(&value).unwrap(), encoded.len());
+        for n in [0, 9, 10, -9, -10, i64::MAX, i64::MIN] {
+            let mut out = Vec::new();
+            write_int(&mut out, n).unwrap();
+            assert_eq!(int_len(n), out.len());
+        }
+        assert!(encoded_len(&BValue::List(vec![BValue::None])).is_err());
+    }
+
+    #[test]
+    fn test_encode_into_buffers() {
+        let value = BValue::List(vec![BValue::Str("spam".to_string()), BValue::Int(7)]);
+
+        let mut buf = [b'.'; 16];
+        assert_eq!(encode_into(&value, &mut buf).unwrap(), 11);
+        assert_eq!(&buf, b"l4:spami7ee.....");
+
+        let mut small = [b'.'; 10];
+        let err = encode_into(&value, &mut small).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WriteZero);
+        assert_eq!(&small, b"..........");
+
+        let mut out = b"x".to_vec();
+        encode_into_vec(&value, &mut out).unwrap();
+        assert_eq!(out, b"xl4:spami7ee");
+    }
+
+    #[test]
+    fn test_streams_into_writer() {
+        let value = BValue::List(vec![BValue::Str("spam".to_string()), BValue::Int(7)]);
+        let mut sink = io::Cursor::new(Vec::new());
+        encode_to_writer(&value, &mut sink).unwrap();
+        assert_eq!(sink.into_inner(), b"l4:spami7ee");
+
+        let err = encode(&BValue::List(vec![BValue::None])).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
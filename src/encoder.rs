@@ -0,0 +1,270 @@
+use std::io::{self, Write};
+
+use crate::encode::{write_bytes, write_int};
+use crate::{BValue, DELIM_END, DICT_DELIM_BEGIN, LIST_DELIM_BEGIN};
+
+// Imperative counterpart to `StreamingEncoder`: one value that is driven
+// through `&mut self` calls, which suits loops over data that is never
+// collected into a `BValue`. Balance and key order are checked at runtime
+// instead of by the type system; misuse is an `InvalidInput` error and
+// nothing is written for the offending call.
+//
+//     let mut enc = Encoder::new(out);
+//     enc.begin_dict()?.key(b"files")?.begin_dict()?;
+//     for (hash, stats) in scrape {
+//         enc.key(hash)?.begin_dict()?;
+//         enc.key(b"complete")?.int(stats.complete)?.end()?;
+//     }
+//     enc.end()?.end()?;
+//     let out = enc.finish()?;
+
+#[derive(Debug)]
+enum Open {
+    List,
+    Dict {
+        last_key: Option<Vec<u8>>,
+        /// A key was written and its value is pending.
+        has_key: bool,
+    },
+}
+
+#[derive(Debug)]
+pub struct Encoder<W> {
+    w: W,
+    stack: Vec<Open>,
+    done: bool,
+}
+
+impl<W: Write> Encoder<W> {
+    pub fn new(w: W) -> Self {
+        Encoder {
+            w,
+            stack: Vec::new(),
+            done: false,
+        }
+    }
+
+    pub fn begin_dict(&mut self) -> io::Result<&mut Self> {
+        self.start_value()?;
+        self.w.write_all(&[DICT_DELIM_BEGIN])?;
+        self.stack.push(Open::Dict {
+            last_key: None,
+            has_key: false,
+        });
+        Ok(self)
+    }
+
+    pub fn begin_list(&mut self) -> io::Result<&mut Self> {
+        self.start_value()?;
+        self.w.write_all(&[LIST_DELIM_BEGIN])?;
+        self.stack.push(Open::List);
+        Ok(self)
+    }
+
+    /// Writes a dict key. Keys must come in strictly increasing byte order.
+    pub fn key(&mut self, key: &[u8]) -> io::Result<&mut Self> {
+        let Some(Open::Dict { last_key, has_key }) = self.stack.last_mut() else {
+            return Err(misuse("key outside of a dict"));
+        };
+        if *has_key {
+            return Err(misuse("key written while a value was expected"));
+        }
+        if last_key.as_deref().is_some_and(|last| last >= key) {
+            return Err(misuse("dict key is out of order"));
+        }
+        write_bytes(&mut self.w, key)?;
+        *last_key = Some(key.to_vec());
+        *has_key = true;
+        Ok(self)
+    }
+
+    pub fn int(&mut self, n: i64) -> io::Result<&mut Self> {
+        self.start_value()?;
+        write_int(&mut self.w, n)?;
+        self.end_value();
+        Ok(self)
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) -> io::Result<&mut Self> {
+        self.start_value()?;
+        write_bytes(&mut self.w, bytes)?;
+        self.end_value();
+        Ok(self)
+    }
+
+    pub fn str(&mut self, s: &str) -> io::Result<&mut Self> {
+        self.bytes(s.as_bytes())
+    }
+
+    /// Writes a whole decoded value.
+    pub fn value(&mut self, value: &BValue) -> io::Result<&mut Self> {
+        self.start_value()?;
+        crate::encode_to_writer(value, &mut self.w)?;
+        self.end_value();
+        Ok(self)
+    }
+
+    /// Copies one already-encoded value verbatim, e.g. an `info` dict taken
+    /// from `get_raw`, so its hash is unchanged. `encoded` must be exactly one
+    /// well-formed value; its own key order is not checked.
+    pub fn raw(&mut self, encoded: &[u8]) -> io::Result<&mut Self> {
+        self.start_value()?;
+        crate::validate(encoded)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        self.w.write_all(encoded)?;
+        self.end_value();
+        Ok(self)
+    }
+
+    /// Closes the innermost open list or dict.
+    pub fn end(&mut self) -> io::Result<&mut Self> {
+        match self.stack.last() {
+            None => return Err(misuse("end with no open list or dict")),
+            Some(Open::Dict { has_key: true, .. }) => {
+                return Err(misuse("dict closed after a key without a value"));
+            }
+            Some(_) => {}
+        }
+        self.w.write_all(&[DELIM_END])?;
+        self.stack.pop();
+        self.end_value();
+        Ok(self)
+    }
+
+    /// Number of lists and dicts still open.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Returns the writer once exactly one complete value has been written.
+    pub fn finish(self) -> io::Result<W> {
+        if !self.done {
+            return Err(misuse("value is incomplete"));
+        }
+        Ok(self.w)
+    }
+
+    fn start_value(&self) -> io::Result<()> {
+        match self.stack.last() {
+            None if self.done => Err(misuse("top-level value already written")),
+            Some(Open::Dict { has_key: false, .. }) => Err(misuse("dict value without a key")),
+            _ => Ok(()),
+        }
+    }
+
+    fn end_value(&mut self) {
+        match self.stack.last_mut() {
+            None => self.done = true,
+            Some(Open::Dict { has_key, .. }) => *has_key = false,
+            Some(Open::List) => {}
+        }
+    }
+}
+
+impl Encoder<Vec<u8>> {
+    /// Runs `write` on an encoder over a new `Vec` and returns the bytes.
+    /// Like `finish`, this fails unless exactly one complete value was
+    /// written, so a misused encoder is never mistaken for a valid body.
+    pub fn to_vec(write: impl FnOnce(&mut Self) -> io::Result<()>) -> io::Result<Vec<u8>> {
+        let mut enc = Encoder::new(Vec::new());
+        write(&mut enc)?;
+        enc.finish()
+    }
+}
+
+fn misuse(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrape_response() -> io::Result<()> {
+        let mut enc = Encoder::new(Vec::new());
+        enc.begin_dict()?.key(b"files")?.begin_dict()?;
+        for (hash, complete) in [(b"aaaa", 5), (b"bbbb", 0)] {
+            enc.key(hash)?.begin_dict()?;
+            enc.key(b"complete")?.int(complete)?;
+            enc.key(b"name")?.str("x")?.end()?;
+        }
+        enc.end()?.key(b"flags")?.begin_list()?.value(&BValue::Int(1))?.end()?;
+        assert_eq!(enc.depth(), 1);
+        enc.end()?;
+
+        assert_eq!(
+            enc.finish()?,
+            b"d5:filesd4:aaaad8:completei5e4:name1:xe4:bbbbd8:completei0e4:name1:xee5:flagsli1eee"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_raw_info_keeps_its_hash() -> io::Result<()> {
+        // Unsorted keys: re-encoding would change the hash.
+        let torrent = b"d4:infod6:pieces0:4:name1:nee";
+        let info = crate::get_raw(torrent, "info").unwrap().unwrap();
+
+        let mut enc = Encoder::new(Vec::new());
+        enc.begin_dict()?.key(b"announce")?.str("url")?;
+        enc.key(b"info")?.raw(info)?.end()?;
+        let out = enc.finish()?;
+        assert_eq!(out, b"d8:announce3:url4:infod6:pieces0:4:name1:nee");
+        assert_eq!(
+            crate::sha1(crate::get_raw(&out, "info").unwrap().unwrap()),
+            crate::sha1(info)
+        );
+
+        let mut enc = Encoder::new(Vec::new());
+        enc.begin_list()?;
+        for bad in [&b"i1"[..], b"i1ei2e", b""] {
+            let err = enc.raw(bad).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+        enc.end()?;
+        assert_eq!(enc.finish()?, b"le");
+        Ok(())
+    }
+
+    #[test]
+    fn test_raw_binary_info() -> io::Result<()> {
+        // Neither the pieces nor the length would survive a decode.
+        let info = b"d6:lengthi5000000000e4:name1:n6:pieces2:\xff\x00e";
+        let out = Encoder::to_vec(|enc| {
+            enc.begin_dict()?.key(b"info")?.raw(info)?.end()?;
+            Ok(())
+        })?;
+        assert_eq!(&out[..], &b"d4:infod6:lengthi5000000000e4:name1:n6:pieces2:\xff\x00ee"[..]);
+        assert_eq!(crate::get_raw(&out, "info").unwrap(), Some(&info[..]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_misuse_is_rejected() -> io::Result<()> {
+        let invalid = |r: io::Result<&mut Encoder<Vec<u8>>>| {
+            r.err().map(|e| e.kind()) == Some(io::ErrorKind::InvalidInput)
+        };
+
+        let mut enc = Encoder::new(Vec::new());
+        assert!(invalid(enc.end()));
+        assert!(invalid(enc.key(b"a")));
+        enc.begin_dict()?;
+        assert!(invalid(enc.int(1)));
+        enc.key(b"b")?;
+        assert!(invalid(enc.key(b"c")));
+        assert!(invalid(enc.end()));
+        enc.int(1)?;
+        assert!(invalid(enc.key(b"a")));
+        enc.end()?;
+        assert!(invalid(enc.int(2)));
+
+        // Rejected calls write nothing.
+        assert_eq!(enc.finish()?, b"d1:bi1ee");
+
+        let mut enc = Encoder::new(Vec::new());
+        enc.begin_list()?;
+        assert!(enc.finish().is_err());
+        Ok(())
+    }
+}
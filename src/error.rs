@@ -0,0 +1,110 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// No input at all. Distinct from `UnexpectedEof`, which means the input
+    /// stopped partway through a value.
+    EmptyInput,
+    /// Input ended in the middle of a value.
+    UnexpectedEof { offset: usize },
+    /// End marker with no open list or dict to close.
+    UnexpectedEnd { offset: usize },
+    /// A byte that cannot start or continue a value at this position.
+    UnexpectedByte { offset: usize, byte: u8 },
+    EmptyInteger { offset: usize },
+    InvalidInteger { offset: usize },
+    InvalidStringLength { offset: usize },
+    /// String length that is valid but does not fit in `usize` on this
+    /// target, e.g. a length above 4 GiB on a 32-bit platform.
+    LengthExceedsPlatform { offset: usize },
+    InvalidUtf8 { offset: usize },
+    /// Dict key that is not a byte string.
+    NonStringKey { offset: usize },
+    /// Dict key followed by the end marker instead of a value.
+    MissingValue { offset: usize },
+    /// Bytes left over after a complete top-level value.
+    TrailingData { offset: usize },
+}
+
+impl DecodeError {
+    /// Byte offset in the input where the error was detected.
+    pub fn offset(&self) -> usize {
+        match *self {
+            DecodeError::EmptyInput => 0,
+            DecodeError::UnexpectedEof { offset }
+            | DecodeError::UnexpectedEnd { offset }
+            | DecodeError::UnexpectedByte { offset, .. }
+            | DecodeError::EmptyInteger { offset }
+            | DecodeError::InvalidInteger { offset }
+            | DecodeError::InvalidStringLength { offset }
+            | DecodeError::LengthExceedsPlatform { offset }
+            | DecodeError::InvalidUtf8 { offset }
+            | DecodeError::NonStringKey { offset }
+            | DecodeError::MissingValue { offset }
+            | DecodeError::TrailingData { offset } => offset,
+        }
+    }
+
+    // Errors from nested calls are relative to the sub-slice they saw.
+    pub(crate) fn offset_by(self, base: usize) -> Self {
+        match self {
+            DecodeError::EmptyInput => DecodeError::EmptyInput,
+            DecodeError::UnexpectedEof { offset } => DecodeError::UnexpectedEof {
+                offset: offset + base,
+            },
+            DecodeError::UnexpectedEnd { offset } => DecodeError::UnexpectedEnd {
+                offset: offset + base,
+            },
+            DecodeError::UnexpectedByte { offset, byte } => DecodeError::UnexpectedByte {
+                offset: offset + base,
+                byte,
+            },
+            DecodeError::EmptyInteger { offset } => DecodeError::EmptyInteger {
+                offset: offset + base,
+            },
+            DecodeError::InvalidInteger { offset } => DecodeError::InvalidInteger {
+                offset: offset + base,
+            },
+            DecodeError::InvalidStringLength { offset } => DecodeError::InvalidStringLength {
+                offset: offset + base,
+            },
+            DecodeError::LengthExceedsPlatform { offset } => DecodeError::LengthExceedsPlatform {
+                offset: offset + base,
+            },
+            DecodeError::InvalidUtf8 { offset } => DecodeError::InvalidUtf8 {
+                offset: offset + base,
+            },
+            DecodeError::NonStringKey { offset } => DecodeError::NonStringKey {
+                offset: offset + base,
+            },
+            DecodeError::MissingValue { offset } => DecodeError::MissingValue {
+                offset: offset + base,
+            },
+            DecodeError::TrailingData { offset } => DecodeError::TrailingData {
+                offset: offset + base,
+            },
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            DecodeError::EmptyInput => "Empty input",
+            DecodeError::UnexpectedEof { .. } => "Unexpected end of input",
+            DecodeError::UnexpectedEnd { .. } => "Unexpected end marker",
+            DecodeError::UnexpectedByte { .. } => "Unexpected byte",
+            DecodeError::EmptyInteger { .. } => "Empty Integer Not-allowed",
+            DecodeError::InvalidInteger { .. } => "Ill-formatted Integer",
+            DecodeError::InvalidStringLength { .. } => "Invalid string length",
+            DecodeError::LengthExceedsPlatform { .. } => "String length too large for this platform",
+            DecodeError::InvalidUtf8 { .. } => "String is not valid UTF-8",
+            DecodeError::NonStringKey { .. } => "Dict key is not a string",
+            DecodeError::MissingValue { .. } => "Dict key has no value",
+            DecodeError::TrailingData { .. } => "Trailing data after value",
+        };
+        write!(f, "Decoding Error: {} at byte {}.", msg, self.offset())
+    }
+}
+
+impl std::error::Error for DecodeError {}
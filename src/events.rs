@@ -0,0 +1,279 @@
+use crate::memchr::memchr;
+use crate::{COLON_DELIM, DELIM_END, DICT_DELIM_BEGIN, DecodeError, INT_DELIM_BEGIN, LIST_DELIM_BEGIN};
+
+// SAX-style event stream over encoded bytes. No tree is built; the only
+// state is a stack of open containers, so memory is bounded by nesting depth
+// rather than document size. Consecutive top-level values (as in capture
+// files) are reported one after another.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event<'a> {
+    DictStart,
+    /// A dict key. Strings in key position are always reported as keys.
+    Key(&'a [u8]),
+    Int(i64),
+    Str(&'a [u8]),
+    ListStart,
+    /// Closes the innermost open list or dict.
+    End,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Frame {
+    List,
+    Dict { expect_key: bool },
+}
+
+pub struct Events<'a> {
+    input: &'a [u8],
+    pos: usize,
+    stack: Vec<Frame>,
+    failed: bool,
+}
+
+impl<'a> Events<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        Events {
+            input,
+            pos: 0,
+            stack: Vec::new(),
+            failed: false,
+        }
+    }
+
+    /// Continues a scan over `input` from a state saved with `into_state`.
+    /// `input` may have grown since, but must keep the same prefix.
+    pub(crate) fn resume(input: &'a [u8], pos: usize, stack: Vec<Frame>) -> Self {
+        Events {
+            input,
+            pos,
+            stack,
+            failed: false,
+        }
+    }
+
+    /// Position and open containers, as taken by `resume`. Incomplete
+    /// tokens are never consumed, so this is always a token boundary.
+    pub(crate) fn into_state(self) -> (usize, Vec<Frame>) {
+        (self.pos, self.stack)
+    }
+
+    /// Offset of the next byte to be read.
+    pub fn offset(&self) -> usize {
+        self.pos
+    }
+
+    /// Number of lists and dicts currently open.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Like `next`, for a caller inside a value it knows is unfinished:
+    /// running out of input is an error.
+    pub(crate) fn expect_next(&mut self) -> Result<Event<'a>, DecodeError> {
+        let offset = self.pos;
+        self.next()
+            .unwrap_or(Err(DecodeError::UnexpectedEof { offset }))
+    }
+
+    /// Consumes the next value whole, such as the value of a key the
+    /// caller does not use.
+    pub(crate) fn skip_value(&mut self) -> Result<(), DecodeError> {
+        let depth = self.depth();
+        loop {
+            self.expect_next()?;
+            if self.depth() == depth {
+                return Ok(());
+            }
+        }
+    }
+
+    fn event(&mut self) -> Result<Option<Event<'a>>, DecodeError> {
+        let Some(&byte) = self.input.get(self.pos) else {
+            if self.stack.is_empty() {
+                return Ok(None);
+            }
+            return Err(DecodeError::UnexpectedEof { offset: self.pos });
+        };
+
+        if byte == DELIM_END {
+            return match self.stack.pop() {
+                None => Err(DecodeError::UnexpectedEnd { offset: self.pos }),
+                Some(Frame::Dict { expect_key: false }) => {
+                    Err(DecodeError::MissingValue { offset: self.pos })
+                }
+                Some(_) => {
+                    self.pos += 1;
+                    Ok(Some(Event::End))
+                }
+            };
+        }
+
+        if let Some(Frame::Dict { expect_key: true }) = self.stack.last() {
+            if !byte.is_ascii_digit() {
+                return Err(DecodeError::NonStringKey { offset: self.pos });
+            }
+            let key = self.string()?;
+            self.set_expect_key(false);
+            return Ok(Some(Event::Key(key)));
+        }
+
+        // State only changes once a token is complete, so a scan that hits
+        // the end of a partial buffer can be resumed.
+        let event = match byte {
+            INT_DELIM_BEGIN => Event::Int(self.int()?),
+            b'0'..=b'9' => Event::Str(self.string()?),
+            LIST_DELIM_BEGIN => {
+                self.pos += 1;
+                Event::ListStart
+            }
+            DICT_DELIM_BEGIN => {
+                self.pos += 1;
+                Event::DictStart
+            }
+            byte => {
+                return Err(DecodeError::UnexpectedByte {
+                    offset: self.pos,
+                    byte,
+                });
+            }
+        };
+
+        self.set_expect_key(true);
+        match event {
+            Event::ListStart => self.stack.push(Frame::List),
+            Event::DictStart => self.stack.push(Frame::Dict { expect_key: true }),
+            _ => {}
+        }
+        Ok(Some(event))
+    }
+
+    fn set_expect_key(&mut self, expect: bool) {
+        if let Some(Frame::Dict { expect_key }) = self.stack.last_mut() {
+            *expect_key = expect;
+        }
+    }
+
+    fn int(&mut self) -> Result<i64, DecodeError> {
+        let start = self.pos;
+        let end = self.find_delim(DELIM_END, start + 1)?;
+        let digits = &self.input[start + 1..end];
+        if digits.is_empty() {
+            return Err(DecodeError::EmptyInteger { offset: start });
+        }
+
+        let n = crate::parse_int(digits).ok_or(DecodeError::InvalidInteger { offset: start })?;
+        self.pos = end + 1;
+        Ok(n)
+    }
+
+    fn string(&mut self) -> Result<&'a [u8], DecodeError> {
+        let start = self.pos;
+        let colon = self.find_delim(COLON_DELIM, start)?;
+        let len = crate::parse_len(&self.input[start..colon], start)?;
+
+        let data_start = colon + 1;
+        let data = data_start
+            .checked_add(len)
+            .and_then(|end| self.input.get(data_start..end))
+            .ok_or(DecodeError::UnexpectedEof {
+                offset: self.input.len(),
+            })?;
+        self.pos = data_start + len;
+        Ok(data)
+    }
+
+    fn find_delim(&self, delim: u8, from: usize) -> Result<usize, DecodeError> {
+        memchr(delim, &self.input[from..])
+            .map(|n| from + n)
+            .ok_or(DecodeError::UnexpectedEof {
+                offset: self.input.len(),
+            })
+    }
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = Result<Event<'a>, DecodeError>;
+
+    /// Yields events until the input is exhausted. After an error the
+    /// stream ends.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        match self.event() {
+            Ok(event) => event.map(Ok),
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect(input: &[u8]) -> Vec<Result<Event<'_>, DecodeError>> {
+        Events::new(input).collect()
+    }
+
+    #[test]
+    fn test_event_sequence() {
+        assert_eq!(
+            collect(b"d4:spaml1:ai-3000000000eee"),
+            vec![
+                Ok(Event::DictStart),
+                Ok(Event::Key(b"spam")),
+                Ok(Event::ListStart),
+                Ok(Event::Str(b"a")),
+                Ok(Event::Int(-3000000000)),
+                Ok(Event::End),
+                Ok(Event::End),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_consecutive_documents() {
+        assert_eq!(
+            collect(b"i1ed1:k1:ve"),
+            vec![
+                Ok(Event::Int(1)),
+                Ok(Event::DictStart),
+                Ok(Event::Key(b"k")),
+                Ok(Event::Str(b"v")),
+                Ok(Event::End),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors_end_the_stream() {
+        assert_eq!(
+            collect(b"di1ee"),
+            vec![
+                Ok(Event::DictStart),
+                Err(DecodeError::NonStringKey { offset: 1 })
+            ]
+        );
+        assert_eq!(
+            collect(b"l1:a"),
+            vec![
+                Ok(Event::ListStart),
+                Ok(Event::Str(b"a")),
+                Err(DecodeError::UnexpectedEof { offset: 4 })
+            ]
+        );
+        assert_eq!(collect(b"e"), vec![Err(DecodeError::UnexpectedEnd { offset: 0 })]);
+        assert_eq!(
+            collect(b"d1:ke"),
+            vec![
+                Ok(Event::DictStart),
+                Ok(Event::Key(b"k")),
+                Err(DecodeError::MissingValue { offset: 4 })
+            ]
+        );
+    }
+}
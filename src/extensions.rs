@@ -0,0 +1,430 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::{BValue, DecodeError, Encoder, Event, Events};
+
+// BEP 10 extension negotiation. Each side's handshake carries an `m` dict
+// mapping extension names to the message id it wants to receive that
+// extension on (0 meaning disabled). The negotiated set is the extensions
+// both sides enable, with the id to send on and the id to expect.
+//
+// `Handshake` is the whole extended handshake payload. `yourip` is a raw
+// address and `metadata_size` easily exceeds `i16`, so it is read from
+// `Events` rather than a `BValue`.
+
+/// Message ids for one negotiated extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtensionIds {
+    /// Id we receive this extension on (from our own `m`).
+    pub local: u8,
+    /// Id to send this extension with (from the peer's `m`).
+    pub remote: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Local,
+    Remote,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Conflict {
+    /// Two extensions claim the same id on one side. Both are dropped.
+    DuplicateId { side: Side, id: u8, names: (String, String) },
+    /// The id is not an integer in 0..=255. The extension is dropped.
+    InvalidId { side: Side, name: String },
+    /// The `m` value is not a dict at all.
+    NotADict { side: Side },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Negotiated {
+    pub extensions: BTreeMap<String, ExtensionIds>,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl Negotiated {
+    /// Id to put on outgoing messages for `name`.
+    pub fn remote_id(&self, name: &str) -> Option<u8> {
+        self.extensions.get(name).map(|ids| ids.remote)
+    }
+
+    /// Extension an incoming message id refers to.
+    pub fn local_name(&self, id: u8) -> Option<&str> {
+        self.extensions
+            .iter()
+            .find(|(_, ids)| ids.local == id)
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+/// The extended handshake (message id 0). Keys other than these are
+/// ignored when parsing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Handshake {
+    /// `m`: extension name to the id the sender receives it on.
+    pub extensions: BTreeMap<String, u8>,
+    /// `p`: the sender's listen port.
+    pub port: Option<u16>,
+    /// `v`: client name and version.
+    pub client: Option<String>,
+    /// `metadata_size`: length of the info dict (BEP 9).
+    pub metadata_size: Option<u64>,
+    /// `reqq`: how many outstanding requests the sender allows.
+    pub reqq: Option<u32>,
+    /// `yourip`: the receiver's address as the sender sees it.
+    pub your_ip: Option<IpAddr>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HandshakeError {
+    Decode(DecodeError),
+    /// The named key has the wrong type or an out-of-range value. `""` is
+    /// the payload itself.
+    Invalid(&'static str),
+}
+
+impl fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HandshakeError::Decode(e) => e.fmt(f),
+            HandshakeError::Invalid(key) => write!(f, "invalid handshake key {:?}", key),
+        }
+    }
+}
+
+impl std::error::Error for HandshakeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HandshakeError::Decode(e) => Some(e),
+            HandshakeError::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<DecodeError> for HandshakeError {
+    fn from(e: DecodeError) -> Self {
+        HandshakeError::Decode(e)
+    }
+}
+
+impl Handshake {
+    /// Id the sender wants `name` sent on, if it enables it.
+    pub fn extension_id(&self, name: &str) -> Option<u8> {
+        self.extensions.get(name).copied().filter(|&id| id != 0)
+    }
+
+    /// The `m` dict as a value, for `negotiate`.
+    pub fn m(&self) -> BValue {
+        BValue::Dict(
+            self.extensions
+                .iter()
+                .map(|(name, &id)| (name.clone(), BValue::Int(i16::from(id))))
+                .collect(),
+        )
+    }
+
+    /// Negotiates our handshake (`self`) against the peer's.
+    pub fn negotiate(&self, remote: &Handshake) -> Negotiated {
+        negotiate(&self.m(), &remote.m())
+    }
+
+    pub fn from_bytes(input: &[u8]) -> Result<Handshake, HandshakeError> {
+        if input.is_empty() {
+            return Err(DecodeError::EmptyInput.into());
+        }
+        let mut events = Events::new(input);
+        if events.expect_next()? != Event::DictStart {
+            return Err(HandshakeError::Invalid(""));
+        }
+        let mut handshake = Handshake::default();
+        loop {
+            let key = match events.expect_next()? {
+                Event::End => break,
+                Event::Key(key) => key,
+                _ => return Err(HandshakeError::Invalid("")),
+            };
+            let invalid = HandshakeError::Invalid;
+            match key {
+                b"m" => handshake.extensions = m(&mut events)?,
+                b"p" => handshake.port = Some(int(events.expect_next()?).ok_or(invalid("p"))?),
+                b"v" => match events.expect_next()? {
+                    Event::Str(v) => {
+                        handshake.client = Some(String::from_utf8_lossy(v).into_owned())
+                    }
+                    _ => return Err(invalid("v")),
+                },
+                b"metadata_size" => {
+                    let size = int(events.expect_next()?).ok_or(invalid("metadata_size"))?;
+                    handshake.metadata_size = Some(size);
+                }
+                b"reqq" => {
+                    handshake.reqq = Some(int(events.expect_next()?).ok_or(invalid("reqq"))?)
+                }
+                b"yourip" => {
+                    handshake.your_ip = Some(ip(events.expect_next()?).ok_or(invalid("yourip"))?)
+                }
+                _ => events.skip_value()?,
+            }
+        }
+        if events.offset() < input.len() {
+            return Err(DecodeError::TrailingData {
+                offset: events.offset(),
+            }
+            .into());
+        }
+        Ok(handshake)
+    }
+
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        Encoder::to_vec(|enc| {
+            enc.begin_dict()?.key(b"m")?.begin_dict()?;
+            for (name, &id) in &self.extensions {
+                enc.key(name.as_bytes())?.int(i64::from(id))?;
+            }
+            enc.end()?;
+            if let Some(size) = self.metadata_size {
+                enc.key(b"metadata_size")?.int(size as i64)?;
+            }
+            if let Some(port) = self.port {
+                enc.key(b"p")?.int(i64::from(port))?;
+            }
+            if let Some(reqq) = self.reqq {
+                enc.key(b"reqq")?.int(i64::from(reqq))?;
+            }
+            if let Some(client) = &self.client {
+                enc.key(b"v")?.str(client)?;
+            }
+            match self.your_ip {
+                Some(IpAddr::V4(ip)) => {
+                    enc.key(b"yourip")?.bytes(&ip.octets())?;
+                }
+                Some(IpAddr::V6(ip)) => {
+                    enc.key(b"yourip")?.bytes(&ip.octets())?;
+                }
+                None => {}
+            }
+            enc.end()?;
+            Ok(())
+        })
+    }
+}
+
+fn int<T: TryFrom<i64>>(event: Event<'_>) -> Option<T> {
+    match event {
+        Event::Int(n) => T::try_from(n).ok(),
+        _ => None,
+    }
+}
+
+fn ip(event: Event<'_>) -> Option<IpAddr> {
+    match event {
+        Event::Str(bytes) => match bytes.len() {
+            4 => Some(Ipv4Addr::from(<[u8; 4]>::try_from(bytes).ok()?).into()),
+            16 => Some(Ipv6Addr::from(<[u8; 16]>::try_from(bytes).ok()?).into()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn m(events: &mut Events<'_>) -> Result<BTreeMap<String, u8>, HandshakeError> {
+    let invalid = || HandshakeError::Invalid("m");
+    if events.expect_next()? != Event::DictStart {
+        return Err(invalid());
+    }
+    let mut extensions = BTreeMap::new();
+    loop {
+        match events.expect_next()? {
+            Event::End => return Ok(extensions),
+            Event::Key(name) => {
+                let name = String::from_utf8(name.to_vec()).map_err(|_| invalid())?;
+                let id = int(events.expect_next()?).ok_or_else(invalid)?;
+                extensions.insert(name, id);
+            }
+            _ => return Err(invalid()),
+        }
+    }
+}
+
+/// Negotiates from our `m` dict and the peer's. Conflicting entries are
+/// left out of the result and listed in `conflicts`.
+pub fn negotiate(local: &BValue, remote: &BValue) -> Negotiated {
+    let mut conflicts = Vec::new();
+    let local_ids = enabled(local, Side::Local, &mut conflicts);
+    let remote_ids = enabled(remote, Side::Remote, &mut conflicts);
+
+    let extensions = local_ids
+        .iter()
+        .filter_map(|(name, &local)| {
+            let remote = *remote_ids.get(name)?;
+            Some((name.clone(), ExtensionIds { local, remote }))
+        })
+        .collect();
+
+    Negotiated {
+        extensions,
+        conflicts,
+    }
+}
+
+/// Builds an `m` dict from `(name, id)` pairs.
+pub fn m_dict(extensions: &[(&str, u8)]) -> BValue {
+    BValue::Dict(
+        extensions
+            .iter()
+            .map(|&(name, id)| (name.to_string(), BValue::Int(id as i16)))
+            .collect(),
+    )
+}
+
+// Enabled extensions of one side with their ids, minus conflicts.
+fn enabled(m: &BValue, side: Side, conflicts: &mut Vec<Conflict>) -> HashMap<String, u8> {
+    let BValue::Dict(m) = m else {
+        conflicts.push(Conflict::NotADict { side });
+        return HashMap::new();
+    };
+
+    // Sorted so conflicts are reported deterministically.
+    let mut names: Vec<&String> = m.keys().collect();
+    names.sort();
+
+    let mut ids: HashMap<String, u8> = HashMap::new();
+    let mut owners: HashMap<u8, &String> = HashMap::new();
+    for name in names {
+        let id = match &m[name] {
+            BValue::Int(0) => continue,
+            BValue::Int(n) => match u8::try_from(*n) {
+                Ok(id) => id,
+                Err(_) => {
+                    conflicts.push(Conflict::InvalidId {
+                        side,
+                        name: name.clone(),
+                    });
+                    continue;
+                }
+            },
+            _ => {
+                conflicts.push(Conflict::InvalidId {
+                    side,
+                    name: name.clone(),
+                });
+                continue;
+            }
+        };
+
+        match owners.get(&id) {
+            Some(&owner) => {
+                ids.remove(owner);
+                conflicts.push(Conflict::DuplicateId {
+                    side,
+                    id,
+                    names: (owner.clone(), name.clone()),
+                });
+            }
+            None => {
+                owners.insert(id, name);
+                ids.insert(name.clone(), id);
+            }
+        }
+    }
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiates_common_extensions() {
+        let local = m_dict(&[("ut_pex", 1), ("ut_metadata", 2), ("lt_donthave", 3)]);
+        let remote = m_dict(&[("ut_metadata", 3), ("ut_pex", 0), ("upload_only", 4)]);
+
+        let negotiated = negotiate(&local, &remote);
+        assert!(negotiated.conflicts.is_empty());
+        assert_eq!(negotiated.extensions.len(), 1);
+        assert_eq!(negotiated.remote_id("ut_metadata"), Some(3));
+        assert_eq!(negotiated.local_name(2), Some("ut_metadata"));
+        assert_eq!(negotiated.remote_id("ut_pex"), None);
+    }
+
+    #[test]
+    fn test_reports_conflicts() {
+        let local = m_dict(&[("a", 1), ("b", 2)]);
+        let mut remote = m_dict(&[("a", 7), ("b", 7)]);
+        if let BValue::Dict(m) = &mut remote {
+            m.insert("c".to_string(), BValue::Int(300));
+        }
+
+        let negotiated = negotiate(&local, &remote);
+        assert!(negotiated.extensions.is_empty());
+        assert_eq!(
+            negotiated.conflicts,
+            vec![
+                Conflict::DuplicateId {
+                    side: Side::Remote,
+                    id: 7,
+                    names: ("a".to_string(), "b".to_string()),
+                },
+                Conflict::InvalidId {
+                    side: Side::Remote,
+                    name: "c".to_string(),
+                },
+            ]
+        );
+
+        let negotiated = negotiate(&BValue::Int(1), &local);
+        assert_eq!(negotiated.conflicts, vec![Conflict::NotADict { side: Side::Local }]);
+    }
+
+    #[test]
+    fn test_handshake_round_trip() {
+        let input = b"d1:md11:lt_donthavei7e12:ut_holepunchi0e11:ut_metadatai2e6:ut_pexi1ee\
+                      13:metadata_sizei5000000e1:pi6881e4:reqqi500e\
+                      11:upload_onlyi1e1:v13:qBittorrent/56:yourip4:\x7f\0\0\x01e";
+        let handshake = Handshake::from_bytes(input).unwrap();
+        assert_eq!(handshake.port, Some(6881));
+        assert_eq!(handshake.client.as_deref(), Some("qBittorrent/5"));
+        assert_eq!(handshake.metadata_size, Some(5_000_000));
+        assert_eq!(handshake.reqq, Some(500));
+        assert_eq!(handshake.your_ip, Some(Ipv4Addr::LOCALHOST.into()));
+        assert_eq!(handshake.extensions.len(), 4);
+        assert_eq!(handshake.extension_id("ut_metadata"), Some(2));
+        assert_eq!(handshake.extension_id("ut_holepunch"), None);
+        assert_eq!(handshake.extension_id("ut_comment"), None);
+
+        // `upload_only` is not modelled, so it is the only key lost.
+        let encoded = handshake.to_bytes().unwrap();
+        assert_eq!(Handshake::from_bytes(&encoded), Ok(handshake.clone()));
+        assert_eq!(encoded.len(), input.len() - b"11:upload_onlyi1e".len());
+
+        let ours = Handshake {
+            extensions: [("ut_metadata".to_string(), 3), ("ut_pex".to_string(), 1)].into(),
+            ..Handshake::default()
+        };
+        assert_eq!(ours.to_bytes().unwrap(), b"d1:md11:ut_metadatai3e6:ut_pexi1eee");
+        let negotiated = ours.negotiate(&handshake);
+        assert_eq!(negotiated.remote_id("ut_metadata"), Some(2));
+        assert_eq!(negotiated.local_name(1), Some("ut_pex"));
+    }
+
+    #[test]
+    fn test_handshake_errors() {
+        let cases: [(&[u8], HandshakeError); 6] = [
+            (b"d1:pi70000ee", HandshakeError::Invalid("p")),
+            (b"d1:md1:ai256eee", HandshakeError::Invalid("m")),
+            (b"d1:mli1eee", HandshakeError::Invalid("m")),
+            (b"d6:yourip3:abce", HandshakeError::Invalid("yourip")),
+            (b"li1ee", HandshakeError::Invalid("")),
+            (b"d1:pi1e", DecodeError::UnexpectedEof { offset: 7 }.into()),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(Handshake::from_bytes(input), Err(expected));
+        }
+        // Unknown keys of any shape are skipped.
+        let handshake = Handshake::from_bytes(b"d1:xld1:ai1eee1:pi1ee").unwrap();
+        assert_eq!(handshake.port, Some(1));
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::{
+    DELIM_END, DICT_DELIM_BEGIN, DecodeError, Event, Events, LIST_DELIM_BEGIN, get_raw, sha1,
+    to_hex as hex,
+};
+
+// Language-neutral JSON manifests describing how this crate reads a
+// document: the value, its canonical re-encoding and the hashes of both.
+// Other implementations can diff their output against these. The document
+// is read as events, so strings are bytes and integers are `i64` as in
+// real torrents and DHT traffic.
+//
+// {
+//   "input_hex": "...",        raw input bytes
+//   "input_sha1": "...",
+//   "canonical_hex": "...",    re-encoded with sorted keys
+//   "canonical_sha1": "...",
+//   "info_sha1": "..." | null, infohash over the original `info` bytes
+//   "value": <node>
+// }
+//
+// node: {"type": "int", "value": 42}
+//       {"type": "bytes", "hex": "...", "utf8": "..." | null}
+//       {"type": "list", "items": [node, ...]}
+//       {"type": "dict", "entries": [{"key": "..." | null, "key_hex": "...", "value": node}, ...]}
+// Dict entries are listed in sorted key order; of duplicate keys the last
+// wins. `utf8` and `key` are null for bytes that are not UTF-8.
+
+/// A value as the manifest describes it.
+enum Node<'a> {
+    Int(i64),
+    Bytes(&'a [u8]),
+    List(Vec<Node<'a>>),
+    Dict(BTreeMap<&'a [u8], Node<'a>>),
+}
+
+/// Reads `input` and renders its JSON manifest.
+pub fn export_fixture(input: &[u8]) -> Result<String, DecodeError> {
+    if input.is_empty() {
+        return Err(DecodeError::EmptyInput);
+    }
+    let mut events = Events::new(input);
+    let first = events.expect_next()?;
+    let value = read(&mut events, first)?;
+    if events.offset() < input.len() {
+        return Err(DecodeError::TrailingData {
+            offset: events.offset(),
+        });
+    }
+    let mut canonical = Vec::new();
+    encode(&value, &mut canonical);
+    let info = get_raw(input, "info")?;
+
+    let mut out = String::from("{\n");
+    let _ = writeln!(out, "  \"input_hex\": \"{}\",", hex(input));
+    let _ = writeln!(out, "  \"input_sha1\": \"{}\",", hex(&sha1(input)));
+    let _ = writeln!(out, "  \"canonical_hex\": \"{}\",", hex(&canonical));
+    let _ = writeln!(out, "  \"canonical_sha1\": \"{}\",", hex(&sha1(&canonical)));
+    match info {
+        Some(info) => {
+            let _ = writeln!(out, "  \"info_sha1\": \"{}\",", hex(&sha1(info)));
+        }
+        None => out.push_str("  \"info_sha1\": null,\n"),
+    }
+    out.push_str("  \"value\": ");
+    node(&value, &mut out);
+    out.push_str("\n}\n");
+    Ok(out)
+}
+
+/// Reads the value that starts with `event`.
+fn read<'a>(events: &mut Events<'a>, event: Event<'a>) -> Result<Node<'a>, DecodeError> {
+    match event {
+        Event::Int(n) => Ok(Node::Int(n)),
+        Event::Str(bytes) => Ok(Node::Bytes(bytes)),
+        Event::ListStart => {
+            let mut items = Vec::new();
+            loop {
+                match events.expect_next()? {
+                    Event::End => return Ok(Node::List(items)),
+                    event => items.push(read(events, event)?),
+                }
+            }
+        }
+        Event::DictStart => {
+            let mut entries = BTreeMap::new();
+            loop {
+                let offset = events.offset();
+                match events.expect_next()? {
+                    Event::End => return Ok(Node::Dict(entries)),
+                    Event::Key(key) => {
+                        let event = events.expect_next()?;
+                        entries.insert(key, read(events, event)?);
+                    }
+                    _ => return Err(DecodeError::NonStringKey { offset }),
+                }
+            }
+        }
+        Event::Key(_) | Event::End => Err(DecodeError::UnexpectedEnd {
+            offset: events.offset(),
+        }),
+    }
+}
+
+/// Canonical encoding: the entries of a `Node::Dict` are already sorted.
+fn encode(value: &Node<'_>, out: &mut Vec<u8>) {
+    match value {
+        Node::Int(n) => out.extend_from_slice(format!("i{}e", n).as_bytes()),
+        Node::Bytes(bytes) => encode_bytes(bytes, out),
+        Node::List(items) => {
+            out.push(LIST_DELIM_BEGIN);
+            for item in items {
+                encode(item, out);
+            }
+            out.push(DELIM_END);
+        }
+        Node::Dict(entries) => {
+            out.push(DICT_DELIM_BEGIN);
+            for (key, value) in entries {
+                encode_bytes(key, out);
+                encode(value, out);
+            }
+            out.push(DELIM_END);
+        }
+    }
+}
+
+fn encode_bytes(bytes: &[u8], out: &mut Vec<u8>) {
+    out.extend_from_slice(format!("{}:", bytes.len()).as_bytes());
+    out.extend_from_slice(bytes);
+}
+
+fn node(value: &Node<'_>, out: &mut String) {
+    match value {
+        Node::Int(n) => {
+            let _ = write!(out, "{{\"type\": \"int\", \"value\": {}}}", n);
+        }
+        Node::Bytes(bytes) => {
+            let _ = write!(
+                out,
+                "{{\"type\": \"bytes\", \"hex\": \"{}\", \"utf8\": {}}}",
+                hex(bytes),
+                json_utf8(bytes)
+            );
+        }
+        Node::List(list) => {
+            out.push_str("{\"type\": \"list\", \"items\": [");
+            for (i, item) in list.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                node(item, out);
+            }
+            out.push_str("]}");
+        }
+        Node::Dict(dict) => {
+            out.push_str("{\"type\": \"dict\", \"entries\": [");
+            for (i, (key, value)) in dict.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                let _ = write!(
+                    out,
+                    "{{\"key\": {}, \"key_hex\": \"{}\", \"value\": ",
+                    json_utf8(key),
+                    hex(key)
+                );
+                node(value, out);
+                out.push('}');
+            }
+            out.push_str("]}");
+        }
+    }
+}
+
+/// `bytes` as a JSON string, or `null` if they are not UTF-8.
+fn json_utf8(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(s) => json_string(s),
+        Err(_) => "null".to_string(),
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest() {
+        let input = b"d1:bi1e1:al2:q\"ee";
+        let canonical = b"d1:al2:q\"e1:bi1ee";
+        let expected = format!(
+            "{{\n  \"input_hex\": \"{}\",\n  \"input_sha1\": \"{}\",\n  \
+             \"canonical_hex\": \"{}\",\n  \"canonical_sha1\": \"{}\",\n  \
+             \"info_sha1\": null,\n  \"value\": {}\n}}\n",
+            hex(input),
+            hex(&sha1(input)),
+            hex(canonical),
+            hex(&sha1(canonical)),
+            "{\"type\": \"dict\", \"entries\": [\
+             {\"key\": \"a\", \"key_hex\": \"61\", \"value\": {\"type\": \"list\", \"items\": [\
+             {\"type\": \"bytes\", \"hex\": \"7122\", \"utf8\": \"q\\\"\"}]}}, \
+             {\"key\": \"b\", \"key_hex\": \"62\", \"value\": {\"type\": \"int\", \"value\": 1}}]}"
+        );
+        assert_eq!(export_fixture(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_info_hash_uses_original_bytes() {
+        let input = b"d4:infod1:bi1e1:ai2eee";
+        let manifest = export_fixture(input).unwrap();
+        let expected = format!("\"info_sha1\": \"{}\"", hex(&sha1(b"d1:bi1e1:ai2ee")));
+        assert!(manifest.contains(&expected));
+        assert!(export_fixture(b"i1ei2e").is_err());
+    }
+
+    #[test]
+    fn test_binary_and_large_values() {
+        let input = b"d4:infod6:lengthi5000000000e6:pieces2:\xff\0e1:\xfei1ee";
+        let manifest = export_fixture(input).unwrap();
+        assert!(manifest.contains("{\"type\": \"int\", \"value\": 5000000000}"));
+        assert!(manifest.contains("{\"type\": \"bytes\", \"hex\": \"ff00\", \"utf8\": null}"));
+        // The binary key sorts after `info`.
+        let canonical = hex(b"d4:infod6:lengthi5000000000e6:pieces2:\xff\0e1:\xfei1ee");
+        assert!(manifest.contains(&canonical));
+        assert!(manifest.contains("{\"key\": null, \"key_hex\": \"fe\""));
+    }
+}
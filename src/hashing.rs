@@ -0,0 +1,168 @@
+use std::io::{self, Read};
+use std::ops::Range;
+
+// Reader adapter that feeds selected byte ranges of a stream into digests
+// while the bytes pass through to the consumer (e.g. the decoder), and the
+// v1 piece hasher shared by torrent creation and verification.
+
+/// Anything that can absorb bytes incrementally, such as a SHA-1 hasher.
+pub trait Digest {
+    fn update(&mut self, data: &[u8]);
+}
+
+/// Collects the raw bytes of a range. Handy for capturing a slice of the
+/// stream without buffering all of it.
+impl Digest for Vec<u8> {
+    fn update(&mut self, data: &[u8]) {
+        self.extend_from_slice(data);
+    }
+}
+
+/// Passes reads through to `inner` while feeding the registered ranges to
+/// their digests. Only `Read` is implemented; `AsyncRead` is not
+/// supported yet.
+pub struct HashingReader<R, D> {
+    inner: R,
+    pos: u64,
+    targets: Vec<(Range<u64>, D)>,
+}
+
+impl<R: Read, D: Digest> HashingReader<R, D> {
+    pub fn new(inner: R) -> Self {
+        HashingReader {
+            inner,
+            pos: 0,
+            targets: Vec::new(),
+        }
+    }
+
+    /// Feeds the bytes at stream offsets `range` into `digest`. Bytes that
+    /// already went past are not replayed. Returns the digest's index.
+    pub fn track(&mut self, range: Range<u64>, digest: D) -> usize {
+        self.targets.push((range, digest));
+        self.targets.len() - 1
+    }
+
+    /// Number of bytes read through the adapter so far.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    pub fn digest(&self, index: usize) -> Option<&D> {
+        self.targets.get(index).map(|(_, d)| d)
+    }
+
+    pub fn into_parts(self) -> (R, Vec<D>) {
+        let digests = self.targets.into_iter().map(|(_, d)| d).collect();
+        (self.inner, digests)
+    }
+}
+
+impl<R: Read, D: Digest> Read for HashingReader<R, D> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        let chunk_start = self.pos;
+        let chunk_end = chunk_start + n as u64;
+
+        for (range, digest) in self.targets.iter_mut() {
+            let start = range.start.max(chunk_start);
+            let end = range.end.min(chunk_end);
+            if start < end {
+                let from = (start - chunk_start) as usize;
+                let to = (end - chunk_start) as usize;
+                digest.update(&buf[from..to]);
+            }
+        }
+
+        self.pos = chunk_end;
+        Ok(n)
+    }
+}
+
+/// SHA-1 of each `piece_length` run of a byte stream, the way v1 pieces
+/// run across file boundaries.
+#[cfg(feature = "sha1")]
+pub(crate) struct PieceHasher {
+    hasher: crate::Sha1,
+    piece_length: u64,
+    in_piece: u64,
+}
+
+#[cfg(feature = "sha1")]
+impl PieceHasher {
+    pub(crate) fn new(piece_length: u64) -> Self {
+        PieceHasher {
+            hasher: crate::Sha1::new(),
+            piece_length,
+            in_piece: 0,
+        }
+    }
+
+    /// Bytes of the current, unfinished piece seen so far.
+    pub(crate) fn in_piece(&self) -> u64 {
+        self.in_piece
+    }
+
+    /// Hashes the front of `data` up to the end of the current piece.
+    /// Returns how much was taken, and the piece's hash if it is now
+    /// complete.
+    pub(crate) fn update(&mut self, data: &[u8]) -> (usize, Option<[u8; 20]>) {
+        let take = data.len().min((self.piece_length - self.in_piece) as usize);
+        self.hasher.update(&data[..take]);
+        self.in_piece += take as u64;
+        (take, self.end_if_full())
+    }
+
+    /// Like `update` for `len` bytes that are not available. A piece
+    /// completed this way gets a meaningless hash.
+    pub(crate) fn skip(&mut self, len: u64) -> (u64, Option<[u8; 20]>) {
+        let take = len.min(self.piece_length - self.in_piece);
+        self.in_piece += take;
+        (take, self.end_if_full())
+    }
+
+    /// Ends the last, short piece, if there is one, and returns its hash.
+    pub(crate) fn finish(&mut self) -> Option<[u8; 20]> {
+        if self.in_piece == 0 {
+            return None;
+        }
+        self.in_piece = 0;
+        Some(std::mem::take(&mut self.hasher).finish())
+    }
+
+    fn end_if_full(&mut self) -> Option<[u8; 20]> {
+        if self.in_piece < self.piece_length {
+            return None;
+        }
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranges_are_captured_across_reads() {
+        let input: &[u8] = b"d8:announce3:url4:infod6:lengthi42eee";
+        let mut reader = HashingReader::new(input);
+        let info = reader.track(22..36, Vec::new());
+        let all = reader.track(0..u64::MAX, Vec::new());
+
+        // Small buffer so ranges straddle read boundaries.
+        let mut buf = [0u8; 5];
+        let mut out = Vec::new();
+        loop {
+            let n = reader.read(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            out.extend_from_slice(&buf[..n]);
+        }
+
+        assert_eq!(out, input);
+        assert_eq!(reader.position(), input.len() as u64);
+        assert_eq!(reader.digest(info).unwrap(), b"d6:lengthi42ee");
+        assert_eq!(reader.digest(all).unwrap(), input);
+    }
+}
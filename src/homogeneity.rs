@@ -0,0 +1,143 @@
+use std::fmt;
+
+use crate::BValue;
+
+// Opt-in check that the lists at a given path hold a single kind of value,
+// e.g. that every `announce-list` tier is a list of strings.
+
+/// Path component that matches every element of a list.
+pub const ANY_ITEM: &str = "*";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Str,
+    Int,
+    List,
+    Dict,
+    None,
+    External,
+}
+
+impl BValue {
+    pub fn kind(&self) -> Kind {
+        match self {
+            BValue::Str(_) => Kind::Str,
+            BValue::Int(_) => Kind::Int,
+            BValue::List(_) => Kind::List,
+            BValue::Dict(_) => Kind::Dict,
+            BValue::None => Kind::None,
+            BValue::External(_) => Kind::External,
+        }
+    }
+}
+
+/// First list element whose kind differs from the list's first element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heterogeneous {
+    /// Path to the offending list, with wildcards resolved to indices.
+    pub path: Vec<String>,
+    pub index: usize,
+    pub expected: Kind,
+    pub found: Kind,
+}
+
+impl fmt::Display for Heterogeneous {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "List at /{} has a {:?} at index {}, expected {:?}.",
+            self.path.join("/"),
+            self.found,
+            self.index,
+            self.expected
+        )
+    }
+}
+
+impl std::error::Error for Heterogeneous {}
+
+/// Checks that every list reached by `path` is homogeneous. Path components
+/// are dict keys, or `ANY_ITEM` to descend into each element of a list.
+/// Parts of the path that do not exist, or do not lead to a list, are
+/// not checked.
+///
+/// `check_homogeneous(&torrent, &["announce-list", ANY_ITEM])` checks each
+/// tier; `&["announce-list"]` checks that the tiers are all the same kind.
+pub fn check_homogeneous(value: &BValue, path: &[&str]) -> Result<(), Heterogeneous> {
+    let mut trail = Vec::new();
+    walk(value, path, &mut trail)
+}
+
+fn walk(value: &BValue, path: &[&str], trail: &mut Vec<String>) -> Result<(), Heterogeneous> {
+    let Some((head, rest)) = path.split_first() else {
+        return check_list(value, trail);
+    };
+
+    match value {
+        BValue::List(list) if *head == ANY_ITEM => {
+            for (i, item) in list.iter().enumerate() {
+                trail.push(i.to_string());
+                walk(item, rest, trail)?;
+                trail.pop();
+            }
+            Ok(())
+        }
+        BValue::Dict(dict) => match dict.get(*head) {
+            Some(child) => {
+                trail.push(head.to_string());
+                walk(child, rest, trail)?;
+                trail.pop();
+                Ok(())
+            }
+            None => Ok(()),
+        },
+        _ => Ok(()),
+    }
+}
+
+fn check_list(value: &BValue, trail: &[String]) -> Result<(), Heterogeneous> {
+    let BValue::List(list) = value else {
+        return Ok(());
+    };
+    let Some(expected) = list.first().map(BValue::kind) else {
+        return Ok(());
+    };
+
+    match list.iter().position(|v| v.kind() != expected) {
+        Some(index) => Err(Heterogeneous {
+            path: trail.to_vec(),
+            index,
+            expected,
+            found: list[index].kind(),
+        }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode;
+
+    #[test]
+    fn test_homogeneous_tiers_pass() {
+        let (torrent, _) = decode(b"d13:announce-listll1:a1:bel1:ceee").unwrap();
+        assert_eq!(check_homogeneous(&torrent, &["announce-list"]), Ok(()));
+        assert_eq!(check_homogeneous(&torrent, &["announce-list", ANY_ITEM]), Ok(()));
+        assert_eq!(check_homogeneous(&torrent, &["missing"]), Ok(()));
+    }
+
+    #[test]
+    fn test_reports_first_heterogeneous_element() {
+        let (torrent, _) = decode(b"d13:announce-listll1:ael1:bi7eeee").unwrap();
+        assert_eq!(
+            check_homogeneous(&torrent, &["announce-list", ANY_ITEM]),
+            Err(Heterogeneous {
+                path: vec!["announce-list".to_string(), "1".to_string()],
+                index: 1,
+                expected: Kind::Str,
+                found: Kind::Int,
+            })
+        );
+    }
+}
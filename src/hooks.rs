@@ -0,0 +1,293 @@
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::{ANY_ITEM, BValue, DICT_DELIM_BEGIN, DecodeError, Events, LIST_DELIM_BEGIN, Pool};
+
+// Decoding with callbacks registered for specific paths. The value at a
+// hooked path is handed to the callback as raw encoded bytes and never
+// decoded into a `BValue`, so large fields such as `/info/pieces` (which
+// is binary and would not pass as a UTF-8 string anyway) are handled once.
+//
+//     let hooks = DecodeHooks::new().on("/info/pieces", |raw| Pieces::parse(raw));
+//     let (torrent, _) = decode_with_hooks(input, &hooks)?;
+
+/// A user value produced by a decode hook.
+///
+/// Its contents are opaque to this crate: externals print as
+/// `External(..)`, never compare equal and have no encoded form. Clones
+/// share the same value.
+#[derive(Clone)]
+pub struct External(Arc<dyn Any + Send + Sync>);
+
+impl External {
+    pub fn new<T: Any + Send + Sync>(value: T) -> Self {
+        External(Arc::new(value))
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.0.downcast_ref()
+    }
+
+    pub fn downcast<T: Any + Send + Sync>(self) -> Result<Arc<T>, Self> {
+        self.0.downcast().map_err(External)
+    }
+}
+
+impl fmt::Debug for External {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("External(..)")
+    }
+}
+
+impl PartialEq for External {
+    fn eq(&self, _other: &Self) -> bool {
+        false
+    }
+}
+
+type Hook = Box<dyn Fn(&[u8]) -> External + Send + Sync>;
+
+/// Callbacks keyed by path. Paths are `/`-separated dict keys and list
+/// indices; `*` (`ANY_ITEM`) matches any single key or index, so
+/// `/info/files/*/path` covers every file entry.
+#[derive(Default)]
+pub struct DecodeHooks {
+    hooks: Vec<(Vec<String>, Hook)>,
+}
+
+impl DecodeHooks {
+    pub fn new() -> Self {
+        DecodeHooks::default()
+    }
+
+    /// Registers `hook` for the values at `path`. It receives the value's
+    /// raw encoded bytes, as `get_raw` returns them. If several hooks match
+    /// a path, the first registered wins.
+    pub fn on<F, T>(mut self, path: &str, hook: F) -> Self
+    where
+        F: Fn(&[u8]) -> T + Send + Sync + 'static,
+        T: Any + Send + Sync,
+    {
+        let path = path
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect();
+        self.hooks.push((path, Box::new(move |raw| External::new(hook(raw)))));
+        self
+    }
+
+    fn find(&self, path: &[String]) -> Option<&Hook> {
+        self.hooks
+            .iter()
+            .find(|(pattern, _)| pattern.len() == path.len() && matches(pattern, path))
+            .map(|(_, hook)| hook)
+    }
+
+    /// Whether some hook applies strictly below `path`.
+    fn below(&self, path: &[String]) -> bool {
+        self.hooks
+            .iter()
+            .any(|(pattern, _)| pattern.len() > path.len() && matches(pattern, path))
+    }
+}
+
+impl fmt::Debug for DecodeHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.hooks.iter().map(|(path, _)| path.join("/")))
+            .finish()
+    }
+}
+
+fn matches(pattern: &[String], path: &[String]) -> bool {
+    pattern
+        .iter()
+        .zip(path)
+        .all(|(p, s)| p == ANY_ITEM || p == s)
+}
+
+/// Like `decode_prefix`, but values at hooked paths become
+/// `BValue::External` holding whatever their hook returned.
+pub fn decode_with_hooks<'a>(
+    input: &'a [u8],
+    hooks: &DecodeHooks,
+) -> Result<(BValue, &'a [u8]), DecodeError> {
+    if input.is_empty() {
+        return Err(DecodeError::EmptyInput);
+    }
+
+    let mut decoder = Hooked {
+        input,
+        hooks,
+        path: Vec::new(),
+        pool: Pool::with_capacity(0),
+    };
+    let (value, end) = decoder.value(0)?;
+    Ok((value, &input[end..]))
+}
+
+struct Hooked<'a, 'h> {
+    input: &'a [u8],
+    hooks: &'h DecodeHooks,
+    path: Vec<String>,
+    pool: Pool,
+}
+
+impl Hooked<'_, '_> {
+    /// Decodes the value at `pos`, returning it and the offset just past it.
+    fn value(&mut self, pos: usize) -> Result<(BValue, usize), DecodeError> {
+        if let Some(hook) = self.hooks.find(&self.path) {
+            let end = self.value_end(pos)?;
+            return Ok((BValue::External(hook(&self.input[pos..end])), end));
+        }
+
+        match self.input.get(pos) {
+            Some(&LIST_DELIM_BEGIN) if self.hooks.below(&self.path) => self.list(pos),
+            Some(&DICT_DELIM_BEGIN) if self.hooks.below(&self.path) => self.dict(pos),
+            // Nothing hooked in here; decode as usual.
+            _ => self.plain(pos),
+        }
+    }
+
+    fn plain(&mut self, pos: usize) -> Result<(BValue, usize), DecodeError> {
+        let (value, consumed) =
+            crate::decode_at(&self.input[pos..], &mut self.pool).map_err(|e| e.offset_by(pos))?;
+        Ok((value, pos + consumed))
+    }
+
+    fn list(&mut self, pos: usize) -> Result<(BValue, usize), DecodeError> {
+        let mut idx = pos + 1;
+        let mut list = self.pool.list();
+        while !crate::at_end(self.input, idx)? {
+            self.path.push(list.len().to_string());
+            let (value, end) = self.value(idx)?;
+            self.path.pop();
+            idx = end;
+            list.push(value);
+        }
+        Ok((BValue::List(list), idx + 1))
+    }
+
+    fn dict(&mut self, pos: usize) -> Result<(BValue, usize), DecodeError> {
+        let mut idx = pos + 1;
+        let mut dict = self.pool.dict();
+        while !crate::at_end(self.input, idx)? {
+            let key_pos = idx;
+            let (key, end) = self.plain(idx)?;
+            idx = end;
+            let BValue::Str(key) = key else {
+                return Err(DecodeError::NonStringKey { offset: key_pos });
+            };
+
+            if crate::at_end(self.input, idx)? {
+                return Err(DecodeError::MissingValue { offset: key_pos });
+            }
+            self.path.push(key);
+            let (value, end) = self.value(idx)?;
+            let key = self.path.pop().unwrap_or_default();
+            idx = end;
+
+            dict.insert(key, value);
+        }
+        Ok((BValue::Dict(dict), idx + 1))
+    }
+
+    /// End of the value at `pos`, checking syntax only. Strings may hold
+    /// any bytes, since the hook decides what they mean.
+    fn value_end(&self, pos: usize) -> Result<usize, DecodeError> {
+        let mut events = Events::new(&self.input[pos..]);
+        loop {
+            match events.next() {
+                Some(Ok(_)) if events.depth() == 0 => return Ok(pos + events.offset()),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e.offset_by(pos)),
+                None => {
+                    return Err(DecodeError::UnexpectedEof {
+                        offset: self.input.len(),
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Pieces(Vec<[u8; 2]>);
+
+    fn pieces(raw: &[u8]) -> Pieces {
+        let colon = raw.iter().position(|&b| b == b':').unwrap();
+        Pieces(
+            raw[colon + 1..]
+                .chunks_exact(2)
+                .map(|c| [c[0], c[1]])
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_hooked_path_becomes_external() {
+        let hooks = DecodeHooks::new().on("/info/pieces", pieces);
+        let input = b"d4:infod4:name3:foo6:pieces4:\xff\x00\x01\x02ee";
+        let (value, rest) = decode_with_hooks(input, &hooks).unwrap();
+        assert!(rest.is_empty());
+
+        let BValue::Dict(torrent) = value else { panic!() };
+        let BValue::Dict(info) = &torrent["info"] else { panic!() };
+        assert_eq!(info["name"], BValue::Str("foo".to_string()));
+        let BValue::External(external) = &info["pieces"] else { panic!() };
+        assert_eq!(
+            external.downcast_ref::<Pieces>(),
+            Some(&Pieces(vec![[0xff, 0x00], [0x01, 0x02]]))
+        );
+
+        // Without the hook, the binary field is not a valid string.
+        assert!(crate::decode(input).is_err());
+    }
+
+    #[test]
+    fn test_wildcards_and_list_indices() {
+        let hooks = DecodeHooks::new()
+            .on("/files/*/length", |raw| raw.len())
+            .on("/files/1/path", |raw| raw.to_vec());
+        let input = b"d5:filesld6:lengthi42e4:path1:aed6:lengthi7e4:path1:beee";
+        let (value, _) = decode_with_hooks(input, &hooks).unwrap();
+
+        let BValue::Dict(root) = value else { panic!() };
+        let BValue::List(files) = &root["files"] else { panic!() };
+        let BValue::Dict(first) = &files[0] else { panic!() };
+        let BValue::Dict(second) = &files[1] else { panic!() };
+
+        let length = |v: &BValue| match v {
+            BValue::External(e) => e.downcast_ref::<usize>().copied(),
+            _ => None,
+        };
+        assert_eq!(length(&first["length"]), Some(4));
+        assert_eq!(length(&second["length"]), Some(3));
+        assert_eq!(first["path"], BValue::Str("a".to_string()));
+        let BValue::External(path) = &second["path"] else { panic!() };
+        assert_eq!(path.downcast_ref::<Vec<u8>>().unwrap(), b"1:b");
+    }
+
+    #[test]
+    fn test_errors_keep_absolute_offsets() {
+        let hooks = DecodeHooks::new().on("/a", |raw| raw.len());
+        assert_eq!(
+            decode_with_hooks(b"d1:ai1", &hooks),
+            Err(DecodeError::UnexpectedEof { offset: 6 })
+        );
+        assert_eq!(
+            decode_with_hooks(b"d1:bi1xe1:ai1ee", &hooks),
+            Err(DecodeError::InvalidInteger { offset: 4 })
+        );
+        assert_eq!(
+            decode_with_hooks(b"d1:ae", &hooks),
+            Err(DecodeError::MissingValue { offset: 1 })
+        );
+    }
+}
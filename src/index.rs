@@ -0,0 +1,125 @@
+use std::ops::Index;
+
+use crate::BValue;
+
+// Lookups by dict key or list position. `get`/`get_mut` return `None` on a
+// miss; `value["info"]["piece length"]` style indexing instead yields
+// `BValue::None`, as serde_json does, so chains stay short and the final
+// `as_*` accessor decides.
+
+/// Something a `BValue` can be indexed by: `&str` keys for dicts and
+/// `usize` positions for lists.
+pub trait ValueIndex {
+    fn index_into<'v>(&self, value: &'v BValue) -> Option<&'v BValue>;
+    fn index_into_mut<'v>(&self, value: &'v mut BValue) -> Option<&'v mut BValue>;
+}
+
+impl ValueIndex for str {
+    fn index_into<'v>(&self, value: &'v BValue) -> Option<&'v BValue> {
+        match value {
+            BValue::Dict(dict) => dict.get(self),
+            _ => None,
+        }
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut BValue) -> Option<&'v mut BValue> {
+        match value {
+            BValue::Dict(dict) => dict.get_mut(self),
+            _ => None,
+        }
+    }
+}
+
+impl ValueIndex for String {
+    fn index_into<'v>(&self, value: &'v BValue) -> Option<&'v BValue> {
+        self.as_str().index_into(value)
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut BValue) -> Option<&'v mut BValue> {
+        self.as_str().index_into_mut(value)
+    }
+}
+
+impl ValueIndex for usize {
+    fn index_into<'v>(&self, value: &'v BValue) -> Option<&'v BValue> {
+        match value {
+            BValue::List(list) => list.get(*self),
+            _ => None,
+        }
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut BValue) -> Option<&'v mut BValue> {
+        match value {
+            BValue::List(list) => list.get_mut(*self),
+            _ => None,
+        }
+    }
+}
+
+impl<T: ValueIndex + ?Sized> ValueIndex for &T {
+    fn index_into<'v>(&self, value: &'v BValue) -> Option<&'v BValue> {
+        (**self).index_into(value)
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut BValue) -> Option<&'v mut BValue> {
+        (**self).index_into_mut(value)
+    }
+}
+
+impl BValue {
+    /// The dict entry or list item at `index`, if there is one.
+    pub fn get<I: ValueIndex>(&self, index: I) -> Option<&BValue> {
+        index.index_into(self)
+    }
+
+    pub fn get_mut<I: ValueIndex>(&mut self, index: I) -> Option<&mut BValue> {
+        index.index_into_mut(self)
+    }
+}
+
+static NONE: BValue = BValue::None;
+
+impl<I: ValueIndex> Index<I> for BValue {
+    type Output = BValue;
+
+    fn index(&self, index: I) -> &BValue {
+        index.index_into(self).unwrap_or(&NONE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode_exact;
+
+    #[test]
+    fn test_chained_lookups() {
+        let torrent =
+            decode_exact(b"d4:infod5:filesld6:lengthi42eee12:piece lengthi16eee").unwrap();
+        assert_eq!(torrent["info"]["piece length"].as_int(), Some(16));
+        assert_eq!(torrent["info"]["files"][0]["length"].as_int(), Some(42));
+
+        assert_eq!(torrent["missing"]["deeper"], BValue::None);
+        assert_eq!(torrent["info"]["files"][7], BValue::None);
+        assert_eq!(torrent[0], BValue::None);
+        assert_eq!(torrent["info"]["piece length"]["x"], BValue::None);
+    }
+
+    #[test]
+    fn test_get_and_get_mut() {
+        let mut torrent = decode_exact(b"d4:infod5:filesld6:lengthi42eeeee").unwrap();
+        let length = torrent
+            .get("info")
+            .and_then(|info| info.get("files"))
+            .and_then(|files| files.get(0))
+            .and_then(|file| file.get("length".to_string()));
+        assert_eq!(length, Some(&BValue::Int(42)));
+        assert_eq!(torrent.get(0), None);
+        assert_eq!(torrent.get("info").and_then(|i| i.get("nope")), None);
+
+        if let Some(files) = torrent.get_mut("info").and_then(|i| i.get_mut("files")) {
+            *files.get_mut(0).unwrap() = BValue::Int(1);
+        }
+        assert_eq!(torrent["info"]["files"][0], BValue::Int(1));
+    }
+}
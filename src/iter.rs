@@ -0,0 +1,234 @@
+use std::slice;
+use std::vec;
+
+use crate::BValue;
+
+// Iteration over container values without matching on the variant first.
+// Lists yield `Item::Element`, dicts yield `Item::Entry` in sorted key order
+// (the order they are encoded in), and every other value yields nothing.
+//
+//     for file in torrent["info"]["files"].iter() {
+//         let length = file.value()["length"].as_int();
+//     }
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item<K, V> {
+    Element(V),
+    Entry(K, V),
+}
+
+impl<K, V> Item<K, V> {
+    /// The dict key, or `None` for a list element.
+    pub fn key(&self) -> Option<&K> {
+        match self {
+            Item::Element(_) => None,
+            Item::Entry(key, _) => Some(key),
+        }
+    }
+
+    pub fn value(&self) -> &V {
+        match self {
+            Item::Element(value) | Item::Entry(_, value) => value,
+        }
+    }
+
+    pub fn into_value(self) -> V {
+        match self {
+            Item::Element(value) | Item::Entry(_, value) => value,
+        }
+    }
+}
+
+pub struct Iter<'a>(Inner<slice::Iter<'a, BValue>, (&'a String, &'a BValue)>);
+
+pub struct IterMut<'a>(Inner<slice::IterMut<'a, BValue>, (&'a String, &'a mut BValue)>);
+
+pub struct IntoIter(Inner<vec::IntoIter<BValue>, (String, BValue)>);
+
+enum Inner<L, E> {
+    Empty,
+    List(L),
+    Dict(vec::IntoIter<E>),
+}
+
+impl<L: Iterator, E> Inner<L, E> {
+    fn next<K, V>(&mut self) -> Option<Item<K, V>>
+    where
+        L::Item: Into<V>,
+        E: Into<(K, V)>,
+    {
+        match self {
+            Inner::Empty => None,
+            Inner::List(items) => items.next().map(|v| Item::Element(v.into())),
+            Inner::Dict(entries) => entries.next().map(|e| {
+                let (key, value) = e.into();
+                Item::Entry(key, value)
+            }),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            Inner::Empty => (0, Some(0)),
+            Inner::List(items) => items.size_hint(),
+            Inner::Dict(entries) => entries.size_hint(),
+        }
+    }
+}
+
+fn sorted<K: Ord, V>(mut entries: Vec<(K, V)>) -> vec::IntoIter<(K, V)> {
+    entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    entries.into_iter()
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = Item<&'a String, &'a BValue>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a> Iterator for IterMut<'a> {
+    type Item = Item<&'a String, &'a mut BValue>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl Iterator for IntoIter {
+    type Item = Item<String, BValue>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+impl ExactSizeIterator for IterMut<'_> {}
+impl ExactSizeIterator for IntoIter {}
+
+impl BValue {
+    /// Dict entries in canonical order, i.e. sorted by key bytes as they are
+    /// encoded. Empty for anything but a dict.
+    pub fn dict_iter_sorted(&self) -> vec::IntoIter<(&String, &BValue)> {
+        match self {
+            BValue::Dict(dict) => sorted(dict.iter().collect()),
+            _ => Vec::new().into_iter(),
+        }
+    }
+
+    /// List elements or dict entries; empty for anything else.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter(match self {
+            BValue::List(list) => Inner::List(list.iter()),
+            BValue::Dict(_) => Inner::Dict(self.dict_iter_sorted()),
+            _ => Inner::Empty,
+        })
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
+        IterMut(match self {
+            BValue::List(list) => Inner::List(list.iter_mut()),
+            BValue::Dict(dict) => Inner::Dict(sorted(dict.iter_mut().collect())),
+            _ => Inner::Empty,
+        })
+    }
+}
+
+impl IntoIterator for BValue {
+    type Item = Item<String, BValue>;
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> IntoIter {
+        IntoIter(match self {
+            BValue::List(list) => Inner::List(list.into_iter()),
+            BValue::Dict(dict) => Inner::Dict(sorted(dict.into_iter().collect())),
+            _ => Inner::Empty,
+        })
+    }
+}
+
+impl<'a> IntoIterator for &'a BValue {
+    type Item = Item<&'a String, &'a BValue>;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut BValue {
+    type Item = Item<&'a String, &'a mut BValue>;
+    type IntoIter = IterMut<'a>;
+
+    fn into_iter(self) -> IterMut<'a> {
+        self.iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode_exact;
+
+    #[test]
+    fn test_iterate_list_and_dict() {
+        let files = decode_exact(b"ld6:lengthi1eed6:lengthi2eee").unwrap();
+        let lengths: Vec<_> = files.iter().map(|f| f.value()["length"].as_int()).collect();
+        assert_eq!(lengths, vec![Some(1), Some(2)]);
+        assert!(files.iter().all(|f| f.key().is_none()));
+
+        let dict = decode_exact(b"d1:ci3e1:ai1e1:bi2ee").unwrap();
+        let keys: Vec<_> = (&dict).into_iter().filter_map(|e| e.key().cloned()).collect();
+        assert_eq!(keys, vec!["a", "b", "c"]);
+        assert_eq!(dict.iter().len(), 3);
+
+        assert_eq!(BValue::Int(1).iter().next(), None);
+        assert_eq!(BValue::None.into_iter().next(), None);
+    }
+
+    #[test]
+    fn test_dict_iter_sorted() {
+        let dict = decode_exact("d1:bi2e2:\u{e9}i3e1:Ai0e1:ai1ee".as_bytes()).unwrap();
+        let keys: Vec<&str> = dict.dict_iter_sorted().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, vec!["A", "a", "b", "\u{e9}"]);
+        assert_eq!(dict.dict_iter_sorted().nth(1).map(|(_, v)| v), Some(&BValue::Int(1)));
+        assert_eq!(BValue::List(vec![]).dict_iter_sorted().len(), 0);
+    }
+
+    #[test]
+    fn test_iter_mut_and_into_iter() {
+        let mut dict = decode_exact(b"d1:ai1e1:bi2ee").unwrap();
+        for mut entry in &mut dict {
+            if let Item::Entry(_, value) = &mut entry {
+                **value = BValue::Int(0);
+            }
+        }
+        let owned: Vec<_> = dict.into_iter().collect();
+        assert_eq!(
+            owned,
+            vec![
+                Item::Entry("a".to_string(), BValue::Int(0)),
+                Item::Entry("b".to_string(), BValue::Int(0)),
+            ]
+        );
+
+        let list = decode_exact(b"li1e1:xe").unwrap();
+        let values: Vec<_> = list.into_iter().map(Item::into_value).collect();
+        assert_eq!(values, vec![BValue::Int(1), BValue::Str("x".to_string())]);
+    }
+}
@@ -0,0 +1,703 @@
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+
+use crate::{
+    COMPACT_V4_LEN, COMPACT_V6_LEN, DecodeError, Encoder, Event, Events, compact_peers_v4,
+    compact_peers_v6, encode_compact_peers_v4, encode_compact_peers_v6,
+};
+
+// KRPC, the message format of the mainline DHT (BEP 5). Every message is a
+// dict with a transaction ID `t` and a type `y`: a query (`q` names the
+// method, `a` holds the arguments), a response (`r`), or an error (`e`, a
+// code and a message). Node IDs, tokens and peers are raw bytes, so
+// messages are read straight from `Events` instead of through a `BValue`.
+//
+// Responses do not say which query they answer; `Response` has every field
+// the four methods use and the caller matches it up by transaction ID.
+//
+// Nodes travel as compact node info: the 20-byte ID followed by a compact
+// address, 26 bytes per node under `nodes` and 38 under `nodes6` (BEP 32).
+
+pub type NodeId = [u8; 20];
+
+/// Length of one IPv4 node in `nodes`.
+pub const COMPACT_NODE_V4_LEN: usize = 20 + COMPACT_V4_LEN;
+/// Length of one IPv6 node in `nodes6`.
+pub const COMPACT_NODE_V6_LEN: usize = 20 + COMPACT_V6_LEN;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    /// Chosen by the querying node and echoed back in the reply.
+    pub transaction_id: Vec<u8>,
+    /// Client version (`v`), if the sender included one.
+    pub version: Option<Vec<u8>>,
+    pub body: Body,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Body {
+    Query(Query),
+    Response(Response),
+    Error(ErrorReply),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Query {
+    Ping {
+        id: NodeId,
+    },
+    FindNode {
+        id: NodeId,
+        target: NodeId,
+    },
+    GetPeers {
+        id: NodeId,
+        info_hash: [u8; 20],
+    },
+    AnnouncePeer {
+        id: NodeId,
+        info_hash: [u8; 20],
+        port: u16,
+        /// Use the port the query came from instead of `port`.
+        implied_port: bool,
+        /// From an earlier `get_peers` response.
+        token: Vec<u8>,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Response {
+    /// The responding node.
+    pub id: NodeId,
+    /// Closest nodes for `find_node` and `get_peers`, from `nodes` and
+    /// then `nodes6`.
+    pub nodes: Vec<(NodeId, SocketAddr)>,
+    /// Required by a later `announce_peer`.
+    pub token: Option<Vec<u8>>,
+    /// Peers for `get_peers`.
+    pub values: Vec<SocketAddr>,
+}
+
+/// The `e` list of an error message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorReply {
+    pub code: i64,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KrpcError {
+    Decode(DecodeError),
+    /// A required key is absent.
+    Missing(&'static str),
+    /// A key has the wrong type or an impossible value. `""` is the message
+    /// itself.
+    Invalid(&'static str),
+    /// A query for a method this module does not know. The transaction ID
+    /// is kept so a 204 error can be sent back.
+    UnknownMethod {
+        transaction_id: Vec<u8>,
+        method: String,
+    },
+}
+
+impl fmt::Display for KrpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KrpcError::Decode(e) => e.fmt(f),
+            KrpcError::Missing(key) => write!(f, "missing key {:?}", key),
+            KrpcError::Invalid(key) => write!(f, "invalid key {:?}", key),
+            KrpcError::UnknownMethod { method, .. } => write!(f, "unknown method {:?}", method),
+        }
+    }
+}
+
+impl std::error::Error for KrpcError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            KrpcError::Decode(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<DecodeError> for KrpcError {
+    fn from(e: DecodeError) -> Self {
+        KrpcError::Decode(e)
+    }
+}
+
+impl ErrorReply {
+    pub const GENERIC: i64 = 201;
+    pub const SERVER: i64 = 202;
+    /// Malformed packet, invalid arguments or bad token.
+    pub const PROTOCOL: i64 = 203;
+    pub const METHOD_UNKNOWN: i64 = 204;
+
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        ErrorReply {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl Query {
+    /// The `q` value.
+    pub fn method(&self) -> &'static str {
+        match self {
+            Query::Ping { .. } => "ping",
+            Query::FindNode { .. } => "find_node",
+            Query::GetPeers { .. } => "get_peers",
+            Query::AnnouncePeer { .. } => "announce_peer",
+        }
+    }
+
+    /// The querying node.
+    pub fn id(&self) -> &NodeId {
+        match self {
+            Query::Ping { id }
+            | Query::FindNode { id, .. }
+            | Query::GetPeers { id, .. }
+            | Query::AnnouncePeer { id, .. } => id,
+        }
+    }
+}
+
+/// Keys of an `a` or `r` dict, before checking which ones the message
+/// needs.
+#[derive(Default)]
+struct Fields<'a> {
+    id: Option<&'a [u8]>,
+    target: Option<&'a [u8]>,
+    info_hash: Option<&'a [u8]>,
+    port: Option<i64>,
+    implied_port: Option<i64>,
+    token: Option<&'a [u8]>,
+    nodes: Option<&'a [u8]>,
+    nodes6: Option<&'a [u8]>,
+    values: Option<Vec<SocketAddr>>,
+}
+
+impl Message {
+    pub fn query(transaction_id: impl Into<Vec<u8>>, query: Query) -> Self {
+        Message {
+            transaction_id: transaction_id.into(),
+            version: None,
+            body: Body::Query(query),
+        }
+    }
+
+    pub fn response(transaction_id: impl Into<Vec<u8>>, response: Response) -> Self {
+        Message {
+            transaction_id: transaction_id.into(),
+            version: None,
+            body: Body::Response(response),
+        }
+    }
+
+    pub fn error(transaction_id: impl Into<Vec<u8>>, error: ErrorReply) -> Self {
+        Message {
+            transaction_id: transaction_id.into(),
+            version: None,
+            body: Body::Error(error),
+        }
+    }
+
+    /// Parses one datagram. Keys the message type does not use are
+    /// ignored.
+    pub fn from_bytes(input: &[u8]) -> Result<Message, KrpcError> {
+        if input.is_empty() {
+            return Err(DecodeError::EmptyInput.into());
+        }
+        let mut events = Events::new(input);
+        if events.expect_next()? != Event::DictStart {
+            return Err(KrpcError::Invalid(""));
+        }
+        let (mut t, mut y, mut q, mut v) = (None, None, None, None);
+        let (mut a, mut r, mut e) = (None, None, None);
+        loop {
+            match events.expect_next()? {
+                Event::End => break,
+                Event::Key(b"t") => t = Some(string(&mut events, "t")?),
+                Event::Key(b"y") => y = Some(string(&mut events, "y")?),
+                Event::Key(b"q") => q = Some(string(&mut events, "q")?),
+                Event::Key(b"v") => v = Some(string(&mut events, "v")?),
+                Event::Key(b"a") => a = Some(fields(&mut events, "a")?),
+                Event::Key(b"r") => r = Some(fields(&mut events, "r")?),
+                Event::Key(b"e") => e = Some(error_list(&mut events)?),
+                Event::Key(_) => events.skip_value()?,
+                _ => return Err(KrpcError::Invalid("")),
+            }
+        }
+        if events.offset() < input.len() {
+            return Err(DecodeError::TrailingData {
+                offset: events.offset(),
+            }
+            .into());
+        }
+
+        let transaction_id = t.ok_or(KrpcError::Missing("t"))?.to_vec();
+        let body = match y.ok_or(KrpcError::Missing("y"))? {
+            b"q" => {
+                let method = q.ok_or(KrpcError::Missing("q"))?;
+                let args = a.ok_or(KrpcError::Missing("a"))?;
+                match parse_query(method, &args)? {
+                    Some(query) => Body::Query(query),
+                    None => {
+                        return Err(KrpcError::UnknownMethod {
+                            transaction_id,
+                            method: String::from_utf8_lossy(method).into_owned(),
+                        });
+                    }
+                }
+            }
+            b"r" => {
+                let fields = r.ok_or(KrpcError::Missing("r"))?;
+                let mut nodes = Vec::new();
+                if let Some(bytes) = fields.nodes {
+                    nodes = compact_nodes_v4(bytes).ok_or(KrpcError::Invalid("nodes"))?;
+                }
+                if let Some(bytes) = fields.nodes6 {
+                    nodes.extend(compact_nodes_v6(bytes).ok_or(KrpcError::Invalid("nodes6"))?);
+                }
+                Body::Response(Response {
+                    id: node_id(fields.id, "id")?,
+                    nodes,
+                    token: fields.token.map(<[u8]>::to_vec),
+                    values: fields.values.unwrap_or_default(),
+                })
+            }
+            b"e" => Body::Error(e.ok_or(KrpcError::Missing("e"))?),
+            _ => return Err(KrpcError::Invalid("y")),
+        };
+        Ok(Message {
+            transaction_id,
+            version: v.map(<[u8]>::to_vec),
+            body,
+        })
+    }
+
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        Encoder::to_vec(|enc| {
+            enc.begin_dict()?;
+            match &self.body {
+                Body::Query(query) => {
+                    enc.key(b"a")?.begin_dict()?;
+                    enc.key(b"id")?.bytes(query.id())?;
+                    match query {
+                        Query::Ping { .. } => {}
+                        Query::FindNode { target, .. } => {
+                            enc.key(b"target")?.bytes(target)?;
+                        }
+                        Query::GetPeers { info_hash, .. } => {
+                            enc.key(b"info_hash")?.bytes(info_hash)?;
+                        }
+                        Query::AnnouncePeer {
+                            info_hash,
+                            port,
+                            implied_port,
+                            token,
+                            ..
+                        } => {
+                            if *implied_port {
+                                enc.key(b"implied_port")?.int(1)?;
+                            }
+                            enc.key(b"info_hash")?.bytes(info_hash)?;
+                            enc.key(b"port")?.int(i64::from(*port))?;
+                            enc.key(b"token")?.bytes(token)?;
+                        }
+                    }
+                    enc.end()?;
+                    enc.key(b"q")?.str(query.method())?;
+                }
+                Body::Response(response) => {
+                    enc.key(b"r")?.begin_dict()?;
+                    enc.key(b"id")?.bytes(&response.id)?;
+                    let (nodes, nodes6) = encode_compact_nodes(&response.nodes);
+                    if !nodes.is_empty() {
+                        enc.key(b"nodes")?.bytes(&nodes)?;
+                    }
+                    if !nodes6.is_empty() {
+                        enc.key(b"nodes6")?.bytes(&nodes6)?;
+                    }
+                    if let Some(token) = &response.token {
+                        enc.key(b"token")?.bytes(token)?;
+                    }
+                    if !response.values.is_empty() {
+                        enc.key(b"values")?.begin_list()?;
+                        for peer in &response.values {
+                            enc.bytes(&compact_peer(peer))?;
+                        }
+                        enc.end()?;
+                    }
+                    enc.end()?;
+                }
+                Body::Error(error) => {
+                    enc.key(b"e")?.begin_list()?;
+                    enc.int(error.code)?.str(&error.message)?.end()?;
+                }
+            }
+            enc.key(b"t")?.bytes(&self.transaction_id)?;
+            if let Some(version) = &self.version {
+                enc.key(b"v")?.bytes(version)?;
+            }
+            let y: &[u8] = match self.body {
+                Body::Query(_) => b"q",
+                Body::Response(_) => b"r",
+                Body::Error(_) => b"e",
+            };
+            enc.key(b"y")?.bytes(y)?;
+            enc.end()?;
+            Ok(())
+        })
+    }
+}
+
+/// Splits a `nodes` string. `None` unless the length is a multiple of
+/// `COMPACT_NODE_V4_LEN`.
+pub fn compact_nodes_v4(bytes: &[u8]) -> Option<Vec<(NodeId, SocketAddr)>> {
+    if !bytes.len().is_multiple_of(COMPACT_NODE_V4_LEN) {
+        return None;
+    }
+    bytes
+        .chunks_exact(COMPACT_NODE_V4_LEN)
+        .map(|c| {
+            let (id, addr) = c.split_first_chunk::<20>()?;
+            Some((*id, SocketAddr::V4(compact_peers_v4(addr)?[0])))
+        })
+        .collect()
+}
+
+/// Splits a `nodes6` string. `None` unless the length is a multiple of
+/// `COMPACT_NODE_V6_LEN`.
+pub fn compact_nodes_v6(bytes: &[u8]) -> Option<Vec<(NodeId, SocketAddr)>> {
+    if !bytes.len().is_multiple_of(COMPACT_NODE_V6_LEN) {
+        return None;
+    }
+    bytes
+        .chunks_exact(COMPACT_NODE_V6_LEN)
+        .map(|c| {
+            let (id, addr) = c.split_first_chunk::<20>()?;
+            Some((*id, SocketAddr::V6(compact_peers_v6(addr)?[0])))
+        })
+        .collect()
+}
+
+/// Packs nodes into the `nodes` and `nodes6` strings, by address family.
+pub fn encode_compact_nodes(nodes: &[(NodeId, SocketAddr)]) -> (Vec<u8>, Vec<u8>) {
+    let (mut v4, mut v6) = (Vec::new(), Vec::new());
+    for (id, addr) in nodes {
+        let out = if addr.is_ipv4() { &mut v4 } else { &mut v6 };
+        out.extend_from_slice(id);
+        out.extend(compact_peer(addr));
+    }
+    (v4, v6)
+}
+
+/// `None` for a method this module does not know.
+fn parse_query(method: &[u8], args: &Fields<'_>) -> Result<Option<Query>, KrpcError> {
+    let id = node_id(args.id, "id")?;
+    let query = match method {
+        b"ping" => Query::Ping { id },
+        b"find_node" => Query::FindNode {
+            id,
+            target: node_id(args.target, "target")?,
+        },
+        b"get_peers" => Query::GetPeers {
+            id,
+            info_hash: node_id(args.info_hash, "info_hash")?,
+        },
+        b"announce_peer" => Query::AnnouncePeer {
+            id,
+            info_hash: node_id(args.info_hash, "info_hash")?,
+            port: args
+                .port
+                .ok_or(KrpcError::Missing("port"))
+                .and_then(|port| u16::try_from(port).map_err(|_| KrpcError::Invalid("port")))?,
+            implied_port: args.implied_port.is_some_and(|n| n != 0),
+            token: args.token.ok_or(KrpcError::Missing("token"))?.to_vec(),
+        },
+        _ => return Ok(None),
+    };
+    Ok(Some(query))
+}
+
+fn node_id(bytes: Option<&[u8]>, key: &'static str) -> Result<NodeId, KrpcError> {
+    bytes
+        .ok_or(KrpcError::Missing(key))?
+        .try_into()
+        .map_err(|_| KrpcError::Invalid(key))
+}
+
+fn compact_peer(addr: &SocketAddr) -> Vec<u8> {
+    match addr {
+        SocketAddr::V4(addr) => encode_compact_peers_v4(&[*addr]),
+        SocketAddr::V6(addr) => encode_compact_peers_v6(&[*addr]),
+    }
+}
+
+fn string<'a>(events: &mut Events<'a>, key: &'static str) -> Result<&'a [u8], KrpcError> {
+    match events.expect_next()? {
+        Event::Str(bytes) => Ok(bytes),
+        _ => Err(KrpcError::Invalid(key)),
+    }
+}
+
+fn int(events: &mut Events<'_>, key: &'static str) -> Result<i64, KrpcError> {
+    match events.expect_next()? {
+        Event::Int(n) => Ok(n),
+        _ => Err(KrpcError::Invalid(key)),
+    }
+}
+
+/// An `a` or `r` dict.
+fn fields<'a>(events: &mut Events<'a>, key: &'static str) -> Result<Fields<'a>, KrpcError> {
+    if events.expect_next()? != Event::DictStart {
+        return Err(KrpcError::Invalid(key));
+    }
+    let mut fields = Fields::default();
+    loop {
+        match events.expect_next()? {
+            Event::End => return Ok(fields),
+            Event::Key(b"id") => fields.id = Some(string(events, "id")?),
+            Event::Key(b"target") => fields.target = Some(string(events, "target")?),
+            Event::Key(b"info_hash") => fields.info_hash = Some(string(events, "info_hash")?),
+            Event::Key(b"port") => fields.port = Some(int(events, "port")?),
+            Event::Key(b"implied_port") => fields.implied_port = Some(int(events, "implied_port")?),
+            Event::Key(b"token") => fields.token = Some(string(events, "token")?),
+            Event::Key(b"nodes") => fields.nodes = Some(string(events, "nodes")?),
+            Event::Key(b"nodes6") => fields.nodes6 = Some(string(events, "nodes6")?),
+            Event::Key(b"values") => fields.values = Some(values(events)?),
+            Event::Key(_) => events.skip_value()?,
+            _ => return Err(KrpcError::Invalid(key)),
+        }
+    }
+}
+
+/// `values`: a list of compact peers, each its own string.
+fn values(events: &mut Events<'_>) -> Result<Vec<SocketAddr>, KrpcError> {
+    if events.expect_next()? != Event::ListStart {
+        return Err(KrpcError::Invalid("values"));
+    }
+    let mut peers = Vec::new();
+    loop {
+        let peer = match events.expect_next()? {
+            Event::End => return Ok(peers),
+            Event::Str(bytes) if bytes.len() == COMPACT_V4_LEN => {
+                compact_peers_v4(bytes).map(|addrs| SocketAddr::V4(addrs[0]))
+            }
+            Event::Str(bytes) if bytes.len() == COMPACT_V6_LEN => {
+                compact_peers_v6(bytes).map(|addrs| SocketAddr::V6(addrs[0]))
+            }
+            _ => None,
+        };
+        peers.push(peer.ok_or(KrpcError::Invalid("values"))?);
+    }
+}
+
+/// The `e` list: `[code, message]`.
+fn error_list(events: &mut Events<'_>) -> Result<ErrorReply, KrpcError> {
+    let invalid = || KrpcError::Invalid("e");
+    if events.expect_next()? != Event::ListStart {
+        return Err(invalid());
+    }
+    let code = int(events, "e")?;
+    let message = string(events, "e")?;
+    if events.expect_next()? != Event::End {
+        return Err(invalid());
+    }
+    Ok(ErrorReply {
+        code,
+        message: String::from_utf8_lossy(message).into_owned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Examples from BEP 5.
+    const PING: &[u8] = b"d1:ad2:id20:abcdefghij0123456789e1:q4:ping1:t2:aa1:y1:qe";
+    const PONG: &[u8] = b"d1:rd2:id20:mnopqrstuvwxyz123456e1:t2:aa1:y1:re";
+    const ERROR: &[u8] = b"d1:eli201e23:A Generic Error Ocurrede1:t2:aa1:y1:ee";
+    const ANNOUNCE: &[u8] = b"d1:ad2:id20:abcdefghij012345678912:implied_porti1e\
+        9:info_hash20:mnopqrstuvwxyz1234564:porti6881e5:token8:aoeusnthe\
+        1:q13:announce_peer1:t2:aa1:y1:qe";
+
+    #[test]
+    fn test_bep5_examples_round_trip() {
+        let ping = Message::from_bytes(PING).unwrap();
+        assert_eq!(
+            ping,
+            Message::query(
+                "aa",
+                Query::Ping {
+                    id: *b"abcdefghij0123456789"
+                }
+            )
+        );
+
+        let pong = Message::from_bytes(PONG).unwrap();
+        let Body::Response(response) = &pong.body else {
+            panic!("not a response: {:?}", pong);
+        };
+        assert_eq!(&response.id, b"mnopqrstuvwxyz123456");
+
+        let error = Message::from_bytes(ERROR).unwrap();
+        assert_eq!(
+            error.body,
+            Body::Error(ErrorReply::new(
+                ErrorReply::GENERIC,
+                "A Generic Error Ocurred"
+            ))
+        );
+
+        let announce = Message::from_bytes(ANNOUNCE).unwrap();
+        assert_eq!(
+            announce.body,
+            Body::Query(Query::AnnouncePeer {
+                id: *b"abcdefghij0123456789",
+                info_hash: *b"mnopqrstuvwxyz123456",
+                port: 6881,
+                implied_port: true,
+                token: b"aoeusnth".to_vec(),
+            })
+        );
+
+        for (message, input) in [
+            (ping, PING),
+            (pong, PONG),
+            (error, ERROR),
+            (announce, ANNOUNCE),
+        ] {
+            assert_eq!(message.to_bytes().unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn test_get_peers() {
+        let query = Message::query(
+            b"\x00\x01".to_vec(),
+            Query::GetPeers {
+                id: [1; 20],
+                info_hash: [0xff; 20],
+            },
+        );
+        assert_eq!(Message::from_bytes(&query.to_bytes().unwrap()), Ok(query));
+
+        let input = b"d1:rd2:id20:abcdefghij01234567895:token8:aoeusnth\
+                      6:valuesl6:axje.u18:\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\x01\x1a\xe1ee\
+                      1:t2:aa1:v4:UT\x01\x021:y1:re";
+        let message = Message::from_bytes(input).unwrap();
+        assert_eq!(message.version.as_deref(), Some(&b"UT\x01\x02"[..]));
+        let Body::Response(response) = &message.body else {
+            panic!("not a response: {:?}", message);
+        };
+        assert_eq!(response.token.as_deref(), Some(&b"aoeusnth"[..]));
+        assert_eq!(
+            response.values,
+            [
+                "97.120.106.101:11893".parse().unwrap(),
+                "[::1]:6881".parse().unwrap()
+            ]
+        );
+        assert!(response.nodes.is_empty());
+        assert_eq!(message.to_bytes().unwrap(), input);
+    }
+
+    #[test]
+    fn test_compact_nodes() {
+        let mut nodes = Vec::new();
+        nodes.extend([1; 20]);
+        nodes.extend([10, 0, 0, 1, 0x1a, 0xe1]);
+        nodes.extend([2; 20]);
+        nodes.extend([192, 168, 1, 2, 0, 80]);
+        let mut nodes6 = Vec::new();
+        nodes6.extend([3; 20]);
+        nodes6.extend(std::net::Ipv6Addr::LOCALHOST.octets());
+        nodes6.extend([0, 1]);
+
+        let v4 = compact_nodes_v4(&nodes).unwrap();
+        assert_eq!(
+            v4,
+            [
+                ([1; 20], "10.0.0.1:6881".parse().unwrap()),
+                ([2; 20], "192.168.1.2:80".parse().unwrap()),
+            ]
+        );
+        let v6 = compact_nodes_v6(&nodes6).unwrap();
+        assert_eq!(v6, [([3; 20], "[::1]:1".parse().unwrap())]);
+        assert_eq!(compact_nodes_v4(&nodes[1..]), None);
+        assert_eq!(compact_nodes_v6(&nodes), None);
+        assert_eq!(
+            encode_compact_nodes(&[v4.clone(), v6.clone()].concat()),
+            (nodes.clone(), nodes6)
+        );
+
+        let mut input = b"d1:rd2:id20:abcdefghij01234567895:nodes52:".to_vec();
+        input.extend(&nodes);
+        input.extend_from_slice(b"6:nodes638:");
+        input.extend(encode_compact_nodes(&v6).1);
+        input.extend_from_slice(b"e1:t2:aa1:y1:re");
+        let message = Message::from_bytes(&input).unwrap();
+        let Body::Response(response) = &message.body else {
+            panic!("not a response: {:?}", message);
+        };
+        assert_eq!(response.nodes, [v4, v6].concat());
+        assert_eq!(message.to_bytes().unwrap(), input);
+
+        assert_eq!(
+            Message::from_bytes(b"d1:rd2:id20:abcdefghij01234567895:nodes3:abce1:t2:aa1:y1:re"),
+            Err(KrpcError::Invalid("nodes"))
+        );
+    }
+
+    #[test]
+    fn test_unknown_keys_and_methods() {
+        // An `ip` key (BEP 42) with binary content and an unknown argument.
+        let input = b"d1:ad2:id20:abcdefghij01234567893:fooli1eee2:ip6:\xff\xfe\0\0\0\x011:q4:ping\
+                      1:t1:x1:y1:qe";
+        assert!(matches!(
+            Message::from_bytes(input).unwrap().body,
+            Body::Query(Query::Ping { .. })
+        ));
+
+        let input = b"d1:ad2:id20:abcdefghij0123456789e1:q6:vote_x1:t1:x1:y1:qe";
+        assert_eq!(
+            Message::from_bytes(input),
+            Err(KrpcError::UnknownMethod {
+                transaction_id: b"x".to_vec(),
+                method: "vote_x".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let cases: [(&[u8], KrpcError); 8] = [
+            (b"", DecodeError::EmptyInput.into()),
+            (b"le", KrpcError::Invalid("")),
+            (b"d1:y1:qe", KrpcError::Missing("t")),
+            (b"d1:t1:xe", KrpcError::Missing("y")),
+            (b"d1:t1:x1:y1:ze", KrpcError::Invalid("y")),
+            (
+                b"d1:ad2:id3:abce1:q4:ping1:t1:x1:y1:qe",
+                KrpcError::Invalid("id"),
+            ),
+            (
+                b"d1:ad2:id20:abcdefghij0123456789e1:q9:find_node1:t1:x1:y1:qe",
+                KrpcError::Missing("target"),
+            ),
+            (b"d1:eli201ee1:t1:x1:y1:ee", KrpcError::Invalid("e")),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(Message::from_bytes(input), Err(expected));
+        }
+        assert_eq!(
+            Message::from_bytes(&[PONG, b"x"].concat()),
+            Err(DecodeError::TrailingData { offset: PONG.len() }.into())
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use std::ops::Range;
+
+use crate::memchr::memchr;
+use crate::validate::skip;
+use crate::{BValue, COLON_DELIM, DELIM_END, DICT_DELIM_BEGIN, DecodeError, INT_DELIM_BEGIN, Kind, LIST_DELIM_BEGIN};
+
+// A view over encoded bytes that locates dict entries and list items on
+// access instead of building the whole tree up front. The value's syntax
+// is checked once when it is created, so later lookups cannot fail. Only
+// syntax is checked: strings are bytes and integers are `i64`, so binary
+// hashes and large lengths in real torrents are fine.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LazyValue<'a> {
+    raw: &'a [u8],
+}
+
+impl<'a> LazyValue<'a> {
+    /// Checks the value at the front of `input` and returns a view over it
+    /// together with the unconsumed remainder.
+    pub fn parse(input: &'a [u8]) -> Result<(LazyValue<'a>, &'a [u8]), DecodeError> {
+        if input.is_empty() {
+            return Err(DecodeError::EmptyInput);
+        }
+        let end = skip(input, 0)?;
+        Ok((LazyValue { raw: &input[..end] }, &input[end..]))
+    }
+
+    /// The encoded bytes of this value.
+    pub fn raw(&self) -> &'a [u8] {
+        self.raw
+    }
+
+    pub fn kind(&self) -> Kind {
+        match self.raw[0] {
+            INT_DELIM_BEGIN => Kind::Int,
+            LIST_DELIM_BEGIN => Kind::List,
+            DICT_DELIM_BEGIN => Kind::Dict,
+            _ => Kind::Str,
+        }
+    }
+
+    /// The contents of a string, which need not be UTF-8.
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        if self.kind() != Kind::Str {
+            return None;
+        }
+        let colon = memchr(COLON_DELIM, self.raw)?;
+        Some(&self.raw[colon + 1..])
+    }
+
+    pub fn as_str(&self) -> Option<&'a str> {
+        std::str::from_utf8(self.as_bytes()?).ok()
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        if self.kind() != Kind::Int {
+            return None;
+        }
+        crate::parse_int(&self.raw[1..self.raw.len() - 1])
+    }
+
+    /// Looks up `key` in a dict without materializing anything. `None` for
+    /// non-dicts.
+    pub fn get(&self, key: impl AsRef<[u8]>) -> Option<LazyValue<'a>> {
+        let key = key.as_ref();
+        self.entries().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+
+    /// The `i`th element of a list. `None` for non-lists.
+    pub fn index(&self, i: usize) -> Option<LazyValue<'a>> {
+        self.items().nth(i)
+    }
+
+    /// Elements of a list; empty for non-lists.
+    pub fn items(&self) -> impl Iterator<Item = LazyValue<'a>> + 'a {
+        match self.kind() {
+            Kind::List => Children::new(self.raw),
+            _ => Children::empty(),
+        }
+    }
+
+    /// Key/value pairs of a dict in encoded order; empty for non-dicts.
+    /// Keys are raw bytes, as with `Event::Key`.
+    pub fn entries(&self) -> impl Iterator<Item = (&'a [u8], LazyValue<'a>)> + 'a {
+        let mut children = match self.kind() {
+            Kind::Dict => Children::new(self.raw),
+            _ => Children::empty(),
+        };
+        std::iter::from_fn(move || {
+            let key = children.next()?;
+            let value = children.next()?;
+            Some((key.as_bytes()?, value))
+        })
+    }
+
+    /// Fully decodes this value. Fails where `decode` would, on strings
+    /// that are not UTF-8 and integers outside `i16`.
+    pub fn to_value(&self) -> Result<BValue, DecodeError> {
+        crate::decode_exact(self.raw)
+    }
+}
+
+/// The exact encoded bytes of `key`'s value in the top-level dict of
+/// `input`, e.g. `get_raw(torrent, "info")` for infohash computation.
+/// `None` if the key is missing or the top-level value is not a dict.
+pub fn get_raw<'a>(input: &'a [u8], key: &str) -> Result<Option<&'a [u8]>, DecodeError> {
+    Ok(raw_range(input, key)?.map(|range| &input[range]))
+}
+
+/// Where `get_raw` finds `key`, as a range of `input`.
+pub(crate) fn raw_range(input: &[u8], key: &str) -> Result<Option<Range<usize>>, DecodeError> {
+    let (value, _) = LazyValue::parse(input)?;
+    Ok(value.get(key).map(|v| {
+        let start = v.raw().as_ptr() as usize - input.as_ptr() as usize;
+        start..start + v.raw().len()
+    }))
+}
+
+/// Walks the direct children of a list or dict.
+struct Children<'a> {
+    raw: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Children<'a> {
+    fn new(raw: &'a [u8]) -> Self {
+        Children { raw, pos: 1 }
+    }
+
+    fn empty() -> Self {
+        Children { raw: &[], pos: 0 }
+    }
+}
+
+impl<'a> Iterator for Children<'a> {
+    type Item = LazyValue<'a>;
+
+    fn next(&mut self) -> Option<LazyValue<'a>> {
+        if *self.raw.get(self.pos)? == DELIM_END {
+            return None;
+        }
+        let end = skip(self.raw, self.pos).ok()?;
+        let child = LazyValue {
+            raw: &self.raw[self.pos..end],
+        };
+        self.pos = end;
+        Some(child)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_without_full_decode() {
+        let input = b"d8:announce3:url4:infod6:lengthi42e4:pathl1:a1:beee";
+        let (torrent, rest) = LazyValue::parse(input).unwrap();
+        assert!(rest.is_empty());
+
+        assert_eq!(torrent.get("announce").unwrap().as_str(), Some("url"));
+        let info = torrent.get("info").unwrap();
+        assert_eq!(info.raw(), b"d6:lengthi42e4:pathl1:a1:bee");
+        assert_eq!(info.get("length").unwrap().as_int(), Some(42));
+        assert_eq!(info.get("path").unwrap().index(1).unwrap().as_str(), Some("b"));
+        assert_eq!(info.get("missing"), None);
+        assert_eq!(info.index(0), None);
+    }
+
+    #[test]
+    fn test_to_value_matches_decode() {
+        let input = b"l4:spami42ee";
+        let (lazy, _) = LazyValue::parse(input).unwrap();
+        assert_eq!(lazy.items().count(), 2);
+        assert_eq!(lazy.to_value(), Ok(crate::decode(input).unwrap().0));
+    }
+
+    #[test]
+    fn test_binary_and_large_values() {
+        let input = b"d4:infod6:lengthi5000000000e6:pieces4:\xff\0\xfe\x01ee";
+        let (torrent, _) = LazyValue::parse(input).unwrap();
+        let info = torrent.get("info").unwrap();
+        assert_eq!(info.get("length").unwrap().as_int(), Some(5_000_000_000));
+        let pieces = info.get(b"pieces").unwrap();
+        assert_eq!(pieces.as_bytes(), Some(&b"\xff\0\xfe\x01"[..]));
+        assert_eq!(pieces.as_str(), None);
+        assert_eq!(info.entries().count(), 2);
+        assert!(info.to_value().is_err());
+    }
+
+    #[test]
+    fn test_get_raw() {
+        let input = b"d8:announce3:url4:infod6:lengthi42eee";
+        assert_eq!(get_raw(input, "info"), Ok(Some(&b"d6:lengthi42ee"[..])));
+        assert_eq!(get_raw(input, "missing"), Ok(None));
+        assert_eq!(get_raw(b"li1ee", "info"), Ok(None));
+        assert!(get_raw(b"d4:info", "info").is_err());
+
+        let input = b"d8:announce3:url4:infod6:pieces2:\xff\xfe12:piece lengthi262144eee";
+        assert_eq!(raw_range(input, "info"), Ok(Some(22..input.len() - 1)));
+        assert_eq!(
+            get_raw(input, "info"),
+            Ok(Some(&b"d6:pieces2:\xff\xfe12:piece lengthi262144ee"[..]))
+        );
+    }
+
+    #[test]
+    fn test_malformed_input_is_rejected_up_front() {
+        assert!(LazyValue::parse(b"d3:foo").is_err());
+        assert_eq!(LazyValue::parse(b""), Err(DecodeError::EmptyInput));
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+
+use crate::{BValue, COLON_DELIM, DecodeError, DELIM_END, DICT_DELIM_BEGIN, INT_DELIM_BEGIN, LIST_DELIM_BEGIN};
+
+// Best-effort decoding for forensic inspection of damaged input.
+// Recoverable problems are recorded as diagnostics and the offending
+// node is replaced with `BValue::None` so the rest of the tree survives.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// Byte offset in the input where the problem was detected.
+    pub offset: usize,
+    pub message: String,
+}
+
+/// Tolerance knobs for messy producers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LenientOptions {
+    /// Accept integers written with an explicit `+` sign (`i+5e`) without a
+    /// diagnostic. Off by default since the format does not allow it, and
+    /// the strict parsers reject it.
+    pub accept_plus_sign: bool,
+}
+
+pub fn decode_lenient(input: &[u8]) -> (BValue, Vec<Diagnostic>) {
+    decode_lenient_with(input, LenientOptions::default())
+}
+
+pub fn decode_lenient_with(input: &[u8], options: LenientOptions) -> (BValue, Vec<Diagnostic>) {
+    let mut parser = Parser {
+        input,
+        pos: 0,
+        diagnostics: Vec::new(),
+        options,
+    };
+
+    if input.is_empty() {
+        parser.report(0, "Empty input.");
+        return (BValue::None, parser.diagnostics);
+    }
+
+    let value = parser.value();
+    if parser.pos < input.len() {
+        parser.report(parser.pos, "Trailing data after top-level value.");
+    }
+
+    (value, parser.diagnostics)
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+    diagnostics: Vec<Diagnostic>,
+    options: LenientOptions,
+}
+
+impl Parser<'_> {
+    fn report(&mut self, offset: usize, message: &str) {
+        self.diagnostics.push(Diagnostic {
+            offset,
+            message: String::from(message),
+        });
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn value(&mut self) -> BValue {
+        match self.peek() {
+            None => {
+                self.report(self.pos, "Unexpected end of input.");
+                BValue::None
+            }
+            Some(DELIM_END) => {
+                self.report(self.pos, "Unexpected end marker.");
+                self.pos += 1;
+                BValue::None
+            }
+            Some(INT_DELIM_BEGIN) => self.int(),
+            Some(LIST_DELIM_BEGIN) => self.list(),
+            Some(DICT_DELIM_BEGIN) => self.dict(),
+            Some(b'0'..=b'9') => self.string(),
+            Some(_) => {
+                self.report(self.pos, "Unexpected byte.");
+                self.pos += 1;
+                BValue::None
+            }
+        }
+    }
+
+    fn int(&mut self) -> BValue {
+        let start = self.pos;
+        self.pos += 1;
+
+        let end = match self.input[self.pos..].iter().position(|&b| b == DELIM_END) {
+            Some(n) => self.pos + n,
+            None => {
+                self.report(start, "Unterminated integer.");
+                self.input.len()
+            }
+        };
+
+        let digits = String::from_utf8_lossy(&self.input[self.pos..end]);
+        self.pos = (end + 1).min(self.input.len());
+
+        if digits.starts_with('+') && !self.options.accept_plus_sign {
+            self.report(start, "Integer has an explicit '+' sign.");
+            return BValue::None;
+        }
+
+        match digits.parse::<i16>() {
+            Ok(n) => BValue::Int(n),
+            Err(_) => {
+                let message = match integer_hint(&digits) {
+                    Some(hint) => format!("Ill-formatted integer; {}.", hint),
+                    None => String::from("Ill-formatted integer."),
+                };
+                self.report(start, &message);
+                BValue::None
+            }
+        }
+    }
+
+    fn string(&mut self) -> BValue {
+        let start = self.pos;
+
+        let colon = match self.input[self.pos..]
+            .iter()
+            .position(|&b| b == COLON_DELIM)
+        {
+            Some(n) => self.pos + n,
+            None => {
+                self.report(start, "String length without ':' delimiter.");
+                self.pos = self.input.len();
+                return BValue::None;
+            }
+        };
+
+        let len = match crate::parse_len(&self.input[self.pos..colon], start) {
+            Ok(len) => len,
+            Err(DecodeError::LengthExceedsPlatform { .. }) => {
+                // No input this large can be in memory; treat it as truncated.
+                usize::MAX
+            }
+            Err(_) => {
+                // Skip the bad length prefix and resume after the colon.
+                self.report(start, "Invalid string length.");
+                self.pos = colon + 1;
+                return BValue::None;
+            }
+        };
+
+        let data_start = colon + 1;
+        let data_end = match data_start.checked_add(len) {
+            Some(end) if end <= self.input.len() => end,
+            _ => {
+                self.report(start, "String runs past end of input. Truncated.");
+                self.input.len()
+            }
+        };
+        self.pos = data_end;
+
+        let bytes = &self.input[data_start..data_end];
+        match String::from_utf8(bytes.to_vec()) {
+            Ok(s) => BValue::Str(s),
+            Err(_) => {
+                self.report(start, "String is not valid UTF-8. Lossily converted.");
+                BValue::Str(String::from_utf8_lossy(bytes).into_owned())
+            }
+        }
+    }
+
+    fn list(&mut self) -> BValue {
+        let start = self.pos;
+        self.pos += 1;
+        let mut list = Vec::new();
+
+        loop {
+            match self.peek() {
+                None => {
+                    self.report(start, "Unterminated list.");
+                    break;
+                }
+                Some(DELIM_END) => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(_) => list.push(self.value()),
+            }
+        }
+
+        BValue::List(list)
+    }
+
+    fn dict(&mut self) -> BValue {
+        let start = self.pos;
+        self.pos += 1;
+        let mut dict = HashMap::new();
+
+        loop {
+            let key_pos = self.pos;
+            match self.peek() {
+                None => {
+                    self.report(start, "Unterminated dict.");
+                    break;
+                }
+                Some(DELIM_END) => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(_) => {}
+            }
+
+            let key = match self.value() {
+                BValue::Str(key) => key,
+                BValue::None => continue,
+                _ => {
+                    self.report(key_pos, "Non-string dict key. Skipped.");
+                    continue;
+                }
+            };
+
+            let value = match self.peek() {
+                None | Some(DELIM_END) => {
+                    self.report(key_pos, "Dict key without a value.");
+                    BValue::None
+                }
+                Some(_) => self.value(),
+            };
+
+            dict.insert(key, value);
+        }
+
+        BValue::Dict(dict)
+    }
+}
+
+// Best guess at what a producer meant by an integer literal that failed to
+// parse, for the diagnostic message.
+fn integer_hint(literal: &str) -> Option<&'static str> {
+    let unsigned = literal.trim_start_matches(['-', '+']);
+    if literal.is_empty() {
+        Some("empty")
+    } else if literal.chars().any(char::is_whitespace) {
+        Some("contains whitespace")
+    } else if unsigned.starts_with("0x") || unsigned.starts_with("0X") {
+        Some("looks like hex")
+    } else if unsigned.parse::<f64>().is_ok() && unsigned.contains(['.', 'e', 'E']) {
+        Some("looks like a float")
+    } else if unsigned.chars().all(|c| c.is_ascii_digit()) {
+        Some("out of range")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_well_formed_input_has_no_diagnostics() {
+        let (value, diagnostics) = decode_lenient(b"l4:spami42ee");
+        assert_eq!(
+            value,
+            BValue::List(vec![BValue::Str("spam".to_string()), BValue::Int(42)])
+        );
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_recovers_from_bad_nodes() {
+        // Bad integer is replaced, the rest of the list survives.
+        let (value, diagnostics) = decode_lenient(b"li4xe4:spame");
+        assert_eq!(
+            value,
+            BValue::List(vec![BValue::None, BValue::Str("spam".to_string())])
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].offset, 1);
+
+        // Truncated string and unterminated containers.
+        let (value, diagnostics) = decode_lenient(b"d3:fool10:abc");
+        let mut expected = HashMap::new();
+        expected.insert(
+            "foo".to_string(),
+            BValue::List(vec![BValue::Str("abc".to_string())]),
+        );
+        assert_eq!(value, BValue::Dict(expected));
+        assert_eq!(diagnostics.len(), 3);
+    }
+
+    #[test]
+    fn test_dict_key_problems() {
+        let (value, diagnostics) = decode_lenient(b"di1e3:bar3:fooe");
+        let mut expected = HashMap::new();
+        expected.insert("bar".to_string(), BValue::Str("foo".to_string()));
+        assert_eq!(value, BValue::Dict(expected));
+        // Integer key is skipped, which leaves "bar" and "foo" paired up.
+        assert_eq!(diagnostics.len(), 1);
+
+        let (value, diagnostics) = decode_lenient(b"d3:fooe");
+        let mut expected = HashMap::new();
+        expected.insert("foo".to_string(), BValue::None);
+        assert_eq!(value, BValue::Dict(expected));
+        assert_eq!(diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn test_integer_hints() {
+        let messages = |input: &[u8]| -> Vec<String> {
+            decode_lenient(input)
+                .1
+                .into_iter()
+                .map(|d| d.message)
+                .collect()
+        };
+
+        assert_eq!(messages(b"i0x1fe"), vec!["Ill-formatted integer; looks like hex."]);
+        assert_eq!(messages(b"i1.5e"), vec!["Ill-formatted integer; looks like a float."]);
+        assert_eq!(messages(b"i1 2e"), vec!["Ill-formatted integer; contains whitespace."]);
+        assert_eq!(messages(b"i99999e"), vec!["Ill-formatted integer; out of range."]);
+        assert_eq!(messages(b"i12ae"), vec!["Ill-formatted integer."]);
+    }
+
+    #[test]
+    fn test_plus_sign_tolerance() {
+        let (value, diagnostics) = decode_lenient(b"i+5e");
+        assert_eq!(value, BValue::None);
+        assert_eq!(diagnostics[0].message, "Integer has an explicit '+' sign.");
+
+        let options = LenientOptions {
+            accept_plus_sign: true,
+        };
+        let (value, diagnostics) = decode_lenient_with(b"i+5e", options);
+        assert_eq!(value, BValue::Int(5));
+        assert!(diagnostics.is_empty());
+    }
+}
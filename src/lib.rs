@@ -1,5 +1,151 @@
 use std::collections::HashMap;
 
+// Lets derive output name `::bencode_rs` from inside this crate too.
+extern crate self as bencode_rs;
+
+mod aliases;
+#[cfg(feature = "archive")]
+mod archive;
+mod binary;
+mod builder;
+mod bytes;
+mod convert;
+#[cfg(feature = "sha1")]
+mod create;
+mod cursor;
+mod debug;
+mod diff;
+mod encode;
+mod encoder;
+mod error;
+mod events;
+mod extensions;
+mod fixture;
+mod hashing;
+mod homogeneity;
+mod hooks;
+mod index;
+mod iter;
+mod krpc;
+mod lazy;
+mod lenient;
+mod magnet;
+mod memchr;
+mod merge;
+mod metadata;
+#[cfg(all(feature = "mmap", unix))]
+mod mmap;
+mod patch;
+mod path;
+mod pex;
+mod pool;
+mod preserve;
+mod pretty;
+mod push;
+mod reader;
+mod resume;
+mod schema;
+mod sha1;
+mod sha256;
+mod spans;
+mod splitter;
+mod streaming;
+mod summary;
+mod take;
+mod torrent;
+mod tracker;
+#[cfg(feature = "tracker-server")]
+mod tracker_server;
+mod validate;
+#[cfg(feature = "sha1")]
+mod verify;
+mod visit;
+mod walk;
+
+pub use aliases::Aliases;
+#[cfg(feature = "archive")]
+pub use archive::{ArchivedValue, archive};
+pub use binary::{
+    COMPACT_V4_LEN, COMPACT_V6_LEN, compact_peers_v4, compact_peers_v6, encode_compact_peers_v4,
+    encode_compact_peers_v6, read_be_u16, read_be_u32, read_be_u64, read_ipv4, read_ipv6,
+};
+pub use builder::{DictBuilder, ListBuilder};
+pub use bytes::{Bytes, from_base32, from_hex, to_base32, to_hex};
+pub use convert::{FromBencode, FromBencodeError, ToBencode, decode_into};
+#[cfg(feature = "sha1")]
+pub use create::TorrentBuilder;
+#[cfg(feature = "derive")]
+pub use bencode_derive::{FromBencode, ToBencode};
+pub use cursor::Cursor;
+pub use diff::render_diff;
+pub use encode::{
+    encode, encode_canonical, encode_into, encode_into_vec, encode_to_writer, encoded_len,
+    is_canonical,
+};
+pub use encoder::Encoder;
+pub use error::DecodeError;
+pub use events::{Event, Events};
+pub use extensions::{
+    Conflict, ExtensionIds, Handshake, HandshakeError, Negotiated, Side, m_dict, negotiate,
+};
+pub use fixture::export_fixture;
+pub use hashing::{Digest, HashingReader};
+pub use hooks::{DecodeHooks, External, decode_with_hooks};
+pub use index::ValueIndex;
+pub use iter::{IntoIter, Item, Iter, IterMut};
+pub use krpc::{
+    Body, COMPACT_NODE_V4_LEN, COMPACT_NODE_V6_LEN, ErrorReply, KrpcError, Message, NodeId, Query,
+    Response, compact_nodes_v4, compact_nodes_v6, encode_compact_nodes,
+};
+pub use homogeneity::{ANY_ITEM, Heterogeneous, Kind, check_homogeneous};
+pub use lazy::{LazyValue, get_raw};
+pub use lenient::{Diagnostic, LenientOptions, decode_lenient, decode_lenient_with};
+pub use magnet::{Magnet, MagnetError};
+pub use merge::MergeOptions;
+pub use metadata::{METADATA_PIECE_LEN, MetadataError, MetadataMessage, metadata_pieces};
+#[cfg(feature = "sha1")]
+pub use metadata::MetadataAssembler;
+#[cfg(all(feature = "mmap", unix))]
+pub use mmap::MappedFile;
+pub use patch::{PatchError, patch};
+pub use path::{PathError, PathSegment, format_path, parse_path};
+pub use pex::{PexError, PexMessage, PexPeer};
+pub use pool::Pool;
+pub use preserve::{Preserved, Verbatim, decode_preserving};
+pub use pretty::pretty_print;
+pub use push::{Chunks, PushParser, decode_chunks};
+pub use reader::decode_from_reader;
+pub use resume::{ResumeData, ResumeError};
+pub use schema::{DictSchema, Problem, Schema, Violation};
+pub use sha1::{Sha1, sha1};
+pub use sha256::{Sha256, sha256};
+pub use spans::{Span, decode_with_spans};
+pub use splitter::{Delivery, Dispatched, Frame, Splitter};
+pub use streaming::{
+    DictEncoder, DictValue, Finished, ListEncoder, Sink, StreamingEncoder, ValueSlot,
+};
+pub use summary::summarize;
+pub use torrent::{
+    FileEntry, FileMismatch, Files, Inconsistency, Info, MetaInfo, Node, Pieces, TorrentError,
+    TorrentVersion, TreeEntry, UnsafePath, pieces,
+};
+#[cfg(all(feature = "sha1", feature = "sha256"))]
+pub use torrent::InfoHashes;
+#[cfg(feature = "sha1")]
+pub use torrent::info_hash;
+#[cfg(feature = "sha256")]
+pub use torrent::info_hash_v2;
+pub use tracker::{
+    AnnounceResponse, Peer, ScrapeResponse, ScrapeStats, TrackerError, encode_failure,
+};
+#[cfg(feature = "tracker-server")]
+pub use tracker_server::{AnnounceEvent, AnnounceRequest, Tracker};
+pub use validate::{skip_value, validate};
+#[cfg(feature = "sha1")]
+pub use verify::{verify_dir, verify_pieces};
+pub use visit::BVisitor;
+pub use walk::Walk;
+
 // https://en.wikipedia.org/wiki/Bencode
 
 const INT_DELIM_BEGIN: u8 = b'i';
@@ -8,126 +154,316 @@ const LIST_DELIM_BEGIN: u8 = b'l';
 const DELIM_END: u8 = b'e';
 const COLON_DELIM: u8 = b':';
 
-#[derive(Debug, PartialEq)]
+/// A decoded value. It does not implement serde's `Serialize` or
+/// `Deserialize` yet.
+#[derive(Clone, Default, PartialEq)]
 pub enum BValue {
     Str(String),
     Int(i16),
     List(Vec<BValue>),
     Dict(HashMap<String, BValue>),
+    #[default]
     None,
+    /// User value produced by a decode hook; see `decode_with_hooks`.
+    External(External),
 }
 
-pub fn decode(input: &[u8]) -> Result<(BValue, usize), String> {
-    if input.len() == 0 {
-        return Err(String::from("Decoding Err. Invalid input length."));
+impl BValue {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            BValue::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        self.as_str().map(str::as_bytes)
+    }
+
+    pub fn as_int(&self) -> Option<i16> {
+        match self {
+            BValue::Int(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[BValue]> {
+        match self {
+            BValue::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    pub fn as_dict(&self) -> Option<&HashMap<String, BValue>> {
+        match self {
+            BValue::Dict(dict) => Some(dict),
+            _ => None,
+        }
+    }
+
+    pub fn is_str(&self) -> bool {
+        matches!(self, BValue::Str(_))
+    }
+
+    pub fn is_int(&self) -> bool {
+        matches!(self, BValue::Int(_))
+    }
+
+    pub fn is_list(&self) -> bool {
+        matches!(self, BValue::List(_))
+    }
+
+    pub fn is_dict(&self) -> bool {
+        matches!(self, BValue::Dict(_))
+    }
+
+    /// Moves the string out, or hands the value back if it is not one.
+    pub fn into_string(self) -> Result<String, BValue> {
+        match self {
+            BValue::Str(s) => Ok(s),
+            other => Err(other),
+        }
+    }
+
+    pub fn into_bytes(self) -> Result<Vec<u8>, BValue> {
+        self.into_string().map(String::into_bytes)
+    }
+
+    pub fn into_int(self) -> Result<i16, BValue> {
+        match self {
+            BValue::Int(n) => Ok(n),
+            other => Err(other),
+        }
+    }
+
+    pub fn into_list(self) -> Result<Vec<BValue>, BValue> {
+        match self {
+            BValue::List(list) => Ok(list),
+            other => Err(other),
+        }
+    }
+
+    pub fn into_dict(self) -> Result<HashMap<String, BValue>, BValue> {
+        match self {
+            BValue::Dict(dict) => Ok(dict),
+            other => Err(other),
+        }
+    }
+
+    /// Moves the value out, leaving `BValue::None` in its place.
+    pub fn take(&mut self) -> BValue {
+        std::mem::take(self)
+    }
+
+    pub fn replace(&mut self, value: BValue) -> BValue {
+        std::mem::replace(self, value)
+    }
+}
+
+// Comparisons against plain Rust values, so `torrent["info"]["name"] == "x"`
+// works. A value of another kind is simply unequal.
+
+impl PartialEq<str> for BValue {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == Some(other)
+    }
+}
+
+impl PartialEq<&str> for BValue {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl PartialEq<String> for BValue {
+    fn eq(&self, other: &String) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl PartialEq<[u8]> for BValue {
+    fn eq(&self, other: &[u8]) -> bool {
+        self.as_bytes() == Some(other)
+    }
+}
+
+impl PartialEq<&[u8]> for BValue {
+    fn eq(&self, other: &&[u8]) -> bool {
+        self == *other
+    }
+}
+
+impl PartialEq<i64> for BValue {
+    fn eq(&self, other: &i64) -> bool {
+        self.as_int().map(i64::from) == Some(*other)
+    }
+}
+
+/// Same as `decode_prefix`.
+pub fn decode(input: &[u8]) -> Result<(BValue, &[u8]), DecodeError> {
+    decode_prefix(input)
+}
+
+/// Decodes one value from the front of `input` and returns it together
+/// with the unconsumed remainder. Trailing bytes are allowed.
+///
+/// Empty input is `DecodeError::EmptyInput`; a non-empty input that stops
+/// partway through a value is `DecodeError::UnexpectedEof`.
+pub fn decode_prefix(input: &[u8]) -> Result<(BValue, &[u8]), DecodeError> {
+    decode_with(input, &mut Pool::with_capacity(0))
+}
+
+/// Decodes `input` as exactly one value. Trailing bytes are an error.
+pub fn decode_exact(input: &[u8]) -> Result<BValue, DecodeError> {
+    let (value, rest) = decode_prefix(input)?;
+    if !rest.is_empty() {
+        return Err(DecodeError::TrailingData {
+            offset: input.len() - rest.len(),
+        });
+    }
+    Ok(value)
+}
+
+fn decode_with<'a>(input: &'a [u8], pool: &mut Pool) -> Result<(BValue, &'a [u8]), DecodeError> {
+    if input.is_empty() {
+        return Err(DecodeError::EmptyInput);
+    }
+    let (value, consumed) = decode_at(input, pool)?;
+    Ok((value, &input[consumed..]))
+}
+
+fn decode_at(input: &[u8], pool: &mut Pool) -> Result<(BValue, usize), DecodeError> {
+    if input.is_empty() {
+        return Err(DecodeError::UnexpectedEof { offset: 0 });
     }
     
     match input[0] {
         DELIM_END => {
-            // Empty
-            Ok((BValue::None, 1))
+            // End markers are consumed by the list/dict that opened them,
+            // so reaching one here means it closes nothing.
+            Err(DecodeError::UnexpectedEnd { offset: 0 })
         }
         // Integers
         INT_DELIM_BEGIN => {
-            // move forward to the first digit
-            let mut idx = 1;
-            let mut n = String::new();
-
-            unsafe {
-                let vec = n.as_mut_vec();
-
-                while input[idx] != DELIM_END {
-                    vec.push(input[idx]);
-                    idx += 1;
-                }
-
-                if vec.is_empty() {
-                    return Err(String::from("Decoding Error: Empty Integer Not-allowed."));
-                }
+            // digits run up to the closing `e`
+            let idx = memchr::memchr(DELIM_END, &input[1..])
+                .ok_or(DecodeError::UnexpectedEof { offset: input.len() })?
+                + 1;
+            let digits = &input[1..idx];
+            if digits.is_empty() {
+                return Err(DecodeError::EmptyInteger { offset: 0 });
             }
+            let n = parse_int(digits)
+                .and_then(|n| i16::try_from(n).ok())
+                .ok_or(DecodeError::InvalidInteger { offset: 0 })?;
 
-            let n = n
-                .parse::<i16>()
-                .map_err(|_e| String::from("Decoding Error: Ill-formatted Integer."))?;
-
-            return Ok((BValue::Int(n), idx + 1));
+            Ok((BValue::Int(n), idx + 1))
         }
         LIST_DELIM_BEGIN => {
             // Lists
             let mut idx = 1;
-            let mut list = Vec::new();
-            loop {
-                let (value, consumed) = decode(&input[idx..])?;
+            let mut list = pool.list();
+            while !at_end(input, idx)? {
+                let (value, consumed) = decode_at(&input[idx..], pool).map_err(|e| e.offset_by(idx))?;
                 idx += consumed;
-                match value {
-                    BValue::None => {
-                        return Ok((BValue::List(list), idx));
-                    }
-                    v => {
-                        list.push(v);
-                    }
-                }
+                list.push(value);
             }
+            Ok((BValue::List(list), idx + 1))
         }
         DICT_DELIM_BEGIN => {
             // Dictionaries
             let mut idx = 1;
-            let mut dict: HashMap<String, BValue> = HashMap::new();
-            let mut key_val = (None, None);
-
-            loop {
-                let (value, consumed) = decode(&input[idx..])?;
-
-                match value {
-                    BValue::None => {
-                        idx += consumed;
-                        break;
-                    }
-                    val => {
-                        match val {
-                            BValue::Str(s) if key_val.0.is_none() => {
-                                key_val.0 = Some(s);
-                            }
-                            v => {
-                                key_val.1 = Some(v);
-                            }
-                        }
-                        idx += consumed;
-                    }
-                }
-
-                if key_val.0.is_some() && key_val.1.is_some() {
-                    let key = key_val.0.unwrap();
-                    let val = key_val.1.unwrap();
+            let mut dict = pool.dict();
 
-                    dict.insert(key, val);
+            // Keys and values strictly alternate; the end marker may only
+            // appear where a key would.
+            while !at_end(input, idx)? {
+                let key_pos = idx;
+                let (key, consumed) = decode_at(&input[idx..], pool).map_err(|e| e.offset_by(idx))?;
+                idx += consumed;
+                let BValue::Str(key) = key else {
+                    return Err(DecodeError::NonStringKey { offset: key_pos });
+                };
 
-                    key_val.0 = None;
-                    key_val.1 = None;
+                if at_end(input, idx)? {
+                    return Err(DecodeError::MissingValue { offset: key_pos });
                 }
+                let (value, consumed) = decode_at(&input[idx..], pool).map_err(|e| e.offset_by(idx))?;
+                idx += consumed;
+
+                dict.insert(key, value);
             }
-            
-            Ok((BValue::Dict(dict), idx))
+
+            Ok((BValue::Dict(dict), idx + 1))
         }
-        _ => {
+        b'0'..=b'9' => {
             // Strings
-            let mut idx = 0;
-            while input[idx] != COLON_DELIM {
-                idx += 1;
-            }
-            let len = String::from_utf8_lossy(&input[..idx]);
-            let len = len
-                .parse::<usize>()
-                .map_err(|_e| String::from("Decoding Error. Invalid string length."))?;
+            let mut idx = memchr::memchr(COLON_DELIM, input)
+                .ok_or(DecodeError::UnexpectedEof { offset: input.len() })?;
+            let len = parse_len(&input[..idx], 0)?;
             idx += 1;
 
-            let string = &input
-                .get(idx..idx + len)
-                .ok_or(String::from("Decoding Error. Invalid string length."))?;
-            let string = String::from_utf8(string.to_vec()).unwrap();
+            let string = &idx
+                .checked_add(len)
+                .and_then(|end| input.get(idx..end))
+                .ok_or(DecodeError::UnexpectedEof { offset: input.len() })?;
+            let string = std::str::from_utf8(string)
+                .map_err(|_e| DecodeError::InvalidUtf8 { offset: idx })?;
+            let mut buf = pool.string();
+            buf.push_str(string);
+
+            Ok((BValue::Str(buf), idx + len))
+        }
+        byte => Err(DecodeError::UnexpectedByte { offset: 0, byte }),
+    }
+}
+
+/// Parses the digits of a string length prefix starting at `offset`.
+/// Lengths are read as `u64` so that a length too large for this target is
+/// reported as such rather than as malformed.
+pub(crate) fn parse_len(digits: &[u8], offset: usize) -> Result<usize, DecodeError> {
+    let len = std::str::from_utf8(digits)
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .ok_or(DecodeError::InvalidStringLength { offset })?;
+    usize::try_from(len).map_err(|_e| DecodeError::LengthExceedsPlatform { offset })
+}
 
-            return Ok((BValue::Str(string), idx + len));
+/// Parses integer digits straight from the bytes, as the format spells
+/// them: an optional `-` then ASCII digits, without a `+`, leading zeros
+/// or `-0`. `decode_lenient` is the place for anything looser.
+pub(crate) fn parse_int(digits: &[u8]) -> Option<i64> {
+    let (negative, digits) = match digits {
+        [b'-', rest @ ..] => (true, rest),
+        _ => (false, digits),
+    };
+    match digits {
+        [] => return None,
+        [b'0', ..] if negative || digits.len() > 1 => return None,
+        _ => {}
+    }
+    // Negative values accumulate downwards so that `i64::MIN` fits.
+    let mut n: i64 = 0;
+    for &b in digits {
+        if !b.is_ascii_digit() {
+            return None;
         }
+        let digit = i64::from(b - b'0');
+        n = n.checked_mul(10)?;
+        n = if negative {
+            n.checked_sub(digit)?
+        } else {
+            n.checked_add(digit)?
+        };
+    }
+    Some(n)
+}
+
+/// Whether the container being decoded closes at `idx`.
+fn at_end(input: &[u8], idx: usize) -> Result<bool, DecodeError> {
+    match input.get(idx) {
+        Some(&b) => Ok(b == DELIM_END),
+        None => Err(DecodeError::UnexpectedEof { offset: input.len() }),
     }
 }
 
@@ -136,6 +472,12 @@ mod tests {
     use super::*;
     use std::collections::HashMap;
 
+    /// A path in the temp dir that no other test process will use.
+    #[cfg(any(feature = "sha1", all(feature = "mmap", unix)))]
+    pub(crate) fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("bencode-rs-{}-{}", std::process::id(), name))
+    }
+
     #[test]
     fn test_integer_decoding() {
         // Basic integers
@@ -144,12 +486,38 @@ mod tests {
         assert_eq!(decode(b"i-42e").unwrap().0, BValue::Int(-42));
 
         // Edge cases
-        assert_eq!(decode(b"i042e").unwrap().0, BValue::Int(42)); // Leading zeros not allowed. ALERT! will be normalized
-        assert_eq!(decode(b"i-0e").unwrap().0, BValue::Int(0)); // Negative zero not allowed. ALERT! will be normalized
+        assert!(decode(b"i042e").is_err()); // Leading zeros not allowed
+        assert!(decode(b"i-0e").is_err()); // Negative zero not allowed
         assert!(decode(b"ie").is_err()); // Empty integer not allowed
         assert!(decode(b"i32be").is_err()); // Non-digit characters not allowed
     }
 
+    #[test]
+    fn test_parse_int() {
+        let (max, min) = (i64::MAX.to_string(), i64::MIN.to_string());
+        for digits in ["0", "7", "-42", "32768", "10", &max, &min] {
+            assert_eq!(parse_int(digits.as_bytes()), digits.parse::<i64>().ok(), "{}", digits);
+        }
+        let (over, under) = ("9223372036854775808", "-9223372036854775809");
+        for digits in ["", "-", "+", over, under, "1-2", "--1", "4 2", "\u{661}"] {
+            assert_eq!(parse_int(digits.as_bytes()), None, "{:?}", digits);
+        }
+        // Spellings `str::parse` accepts but the format does not.
+        for digits in ["+5", "-0", "00", "0042", "-05"] {
+            assert_eq!(parse_int(digits.as_bytes()), None, "{:?}", digits);
+        }
+        for input in [&b"i+5e"[..], b"i-0e", b"i03e"] {
+            let invalid = Err(DecodeError::InvalidInteger { offset: 0 });
+            assert_eq!(decode(input).map(|_| ()), invalid);
+            assert_eq!(crate::validate(input), invalid);
+            assert_eq!(Events::new(input).next().map(|e| e.map(|_| ())), Some(invalid));
+        }
+        assert_eq!(
+            decode(b"i99999e"),
+            Err(DecodeError::InvalidInteger { offset: 0 })
+        );
+    }
+
     #[test]
     fn test_string_decoding() {
         // Basic strings
@@ -166,6 +534,91 @@ mod tests {
         assert!(decode(b"1x:a").is_err()); // Invalid length delimiter
     }
 
+    #[test]
+    fn test_accessors() {
+        let (value, _) = decode(b"d1:ai1e1:bl3:fooee").unwrap();
+        assert!(value.is_dict() && !value.is_list());
+        let dict = value.as_dict().unwrap();
+        assert_eq!(dict["a"].as_int(), Some(1));
+        assert_eq!(dict["a"].as_str(), None);
+
+        let list = dict["b"].as_list().unwrap();
+        assert!(list[0].is_str() && !list[0].is_int());
+        assert_eq!(list[0].as_str(), Some("foo"));
+        assert_eq!(list[0].as_bytes(), Some(&b"foo"[..]));
+        assert_eq!(BValue::None.as_list(), None);
+    }
+
+    #[test]
+    fn test_compare_with_primitives() {
+        let (value, _) = decode(b"d4:infod4:name10:ubuntu.isoe4:porti80ee").unwrap();
+        assert!(value["info"]["name"] == "ubuntu.iso");
+        assert!(value["info"]["name"] == *"ubuntu.iso");
+        let name = String::from("ubuntu.iso");
+        let bytes: &[u8] = name.as_bytes();
+        assert!(value["info"]["name"] == name);
+        assert!(value["info"]["name"] == bytes);
+        assert!(value["info"]["name"] == *bytes);
+        assert!(value["port"] == 80i64);
+
+        assert!(value["port"] != "80");
+        assert!(value["info"]["name"] != 0i64);
+        assert!(value["port"] != 1i64 << 40);
+        assert!(value["missing"] != "");
+    }
+
+    #[test]
+    fn test_take_and_replace() {
+        let (mut value, _) = decode(b"d4:infod5:filesli1ei2eeee").unwrap();
+        let files = value.get_path_mut("info.files").unwrap().take();
+        assert_eq!(files, BValue::List(vec![BValue::Int(1), BValue::Int(2)]));
+        assert_eq!(value["info"]["files"], BValue::None);
+
+        let old = value.get_mut("info").unwrap().replace(BValue::Int(7));
+        assert!(old.is_dict());
+        assert_eq!(value["info"], 7i64);
+    }
+
+    #[test]
+    fn test_consuming_conversions() {
+        let (value, _) = decode(b"d1:ai1e1:bl3:fooee").unwrap();
+        let mut dict = value.into_dict().unwrap();
+        assert_eq!(dict.remove("a").unwrap().into_int(), Ok(1));
+
+        let mut list = dict.remove("b").unwrap().into_list().unwrap();
+        assert_eq!(list.pop().unwrap().into_bytes(), Ok(b"foo".to_vec()));
+
+        assert_eq!(BValue::Int(3).into_string(), Err(BValue::Int(3)));
+        assert_eq!(BValue::None.into_dict(), Err(BValue::None));
+        assert_eq!(BValue::List(vec![]).into_int(), Err(BValue::List(vec![])));
+    }
+
+    #[test]
+    fn test_huge_string_lengths() {
+        // Lengths near usize::MAX must not overflow when added to the offset.
+        for len in [usize::MAX, usize::MAX - 1] {
+            let input = format!("l{}:ae", len);
+            let eof = DecodeError::UnexpectedEof { offset: input.len() };
+            assert_eq!(decode(input.as_bytes()), Err(eof.clone()));
+            assert_eq!(validate::validate(input.as_bytes()), Err(eof.clone()));
+            assert_eq!(Events::new(input.as_bytes()).last(), Some(Err(eof)));
+        }
+
+        // Past u64 the length is malformed on every target.
+        assert_eq!(
+            decode(b"18446744073709551616:a"),
+            Err(DecodeError::InvalidStringLength { offset: 0 })
+        );
+
+        let max = u64::MAX.to_string();
+        let expected = if cfg!(target_pointer_width = "64") {
+            Ok(usize::MAX)
+        } else {
+            Err(DecodeError::LengthExceedsPlatform { offset: 3 })
+        };
+        assert_eq!(parse_len(max.as_bytes(), 3), expected);
+    }
+
     #[test]
     fn test_list_decoding() {
         // Empty list
@@ -208,6 +661,69 @@ mod tests {
 
         // Edge cases
         assert!(decode(b"d3:foo").is_err()); // Incomplete dict
+        assert_eq!(
+            decode(b"di1e3:fooe"),
+            Err(DecodeError::NonStringKey { offset: 1 })
+        );
+        assert_eq!(
+            decode(b"d3:bari1e3:fooe"),
+            Err(DecodeError::MissingValue { offset: 9 })
+        );
+        assert_eq!(
+            decode(b"dli1ee3:fooe"),
+            Err(DecodeError::NonStringKey { offset: 1 })
+        );
+    }
+
+    #[test]
+    fn test_decode_returns_remainder() {
+        let (value, rest) = decode(b"i42e4:spam").unwrap();
+        assert_eq!(value, BValue::Int(42));
+        assert_eq!(rest, b"4:spam");
+
+        let (value, rest) = decode(rest).unwrap();
+        assert_eq!(value, BValue::Str("spam".to_string()));
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn test_empty_and_partial_input() {
+        assert_eq!(decode(b""), Err(DecodeError::EmptyInput));
+
+        // Truncated prefixes report where the input ran out.
+        assert_eq!(decode(b"i42"), Err(DecodeError::UnexpectedEof { offset: 3 }));
+        assert_eq!(decode(b"l4"), Err(DecodeError::UnexpectedEof { offset: 2 }));
+        assert_eq!(decode(b"l"), Err(DecodeError::UnexpectedEof { offset: 1 }));
+    }
+
+    #[test]
+    fn test_stray_end_markers() {
+        assert_eq!(decode(b"e"), Err(DecodeError::UnexpectedEnd { offset: 0 }));
+        assert_eq!(
+            decode_exact(b"lee"),
+            Err(DecodeError::TrailingData { offset: 2 })
+        );
+        assert_eq!(
+            decode(b"x"),
+            Err(DecodeError::UnexpectedByte { offset: 0, byte: b'x' })
+        );
+        assert_eq!(
+            decode(b"d1:ae"),
+            Err(DecodeError::MissingValue { offset: 1 })
+        );
+    }
+
+    #[test]
+    fn test_prefix_and_exact() {
+        let (value, rest) = decode_prefix(b"i1ei2e").unwrap();
+        assert_eq!(value, BValue::Int(1));
+        assert_eq!(rest, b"i2e");
+
+        assert_eq!(decode_exact(b"i1e"), Ok(BValue::Int(1)));
+        assert_eq!(
+            decode_exact(b"i1ei2e"),
+            Err(DecodeError::TrailingData { offset: 3 })
+        );
     }
 
     #[test]
@@ -0,0 +1,305 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::bytes::{from_base32, from_hex, percent_decode, percent_encode, to_hex};
+use crate::{MetaInfo, Node};
+
+// Magnet links (BEP 9). A v1 infohash is written as `urn:btih:` in hex; a
+// v2 infohash as `urn:btmh:` followed by the hex of a SHA-256 multihash
+// (BEP 52), i.e. the 32-byte digest behind the prefix `1220`. A hybrid
+// torrent's link carries both.
+//
+// Parsing accepts a v1 hash in hex or base32, numbered parameters such as
+// `tr.1`, and ignores parameters and `xt` namespaces it does not know.
+
+const SHA256_MULTIHASH: &str = "1220";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Magnet {
+    /// v1 infohash (`xt=urn:btih:`).
+    pub info_hash: Option<[u8; 20]>,
+    /// v2 infohash (`xt=urn:btmh:`).
+    pub info_hash_v2: Option<[u8; 32]>,
+    /// Display name (`dn`).
+    pub name: Option<String>,
+    /// Total length in bytes (`xl`).
+    pub length: Option<u64>,
+    /// Tracker URLs (`tr`), in failover order.
+    pub trackers: Vec<String>,
+    /// Web seed URLs (`ws`, BEP 19).
+    pub web_seeds: Vec<String>,
+    /// Peers to contact directly (`x.pe`).
+    pub peers: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MagnetError {
+    /// The input does not start with `magnet:?`.
+    Scheme,
+    /// The named parameter has a bad escape or value.
+    Invalid(String),
+    /// No BitTorrent infohash in any `xt`.
+    MissingHash,
+}
+
+impl fmt::Display for MagnetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MagnetError::Scheme => write!(f, "not a magnet link"),
+            MagnetError::Invalid(param) => write!(f, "invalid magnet parameter {:?}", param),
+            MagnetError::MissingHash => write!(f, "magnet link has no infohash"),
+        }
+    }
+}
+
+impl std::error::Error for MagnetError {}
+
+impl Magnet {
+    /// A link with only a v1 infohash; fill in the other fields as needed.
+    pub fn new(info_hash: [u8; 20]) -> Self {
+        Magnet {
+            info_hash: Some(info_hash),
+            ..Magnet::default()
+        }
+    }
+
+    pub fn to_uri(&self) -> String {
+        let mut params = Vec::new();
+        if let Some(hash) = &self.info_hash {
+            params.push(format!("xt=urn:btih:{}", to_hex(hash)));
+        }
+        if let Some(hash) = &self.info_hash_v2 {
+            params.push(format!("xt=urn:btmh:{}{}", SHA256_MULTIHASH, to_hex(hash)));
+        }
+        if let Some(name) = &self.name {
+            params.push(format!("dn={}", percent_encode(name.as_bytes())));
+        }
+        if let Some(length) = self.length {
+            params.push(format!("xl={}", length));
+        }
+        for tracker in &self.trackers {
+            params.push(format!("tr={}", percent_encode(tracker.as_bytes())));
+        }
+        for seed in &self.web_seeds {
+            params.push(format!("ws={}", percent_encode(seed.as_bytes())));
+        }
+        for peer in &self.peers {
+            let addr = if peer.host.contains(':') {
+                format!("[{}]:{}", peer.host, peer.port)
+            } else {
+                format!("{}:{}", peer.host, peer.port)
+            };
+            params.push(format!("x.pe={}", percent_encode(addr.as_bytes())));
+        }
+        format!("magnet:?{}", params.join("&"))
+    }
+
+    pub fn parse(uri: &str) -> Result<Magnet, MagnetError> {
+        let query = uri.strip_prefix("magnet:?").ok_or(MagnetError::Scheme)?;
+        let mut magnet = Magnet::default();
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let invalid = || MagnetError::Invalid(name.to_string());
+            let value = percent_decode(value)
+                .and_then(|bytes| String::from_utf8(bytes).ok())
+                .ok_or_else(invalid)?;
+            match param_name(name) {
+                "xt" => magnet.exact_topic(&value).ok_or_else(invalid)?,
+                "dn" => magnet.name = Some(value),
+                "xl" => magnet.length = Some(value.parse().map_err(|_| invalid())?),
+                "tr" => magnet.trackers.push(value),
+                "ws" => magnet.web_seeds.push(value),
+                "x.pe" => magnet.peers.push(peer(&value).ok_or_else(invalid)?),
+                _ => {}
+            }
+        }
+        if magnet.info_hash.is_none() && magnet.info_hash_v2.is_none() {
+            return Err(MagnetError::MissingHash);
+        }
+        Ok(magnet)
+    }
+
+    /// Records an `xt` value; `None` if it is a malformed BitTorrent hash.
+    fn exact_topic(&mut self, xt: &str) -> Option<()> {
+        if let Some(hash) = xt.strip_prefix("urn:btih:") {
+            let bytes = match hash.len() {
+                40 => from_hex(hash)?,
+                32 => from_base32(hash)?,
+                _ => return None,
+            };
+            self.info_hash = Some(bytes.try_into().ok()?);
+        } else if let Some(multihash) = xt.strip_prefix("urn:btmh:") {
+            let digest = multihash.strip_prefix(SHA256_MULTIHASH)?;
+            self.info_hash_v2 = Some(from_hex(digest)?.try_into().ok()?);
+        }
+        Some(())
+    }
+}
+
+/// `tr.1` and the like count as `tr`.
+fn param_name(name: &str) -> &str {
+    match name.rsplit_once('.') {
+        Some((base, n)) if !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()) => base,
+        _ => name,
+    }
+}
+
+/// `host:port`, with IPv6 hosts in brackets.
+fn peer(addr: &str) -> Option<Node> {
+    let (host, port) = addr.rsplit_once(':')?;
+    let host = match host.strip_prefix('[') {
+        Some(v6) => v6.strip_suffix(']')?,
+        None => host,
+    };
+    if host.is_empty() {
+        return None;
+    }
+    Some(Node {
+        host: host.to_string(),
+        port: port.parse().ok()?,
+    })
+}
+
+impl FromStr for Magnet {
+    type Err = MagnetError;
+
+    fn from_str(s: &str) -> Result<Magnet, MagnetError> {
+        Magnet::parse(s)
+    }
+}
+
+impl fmt::Display for Magnet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_uri())
+    }
+}
+
+impl MetaInfo {
+    /// A magnet link with the torrent's name, length, trackers, and each
+    /// infohash its layout has and the enabled features can compute.
+    #[cfg(any(feature = "sha1", feature = "sha256"))]
+    pub fn magnet(&self) -> Magnet {
+        Magnet {
+            #[cfg(feature = "sha1")]
+            info_hash: self.info.is_v1().then(|| self.info_hash()),
+            #[cfg(not(feature = "sha1"))]
+            info_hash: None,
+            #[cfg(feature = "sha256")]
+            info_hash_v2: self.info.is_v2().then(|| self.info_hash_v2()),
+            #[cfg(not(feature = "sha256"))]
+            info_hash_v2: None,
+            name: Some(self.info.name.clone()),
+            length: self.info.total_length(),
+            trackers: self.trackers().map(str::to_string).collect(),
+            web_seeds: self.url_list.clone(),
+            peers: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_uri() {
+        let hash: [u8; 20] = std::array::from_fn(|i| i as u8);
+        let mut magnet = Magnet::new(hash);
+        assert_eq!(
+            magnet.to_string(),
+            "magnet:?xt=urn:btih:000102030405060708090a0b0c0d0e0f10111213"
+        );
+
+        magnet.info_hash_v2 = Some([0xab; 32]);
+        magnet.name = Some("a b&c.iso".to_string());
+        magnet.length = Some(5_000_000_000);
+        magnet.trackers = vec!["udp://t:80/announce".to_string(), "http://u/".to_string()];
+        assert_eq!(
+            magnet.to_uri(),
+            format!(
+                "magnet:?xt=urn:btih:000102030405060708090a0b0c0d0e0f10111213\
+                 &xt=urn:btmh:1220{}&dn=a%20b%26c.iso&xl=5000000000\
+                 &tr=udp%3A%2F%2Ft%3A80%2Fannounce&tr=http%3A%2F%2Fu%2F",
+                "ab".repeat(32)
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let uri = "magnet:?xt=urn:btih:YEX6DQDLXISUVHOJ6UM3GNNKPQJWPKEK&dn=a+b%20c\
+                   &tr.1=udp%3A%2F%2Ft%2F1&tr.2=udp://t/2&ws=http://w/&x.pe=10.0.0.1:6881\
+                   &x.pe=[::1]:80&xl=42&xt=urn:sha1:ignored&so=0-3";
+        let magnet: Magnet = uri.parse().unwrap();
+        assert_eq!(
+            magnet.info_hash.map(|h| to_hex(&h)).as_deref(),
+            Some("c12fe1c06bba254a9dc9f519b335aa7c1367a88a")
+        );
+        assert_eq!(magnet.info_hash_v2, None);
+        assert_eq!(magnet.name.as_deref(), Some("a b c"));
+        assert_eq!(magnet.length, Some(42));
+        assert_eq!(magnet.trackers, ["udp://t/1", "udp://t/2"]);
+        assert_eq!(magnet.web_seeds, ["http://w/"]);
+        assert_eq!(
+            magnet.peers[0].socket_addr(),
+            Some("10.0.0.1:6881".parse().unwrap())
+        );
+        assert_eq!(magnet.peers[1].host, "::1");
+
+        // The generated form parses back to the same value.
+        assert_eq!(Magnet::parse(&magnet.to_uri()), Ok(magnet));
+
+        let v2 = format!("magnet:?xt=urn:btmh:1220{}", "0f".repeat(32));
+        assert_eq!(Magnet::parse(&v2).unwrap().info_hash_v2, Some([0x0f; 32]));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let invalid = |param: &str| Err(MagnetError::Invalid(param.to_string()));
+        assert_eq!(Magnet::parse("http://x"), Err(MagnetError::Scheme));
+        assert_eq!(Magnet::parse("magnet:?dn=x"), Err(MagnetError::MissingHash));
+        assert_eq!(Magnet::parse("magnet:?xt=urn:btih:abc"), invalid("xt"));
+        assert_eq!(
+            Magnet::parse(&format!("magnet:?xt=urn:btih:{}", "g".repeat(40))),
+            invalid("xt")
+        );
+        assert_eq!(
+            Magnet::parse(&format!("magnet:?xt=urn:btmh:1114{}", "00".repeat(20))),
+            invalid("xt")
+        );
+        let hash = format!("xt=urn:btih:{}", "00".repeat(20));
+        assert_eq!(
+            Magnet::parse(&format!("magnet:?{}&xl=-1", hash)),
+            invalid("xl")
+        );
+        assert_eq!(
+            Magnet::parse(&format!("magnet:?{}&dn=%ff", hash)),
+            invalid("dn")
+        );
+        assert_eq!(
+            Magnet::parse(&format!("magnet:?{}&x.pe=host", hash)),
+            invalid("x.pe")
+        );
+    }
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_from_meta_info() {
+        let mut input = b"d8:announce9:udp://t/14:info".to_vec();
+        input.extend_from_slice(b"d6:lengthi70000e4:name3:a b12:piece lengthi65536e6:pieces40:");
+        input.extend([0; 40]);
+        input.extend_from_slice(b"ee");
+        let meta = MetaInfo::from_bytes(&input).unwrap();
+
+        let magnet = meta.magnet();
+        assert_eq!(magnet.info_hash, Some(meta.info_hash()));
+        assert_eq!(magnet.info_hash_v2, None);
+        assert_eq!(
+            magnet.to_uri(),
+            format!(
+                "magnet:?xt=urn:btih:{}&dn=a%20b&xl=70000&tr=udp%3A%2F%2Ft%2F1",
+                to_hex(&meta.info_hash())
+            )
+        );
+    }
+}
@@ -0,0 +1,55 @@
+// Single-byte search for the `:` and `e` delimiters, in the spirit of the
+// `memchr` crate but without the dependency. Whole words are tested for
+// the byte at once; a word that may contain it is rescanned byte by byte,
+// so false positives in the word test only cost time.
+
+const WORD: usize = size_of::<usize>();
+const LO: usize = usize::from_ne_bytes([0x01; WORD]);
+const HI: usize = usize::from_ne_bytes([0x80; WORD]);
+
+/// Index of the first `needle` in `haystack`.
+pub(crate) fn memchr(needle: u8, haystack: &[u8]) -> Option<usize> {
+    let repeated = LO * usize::from(needle);
+    let mut start = 0;
+    for chunk in haystack.chunks_exact(WORD) {
+        let Ok(bytes) = chunk.try_into() else { break };
+        // Zero exactly where the word holds `needle`.
+        let word = usize::from_ne_bytes(bytes) ^ repeated;
+        if word.wrapping_sub(LO) & !word & HI != 0 {
+            break;
+        }
+        start += WORD;
+    }
+    haystack[start..]
+        .iter()
+        .position(|&b| b == needle)
+        .map(|n| start + n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_position() {
+        let haystack: Vec<u8> = (0..=255).chain(0..=255).collect();
+        for len in [0, 1, 7, 8, 9, 31, 300, 512] {
+            let haystack = &haystack[..len];
+            for needle in 0..=255u8 {
+                assert_eq!(
+                    memchr(needle, haystack),
+                    haystack.iter().position(|&b| b == needle),
+                    "needle {} in {} bytes",
+                    needle,
+                    len
+                );
+            }
+        }
+        // High bytes next to the needle must not hide or fake a match.
+        assert_eq!(
+            memchr(b'e', b"\x80\xff\x65\xff\x80\x80\x80\x80\x65"),
+            Some(2)
+        );
+        assert_eq!(memchr(b':', &[0xba; 40]), None);
+    }
+}
@@ -0,0 +1,119 @@
+use crate::BValue;
+
+// Layering one value onto another, e.g. user overrides onto a torrent
+// template. Dict keys missing from the target are always added; what
+// happens to keys present on both sides is up to `MergeOptions`.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MergeOptions {
+    /// On a conflict keep the existing value instead of taking the new one.
+    pub keep_existing: bool,
+    /// Merge dicts found under the same key rather than treating them as a
+    /// conflict. On by default.
+    pub recurse_dicts: bool,
+    /// Append the new list's items to the existing list rather than treating
+    /// them as a conflict.
+    pub concat_lists: bool,
+}
+
+impl Default for MergeOptions {
+    fn default() -> Self {
+        MergeOptions {
+            keep_existing: false,
+            recurse_dicts: true,
+            concat_lists: false,
+        }
+    }
+}
+
+impl BValue {
+    /// Deep-merges `other` into `self`; on conflicts `other` wins.
+    pub fn merge(&mut self, other: BValue) {
+        self.merge_with(other, MergeOptions::default());
+    }
+
+    pub fn merge_with(&mut self, other: BValue, options: MergeOptions) {
+        match (self, other) {
+            (BValue::Dict(dict), BValue::Dict(other)) => {
+                for (key, value) in other {
+                    match dict.get_mut(&key) {
+                        Some(existing @ BValue::Dict(_)) if options.recurse_dicts => {
+                            existing.merge_with(value, options)
+                        }
+                        Some(existing) => existing.resolve(value, options),
+                        None => {
+                            dict.insert(key, value);
+                        }
+                    }
+                }
+            }
+            (this, other) => this.resolve(other, options),
+        }
+    }
+
+    fn resolve(&mut self, other: BValue, options: MergeOptions) {
+        match (self, other) {
+            (BValue::List(list), BValue::List(other)) if options.concat_lists => list.extend(other),
+            (_, _) if options.keep_existing => {}
+            (this, other) => *this = other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decode_exact, encode};
+
+    fn merged(base: &[u8], overrides: &[u8], options: MergeOptions) -> Vec<u8> {
+        let mut value = decode_exact(base).unwrap();
+        value.merge_with(decode_exact(overrides).unwrap(), options);
+        encode(&value).unwrap()
+    }
+
+    const BASE: &[u8] = b"d8:announce1:a4:infod4:name1:n7:privatei0ee4:listli1eee";
+    const OVERRIDES: &[u8] = b"d8:announce1:b4:infod7:privatei1ee4:listli2ee3:new0:e";
+
+    #[test]
+    fn test_default_merge_recurses_and_overwrites() {
+        let mut value = decode_exact(BASE).unwrap();
+        value.merge(decode_exact(OVERRIDES).unwrap());
+        assert_eq!(
+            encode(&value).unwrap(),
+            b"d8:announce1:b4:infod4:name1:n7:privatei1ee4:listli2ee3:new0:e"
+        );
+    }
+
+    #[test]
+    fn test_merge_strategies() {
+        let keep = MergeOptions {
+            keep_existing: true,
+            ..MergeOptions::default()
+        };
+        assert_eq!(
+            merged(BASE, OVERRIDES, keep),
+            b"d8:announce1:a4:infod4:name1:n7:privatei0ee4:listli1ee3:new0:e"
+        );
+
+        let concat = MergeOptions {
+            concat_lists: true,
+            ..MergeOptions::default()
+        };
+        assert_eq!(
+            merged(BASE, OVERRIDES, concat),
+            b"d8:announce1:b4:infod4:name1:n7:privatei1ee4:listli1ei2ee3:new0:e"
+        );
+
+        let shallow = MergeOptions {
+            recurse_dicts: false,
+            ..MergeOptions::default()
+        };
+        assert_eq!(
+            merged(BASE, OVERRIDES, shallow),
+            b"d8:announce1:b4:infod7:privatei1ee4:listli2ee3:new0:e"
+        );
+
+        // Mismatched kinds are a plain conflict.
+        assert_eq!(merged(b"i1e", b"d1:ai1ee", MergeOptions::default()), b"d1:ai1ee");
+    }
+}
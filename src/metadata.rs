@@ -0,0 +1,389 @@
+use std::fmt;
+use std::io;
+
+use crate::{DecodeError, Encoder, Event, Events};
+
+// ut_metadata (BEP 9): fetching the info dict from peers in 16 KiB pieces.
+// A message is a bencoded header dict, and a `data` message carries the
+// piece's bytes straight after it with no length prefix. The header is read
+// with `Events`, whose offset after the closing `e` is where the payload
+// begins; a full decode would treat the payload as trailing garbage.
+//
+// `MetadataAssembler` puts the pieces back together and checks them
+// against the infohash. Finding peers and talking to them is left to the
+// caller: fetching a magnet link's metadata end to end needs DHT and peer
+// wire transports, which this crate does not have yet.
+
+/// Size of every metadata piece except the last.
+pub const METADATA_PIECE_LEN: usize = 16 * 1024;
+
+/// Largest info dict `MetadataAssembler` accepts. Real ones are well
+/// below this; the size comes from the peer, so it needs a bound.
+#[cfg(feature = "sha1")]
+const MAX_METADATA_SIZE: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataMessage<'a> {
+    Request {
+        piece: u32,
+    },
+    Data {
+        piece: u32,
+        /// Length of the whole info dict.
+        total_size: u64,
+        data: &'a [u8],
+    },
+    Reject {
+        piece: u32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetadataError {
+    Decode(DecodeError),
+    /// A required header key is absent.
+    Missing(&'static str),
+    /// A header key has the wrong type or value, or `"data"` if the payload
+    /// is not the length the header implies.
+    Invalid(&'static str),
+    /// The assembled info dict does not hash to the infohash.
+    HashMismatch,
+}
+
+impl fmt::Display for MetadataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetadataError::Decode(e) => e.fmt(f),
+            MetadataError::Missing(key) => write!(f, "missing key {:?}", key),
+            MetadataError::Invalid(key) => write!(f, "invalid key {:?}", key),
+            MetadataError::HashMismatch => write!(f, "metadata does not match the infohash"),
+        }
+    }
+}
+
+impl std::error::Error for MetadataError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MetadataError::Decode(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<DecodeError> for MetadataError {
+    fn from(e: DecodeError) -> Self {
+        MetadataError::Decode(e)
+    }
+}
+
+/// Number of pieces an info dict of `total_size` bytes is fetched in.
+pub fn metadata_pieces(total_size: u64) -> u64 {
+    total_size.div_ceil(METADATA_PIECE_LEN as u64)
+}
+
+/// Length of `piece` of an info dict of `total_size` bytes; `None` if
+/// there is no such piece.
+fn piece_len(total_size: u64, piece: u32) -> Option<u64> {
+    if u64::from(piece) >= metadata_pieces(total_size) {
+        return None;
+    }
+    let start = u64::from(piece) * METADATA_PIECE_LEN as u64;
+    Some((total_size - start).min(METADATA_PIECE_LEN as u64))
+}
+
+impl<'a> MetadataMessage<'a> {
+    /// Parses a message body, without the extension message id in front.
+    pub fn parse(input: &'a [u8]) -> Result<MetadataMessage<'a>, MetadataError> {
+        if input.is_empty() {
+            return Err(DecodeError::EmptyInput.into());
+        }
+        let mut events = Events::new(input);
+        if events.expect_next()? != Event::DictStart {
+            return Err(MetadataError::Invalid(""));
+        }
+        let (mut msg_type, mut piece, mut total_size) = (None, None, None);
+        loop {
+            match events.expect_next()? {
+                Event::End => break,
+                Event::Key(b"msg_type") => msg_type = Some(int(&mut events, "msg_type")?),
+                Event::Key(b"piece") => piece = Some(int(&mut events, "piece")?),
+                Event::Key(b"total_size") => total_size = Some(int(&mut events, "total_size")?),
+                Event::Key(_) => events.skip_value()?,
+                _ => return Err(MetadataError::Invalid("")),
+            }
+        }
+        let payload = &input[events.offset()..];
+
+        let piece = piece.ok_or(MetadataError::Missing("piece"))?;
+        let piece = u32::try_from(piece).map_err(|_| MetadataError::Invalid("piece"))?;
+        let message = match msg_type.ok_or(MetadataError::Missing("msg_type"))? {
+            0 => MetadataMessage::Request { piece },
+            1 => {
+                let total_size = total_size.ok_or(MetadataError::Missing("total_size"))?;
+                let total_size =
+                    u64::try_from(total_size).map_err(|_| MetadataError::Invalid("total_size"))?;
+                let expected =
+                    piece_len(total_size, piece).ok_or(MetadataError::Invalid("piece"))?;
+                if payload.len() as u64 != expected {
+                    return Err(MetadataError::Invalid("data"));
+                }
+                return Ok(MetadataMessage::Data {
+                    piece,
+                    total_size,
+                    data: payload,
+                });
+            }
+            2 => MetadataMessage::Reject { piece },
+            _ => return Err(MetadataError::Invalid("msg_type")),
+        };
+        if !payload.is_empty() {
+            return Err(DecodeError::TrailingData {
+                offset: events.offset(),
+            }
+            .into());
+        }
+        Ok(message)
+    }
+
+    pub fn piece(&self) -> u32 {
+        match *self {
+            MetadataMessage::Request { piece }
+            | MetadataMessage::Data { piece, .. }
+            | MetadataMessage::Reject { piece } => piece,
+        }
+    }
+
+    /// The header followed by the payload of a `Data` message.
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        let (msg_type, total_size, data) = match *self {
+            MetadataMessage::Request { .. } => (0, None, &[][..]),
+            MetadataMessage::Data {
+                total_size, data, ..
+            } => (1, Some(total_size), data),
+            MetadataMessage::Reject { .. } => (2, None, &[][..]),
+        };
+        let mut out = Encoder::to_vec(|enc| {
+            enc.begin_dict()?.key(b"msg_type")?.int(msg_type)?;
+            enc.key(b"piece")?.int(i64::from(self.piece()))?;
+            if let Some(total_size) = total_size {
+                enc.key(b"total_size")?.int(total_size as i64)?;
+            }
+            enc.end()?;
+            Ok(())
+        })?;
+        out.extend_from_slice(data);
+        Ok(out)
+    }
+}
+
+/// Collects the pieces of one info dict as `Data` messages arrive, from
+/// any number of peers, and returns it once it is complete and hashes to
+/// the infohash.
+#[cfg(feature = "sha1")]
+#[derive(Debug, Clone)]
+pub struct MetadataAssembler {
+    info_hash: [u8; 20],
+    /// Set by the first `Data` message.
+    total_size: Option<u64>,
+    pieces: Vec<Option<Vec<u8>>>,
+}
+
+#[cfg(feature = "sha1")]
+impl MetadataAssembler {
+    pub fn new(info_hash: [u8; 20]) -> Self {
+        MetadataAssembler {
+            info_hash,
+            total_size: None,
+            pieces: Vec::new(),
+        }
+    }
+
+    /// Pieces still to request. Only piece 0 until a peer has said how
+    /// large the info dict is.
+    pub fn missing(&self) -> Vec<u32> {
+        if self.total_size.is_none() {
+            return vec![0];
+        }
+        (0..self.pieces.len() as u32)
+            .filter(|&piece| self.pieces[piece as usize].is_none())
+            .collect()
+    }
+
+    /// Stores the piece carried by a `Data` message; other messages are
+    /// ignored. Returns the info dict when this was the last piece. If it
+    /// does not match the infohash, every piece is dropped, since there is
+    /// no telling which peer sent bad data, and `HashMismatch` is returned.
+    pub fn add(&mut self, message: &MetadataMessage<'_>) -> Result<Option<Vec<u8>>, MetadataError> {
+        let MetadataMessage::Data {
+            piece,
+            total_size,
+            data,
+        } = *message
+        else {
+            return Ok(None);
+        };
+        match self.total_size {
+            Some(size) if size != total_size => return Err(MetadataError::Invalid("total_size")),
+            Some(_) => {}
+            None if total_size == 0 || total_size > MAX_METADATA_SIZE => {
+                return Err(MetadataError::Invalid("total_size"));
+            }
+            None => {
+                self.total_size = Some(total_size);
+                self.pieces = vec![None; metadata_pieces(total_size) as usize];
+            }
+        }
+        let expected = piece_len(total_size, piece).ok_or(MetadataError::Invalid("piece"))?;
+        if data.len() as u64 != expected {
+            return Err(MetadataError::Invalid("data"));
+        }
+        self.pieces[piece as usize] = Some(data.to_vec());
+
+        if self.pieces.iter().any(Option::is_none) {
+            return Ok(None);
+        }
+        let info: Vec<u8> = self
+            .pieces
+            .iter_mut()
+            .flat_map(|piece| piece.take().unwrap_or_default())
+            .collect();
+        if crate::sha1(&info) != self.info_hash {
+            return Err(MetadataError::HashMismatch);
+        }
+        Ok(Some(info))
+    }
+}
+
+fn int(events: &mut Events<'_>, key: &'static str) -> Result<i64, MetadataError> {
+    match events.expect_next()? {
+        Event::Int(n) => Ok(n),
+        _ => Err(MetadataError::Invalid(key)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_and_reject() {
+        let request = MetadataMessage::parse(b"d8:msg_typei0e5:piecei0ee").unwrap();
+        assert_eq!(request, MetadataMessage::Request { piece: 0 });
+        assert_eq!(request.to_bytes().unwrap(), b"d8:msg_typei0e5:piecei0ee");
+
+        let reject = MetadataMessage::Reject { piece: 3 };
+        assert_eq!(MetadataMessage::parse(&reject.to_bytes().unwrap()), Ok(reject));
+    }
+
+    #[test]
+    fn test_data_payload_follows_header() {
+        // The payload itself looks like bencode; it must not be parsed.
+        let total = METADATA_PIECE_LEN as u64 + 5;
+        let mut input = format!("d8:msg_typei1e5:piecei1e10:total_sizei{}ee", total).into_bytes();
+        let header_len = input.len();
+        input.extend_from_slice(b"d1:e");
+        input.push(b'e');
+
+        let message = MetadataMessage::parse(&input).unwrap();
+        assert_eq!(
+            message,
+            MetadataMessage::Data {
+                piece: 1,
+                total_size: total,
+                data: &input[header_len..],
+            }
+        );
+        assert_eq!(message.to_bytes().unwrap(), input);
+        assert_eq!(metadata_pieces(total), 2);
+        assert_eq!(metadata_pieces(0), 0);
+    }
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_assembler() {
+        let info: Vec<u8> = (0..20000).map(|i| (i % 251) as u8).collect();
+        let total_size = info.len() as u64;
+        let data = |piece: u32| {
+            let start = piece as usize * METADATA_PIECE_LEN;
+            MetadataMessage::Data {
+                piece,
+                total_size,
+                data: &info[start..info.len().min(start + METADATA_PIECE_LEN)],
+            }
+        };
+
+        let mut assembler = MetadataAssembler::new(crate::sha1(&info));
+        assert_eq!(assembler.missing(), [0]);
+        assert_eq!(assembler.add(&MetadataMessage::Reject { piece: 0 }), Ok(None));
+        assert_eq!(assembler.add(&data(1)), Ok(None));
+        assert_eq!(assembler.missing(), [0]);
+        assert_eq!(assembler.add(&data(0)), Ok(Some(info.clone())));
+
+        let other_size = MetadataMessage::Data {
+            piece: 0,
+            total_size: 5,
+            data: b"abcde",
+        };
+        assert_eq!(
+            assembler.add(&other_size),
+            Err(MetadataError::Invalid("total_size"))
+        );
+
+        // A wrong piece fails the whole dict, which is then fetched again.
+        let mut assembler = MetadataAssembler::new([0; 20]);
+        assert_eq!(assembler.add(&data(0)), Ok(None));
+        assert_eq!(assembler.add(&data(1)), Err(MetadataError::HashMismatch));
+        assert_eq!(assembler.missing(), [0, 1]);
+
+        let huge = MetadataMessage::Data {
+            piece: 0,
+            total_size: u64::MAX,
+            data: &[],
+        };
+        assert_eq!(
+            MetadataAssembler::new([0; 20]).add(&huge),
+            Err(MetadataError::Invalid("total_size"))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let full = vec![0; METADATA_PIECE_LEN];
+        let data = |header: &str, payload: &[u8]| [header.as_bytes(), payload].concat();
+        let cases: [(Vec<u8>, MetadataError); 7] = [
+            (b"d5:piecei0ee".to_vec(), MetadataError::Missing("msg_type")),
+            (b"d8:msg_typei0ee".to_vec(), MetadataError::Missing("piece")),
+            (
+                b"d8:msg_typei9e5:piecei0ee".to_vec(),
+                MetadataError::Invalid("msg_type"),
+            ),
+            (
+                b"d8:msg_typei1e5:piecei0ee".to_vec(),
+                MetadataError::Missing("total_size"),
+            ),
+            // Too short for a piece that is not the last.
+            (
+                data("d8:msg_typei1e5:piecei0e10:total_sizei20000ee", b"abc"),
+                MetadataError::Invalid("data"),
+            ),
+            (
+                data("d8:msg_typei1e5:piecei2e10:total_sizei20000ee", &full),
+                MetadataError::Invalid("piece"),
+            ),
+            (
+                b"d8:msg_typei0e5:piecei0eex".to_vec(),
+                DecodeError::TrailingData { offset: 25 }.into(),
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(MetadataMessage::parse(&input), Err(expected));
+        }
+        assert!(
+            MetadataMessage::parse(&data(
+                "d8:msg_typei1e5:piecei0e10:total_sizei20000ee",
+                &full
+            ))
+            .is_ok()
+        );
+    }
+}
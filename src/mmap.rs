@@ -0,0 +1,188 @@
+use std::ffi::{c_int, c_long, c_void};
+use std::fs::File;
+use std::io;
+use std::ops::Deref;
+use std::os::fd::AsRawFd;
+use std::path::Path;
+use std::ptr;
+
+use crate::{DecodeError, LazyValue, MetaInfo};
+
+// Read-only memory mapping of a whole file, so large metainfo files are
+// read straight from the page cache instead of being copied into a buffer
+// first. Unix only; the two libc calls are declared here to keep the crate
+// free of dependencies.
+//
+// Mapping is `unsafe`: the bytes are handed out as `&[u8]`, which must not
+// change while borrowed, but nothing stops another process from writing to
+// or truncating the file. Callers promise that it won't.
+
+const PROT_READ: c_int = 1;
+const MAP_PRIVATE: c_int = 2;
+
+unsafe extern "C" {
+    fn mmap(
+        addr: *mut c_void,
+        len: usize,
+        prot: c_int,
+        flags: c_int,
+        fd: c_int,
+        offset: c_long,
+    ) -> *mut c_void;
+    fn munmap(addr: *mut c_void, len: usize) -> c_int;
+}
+
+/// A file mapped read-only into memory.
+#[derive(Debug)]
+pub struct MappedFile {
+    ptr: *mut c_void,
+    len: usize,
+}
+
+// The mapping is read-only and only handed out as `&[u8]`; `open`'s
+// contract keeps it unchanged for as long as it exists.
+unsafe impl Send for MappedFile {}
+unsafe impl Sync for MappedFile {}
+
+impl MappedFile {
+    /// Maps the file at `path`.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be written to or truncated, by this or any other
+    /// process, until the `MappedFile` is dropped. A change would alter
+    /// bytes that are borrowed as immutable; a truncation makes reading
+    /// the missing pages raise `SIGBUS`.
+    pub unsafe fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let len = usize::try_from(file.metadata()?.len())
+            .map_err(|_e| io::Error::new(io::ErrorKind::InvalidData, "file too large to map"))?;
+
+        // Zero-length mappings are rejected by the kernel.
+        if len == 0 {
+            return Ok(MappedFile {
+                ptr: ptr::null_mut(),
+                len: 0,
+            });
+        }
+
+        let ptr = unsafe {
+            mmap(
+                ptr::null_mut(),
+                len,
+                PROT_READ,
+                MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        // MAP_FAILED is (void *)-1.
+        if ptr as isize == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(MappedFile { ptr, len })
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        if self.len == 0 {
+            return &[];
+        }
+        // Mapped for `len` bytes, and unchanged per `open`'s contract.
+        unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+
+    /// A lazy view over the mapped value, borrowing from the mapping
+    /// without copying anything.
+    pub fn lazy(&self) -> Result<LazyValue<'_>, DecodeError> {
+        let (value, rest) = LazyValue::parse(self.bytes())?;
+        if !rest.is_empty() {
+            return Err(DecodeError::TrailingData {
+                offset: self.len - rest.len(),
+            });
+        }
+        Ok(value)
+    }
+}
+
+impl Deref for MappedFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.bytes()
+    }
+}
+
+impl Drop for MappedFile {
+    fn drop(&mut self) {
+        if self.len != 0 {
+            unsafe {
+                munmap(self.ptr, self.len);
+            }
+        }
+    }
+}
+
+impl MetaInfo {
+    /// Parses the .torrent file at `path` straight from a mapping of it,
+    /// so the file is never read into a buffer as a whole. Malformed
+    /// contents are an `InvalidData` error wrapping a `TorrentError`.
+    ///
+    /// # Safety
+    ///
+    /// As for `MappedFile::open`, for the duration of the call.
+    pub unsafe fn open(path: impl AsRef<Path>) -> io::Result<MetaInfo> {
+        let file = unsafe { MappedFile::open(path)? };
+        MetaInfo::from_bytes(&file).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::temp_path;
+
+    fn temp_file(name: &str, contents: &[u8]) -> std::path::PathBuf {
+        let path = temp_path(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_lazy_view_of_a_torrent() {
+        let mut input = b"d4:infod6:lengthi5000000000e4:name1:a12:piece lengthi262144e".to_vec();
+        input.extend_from_slice(b"6:pieces20:");
+        input.extend([0xff; 20]);
+        input.extend_from_slice(b"ee");
+        let path = temp_file("torrent", &input);
+        // Test files are private to this process.
+        let mapped = unsafe { MappedFile::open(&path) }.unwrap();
+        let meta = unsafe { MetaInfo::open(&path) }.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let info = mapped.lazy().unwrap().get("info").unwrap();
+        assert_eq!(info.get("length").unwrap().as_int(), Some(5_000_000_000));
+        assert_eq!(
+            info.get("pieces").unwrap().as_bytes(),
+            Some(&[0xff; 20][..])
+        );
+        assert_eq!(meta.info.total_length(), Some(5_000_000_000));
+        assert_eq!(meta.info_bytes(), info.raw());
+    }
+
+    #[test]
+    fn test_empty_and_malformed_files() {
+        let path = temp_file("empty", b"");
+        let mapped = unsafe { MappedFile::open(&path) }.unwrap();
+        let err = unsafe { MetaInfo::open(&path) }.unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(mapped.is_empty());
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let path = temp_file("trailing", b"i1ei2e");
+        let mapped = unsafe { MappedFile::open(&path) }.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(mapped.lazy(), Err(DecodeError::TrailingData { offset: 3 }));
+
+        assert!(unsafe { MetaInfo::open("/nonexistent/bencode-rs") }.is_err());
+    }
+}
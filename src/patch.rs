@@ -0,0 +1,223 @@
+use std::fmt;
+use std::io;
+use std::ops::Range;
+
+use crate::{BValue, DecodeError, Kind, LazyValue, PathError, PathSegment, encode, parse_path};
+
+// Editing one field of a large document without re-encoding the rest: the
+// path is followed through the encoded bytes, only the new value is
+// encoded, and it is spliced over the old one. Everything else, including
+// any non-canonical encoding, is copied through untouched.
+//
+// A missing last key is inserted before the first key that sorts after it,
+// and an index one past the end of a list appends, as with `set_path`.
+// Intermediate containers are not created.
+
+#[derive(Debug)]
+pub enum PatchError {
+    Decode(DecodeError),
+    Path(PathError),
+    /// The replacement has no encoded form (`BValue::None` or `External`).
+    Encode(io::Error),
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::Decode(e) => e.fmt(f),
+            PatchError::Path(e) => e.fmt(f),
+            PatchError::Encode(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PatchError::Decode(e) => Some(e),
+            PatchError::Path(e) => Some(e),
+            PatchError::Encode(e) => Some(e),
+        }
+    }
+}
+
+impl From<DecodeError> for PatchError {
+    fn from(e: DecodeError) -> Self {
+        PatchError::Decode(e)
+    }
+}
+
+impl From<PathError> for PatchError {
+    fn from(e: PathError) -> Self {
+        PatchError::Path(e)
+    }
+}
+
+/// A copy of `input` with the value at `path` replaced by `value`.
+pub fn patch(input: &[u8], path: &str, value: &BValue) -> Result<Vec<u8>, PatchError> {
+    let segments = parse_path(path).ok_or(PathError::Malformed)?;
+    let replacement = encode(value).map_err(PatchError::Encode)?;
+
+    let (mut node, rest) = LazyValue::parse(input)?;
+    if !rest.is_empty() {
+        return Err(DecodeError::TrailingData {
+            offset: input.len() - rest.len(),
+        }
+        .into());
+    }
+    let Some((last, parents)) = segments.split_last() else {
+        return Ok(replacement);
+    };
+
+    for (depth, segment) in parents.iter().enumerate() {
+        let child = match segment {
+            PathSegment::Key(key) => node.get(key),
+            PathSegment::Index(i) => node.index(*i),
+        };
+        node = child.ok_or(PathError::Blocked { depth })?;
+    }
+
+    let blocked = PathError::Blocked {
+        depth: parents.len(),
+    };
+    let (range, splice) = match (last, node.kind()) {
+        (PathSegment::Key(key), Kind::Dict) => dict_slot(input, node, key, replacement),
+        (PathSegment::Index(i), Kind::List) => {
+            let end = offset(input, node) + node.raw().len() - 1;
+            match node.index(*i) {
+                Some(item) => (span(input, item), replacement),
+                None if *i == node.items().count() => (end..end, replacement),
+                None => return Err(blocked.into()),
+            }
+        }
+        _ => return Err(blocked.into()),
+    };
+
+    let mut out = Vec::with_capacity(input.len() - range.len() + splice.len());
+    out.extend_from_slice(&input[..range.start]);
+    out.extend_from_slice(&splice);
+    out.extend_from_slice(&input[range.end..]);
+    Ok(out)
+}
+
+/// Where `key`'s value goes in `dict`, and the bytes to put there.
+fn dict_slot(
+    input: &[u8],
+    dict: LazyValue<'_>,
+    key: &str,
+    replacement: Vec<u8>,
+) -> (Range<usize>, Vec<u8>) {
+    // A key's encoding starts where the previous entry's value ends.
+    let mut key_start = offset(input, dict) + 1;
+    let mut insert_at = None;
+    for (k, value) in dict.entries() {
+        if k == key.as_bytes() {
+            return (span(input, value), replacement);
+        }
+        if insert_at.is_none() && k > key.as_bytes() {
+            insert_at = Some(key_start);
+        }
+        key_start = span(input, value).end;
+    }
+
+    let at = insert_at.unwrap_or(key_start);
+    let mut entry = format!("{}:{}", key.len(), key).into_bytes();
+    entry.extend_from_slice(&replacement);
+    (at..at, entry)
+}
+
+fn offset(input: &[u8], value: LazyValue<'_>) -> usize {
+    value.raw().as_ptr() as usize - input.as_ptr() as usize
+}
+
+fn span(input: &[u8], value: LazyValue<'_>) -> Range<usize> {
+    let start = offset(input, value);
+    start..start + value.raw().len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_existing_value() {
+        let input = b"d8:announce3:url4:infod6:lengthi1e6:pieces4:abcdee";
+        let patched = patch(input, "announce", &BValue::Str("http://t".to_string())).unwrap();
+        assert_eq!(
+            patched,
+            b"d8:announce8:http://t4:infod6:lengthi1e6:pieces4:abcdee"
+        );
+        assert_eq!(
+            patch(input, "info.length", &BValue::Int(-5)).unwrap(),
+            b"d8:announce3:url4:infod6:lengthi-5e6:pieces4:abcdee"
+        );
+        assert_eq!(patch(input, "", &BValue::Int(1)).unwrap(), b"i1e");
+    }
+
+    #[test]
+    fn test_insert_and_append() {
+        let input = b"d1:ai1e1:cli1eee";
+        assert_eq!(
+            patch(input, "b", &BValue::Int(2)).unwrap(),
+            b"d1:ai1e1:bi2e1:cli1eee"
+        );
+        assert_eq!(
+            patch(input, "d", &BValue::Int(2)).unwrap(),
+            b"d1:ai1e1:cli1ee1:di2ee"
+        );
+        assert_eq!(
+            patch(input, "c[1]", &BValue::Int(2)).unwrap(),
+            b"d1:ai1e1:cli1ei2eee"
+        );
+        assert_eq!(
+            patch(input, "c[0]", &BValue::Int(2)).unwrap(),
+            b"d1:ai1e1:cli2eee"
+        );
+
+        // Non-canonical order is left alone.
+        assert_eq!(
+            patch(b"d1:ci0e1:ai0ee", "a", &BValue::Int(1)).unwrap(),
+            b"d1:ci0e1:ai1ee"
+        );
+    }
+
+    #[test]
+    fn test_real_torrent() {
+        // Binary pieces and a length past `i16` would not decode to a tree.
+        let input: &[u8] =
+            b"d8:announce3:url4:infod6:lengthi5000000000e4:name1:n6:pieces2:\xff\x00ee";
+        assert_eq!(
+            patch(input, "announce", &BValue::Str("udp://t".to_string())).unwrap(),
+            b"d8:announce7:udp://t4:infod6:lengthi5000000000e4:name1:n6:pieces2:\xff\x00ee"
+        );
+        assert_eq!(
+            patch(input, "info.private", &BValue::Int(1)).unwrap(),
+            b"d8:announce3:url4:infod6:lengthi5000000000e4:name1:n6:pieces2:\xff\x007:privatei1eee"
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let input = b"d1:ai1ee";
+        assert!(matches!(
+            patch(input, "a.b", &BValue::Int(1)),
+            Err(PatchError::Path(PathError::Blocked { depth: 1 }))
+        ));
+        assert!(matches!(
+            patch(input, "x.y", &BValue::Int(1)),
+            Err(PatchError::Path(PathError::Blocked { depth: 0 }))
+        ));
+        assert!(matches!(
+            patch(input, "a[", &BValue::Int(1)),
+            Err(PatchError::Path(PathError::Malformed))
+        ));
+        assert!(matches!(
+            patch(input, "a", &BValue::None),
+            Err(PatchError::Encode(_))
+        ));
+        assert!(matches!(
+            patch(b"d1:ai1eei1e", "a", &BValue::Int(1)),
+            Err(PatchError::Decode(DecodeError::TrailingData { offset: 8 }))
+        ));
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::BValue;
+
+// Path expressions for nested lookups: dict keys separated by dots, list
+// positions in brackets, e.g. `info.files[2].length`. A backslash makes the
+// next character part of the key, so `a\.b` is the single key "a.b". The
+// empty path is the value itself.
+//
+// `set_path` creates missing dicts along the way, so
+// `set_path("info.private", BValue::Int(1))` works on a torrent without
+// `private`. List items are never created in the middle of a path; an index
+// one past the end appends at the final segment only.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// Splits `path` into segments, or `None` if it is malformed (an unclosed
+/// or non-numeric bracket, a trailing backslash, text after a bracket).
+pub fn parse_path(path: &str) -> Option<Vec<PathSegment>> {
+    let mut segments = Vec::new();
+    if path.is_empty() {
+        return Some(segments);
+    }
+
+    let mut chars = path.chars().peekable();
+    loop {
+        // One dot-separated segment: a key, then any number of indices.
+        let mut key = String::new();
+        while let Some(&c) = chars.peek() {
+            match c {
+                '.' | '[' => break,
+                '\\' => {
+                    chars.next();
+                    key.push(chars.next()?);
+                }
+                _ => {
+                    key.push(c);
+                    chars.next();
+                }
+            }
+        }
+
+        let mut indices = Vec::new();
+        while chars.next_if_eq(&'[').is_some() {
+            let mut digits = String::new();
+            loop {
+                match chars.next()? {
+                    ']' => break,
+                    c => digits.push(c),
+                }
+            }
+            if !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            indices.push(PathSegment::Index(digits.parse().ok()?));
+        }
+
+        if !key.is_empty() || indices.is_empty() {
+            segments.push(PathSegment::Key(key));
+        }
+        segments.extend(indices);
+
+        match chars.next() {
+            None => return Some(segments),
+            Some('.') => {}
+            Some(_) => return None,
+        }
+    }
+}
+
+/// The inverse of `parse_path`: renders segments with dots, brackets and
+/// backslash escapes so the result parses back to the same segments.
+pub fn format_path(segments: &[PathSegment]) -> String {
+    let mut path = String::new();
+    for (i, segment) in segments.iter().enumerate() {
+        match segment {
+            PathSegment::Key(key) => {
+                // An empty key right before an index would be dropped by the
+                // parser, as would a leading one; neither can be expressed.
+                if i > 0 {
+                    path.push('.');
+                }
+                for c in key.chars() {
+                    if matches!(c, '.' | '[' | '\\') {
+                        path.push('\\');
+                    }
+                    path.push(c);
+                }
+            }
+            PathSegment::Index(n) => {
+                path.push('[');
+                path.push_str(&n.to_string());
+                path.push(']');
+            }
+        }
+    }
+    path
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathError {
+    Malformed,
+    /// The segment at this position cannot be applied: the value there is
+    /// not a dict or list, or the index is out of range.
+    Blocked { depth: usize },
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathError::Malformed => write!(f, "malformed path"),
+            PathError::Blocked { depth } => write!(f, "path is blocked at segment {}", depth),
+        }
+    }
+}
+
+impl std::error::Error for PathError {}
+
+impl BValue {
+    /// The value at `path` (see `parse_path`), or `None` if the path is
+    /// malformed or leads nowhere.
+    pub fn get_path(&self, path: &str) -> Option<&BValue> {
+        parse_path(path)?
+            .iter()
+            .try_fold(self, |value, segment| match segment {
+                PathSegment::Key(key) => value.get(key),
+                PathSegment::Index(i) => value.get(*i),
+            })
+    }
+
+    pub fn get_path_mut(&mut self, path: &str) -> Option<&mut BValue> {
+        parse_path(path)?
+            .iter()
+            .try_fold(self, |value, segment| match segment {
+                PathSegment::Key(key) => value.get_mut(key),
+                PathSegment::Index(i) => value.get_mut(*i),
+            })
+    }
+
+    /// Stores `value` at `path`, creating missing dicts on the way, and
+    /// returns the value it replaced.
+    pub fn set_path(&mut self, path: &str, value: BValue) -> Result<Option<BValue>, PathError> {
+        let segments = parse_path(path).ok_or(PathError::Malformed)?;
+        let Some((last, parents)) = segments.split_last() else {
+            return Ok(Some(std::mem::replace(self, value)));
+        };
+
+        let mut target = self;
+        for (depth, segment) in parents.iter().enumerate() {
+            target = match (segment, target) {
+                (PathSegment::Key(key), BValue::Dict(dict)) => dict
+                    .entry(key.clone())
+                    .or_insert_with(|| BValue::Dict(HashMap::new())),
+                (PathSegment::Index(i), BValue::List(list)) => {
+                    list.get_mut(*i).ok_or(PathError::Blocked { depth })?
+                }
+                _ => return Err(PathError::Blocked { depth }),
+            };
+        }
+
+        let depth = parents.len();
+        match (last, target) {
+            (PathSegment::Key(key), BValue::Dict(dict)) => Ok(dict.insert(key.clone(), value)),
+            (PathSegment::Index(i), BValue::List(list)) if *i == list.len() => {
+                list.push(value);
+                Ok(None)
+            }
+            (PathSegment::Index(i), BValue::List(list)) => match list.get_mut(*i) {
+                Some(slot) => Ok(Some(std::mem::replace(slot, value))),
+                None => Err(PathError::Blocked { depth }),
+            },
+            _ => Err(PathError::Blocked { depth }),
+        }
+    }
+
+    /// Removes and returns the value at `path`, as `take_path` does for
+    /// the parsed segments. List items after a removed one shift down.
+    /// `None` if nothing is there; the root itself cannot be removed.
+    pub fn remove_path(&mut self, path: &str) -> Option<BValue> {
+        let components: Vec<String> = parse_path(path)?
+            .into_iter()
+            .map(|segment| match segment {
+                PathSegment::Key(key) => key,
+                PathSegment::Index(i) => i.to_string(),
+            })
+            .collect();
+        let components: Vec<&str> = components.iter().map(String::as_str).collect();
+        self.take_path(&components)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode_exact;
+
+    fn key(k: &str) -> PathSegment {
+        PathSegment::Key(k.to_string())
+    }
+
+    #[test]
+    fn test_parse_path() {
+        assert_eq!(
+            parse_path("info.files[2].length"),
+            Some(vec![key("info"), key("files"), PathSegment::Index(2), key("length")])
+        );
+        assert_eq!(parse_path(r"a\.b.c\\"), Some(vec![key("a.b"), key(r"c\")]));
+        assert_eq!(
+            parse_path("[0][1]"),
+            Some(vec![PathSegment::Index(0), PathSegment::Index(1)])
+        );
+        assert_eq!(parse_path("a..b"), Some(vec![key("a"), key(""), key("b")]));
+        assert_eq!(parse_path(""), Some(vec![]));
+
+        for bad in ["a[", "a[x]", "a[-1]", "a[0]b", "a\\"] {
+            assert_eq!(parse_path(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_format_path_round_trips() {
+        for path in ["info.files[2].length", r"a\.b.c\\", "[0][1]", "a..b", "x\\[y"] {
+            let segments = parse_path(path).unwrap();
+            assert_eq!(parse_path(&format_path(&segments)), Some(segments), "{}", path);
+        }
+        assert_eq!(format_path(&[key("a.b"), PathSegment::Index(3)]), r"a\.b[3]");
+    }
+
+    #[test]
+    fn test_get_path() {
+        let mut torrent = decode_exact(
+            b"d4:infod5:filesld6:lengthi1eed6:lengthi2eee4:name1:ne8:web.seedi3ee",
+        )
+        .unwrap();
+        assert_eq!(torrent.get_path("info.files[1].length"), Some(&BValue::Int(2)));
+        assert_eq!(torrent.get_path(r"web\.seed"), Some(&BValue::Int(3)));
+        assert_eq!(torrent.get_path("web.seed"), None);
+        assert_eq!(torrent.get_path("info.files[5]"), None);
+        assert_eq!(torrent.get_path(""), Some(&torrent));
+
+        *torrent.get_path_mut("info.name").unwrap() = BValue::Int(0);
+        assert_eq!(torrent["info"]["name"], BValue::Int(0));
+    }
+
+    #[test]
+    fn test_set_path_creates_dicts() {
+        let mut torrent = decode_exact(b"d4:infod4:name1:nee").unwrap();
+        assert_eq!(torrent.set_path("info.private", BValue::Int(1)), Ok(None));
+        assert_eq!(
+            torrent.set_path("info.name", BValue::Str("m".to_string())),
+            Ok(Some(BValue::Str("n".to_string())))
+        );
+        assert_eq!(torrent.set_path("a.b.c", BValue::Int(2)), Ok(None));
+        assert_eq!(
+            crate::encode(&torrent).unwrap(),
+            b"d1:ad1:bd1:ci2eee4:infod4:name1:m7:privatei1eee"
+        );
+
+        torrent.set_path("list", BValue::List(vec![])).unwrap();
+        assert_eq!(torrent.set_path("list[0]", BValue::Int(5)), Ok(None));
+        assert_eq!(torrent.set_path("list[0]", BValue::Int(6)), Ok(Some(BValue::Int(5))));
+        assert_eq!(
+            torrent.set_path("list[2]", BValue::Int(7)),
+            Err(PathError::Blocked { depth: 1 })
+        );
+        assert_eq!(
+            torrent.set_path("info.name.x", BValue::Int(7)),
+            Err(PathError::Blocked { depth: 2 })
+        );
+        assert_eq!(torrent.set_path("a[", BValue::Int(7)), Err(PathError::Malformed));
+    }
+
+    #[test]
+    fn test_remove_path() {
+        let mut torrent =
+            decode_exact(b"d13:announce-listll1:ael1:bel1:cee4:infod7:privatei1eee").unwrap();
+        assert_eq!(torrent.remove_path("info.private"), Some(BValue::Int(1)));
+        assert_eq!(torrent.remove_path("info.private"), None);
+        assert_eq!(
+            torrent.remove_path("announce-list[1]"),
+            Some(BValue::List(vec![BValue::Str("b".to_string())]))
+        );
+        assert_eq!(torrent.remove_path("announce-list[2]"), None);
+        assert_eq!(torrent.remove_path(""), None);
+        assert_eq!(
+            crate::encode(&torrent).unwrap(),
+            b"d13:announce-listll1:ael1:cee4:infodee"
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use std::fmt;
+use std::io;
+use std::net::{SocketAddr, SocketAddrV4, SocketAddrV6};
+
+use crate::{
+    DecodeError, Encoder, Event, Events, compact_peers_v4, compact_peers_v6,
+    encode_compact_peers_v4, encode_compact_peers_v6,
+};
+
+// ut_pex peer exchange (BEP 11). Peers are compact strings: `added` and
+// `dropped` for IPv4, `added6` and `dropped6` for IPv6, with one flag byte
+// per added peer in `added.f` and `added6.f`. Both families are merged
+// into one list each way. A flags string of the wrong length is ignored,
+// as clients disagree on whether to send it at all.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PexPeer {
+    pub addr: SocketAddr,
+    /// `added.f` bits; 0 if the sender gave none.
+    pub flags: u8,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PexMessage {
+    pub added: Vec<PexPeer>,
+    pub dropped: Vec<SocketAddr>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PexError {
+    Decode(DecodeError),
+    /// The named key is not a compact peer string. `""` is the message
+    /// itself.
+    Invalid(&'static str),
+}
+
+impl fmt::Display for PexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PexError::Decode(e) => e.fmt(f),
+            PexError::Invalid(key) => write!(f, "invalid pex key {:?}", key),
+        }
+    }
+}
+
+impl std::error::Error for PexError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PexError::Decode(e) => Some(e),
+            PexError::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<DecodeError> for PexError {
+    fn from(e: DecodeError) -> Self {
+        PexError::Decode(e)
+    }
+}
+
+impl PexPeer {
+    /// Prefers encrypted connections.
+    pub const ENCRYPTION: u8 = 0x01;
+    pub const SEED: u8 = 0x02;
+    pub const UTP: u8 = 0x04;
+    pub const HOLEPUNCH: u8 = 0x08;
+    /// The sender has connected to this peer itself.
+    pub const REACHABLE: u8 = 0x10;
+
+    pub fn has(&self, flag: u8) -> bool {
+        self.flags & flag == flag
+    }
+
+    pub fn is_seed(&self) -> bool {
+        self.has(Self::SEED)
+    }
+}
+
+impl PexMessage {
+    pub fn from_bytes(input: &[u8]) -> Result<PexMessage, PexError> {
+        if input.is_empty() {
+            return Err(DecodeError::EmptyInput.into());
+        }
+        let mut events = Events::new(input);
+        if events.expect_next()? != Event::DictStart {
+            return Err(PexError::Invalid(""));
+        }
+        let (mut added, mut added_f, mut dropped) = (None, None, None);
+        let (mut added6, mut added6_f, mut dropped6) = (None, None, None);
+        loop {
+            let slot = match events.expect_next()? {
+                Event::End => break,
+                Event::Key(b"added") => (&mut added, "added"),
+                Event::Key(b"added.f") => (&mut added_f, "added.f"),
+                Event::Key(b"dropped") => (&mut dropped, "dropped"),
+                Event::Key(b"added6") => (&mut added6, "added6"),
+                Event::Key(b"added6.f") => (&mut added6_f, "added6.f"),
+                Event::Key(b"dropped6") => (&mut dropped6, "dropped6"),
+                Event::Key(_) => {
+                    events.skip_value()?;
+                    continue;
+                }
+                _ => return Err(PexError::Invalid("")),
+            };
+            match events.expect_next()? {
+                Event::Str(bytes) => *slot.0 = Some(bytes),
+                _ => return Err(PexError::Invalid(slot.1)),
+            }
+        }
+        if events.offset() < input.len() {
+            return Err(DecodeError::TrailingData {
+                offset: events.offset(),
+            }
+            .into());
+        }
+
+        let v4 = |bytes: Option<&[u8]>, key| {
+            let addrs =
+                compact_peers_v4(bytes.unwrap_or_default()).ok_or(PexError::Invalid(key))?;
+            Ok::<_, PexError>(addrs.into_iter().map(SocketAddr::V4).collect::<Vec<_>>())
+        };
+        let v6 = |bytes: Option<&[u8]>, key| {
+            let addrs =
+                compact_peers_v6(bytes.unwrap_or_default()).ok_or(PexError::Invalid(key))?;
+            Ok::<_, PexError>(addrs.into_iter().map(SocketAddr::V6).collect::<Vec<_>>())
+        };
+        let mut message = PexMessage::default();
+        message
+            .added
+            .extend(with_flags(v4(added, "added")?, added_f));
+        message
+            .added
+            .extend(with_flags(v6(added6, "added6")?, added6_f));
+        message.dropped.extend(v4(dropped, "dropped")?);
+        message.dropped.extend(v6(dropped6, "dropped6")?);
+        Ok(message)
+    }
+
+    /// Encodes every key, empty or not, split by address family.
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        let (mut added, mut added_f, mut added6, mut added6_f) =
+            (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        for peer in &self.added {
+            match peer.addr {
+                SocketAddr::V4(addr) => {
+                    added.push(addr);
+                    added_f.push(peer.flags);
+                }
+                SocketAddr::V6(addr) => {
+                    added6.push(addr);
+                    added6_f.push(peer.flags);
+                }
+            }
+        }
+        let dropped: Vec<SocketAddrV4> = self
+            .dropped
+            .iter()
+            .filter_map(|addr| match addr {
+                SocketAddr::V4(addr) => Some(*addr),
+                SocketAddr::V6(_) => None,
+            })
+            .collect();
+        let dropped6: Vec<SocketAddrV6> = self
+            .dropped
+            .iter()
+            .filter_map(|addr| match addr {
+                SocketAddr::V6(addr) => Some(*addr),
+                SocketAddr::V4(_) => None,
+            })
+            .collect();
+
+        Encoder::to_vec(|enc| {
+            enc.begin_dict()?;
+            enc.key(b"added")?.bytes(&encode_compact_peers_v4(&added))?;
+            enc.key(b"added.f")?.bytes(&added_f)?;
+            enc.key(b"added6")?
+                .bytes(&encode_compact_peers_v6(&added6))?;
+            enc.key(b"added6.f")?.bytes(&added6_f)?;
+            enc.key(b"dropped")?
+                .bytes(&encode_compact_peers_v4(&dropped))?;
+            enc.key(b"dropped6")?
+                .bytes(&encode_compact_peers_v6(&dropped6))?;
+            enc.end()?;
+            Ok(())
+        })
+    }
+}
+
+fn with_flags(addrs: Vec<SocketAddr>, flags: Option<&[u8]>) -> impl Iterator<Item = PexPeer> {
+    let flags = flags.filter(|flags| flags.len() == addrs.len());
+    let flags: Vec<u8> = match flags {
+        Some(flags) => flags.to_vec(),
+        None => vec![0; addrs.len()],
+    };
+    addrs
+        .into_iter()
+        .zip(flags)
+        .map(|(addr, flags)| PexPeer { addr, flags })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let input = b"d5:added12:\x7f\0\0\x01\x1a\xe1\x0a\0\0\x02\0\x507:added.f2:\x12\x016:added6\
+                      18:\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\x01\0\x018:added6.f0:\
+                      7:dropped6:\x0a\0\0\x03\0\x018:dropped60:e";
+        let message = PexMessage::from_bytes(input).unwrap();
+        assert_eq!(
+            message.added,
+            [
+                PexPeer {
+                    addr: "127.0.0.1:6881".parse().unwrap(),
+                    flags: PexPeer::SEED | PexPeer::REACHABLE,
+                },
+                PexPeer {
+                    addr: "10.0.0.2:80".parse().unwrap(),
+                    flags: PexPeer::ENCRYPTION,
+                },
+                // `added6.f` is empty, so flags default to 0.
+                PexPeer {
+                    addr: "[::1]:1".parse().unwrap(),
+                    flags: 0,
+                },
+            ]
+        );
+        assert!(message.added[0].is_seed());
+        assert!(!message.added[1].has(PexPeer::UTP));
+        assert_eq!(message.dropped, ["10.0.0.3:1".parse().unwrap()]);
+
+        assert_eq!(PexMessage::from_bytes(&message.to_bytes().unwrap()), Ok(message));
+
+        let single = PexMessage {
+            added: vec![PexPeer {
+                addr: "1.2.3.4:5".parse().unwrap(),
+                flags: PexPeer::UTP,
+            }],
+            dropped: Vec::new(),
+        };
+        assert_eq!(
+            single.to_bytes().unwrap(),
+            b"d5:added6:\x01\x02\x03\x04\0\x057:added.f1:\x046:added60:8:added6.f0:\
+              7:dropped0:8:dropped60:e"
+        );
+    }
+
+    #[test]
+    fn test_missing_keys_and_errors() {
+        assert_eq!(PexMessage::from_bytes(b"de"), Ok(PexMessage::default()));
+        assert_eq!(
+            PexMessage::from_bytes(b"d5:added5:abcdee"),
+            Err(PexError::Invalid("added"))
+        );
+        assert_eq!(
+            PexMessage::from_bytes(b"d8:dropped6i1ee"),
+            Err(PexError::Invalid("dropped6"))
+        );
+        assert_eq!(PexMessage::from_bytes(b"le"), Err(PexError::Invalid("")));
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use crate::{BValue, DecodeError};
+
+// Free lists of container buffers so servers decoding many short-lived
+// messages can reuse allocations instead of hitting the allocator for every
+// node. Buffers are handed back via `BValue::recycle`.
+
+/// Upper bound on buffers kept per kind, so one huge message does not pin
+/// memory for the lifetime of the pool.
+const DEFAULT_CAPACITY: usize = 1024;
+
+#[derive(Debug)]
+pub struct Pool {
+    strings: Vec<String>,
+    lists: Vec<Vec<BValue>>,
+    dicts: Vec<HashMap<String, BValue>>,
+    capacity: usize,
+}
+
+impl Default for Pool {
+    fn default() -> Self {
+        Pool::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+impl Pool {
+    pub fn new() -> Self {
+        Pool::default()
+    }
+
+    /// Pool that retains at most `capacity` buffers of each kind.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Pool {
+            strings: Vec::new(),
+            lists: Vec::new(),
+            dicts: Vec::new(),
+            capacity,
+        }
+    }
+
+    /// Number of pooled buffers as `(strings, lists, dicts)`.
+    pub fn len(&self) -> (usize, usize, usize) {
+        (self.strings.len(), self.lists.len(), self.dicts.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty() && self.lists.is_empty() && self.dicts.is_empty()
+    }
+
+    /// Decodes like `crate::decode`, drawing buffers from the pool.
+    pub fn decode<'a>(&mut self, input: &'a [u8]) -> Result<(BValue, &'a [u8]), DecodeError> {
+        crate::decode_with(input, self)
+    }
+
+    pub(crate) fn string(&mut self) -> String {
+        self.strings.pop().unwrap_or_default()
+    }
+
+    pub(crate) fn list(&mut self) -> Vec<BValue> {
+        self.lists.pop().unwrap_or_default()
+    }
+
+    pub(crate) fn dict(&mut self) -> HashMap<String, BValue> {
+        self.dicts.pop().unwrap_or_default()
+    }
+
+    fn put_string(&mut self, mut s: String) {
+        if self.strings.len() < self.capacity {
+            s.clear();
+            self.strings.push(s);
+        }
+    }
+
+    fn put_list(&mut self, mut list: Vec<BValue>) {
+        for v in list.drain(..) {
+            v.recycle(self);
+        }
+        if self.lists.len() < self.capacity {
+            self.lists.push(list);
+        }
+    }
+
+    fn put_dict(&mut self, mut dict: HashMap<String, BValue>) {
+        for (k, v) in dict.drain() {
+            self.put_string(k);
+            v.recycle(self);
+        }
+        if self.dicts.len() < self.capacity {
+            self.dicts.push(dict);
+        }
+    }
+}
+
+impl BValue {
+    /// Returns this value's buffers, and those of all its children, to `pool`.
+    pub fn recycle(self, pool: &mut Pool) {
+        match self {
+            BValue::Str(s) => pool.put_string(s),
+            BValue::List(list) => pool.put_list(list),
+            BValue::Dict(dict) => pool.put_dict(dict),
+            BValue::Int(_) | BValue::None | BValue::External(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recycled_buffers_are_reused() {
+        let mut pool = Pool::new();
+        let input = b"d3:fool4:spami42ee3:bar3:baze";
+
+        let (first, _) = pool.decode(input).unwrap();
+        assert!(pool.is_empty());
+        first.recycle(&mut pool);
+        // Keys "foo"/"bar", strings "spam"/"baz", one list, one dict.
+        assert_eq!(pool.len(), (4, 1, 1));
+
+        let (second, _) = pool.decode(input).unwrap();
+        assert!(pool.is_empty());
+        assert_eq!(second, crate::decode(input).unwrap().0);
+    }
+
+    #[test]
+    fn test_capacity_bounds_retained_buffers() {
+        let mut pool = Pool::with_capacity(1);
+        let (value, _) = pool.decode(b"l1:a1:b1:ce").unwrap();
+        value.recycle(&mut pool);
+        assert_eq!(pool.len(), (1, 1, 0));
+    }
+}
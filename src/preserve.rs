@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::encode::{write_bytes, write_value};
+use crate::{
+    BValue, DELIM_END, DICT_DELIM_BEGIN, DecodeError, Event, Events, External, LIST_DELIM_BEGIN,
+    Span,
+};
+
+// Fidelity mode: a decoded value that remembers the exact bytes it came
+// from. `encode` copies the original encoding of every node that still
+// equals what was decoded, so an untouched document comes back byte for
+// byte, unsorted keys and all, and an edit only normalizes the containers
+// on the way to the changed node.
+//
+// Nodes a `BValue` cannot hold (binary strings such as `pieces`, integers
+// outside `i16`, dicts with non-UTF-8 keys such as `piece layers`) are
+// kept as `BValue::External` wrapping their `Verbatim` encoding, and are
+// written back as-is wherever they end up.
+
+#[derive(Debug, Clone)]
+pub struct Preserved {
+    raw: Vec<u8>,
+    span: Span,
+    original: BValue,
+    value: BValue,
+}
+
+/// The original encoding of a node with no `BValue` form, found inside
+/// `BValue::External` in a `Preserved` tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verbatim(pub Vec<u8>);
+
+/// Decodes exactly one value from `input`, keeping a copy of its encoding.
+pub fn decode_preserving(input: &[u8]) -> Result<Preserved, DecodeError> {
+    if input.is_empty() {
+        return Err(DecodeError::EmptyInput);
+    }
+    let mut events = Events::new(input);
+    let (value, span) = read(&mut events, input)?;
+    if span.range.end != input.len() {
+        return Err(DecodeError::TrailingData {
+            offset: span.range.end,
+        });
+    }
+    Ok(Preserved {
+        raw: input.to_vec(),
+        span,
+        original: value.clone(),
+        value,
+    })
+}
+
+fn read(events: &mut Events<'_>, input: &[u8]) -> Result<(BValue, Span), DecodeError> {
+    let start = events.offset();
+    let mut span = Span::default();
+    let value = match events.expect_next()? {
+        Event::Int(n) => i16::try_from(n).ok().map(BValue::Int),
+        Event::Str(bytes) => std::str::from_utf8(bytes)
+            .ok()
+            .map(|s| BValue::Str(s.to_string())),
+        Event::ListStart => {
+            let mut list = Vec::new();
+            while input.get(events.offset()) != Some(&DELIM_END) {
+                let (item, item_span) = read(events, input)?;
+                list.push(item);
+                span.items.push(item_span);
+            }
+            events.expect_next()?;
+            Some(BValue::List(list))
+        }
+        Event::DictStart => {
+            let mut dict = HashMap::new();
+            let mut utf8 = true;
+            while let Event::Key(key) = events.expect_next()? {
+                let (item, item_span) = read(events, input)?;
+                match std::str::from_utf8(key) {
+                    Ok(key) => {
+                        dict.insert(key.to_string(), item);
+                        span.entries.insert(key.as_bytes().to_vec(), item_span);
+                    }
+                    Err(_) => utf8 = false,
+                }
+            }
+            utf8.then_some(BValue::Dict(dict))
+        }
+        // Events never yields these where a value starts.
+        Event::Key(_) | Event::End => {
+            return Err(DecodeError::UnexpectedByte {
+                offset: start,
+                byte: input[start],
+            });
+        }
+    };
+    span.range = start..events.offset();
+    let value = value.unwrap_or_else(|| {
+        span.items.clear();
+        span.entries.clear();
+        BValue::External(External::new(Verbatim(span.bytes(input).to_vec())))
+    });
+    Ok((value, span))
+}
+
+fn verbatim(value: &BValue) -> Option<&Verbatim> {
+    match value {
+        BValue::External(external) => external.downcast_ref(),
+        _ => None,
+    }
+}
+
+// `==`, except that externals never compare equal, so `Verbatim` nodes
+// are compared by their bytes instead.
+fn same(a: &BValue, b: &BValue) -> bool {
+    match (a, b) {
+        (BValue::List(a), BValue::List(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same(a, b))
+        }
+        (BValue::Dict(a), BValue::Dict(b)) => {
+            a.len() == b.len() && a.iter().all(|(k, v)| b.get(k).is_some_and(|w| same(v, w)))
+        }
+        (BValue::External(_), BValue::External(_)) => {
+            verbatim(a).is_some() && verbatim(a) == verbatim(b)
+        }
+        _ => a == b,
+    }
+}
+
+impl Preserved {
+    pub fn value(&self) -> &BValue {
+        &self.value
+    }
+
+    /// Mutable access; nodes changed through it are re-encoded canonically.
+    pub fn value_mut(&mut self) -> &mut BValue {
+        &mut self.value
+    }
+
+    pub fn into_value(self) -> BValue {
+        self.value
+    }
+
+    /// The input as it was decoded.
+    pub fn raw(&self) -> &[u8] {
+        &self.raw
+    }
+
+    pub fn is_modified(&self) -> bool {
+        !same(&self.value, &self.original)
+    }
+
+    /// The original bytes if nothing changed, otherwise a re-encoding that
+    /// reuses the original bytes of every unchanged node.
+    pub fn encode(&self) -> io::Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(self.raw.len());
+        self.write(&self.value, Some((&self.original, &self.span)), &mut out)?;
+        Ok(out)
+    }
+
+    fn write(
+        &self,
+        value: &BValue,
+        was: Option<(&BValue, &Span)>,
+        out: &mut Vec<u8>,
+    ) -> io::Result<()> {
+        if let Some((original, span)) = was
+            && same(value, original)
+        {
+            out.write_all(span.bytes(&self.raw))?;
+            return Ok(());
+        }
+
+        match value {
+            BValue::List(list) => {
+                let old = match was {
+                    Some((BValue::List(old), span)) => Some((old, span)),
+                    _ => None,
+                };
+                out.push(LIST_DELIM_BEGIN);
+                for (i, item) in list.iter().enumerate() {
+                    let was = old.and_then(|(old, span)| old.get(i).zip(span.index(i)));
+                    self.write(item, was, out)?;
+                }
+                out.push(DELIM_END);
+            }
+            BValue::Dict(_) => {
+                let old = match was {
+                    Some((BValue::Dict(old), span)) => Some((old, span)),
+                    _ => None,
+                };
+                out.push(DICT_DELIM_BEGIN);
+                for (key, item) in value.dict_iter_sorted() {
+                    write_bytes(out, key.as_bytes())?;
+                    let was = old.and_then(|(old, span)| old.get(key).zip(span.get(key)));
+                    self.write(item, was, out)?;
+                }
+                out.push(DELIM_END);
+            }
+            _ => match verbatim(value) {
+                Some(Verbatim(bytes)) => out.write_all(bytes)?,
+                None => write_value(value, out)?,
+            },
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_untouched_input_round_trips_exactly() {
+        // Unsorted keys would be normalized by a plain decode/encode.
+        let input = b"d4:infod4:name1:n6:lengthi1ee8:announce3:urle";
+        let preserved = decode_preserving(input).unwrap();
+        assert!(!preserved.is_modified());
+        assert_eq!(preserved.encode().unwrap(), input);
+        assert_ne!(crate::encode(preserved.value()).unwrap(), input);
+    }
+
+    #[test]
+    fn test_edit_keeps_unchanged_subtrees() {
+        let input = b"d8:announce3:url4:infod4:name1:n6:lengthi1eee";
+        let mut preserved = decode_preserving(input).unwrap();
+        preserved
+            .value_mut()
+            .set_path("announce", BValue::Str("new".to_string()))
+            .unwrap();
+        assert!(preserved.is_modified());
+        // The info dict keeps its original, unsorted encoding.
+        assert_eq!(
+            preserved.encode().unwrap(),
+            b"d8:announce3:new4:infod4:name1:n6:lengthi1eee"
+        );
+
+        preserved
+            .value_mut()
+            .set_path("info.private", BValue::Int(1))
+            .unwrap();
+        preserved
+            .value_mut()
+            .set_path("list", BValue::List(vec![]))
+            .unwrap();
+        assert_eq!(
+            preserved.encode().unwrap(),
+            b"d8:announce3:new4:infod6:lengthi1e4:name1:n7:privatei1ee4:listlee"
+        );
+    }
+
+    #[test]
+    fn test_real_torrent_values_survive_edits() {
+        let input: &[u8] = b"d8:announce3:url4:infod6:lengthi5000000000e4:name1:n6:pieces2:\xff\x00e12:piece layersd2:\xaa\xbb1:xee";
+        let mut preserved = decode_preserving(input).unwrap();
+        assert!(!preserved.is_modified());
+        assert_eq!(preserved.encode().unwrap(), input);
+
+        let pieces = preserved.value().get_path("info.pieces").unwrap();
+        let BValue::External(pieces) = pieces else {
+            panic!()
+        };
+        assert_eq!(
+            pieces.downcast_ref(),
+            Some(&Verbatim(b"2:\xff\x00".to_vec()))
+        );
+        assert_eq!(
+            preserved
+                .span
+                .get("info")
+                .unwrap()
+                .get("length")
+                .unwrap()
+                .range,
+            31..43
+        );
+
+        preserved
+            .value_mut()
+            .set_path("announce", BValue::Str("new".to_string()))
+            .unwrap();
+        assert!(preserved.is_modified());
+        assert_eq!(
+            preserved.encode().unwrap(),
+            b"d8:announce3:new4:infod6:lengthi5000000000e4:name1:n6:pieces2:\xff\x00e12:piece layersd2:\xaa\xbb1:xee"
+        );
+
+        // A moved verbatim node keeps its bytes too.
+        let layers = preserved.value_mut().remove_path("piece layers").unwrap();
+        preserved
+            .value_mut()
+            .set_path("info.layers", layers)
+            .unwrap();
+        assert_eq!(
+            preserved.encode().unwrap(),
+            b"d8:announce3:new4:infod6:layersd2:\xaa\xbb1:xe6:lengthi5000000000e4:name1:n6:pieces2:\xff\x00ee"
+        );
+    }
+
+    #[test]
+    fn test_rejects_trailing_data() {
+        assert_eq!(
+            decode_preserving(b"i1ei2e").unwrap_err(),
+            DecodeError::TrailingData { offset: 3 }
+        );
+        assert_eq!(decode_preserving(b"").unwrap_err(), DecodeError::EmptyInput);
+        assert_eq!(
+            decode_preserving(b"li1e").unwrap_err(),
+            DecodeError::UnexpectedEof { offset: 4 }
+        );
+    }
+}
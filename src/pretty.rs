@@ -0,0 +1,203 @@
+use std::fmt::Write;
+
+use crate::{BValue, BVisitor, DecodeError, Event, Events, External};
+
+// Indented, human-readable dumps:
+//
+//     {
+//       "announce": "http://tracker/announce"
+//       "info": {
+//         "length": 42
+//         "pieces": <20 bytes: 6a f3 01 9c 44 be 02 7d ...>
+//       }
+//     }
+//
+// Strings that are not printable text are shown as a hex preview, so piece
+// hashes and compact peer lists don't flood the terminal. `pretty_print`
+// works on raw input and so also handles strings that are not UTF-8.
+
+/// Bytes of a binary string shown before the preview is cut off.
+const HEX_PREVIEW: usize = 8;
+
+impl BValue {
+    pub fn to_pretty_string(&self) -> String {
+        let mut printer = Printer::default();
+        self.accept(&mut printer);
+        printer.out
+    }
+}
+
+/// Renders every value in `input` like `BValue::to_pretty_string`, one
+/// after another.
+pub fn pretty_print(input: &[u8]) -> Result<String, DecodeError> {
+    let mut printer = Printer::default();
+    for event in Events::new(input) {
+        match event? {
+            Event::DictStart => printer.open('{'),
+            Event::ListStart => printer.open('['),
+            Event::End => printer.close(),
+            Event::Key(key) => printer.key(key),
+            Event::Int(n) => printer.scalar(&n.to_string()),
+            Event::Str(bytes) => printer.scalar(&render_bytes(bytes)),
+        }
+    }
+    Ok(printer.out)
+}
+
+#[derive(Default)]
+struct Printer {
+    out: String,
+    /// Closing bracket of each open container, and whether it has children.
+    open: Vec<(char, bool)>,
+    after_key: bool,
+}
+
+impl Printer {
+    /// Starts a new line for the next dict entry or list item. A dict value
+    /// stays on the line of its key.
+    fn begin_item(&mut self) {
+        if self.after_key {
+            self.after_key = false;
+            return;
+        }
+        match self.open.last_mut() {
+            Some((_, has_children)) => *has_children = true,
+            None if self.out.is_empty() => return,
+            None => {}
+        }
+        self.newline(self.open.len());
+    }
+
+    fn newline(&mut self, depth: usize) {
+        self.out.push('\n');
+        for _ in 0..depth {
+            self.out.push_str("  ");
+        }
+    }
+
+    fn open(&mut self, bracket: char) {
+        self.begin_item();
+        self.out.push(bracket);
+        self.open.push((if bracket == '{' { '}' } else { ']' }, false));
+    }
+
+    fn close(&mut self) {
+        if let Some((bracket, has_children)) = self.open.pop() {
+            if has_children {
+                self.newline(self.open.len());
+            }
+            self.out.push(bracket);
+        }
+    }
+
+    fn key(&mut self, key: &[u8]) {
+        self.begin_item();
+        self.out.push_str(&render_bytes(key));
+        self.out.push_str(": ");
+        self.after_key = true;
+    }
+
+    fn scalar(&mut self, text: &str) {
+        self.begin_item();
+        self.out.push_str(text);
+    }
+}
+
+impl BVisitor for Printer {
+    fn visit_int(&mut self, n: i16) {
+        self.scalar(&n.to_string());
+    }
+
+    fn visit_str(&mut self, s: &str) {
+        self.scalar(&render_bytes(s.as_bytes()));
+    }
+
+    fn visit_none(&mut self) {
+        self.scalar("<none>");
+    }
+
+    fn visit_external(&mut self, _external: &External) {
+        self.scalar("<external>");
+    }
+
+    fn visit_list_start(&mut self, _len: usize) {
+        self.open('[');
+    }
+
+    fn visit_list_end(&mut self) {
+        self.close();
+    }
+
+    fn visit_dict_start(&mut self, _len: usize) {
+        self.open('{');
+    }
+
+    fn visit_dict_key(&mut self, key: &str) {
+        self.key(key.as_bytes());
+    }
+
+    fn visit_dict_end(&mut self) {
+        self.close();
+    }
+}
+
+/// Quoted text, or a hex preview if `bytes` is not printable UTF-8.
+fn render_bytes(bytes: &[u8]) -> String {
+    if let Ok(text) = std::str::from_utf8(bytes)
+        && !text.chars().any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t'))
+    {
+        return format!("{:?}", text);
+    }
+
+    let mut out = format!("<{} bytes:", bytes.len());
+    for b in bytes.iter().take(HEX_PREVIEW) {
+        let _ = write!(out, " {:02x}", b);
+    }
+    if bytes.len() > HEX_PREVIEW {
+        out.push_str(" ...");
+    }
+    out.push('>');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode_exact;
+
+    #[test]
+    fn test_pretty_value() {
+        let value = decode_exact(b"d8:announce3:url4:infod6:lengthi42e4:pathl1:a1:bee4:tagsle1:zdee")
+            .unwrap();
+        assert_eq!(
+            value.to_pretty_string(),
+            "{\n  \"announce\": \"url\"\n  \"info\": {\n    \"length\": 42\n    \"path\": [\n      \
+             \"a\"\n      \"b\"\n    ]\n  }\n  \"tags\": []\n  \"z\": {}\n}"
+        );
+        assert_eq!(BValue::Int(-3).to_pretty_string(), "-3");
+    }
+
+    #[test]
+    fn test_binary_strings_as_hex() {
+        let mut input = b"d6:pieces20:".to_vec();
+        input.extend((0..20).map(|i| i * 13));
+        input.extend(b"4:peer2:\x01\x02e");
+        assert_eq!(
+            pretty_print(&input).unwrap(),
+            "{\n  \"pieces\": <20 bytes: 00 0d 1a 27 34 41 4e 5b ...>\n  \"peer\": <2 bytes: 01 02>\n}"
+        );
+
+        let value = BValue::Str("tab\tok\u{0}".to_string());
+        assert_eq!(value.to_pretty_string(), "<7 bytes: 74 61 62 09 6f 6b 00>");
+    }
+
+    #[test]
+    fn test_pretty_print_raw_input() {
+        assert_eq!(pretty_print(b"i1eli2ee").unwrap(), "1\n[\n  2\n]");
+        assert_eq!(pretty_print(b"").unwrap(), "");
+        assert_eq!(
+            pretty_print(b"l1:a"),
+            Err(DecodeError::UnexpectedEof { offset: 4 })
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use crate::events::{Events, Frame};
+use crate::{BValue, DecodeError};
+
+// Push-style parser for values that arrive in pieces, e.g. extension
+// messages split across TCP segments. Bytes are buffered until a complete
+// value is present; the scan resumes where it stopped instead of starting
+// over on every chunk.
+//
+// This is the piece a `tokio_util` `Decoder` would wrap; no such codec is
+// provided until a `tokio` feature exists.
+
+#[derive(Debug, Default)]
+pub struct PushParser {
+    buf: Vec<u8>,
+    /// Scan position in `buf` and containers open at that point.
+    pos: usize,
+    stack: Vec<Frame>,
+}
+
+impl PushParser {
+    pub fn new() -> Self {
+        PushParser::default()
+    }
+
+    /// Buffers `chunk` and returns the next value if one is now complete.
+    /// `Ok(None)` means more bytes are needed.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Option<BValue>, DecodeError> {
+        self.buf.extend_from_slice(chunk);
+        self.poll()
+    }
+
+    /// Returns the next complete value already buffered, without feeding
+    /// more bytes. A single chunk may carry several values.
+    ///
+    /// A malformed value is an error; the bytes scanned so far are
+    /// discarded so the parser can be reused on a fresh stream.
+    pub fn poll(&mut self) -> Result<Option<BValue>, DecodeError> {
+        let mut events = Events::resume(&self.buf, self.pos, std::mem::take(&mut self.stack));
+
+        loop {
+            match events.next() {
+                None | Some(Err(DecodeError::UnexpectedEof { .. })) => {
+                    (self.pos, self.stack) = events.into_state();
+                    return Ok(None);
+                }
+                Some(Err(e)) => {
+                    self.reset();
+                    return Err(e);
+                }
+                Some(Ok(_)) if events.depth() == 0 => {
+                    let end = events.offset();
+                    let value = crate::decode_exact(&self.buf[..end]);
+                    self.buf.drain(..end);
+                    self.pos = 0;
+                    return value.map(Some);
+                }
+                Some(Ok(_)) => {}
+            }
+        }
+    }
+
+    /// Number of bytes buffered but not yet returned as a value.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Drops all buffered bytes and scan state.
+    pub fn reset(&mut self) {
+        self.buf.clear();
+        self.pos = 0;
+        self.stack.clear();
+    }
+}
+
+/// Decoded values from a sequence of byte chunks, with messages allowed to
+/// straddle chunk boundaries. Bytes left over when the chunks run out are
+/// reported as `UnexpectedEof`; after any error the iterator is finished.
+pub fn decode_chunks<I>(chunks: I) -> Chunks<I::IntoIter>
+where
+    I: IntoIterator,
+    I::Item: AsRef<[u8]>,
+{
+    Chunks {
+        chunks: chunks.into_iter(),
+        parser: PushParser::new(),
+        done: false,
+    }
+}
+
+pub struct Chunks<I> {
+    chunks: I,
+    parser: PushParser,
+    done: bool,
+}
+
+impl<I> Iterator for Chunks<I>
+where
+    I: Iterator,
+    I::Item: AsRef<[u8]>,
+{
+    type Item = Result<BValue, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let mut result = self.parser.poll();
+        while let Ok(None) = result {
+            match self.chunks.next() {
+                Some(chunk) => result = self.parser.feed(chunk.as_ref()),
+                None => {
+                    self.done = true;
+                    let buffered = self.parser.buffered();
+                    if buffered == 0 {
+                        return None;
+                    }
+                    return Some(Err(DecodeError::UnexpectedEof { offset: buffered }));
+                }
+            }
+        }
+
+        if result.is_err() {
+            self.done = true;
+        }
+        result.transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_value_split_across_chunks() {
+        let mut parser = PushParser::new();
+        assert_eq!(parser.feed(b"d1:m"), Ok(None));
+        assert_eq!(parser.feed(b"d6:ut_pe"), Ok(None));
+        assert_eq!(parser.feed(b"xi1e"), Ok(None));
+        assert_eq!(parser.feed(b"e1:pi6"), Ok(None));
+
+        let mut m = HashMap::new();
+        m.insert("ut_pex".to_string(), BValue::Int(1));
+        let mut expected = HashMap::new();
+        expected.insert("m".to_string(), BValue::Dict(m));
+        expected.insert("p".to_string(), BValue::Int(6881));
+        assert_eq!(parser.feed(b"881ee"), Ok(Some(BValue::Dict(expected))));
+        assert_eq!(parser.buffered(), 0);
+    }
+
+    #[test]
+    fn test_several_values_in_one_chunk() {
+        let mut parser = PushParser::new();
+        assert_eq!(parser.feed(b"i1e4:spami"), Ok(Some(BValue::Int(1))));
+        assert_eq!(parser.poll(), Ok(Some(BValue::Str("spam".to_string()))));
+        assert_eq!(parser.poll(), Ok(None));
+        assert_eq!(parser.buffered(), 1);
+        assert_eq!(parser.feed(b"2e"), Ok(Some(BValue::Int(2))));
+    }
+
+    #[test]
+    fn test_decode_chunks() {
+        let chunks: Vec<&[u8]> = vec![b"i1e4:sp", b"am", b"", b"li2", b"ee"];
+        let values: Vec<_> = decode_chunks(chunks).collect();
+        assert_eq!(
+            values,
+            vec![
+                Ok(BValue::Int(1)),
+                Ok(BValue::Str("spam".to_string())),
+                Ok(BValue::List(vec![BValue::Int(2)])),
+            ]
+        );
+
+        let values: Vec<_> = decode_chunks([&b"i1ei2"[..]]).collect();
+        assert_eq!(
+            values,
+            vec![Ok(BValue::Int(1)), Err(DecodeError::UnexpectedEof { offset: 2 })]
+        );
+    }
+
+    #[test]
+    fn test_malformed_input_resets() {
+        let mut parser = PushParser::new();
+        assert_eq!(parser.feed(b"l1:a"), Ok(None));
+        assert_eq!(
+            parser.feed(b"x"),
+            Err(DecodeError::UnexpectedByte { offset: 4, byte: b'x' })
+        );
+        assert_eq!(parser.buffered(), 0);
+        assert_eq!(parser.feed(b"le"), Ok(Some(BValue::List(vec![]))));
+    }
+}
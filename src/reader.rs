@@ -0,0 +1,113 @@
+use std::io::{self, Read};
+
+use crate::{BValue, COLON_DELIM, DELIM_END, DICT_DELIM_BEGIN, DecodeError, INT_DELIM_BEGIN, LIST_DELIM_BEGIN};
+
+// Decoding straight from a `Read` source. Exactly the bytes of one value
+// are read, so whatever follows it in the stream is left for the next
+// reader. Wrap unbuffered sources in a `BufReader`: delimiters are read a
+// byte at a time, string bodies in one go.
+//
+// There is no `AsyncRead` counterpart yet; it waits on a `tokio` feature.
+// Async callers can feed received chunks to a `PushParser`, which does not
+// block on I/O.
+
+/// Reads and decodes one value from `r`, returning it together with the
+/// number of bytes consumed. Malformed input is an `InvalidData` error
+/// wrapping a `DecodeError`.
+pub fn decode_from_reader<R: Read>(mut r: R) -> io::Result<(BValue, usize)> {
+    let mut buf = Vec::new();
+    let mut depth = 0usize;
+
+    loop {
+        let byte = read_byte(&mut r, &mut buf)?;
+        match byte {
+            INT_DELIM_BEGIN => while read_byte(&mut r, &mut buf)? != DELIM_END {},
+            LIST_DELIM_BEGIN | DICT_DELIM_BEGIN => depth += 1,
+            DELIM_END if depth > 0 => depth -= 1,
+            b'0'..=b'9' => {
+                let start = buf.len() - 1;
+                while read_byte(&mut r, &mut buf)? != COLON_DELIM {}
+                let len = crate::parse_len(&buf[start..buf.len() - 1], start).map_err(invalid)?;
+
+                // Grows as data arrives rather than trusting the length.
+                let n = r.by_ref().take(len as u64).read_to_end(&mut buf)?;
+                if n < len {
+                    return Err(invalid(DecodeError::UnexpectedEof { offset: buf.len() }));
+                }
+            }
+            // Stray end markers and unknown bytes; let the decoder report them.
+            _ => break,
+        }
+        if depth == 0 {
+            break;
+        }
+    }
+
+    let value = crate::decode_exact(&buf).map_err(invalid)?;
+    Ok((value, buf.len()))
+}
+
+fn read_byte<R: Read>(r: &mut R, buf: &mut Vec<u8>) -> io::Result<u8> {
+    let mut byte = [0u8];
+    loop {
+        match r.read(&mut byte) {
+            Ok(0) if buf.is_empty() => return Err(invalid(DecodeError::EmptyInput)),
+            Ok(0) => return Err(invalid(DecodeError::UnexpectedEof { offset: buf.len() })),
+            Ok(_) => {
+                buf.push(byte[0]);
+                return Ok(byte[0]);
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn invalid(e: DecodeError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_error(e: io::Error) -> DecodeError {
+        e.into_inner().unwrap().downcast::<DecodeError>().map(|e| *e).unwrap()
+    }
+
+    #[test]
+    fn test_reads_only_one_value() {
+        let mut stream: &[u8] = b"d4:spaml1:ai-7eee4:nexti2e";
+        let (value, consumed) = decode_from_reader(&mut stream).unwrap();
+        assert_eq!(consumed, 17);
+        assert_eq!(value, crate::decode(b"d4:spaml1:ai-7eee").unwrap().0);
+        assert_eq!(stream, b"4:nexti2e");
+
+        assert_eq!(
+            decode_from_reader(&mut stream).unwrap(),
+            (BValue::Str("next".to_string()), 6)
+        );
+        assert_eq!(decode_from_reader(&mut stream).unwrap(), (BValue::Int(2), 3));
+    }
+
+    #[test]
+    fn test_errors() {
+        let empty: &[u8] = b"";
+        assert_eq!(
+            decode_error(decode_from_reader(empty).unwrap_err()),
+            DecodeError::EmptyInput
+        );
+
+        let truncated: &[u8] = b"l10:abc";
+        assert_eq!(
+            decode_error(decode_from_reader(truncated).unwrap_err()),
+            DecodeError::UnexpectedEof { offset: 7 }
+        );
+
+        let stray: &[u8] = b"ei1e";
+        assert_eq!(
+            decode_error(decode_from_reader(stray).unwrap_err()),
+            DecodeError::UnexpectedEnd { offset: 0 }
+        );
+    }
+}
@@ -0,0 +1,411 @@
+use std::fmt;
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{DecodeError, Event, Events, compact_peers_v4, compact_peers_v6};
+
+// libtorrent's resume data (`.fastresume`): the per-torrent state a client
+// saves between runs. Most keys are optional and clients add their own, so
+// unknown keys are skipped and absent ones take their zero value. `pieces`
+// and `piece_priority` hold one byte per piece rather than a packed
+// bitfield, and counters routinely exceed `i16`, so this reads `Events`
+// directly instead of decoding to a `BValue`.
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResumeData {
+    pub info_hash: Option<[u8; 20]>,
+    /// `info-hash2`, the v2 info hash of hybrid and v2 torrents.
+    pub info_hash2: Option<[u8; 32]>,
+    pub name: Option<String>,
+    pub save_path: Option<String>,
+    /// Whether each piece is downloaded, from bit 0 of its `pieces` byte.
+    pub pieces: Vec<bool>,
+    /// Per-piece priority, 0 (skip) to 7; empty if never set.
+    pub piece_priority: Vec<u8>,
+    /// Per-file priority, 0 (skip) to 7; empty if never set.
+    pub file_priority: Vec<u8>,
+    /// Paths of files renamed since the torrent was added, by file index.
+    pub mapped_files: Vec<String>,
+    /// Known peers from `peers` and `peers6`.
+    pub peers: Vec<SocketAddr>,
+    pub banned_peers: Vec<SocketAddr>,
+    /// Tracker tiers, in order.
+    pub trackers: Vec<Vec<String>>,
+    /// `url-list` web seeds (BEP 19).
+    pub url_seeds: Vec<String>,
+    /// `httpseeds` (BEP 17).
+    pub http_seeds: Vec<String>,
+    pub total_uploaded: u64,
+    pub total_downloaded: u64,
+    /// Seconds the torrent has been running, seeding and finished.
+    pub active_time: u64,
+    pub seeding_time: u64,
+    pub finished_time: u64,
+    pub added_time: Option<SystemTime>,
+    /// `None` until the download completes; libtorrent writes 0.
+    pub completed_time: Option<SystemTime>,
+    /// Bytes per second; `None` for unlimited, which libtorrent writes as
+    /// 0 or -1.
+    pub upload_rate_limit: Option<u64>,
+    pub download_rate_limit: Option<u64>,
+    pub paused: bool,
+    pub auto_managed: bool,
+    pub seed_mode: bool,
+    pub super_seeding: bool,
+    pub sequential_download: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResumeError {
+    Decode(DecodeError),
+    /// Neither `info-hash` nor `info-hash2` is present.
+    Missing(&'static str),
+    /// The named key has the wrong type or value. `""` is the resume data
+    /// itself.
+    Invalid(&'static str),
+}
+
+impl fmt::Display for ResumeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResumeError::Decode(e) => e.fmt(f),
+            ResumeError::Missing(key) => write!(f, "missing resume key {:?}", key),
+            ResumeError::Invalid(key) => write!(f, "invalid resume key {:?}", key),
+        }
+    }
+}
+
+impl std::error::Error for ResumeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ResumeError::Decode(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<DecodeError> for ResumeError {
+    fn from(e: DecodeError) -> Self {
+        ResumeError::Decode(e)
+    }
+}
+
+impl ResumeData {
+    pub fn from_bytes(input: &[u8]) -> Result<ResumeData, ResumeError> {
+        if input.is_empty() {
+            return Err(DecodeError::EmptyInput.into());
+        }
+        let mut events = Events::new(input);
+        if events.expect_next()? != Event::DictStart {
+            return Err(ResumeError::Invalid(""));
+        }
+        let events = &mut events;
+        let mut data = ResumeData::default();
+        loop {
+            match events.expect_next()? {
+                Event::End => break,
+                Event::Key(b"file-format") => {
+                    if string(events, "file-format")? != b"libtorrent resume file" {
+                        return Err(ResumeError::Invalid("file-format"));
+                    }
+                }
+                Event::Key(b"info-hash") => {
+                    let hash = string(events, "info-hash")?;
+                    let hash = hash
+                        .try_into()
+                        .map_err(|_| ResumeError::Invalid("info-hash"))?;
+                    data.info_hash = Some(hash);
+                }
+                Event::Key(b"info-hash2") => {
+                    let hash = string(events, "info-hash2")?;
+                    let hash = hash
+                        .try_into()
+                        .map_err(|_| ResumeError::Invalid("info-hash2"))?;
+                    data.info_hash2 = Some(hash);
+                }
+                Event::Key(b"name") => data.name = Some(text(events, "name")?),
+                Event::Key(b"save_path") => data.save_path = Some(text(events, "save_path")?),
+                Event::Key(b"pieces") => {
+                    let pieces = string(events, "pieces")?;
+                    data.pieces = pieces.iter().map(|byte| byte & 1 == 1).collect();
+                }
+                Event::Key(b"piece_priority") => {
+                    let priorities = string(events, "piece_priority")?;
+                    if priorities.iter().any(|&p| p > 7) {
+                        return Err(ResumeError::Invalid("piece_priority"));
+                    }
+                    data.piece_priority = priorities.to_vec();
+                }
+                Event::Key(b"file_priority") => {
+                    data.file_priority = list(events, "file_priority", |event| match event {
+                        Event::Int(n @ 0..=7) => Some(n as u8),
+                        _ => None,
+                    })?;
+                }
+                Event::Key(b"mapped_files") => {
+                    data.mapped_files = list(events, "mapped_files", utf8)?;
+                }
+                Event::Key(b"peers") => data.peers.extend(peers(events, "peers", false)?),
+                Event::Key(b"peers6") => data.peers.extend(peers(events, "peers6", true)?),
+                Event::Key(b"banned_peers") => {
+                    data.banned_peers
+                        .extend(peers(events, "banned_peers", false)?);
+                }
+                Event::Key(b"banned_peers6") => {
+                    data.banned_peers
+                        .extend(peers(events, "banned_peers6", true)?);
+                }
+                Event::Key(b"trackers") => data.trackers = tiers(events)?,
+                Event::Key(b"url-list") => data.url_seeds = list(events, "url-list", utf8)?,
+                Event::Key(b"httpseeds") => data.http_seeds = list(events, "httpseeds", utf8)?,
+                Event::Key(b"total_uploaded") => {
+                    data.total_uploaded = count(events, "total_uploaded")?;
+                }
+                Event::Key(b"total_downloaded") => {
+                    data.total_downloaded = count(events, "total_downloaded")?;
+                }
+                Event::Key(b"active_time") => data.active_time = count(events, "active_time")?,
+                Event::Key(b"seeding_time") => data.seeding_time = count(events, "seeding_time")?,
+                Event::Key(b"finished_time") => {
+                    data.finished_time = count(events, "finished_time")?;
+                }
+                Event::Key(b"added_time") => data.added_time = time(events, "added_time")?,
+                Event::Key(b"completed_time") => {
+                    data.completed_time = time(events, "completed_time")?;
+                }
+                Event::Key(b"upload_rate_limit") => {
+                    data.upload_rate_limit = limit(events, "upload_rate_limit")?;
+                }
+                Event::Key(b"download_rate_limit") => {
+                    data.download_rate_limit = limit(events, "download_rate_limit")?;
+                }
+                Event::Key(b"paused") => data.paused = int(events, "paused")? != 0,
+                Event::Key(b"auto_managed") => {
+                    data.auto_managed = int(events, "auto_managed")? != 0
+                }
+                Event::Key(b"seed_mode") => data.seed_mode = int(events, "seed_mode")? != 0,
+                Event::Key(b"super_seeding") => {
+                    data.super_seeding = int(events, "super_seeding")? != 0;
+                }
+                Event::Key(b"sequential_download") => {
+                    data.sequential_download = int(events, "sequential_download")? != 0;
+                }
+                Event::Key(_) => events.skip_value()?,
+                _ => return Err(ResumeError::Invalid("")),
+            }
+        }
+        if events.offset() < input.len() {
+            return Err(DecodeError::TrailingData {
+                offset: events.offset(),
+            }
+            .into());
+        }
+        if data.info_hash.is_none() && data.info_hash2.is_none() {
+            return Err(ResumeError::Missing("info-hash"));
+        }
+        Ok(data)
+    }
+
+    /// Whether piece `index` is downloaded; false past the end of `pieces`.
+    pub fn has_piece(&self, index: usize) -> bool {
+        self.pieces.get(index).copied().unwrap_or(false)
+    }
+
+    pub fn pieces_downloaded(&self) -> usize {
+        self.pieces.iter().filter(|&&have| have).count()
+    }
+}
+
+fn string<'a>(events: &mut Events<'a>, key: &'static str) -> Result<&'a [u8], ResumeError> {
+    match events.expect_next()? {
+        Event::Str(bytes) => Ok(bytes),
+        _ => Err(ResumeError::Invalid(key)),
+    }
+}
+
+fn text(events: &mut Events<'_>, key: &'static str) -> Result<String, ResumeError> {
+    utf8(Event::Str(string(events, key)?)).ok_or(ResumeError::Invalid(key))
+}
+
+fn utf8(event: Event<'_>) -> Option<String> {
+    match event {
+        Event::Str(bytes) => String::from_utf8(bytes.to_vec()).ok(),
+        _ => None,
+    }
+}
+
+fn int(events: &mut Events<'_>, key: &'static str) -> Result<i64, ResumeError> {
+    match events.expect_next()? {
+        Event::Int(n) => Ok(n),
+        _ => Err(ResumeError::Invalid(key)),
+    }
+}
+
+fn count(events: &mut Events<'_>, key: &'static str) -> Result<u64, ResumeError> {
+    u64::try_from(int(events, key)?).map_err(|_| ResumeError::Invalid(key))
+}
+
+/// Unix seconds; 0 means unset. Times the platform cannot represent are
+/// invalid.
+fn time(events: &mut Events<'_>, key: &'static str) -> Result<Option<SystemTime>, ResumeError> {
+    match count(events, key)? {
+        0 => Ok(None),
+        secs => UNIX_EPOCH
+            .checked_add(Duration::from_secs(secs))
+            .map(Some)
+            .ok_or(ResumeError::Invalid(key)),
+    }
+}
+
+fn limit(events: &mut Events<'_>, key: &'static str) -> Result<Option<u64>, ResumeError> {
+    match int(events, key)? {
+        -1 | 0 => Ok(None),
+        n => u64::try_from(n)
+            .map(Some)
+            .map_err(|_| ResumeError::Invalid(key)),
+    }
+}
+
+/// A flat list whose items `item` accepts.
+fn list<T>(
+    events: &mut Events<'_>,
+    key: &'static str,
+    item: impl Fn(Event<'_>) -> Option<T>,
+) -> Result<Vec<T>, ResumeError> {
+    if events.expect_next()? != Event::ListStart {
+        return Err(ResumeError::Invalid(key));
+    }
+    let mut items = Vec::new();
+    loop {
+        match events.expect_next()? {
+            Event::End => return Ok(items),
+            event => items.push(item(event).ok_or(ResumeError::Invalid(key))?),
+        }
+    }
+}
+
+/// `trackers`: a list of tiers, each a list of URLs.
+fn tiers(events: &mut Events<'_>) -> Result<Vec<Vec<String>>, ResumeError> {
+    if events.expect_next()? != Event::ListStart {
+        return Err(ResumeError::Invalid("trackers"));
+    }
+    let mut tiers = Vec::new();
+    loop {
+        match events.expect_next()? {
+            Event::End => return Ok(tiers),
+            Event::ListStart => {}
+            _ => return Err(ResumeError::Invalid("trackers")),
+        }
+        let mut tier = Vec::new();
+        loop {
+            match events.expect_next()? {
+                Event::End => break,
+                event => tier.push(utf8(event).ok_or(ResumeError::Invalid("trackers"))?),
+            }
+        }
+        tiers.push(tier);
+    }
+}
+
+fn peers(
+    events: &mut Events<'_>,
+    key: &'static str,
+    v6: bool,
+) -> Result<Vec<SocketAddr>, ResumeError> {
+    let bytes = string(events, key)?;
+    let addrs = if v6 {
+        compact_peers_v6(bytes).map(|addrs| addrs.into_iter().map(SocketAddr::V6).collect())
+    } else {
+        compact_peers_v4(bytes).map(|addrs| addrs.into_iter().map(SocketAddr::V4).collect())
+    };
+    addrs.ok_or(ResumeError::Invalid(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_bytes() {
+        let input = b"d11:active_timei3600e10:added_timei1700000000e12:auto_managedi1e\
+                      14:completed_timei0e11:file-format22:libtorrent resume file\
+                      12:file-versioni1e13:file_priorityli4ei0ei7ee\
+                      9:info-hash20:aaaaaaaaaaaaaaaaaaaa12:mapped_filesl0:5:b.txte\
+                      4:name3:foo6:pausedi1e5:peers6:\x7f\0\0\x01\x1a\xe1\
+                      6:peers618:\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\x01\0\x01\
+                      6:pieces4:\x01\x00\x03\x0214:total_uploadedi5000000000e\
+                      8:trackersll9:udp://t/19:udp://t/2el9:udp://t/3ee\
+                      17:upload_rate_limiti-1e8:url-listl6:http:/ee";
+        let data = ResumeData::from_bytes(input).unwrap();
+        assert_eq!(data.info_hash, Some([b'a'; 20]));
+        assert_eq!(data.info_hash2, None);
+        assert_eq!(data.name.as_deref(), Some("foo"));
+        assert_eq!(data.pieces, [true, false, true, false]);
+        assert!(data.has_piece(2) && !data.has_piece(9));
+        assert_eq!(data.pieces_downloaded(), 2);
+        assert_eq!(data.file_priority, [4, 0, 7]);
+        assert_eq!(data.mapped_files, ["", "b.txt"]);
+        assert_eq!(
+            data.peers,
+            [
+                "127.0.0.1:6881".parse::<SocketAddr>().unwrap(),
+                "[::1]:1".parse().unwrap(),
+            ]
+        );
+        assert_eq!(
+            data.trackers,
+            [vec!["udp://t/1", "udp://t/2"], vec!["udp://t/3"]]
+        );
+        assert_eq!(data.url_seeds, ["http:/"]);
+        assert_eq!(data.total_uploaded, 5_000_000_000);
+        assert_eq!(data.active_time, 3600);
+        assert_eq!(
+            data.added_time,
+            Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+        assert_eq!(data.completed_time, None);
+        assert_eq!(data.upload_rate_limit, None);
+        assert!(data.paused && data.auto_managed && !data.seed_mode);
+    }
+
+    #[test]
+    fn test_errors() {
+        let hash = "9:info-hash20:aaaaaaaaaaaaaaaaaaaa";
+        let cases: [(String, ResumeError); 6] = [
+            ("de".to_string(), ResumeError::Missing("info-hash")),
+            (
+                "d9:info-hash3:abce".to_string(),
+                ResumeError::Invalid("info-hash"),
+            ),
+            (
+                format!("d11:file-format4:json{}e", hash),
+                ResumeError::Invalid("file-format"),
+            ),
+            (
+                format!("d13:file_priorityli8ee{}e", hash),
+                ResumeError::Invalid("file_priority"),
+            ),
+            (
+                format!("d{}5:peers5:abcdee", hash),
+                ResumeError::Invalid("peers"),
+            ),
+            (
+                format!("d{}14:total_uploadedi-1ee", hash),
+                ResumeError::Invalid("total_uploaded"),
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(ResumeData::from_bytes(input.as_bytes()), Err(expected));
+        }
+        // Whether this fits is up to the platform's `SystemTime`, but it
+        // must never panic.
+        let far = format!("d10:added_timei{}e{}e", i64::MAX, hash);
+        match ResumeData::from_bytes(far.as_bytes()) {
+            Ok(data) => assert!(data.added_time > Some(UNIX_EPOCH)),
+            Err(e) => assert_eq!(e, ResumeError::Invalid("added_time")),
+        }
+        // Keys from other clients are ignored.
+        let other = format!("d{}12:qBt-categoryd1:xli1eeee", hash);
+        assert!(ResumeData::from_bytes(other.as_bytes()).is_ok());
+    }
+}
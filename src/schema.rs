@@ -0,0 +1,246 @@
+use std::fmt;
+
+use crate::{BValue, Kind};
+
+// Declarative shape checks for decoded values, e.g. for linting torrents
+// from untrusted sources. Every violation is collected rather than
+// stopping at the first one.
+//
+//     let torrent = Schema::dict()
+//         .required("announce", Schema::Str)
+//         .required("info", Schema::dict()
+//             .required("piece length", Schema::Int)
+//             .required("pieces", Schema::Bytes));
+//     for violation in torrent.check(&value) { ... }
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Schema {
+    /// Anything at all.
+    Any,
+    Int,
+    /// A string value.
+    Str,
+    /// A byte string, or a hook's output for one (`BValue::External`).
+    Bytes,
+    /// A list whose items all match the inner schema.
+    List(Box<Schema>),
+    Dict(DictSchema),
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DictSchema {
+    entries: Vec<(String, Schema, bool)>,
+    deny_unknown: bool,
+}
+
+impl Schema {
+    pub fn list(items: Schema) -> Self {
+        Schema::List(Box::new(items))
+    }
+
+    /// An open dict; chain `required`/`optional` to describe its entries.
+    pub fn dict() -> Self {
+        Schema::Dict(DictSchema::default())
+    }
+
+    /// Adds a key that must be present. No-op on non-dict schemas.
+    pub fn required(self, key: &str, schema: Schema) -> Self {
+        self.entry(key, schema, true)
+    }
+
+    /// Adds a key that is checked only when present.
+    pub fn optional(self, key: &str, schema: Schema) -> Self {
+        self.entry(key, schema, false)
+    }
+
+    /// Reports keys that were not declared.
+    pub fn deny_unknown(mut self) -> Self {
+        if let Schema::Dict(dict) = &mut self {
+            dict.deny_unknown = true;
+        }
+        self
+    }
+
+    fn entry(mut self, key: &str, schema: Schema, required: bool) -> Self {
+        if let Schema::Dict(dict) = &mut self {
+            dict.entries.push((key.to_string(), schema, required));
+        }
+        self
+    }
+
+    /// All places where `value` does not match, in document order for
+    /// lists and declaration order for dict entries.
+    pub fn check(&self, value: &BValue) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        self.walk(value, &mut Vec::new(), &mut violations);
+        violations
+    }
+
+    fn expected(&self) -> Kind {
+        match self {
+            Schema::Any => Kind::None,
+            Schema::Int => Kind::Int,
+            Schema::Str | Schema::Bytes => Kind::Str,
+            Schema::List(_) => Kind::List,
+            Schema::Dict(_) => Kind::Dict,
+        }
+    }
+
+    fn walk(&self, value: &BValue, path: &mut Vec<String>, out: &mut Vec<Violation>) {
+        let matches = match (self, value) {
+            (Schema::Any, _) => true,
+            (Schema::Int, BValue::Int(_)) | (Schema::Str, BValue::Str(_)) => true,
+            (Schema::Bytes, BValue::Str(_) | BValue::External(_)) => true,
+            (Schema::List(items), BValue::List(list)) => {
+                for (i, item) in list.iter().enumerate() {
+                    path.push(i.to_string());
+                    items.walk(item, path, out);
+                    path.pop();
+                }
+                true
+            }
+            (Schema::Dict(schema), BValue::Dict(dict)) => {
+                for (key, schema, required) in &schema.entries {
+                    path.push(key.clone());
+                    match dict.get(key) {
+                        Some(child) => schema.walk(child, path, out),
+                        None if *required => out.push(Violation {
+                            path: path.clone(),
+                            problem: Problem::Missing,
+                        }),
+                        None => {}
+                    }
+                    path.pop();
+                }
+                if schema.deny_unknown {
+                    let mut unknown: Vec<&String> = dict
+                        .keys()
+                        .filter(|k| !schema.entries.iter().any(|(key, _, _)| key == *k))
+                        .collect();
+                    unknown.sort();
+                    for key in unknown {
+                        path.push(key.clone());
+                        out.push(Violation {
+                            path: path.clone(),
+                            problem: Problem::Unknown,
+                        });
+                        path.pop();
+                    }
+                }
+                true
+            }
+            _ => false,
+        };
+
+        if !matches {
+            out.push(Violation {
+                path: path.clone(),
+                problem: Problem::WrongKind {
+                    expected: self.expected(),
+                    found: value.kind(),
+                },
+            });
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// Dict keys and list indices leading to the offending value.
+    pub path: Vec<String>,
+    pub problem: Problem,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// A required key is absent.
+    Missing,
+    /// A key the schema does not declare, under `deny_unknown`.
+    Unknown,
+    WrongKind { expected: Kind, found: Kind },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.path.join("/");
+        match &self.problem {
+            Problem::Missing => write!(f, "/{}: required key is missing", path),
+            Problem::Unknown => write!(f, "/{}: key is not allowed", path),
+            Problem::WrongKind { expected, found } => {
+                write!(f, "/{}: expected {:?}, found {:?}", path, expected, found)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode_exact;
+
+    fn torrent() -> Schema {
+        Schema::dict()
+            .required("announce", Schema::Str)
+            .optional("announce-list", Schema::list(Schema::list(Schema::Str)))
+            .required(
+                "info",
+                Schema::dict()
+                    .required("name", Schema::Str)
+                    .required("piece length", Schema::Int)
+                    .required("pieces", Schema::Bytes),
+            )
+    }
+
+    #[test]
+    fn test_valid_document() {
+        let value = decode_exact(
+            b"d8:announce3:url4:infod4:name1:n12:piece lengthi16e6:pieces2:abe7:comment1:xe",
+        )
+        .unwrap();
+        assert_eq!(torrent().check(&value), vec![]);
+    }
+
+    #[test]
+    fn test_collects_violations_with_paths() {
+        let value = decode_exact(
+            b"d13:announce-listll1:aei1ee4:infod4:name1:n12:piece length2:16ee",
+        )
+        .unwrap();
+        let violations = torrent().check(&value);
+        let rendered: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "/announce: required key is missing",
+                "/announce-list/1: expected List, found Int",
+                "/info/piece length: expected Int, found Str",
+                "/info/pieces: required key is missing",
+            ]
+        );
+        assert_eq!(
+            violations[1].path,
+            vec!["announce-list".to_string(), "1".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_deny_unknown_keys() {
+        let schema = Schema::dict().optional("a", Schema::Any).deny_unknown();
+        let value = decode_exact(b"d1:ai1e1:ci1e1:bi1ee").unwrap();
+        let unknown: Vec<String> = schema.check(&value).iter().map(|v| v.to_string()).collect();
+        assert_eq!(
+            unknown,
+            vec!["/b: key is not allowed", "/c: key is not allowed"]
+        );
+        assert_eq!(
+            schema.check(&BValue::Int(1)),
+            vec![Violation {
+                path: vec![],
+                problem: Problem::WrongKind {
+                    expected: Kind::Dict,
+                    found: Kind::Int
+                },
+            }]
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::{DELIM_END, DecodeError, Event, Events};
+
+// Byte ranges of every node of an encoded value. Used for exact infohash
+// computation, error highlighting and patching the input in place. Spans
+// are found by walking the input as events, so only syntax is checked:
+// binary strings, large integers and binary keys (as in real torrents)
+// are fine.
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Span {
+    /// Range of the whole encoded node, delimiters included.
+    pub range: Range<usize>,
+    /// Spans of list elements, in order. Empty for non-lists.
+    pub items: Vec<Span>,
+    /// Spans of dict values by raw key; the first of repeated keys wins,
+    /// as with `get_raw`. Empty for non-dicts.
+    pub entries: HashMap<Vec<u8>, Span>,
+}
+
+impl Span {
+    /// The encoded bytes of this node within the original `input`.
+    pub fn bytes<'a>(&self, input: &'a [u8]) -> &'a [u8] {
+        &input[self.range.clone()]
+    }
+
+    pub fn get(&self, key: impl AsRef<[u8]>) -> Option<&Span> {
+        self.entries.get(key.as_ref())
+    }
+
+    pub fn index(&self, i: usize) -> Option<&Span> {
+        self.items.get(i)
+    }
+}
+
+/// The span of the value at the front of `input` and of every node inside
+/// it. Offsets are relative to `input`; anything after the value is left
+/// alone and starts at `range.end`.
+pub fn decode_with_spans(input: &[u8]) -> Result<Span, DecodeError> {
+    if input.is_empty() {
+        return Err(DecodeError::EmptyInput);
+    }
+    read(&mut Events::new(input), input)
+}
+
+fn read(events: &mut Events<'_>, input: &[u8]) -> Result<Span, DecodeError> {
+    let start = events.offset();
+    let mut span = Span::default();
+    match events.expect_next()? {
+        Event::Int(_) | Event::Str(_) => {}
+        Event::ListStart => {
+            while input.get(events.offset()) != Some(&DELIM_END) {
+                span.items.push(read(events, input)?);
+            }
+            events.expect_next()?;
+        }
+        Event::DictStart => {
+            while let Event::Key(key) = events.expect_next()? {
+                let item = read(events, input)?;
+                span.entries.entry(key.to_vec()).or_insert(item);
+            }
+        }
+        // Events never yields these where a value starts.
+        Event::Key(_) | Event::End => {
+            return Err(DecodeError::UnexpectedByte {
+                offset: start,
+                byte: input[start],
+            });
+        }
+    }
+    span.range = start..events.offset();
+    Ok(span)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spans_cover_each_node() {
+        let input = b"d8:announce3:url4:infod6:lengthi42e4:pathl1:a1:beee";
+        let span = decode_with_spans(input).unwrap();
+        assert_eq!(span.range, 0..input.len());
+
+        let info = span.get("info").unwrap();
+        assert_eq!(info.bytes(input), b"d6:lengthi42e4:pathl1:a1:bee");
+        assert_eq!(info.get("length").unwrap().bytes(input), b"i42e");
+
+        let path = info.get("path").unwrap();
+        assert_eq!(path.index(1).unwrap().bytes(input), b"1:b");
+        assert_eq!(span.get("announce").unwrap().range, 11..16);
+    }
+
+    #[test]
+    fn test_spans_stop_at_end_of_value() {
+        let span = decode_with_spans(b"li1eei2e").unwrap();
+        assert_eq!(span.range, 0..5);
+        assert_eq!(span.items[0].range, 1..4);
+
+        assert_eq!(decode_with_spans(b""), Err(DecodeError::EmptyInput));
+        assert_eq!(
+            decode_with_spans(b"l1:a"),
+            Err(DecodeError::UnexpectedEof { offset: 4 })
+        );
+    }
+
+    #[test]
+    fn test_spans_of_a_real_torrent() {
+        let mut input =
+            b"d4:infod6:lengthi70000e4:name1:a12:piece lengthi65536e6:pieces40:".to_vec();
+        input.extend((0..40).map(|i| 0xff - i as u8));
+        input.extend_from_slice(b"e12:piece layersd2:\xff\x00i5000000000eee");
+        let span = decode_with_spans(&input).unwrap();
+        assert_eq!(span.range, 0..input.len());
+
+        let info = span.get("info").unwrap();
+        assert_eq!(info.range, 7..106);
+        assert_eq!(info.get("length").unwrap().bytes(&input), b"i70000e");
+        assert_eq!(info.get("pieces").unwrap().range.len(), 43);
+
+        let layers = span.get("piece layers").unwrap();
+        assert_eq!(layers.get(b"\xff\x00").unwrap().bytes(&input), b"i5000000000e");
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::BTreeMap;
+use std::io::{self, Read};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::DecodeError;
+use crate::events::{self, Events};
+
+// Splits a stream of back-to-back encoded messages (e.g. a DHT traffic
+// capture) into individual frames, and optionally fans the frames out to a
+// pool of worker threads. As in `PushParser`, a frame that spans several
+// reads is scanned once: the scan resumes where the last read ran out.
+
+const READ_CHUNK: usize = 64 * 1024;
+
+/// One complete encoded message and its offset in the stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub offset: u64,
+    pub bytes: Vec<u8>,
+}
+
+/// Order in which `Splitter::dispatch` hands back results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    /// Same order as the frames appear in the stream.
+    Ordered,
+    /// As soon as each worker finishes.
+    Unordered,
+}
+
+pub struct Splitter<R> {
+    inner: R,
+    buf: Vec<u8>,
+    start: usize,
+    /// Stream offset of `buf[0]`.
+    base: u64,
+    /// Scan position relative to `start` and the containers open there.
+    pos: usize,
+    stack: Vec<events::Frame>,
+    done: bool,
+}
+
+impl<R: Read> Splitter<R> {
+    pub fn new(inner: R) -> Self {
+        Splitter {
+            inner,
+            buf: Vec::new(),
+            start: 0,
+            base: 0,
+            pos: 0,
+            stack: Vec::new(),
+            done: false,
+        }
+    }
+
+    fn next_frame(&mut self) -> io::Result<Option<Frame>> {
+        loop {
+            let pending = &self.buf[self.start..];
+            let offset = self.base + self.start as u64;
+            let mut events = Events::resume(pending, self.pos, std::mem::take(&mut self.stack));
+            loop {
+                match events.next() {
+                    // Not enough buffered yet; read more below.
+                    None | Some(Err(DecodeError::UnexpectedEof { .. })) => {
+                        (self.pos, self.stack) = events.into_state();
+                        break;
+                    }
+                    Some(Err(e)) => return Err(invalid_data(e.offset_by(offset as usize))),
+                    Some(Ok(_)) if events.depth() == 0 => {
+                        let len = events.offset();
+                        let bytes = pending[..len].to_vec();
+                        self.start += len;
+                        self.pos = 0;
+                        return Ok(Some(Frame { offset, bytes }));
+                    }
+                    Some(Ok(_)) => {}
+                }
+            }
+
+            if self.fill()? == 0 {
+                if self.start == self.buf.len() {
+                    return Ok(None);
+                }
+                return Err(invalid_data(DecodeError::UnexpectedEof {
+                    offset: (self.base as usize) + self.buf.len(),
+                }));
+            }
+        }
+    }
+
+    fn fill(&mut self) -> io::Result<usize> {
+        if self.start > 0 {
+            self.buf.drain(..self.start);
+            self.base += self.start as u64;
+            self.start = 0;
+        }
+
+        let len = self.buf.len();
+        self.buf.resize(len + READ_CHUNK, 0);
+        let read = loop {
+            match self.inner.read(&mut self.buf[len..]) {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                result => break result,
+            }
+        };
+
+        let n = match read {
+            Ok(n) => n,
+            Err(e) => {
+                self.buf.truncate(len);
+                return Err(e);
+            }
+        };
+        self.buf.truncate(len + n);
+        Ok(n)
+    }
+}
+
+impl<R: Read + Send + 'static> Splitter<R> {
+    /// Runs `work` on every frame using `workers` threads and returns the
+    /// results. A split error is delivered in its place in the sequence and
+    /// ends the stream. If `work` panics, that frame's result is an error
+    /// and the other frames are still processed.
+    pub fn dispatch<T, F>(self, workers: usize, delivery: Delivery, work: F) -> Dispatched<T>
+    where
+        T: Send + 'static,
+        F: Fn(Frame) -> T + Send + Sync + 'static,
+    {
+        let workers = workers.max(1);
+        let (job_tx, job_rx) = mpsc::sync_channel::<(u64, Frame)>(workers * 2);
+        let (result_tx, result_rx) = mpsc::channel();
+        let job_rx = Arc::new(Mutex::new(job_rx));
+        let work = Arc::new(work);
+
+        let feeder_tx = result_tx.clone();
+        thread::spawn(move || {
+            for (seq, frame) in (0u64..).zip(self) {
+                match frame {
+                    Ok(frame) => {
+                        if job_tx.send((seq, frame)).is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => {
+                        let _ = feeder_tx.send((seq, Err(e)));
+                        return;
+                    }
+                }
+            }
+        });
+
+        for _ in 0..workers {
+            let job_rx = Arc::clone(&job_rx);
+            let result_tx = result_tx.clone();
+            let work = Arc::clone(&work);
+            thread::spawn(move || {
+                loop {
+                    let job = job_rx.lock().map(|rx| rx.recv());
+                    let Ok(Ok((seq, frame))) = job else {
+                        return;
+                    };
+                    let offset = frame.offset;
+                    let outcome = panic::catch_unwind(AssertUnwindSafe(|| work(frame)))
+                        .map(|result| (offset, result))
+                        .map_err(|_| {
+                            io::Error::other(format!(
+                                "worker panicked on frame at offset {}",
+                                offset
+                            ))
+                        });
+                    if result_tx.send((seq, outcome)).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+
+        Dispatched {
+            results: result_rx,
+            delivery,
+            pending: BTreeMap::new(),
+            next_seq: 0,
+        }
+    }
+}
+
+impl<R: Read> Iterator for Splitter<R> {
+    type Item = io::Result<Frame>;
+
+    /// Yields frames until the stream ends. After an error the iterator is
+    /// finished.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let frame = self.next_frame().transpose();
+        if !matches!(frame, Some(Ok(_))) {
+            self.done = true;
+        }
+        frame
+    }
+}
+
+type Outcome<T> = io::Result<(u64, T)>;
+
+/// Results of `Splitter::dispatch` as `(frame offset, result)`.
+pub struct Dispatched<T> {
+    results: Receiver<(u64, Outcome<T>)>,
+    delivery: Delivery,
+    pending: BTreeMap<u64, Outcome<T>>,
+    next_seq: u64,
+}
+
+impl<T> Iterator for Dispatched<T> {
+    type Item = Outcome<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.delivery == Delivery::Unordered {
+            return self.results.recv().ok().map(|(_, outcome)| outcome);
+        }
+
+        loop {
+            if let Some(outcome) = self.pending.remove(&self.next_seq) {
+                self.next_seq += 1;
+                return Some(outcome);
+            }
+            let (seq, outcome) = self.results.recv().ok()?;
+            self.pending.insert(seq, outcome);
+        }
+    }
+}
+
+fn invalid_data(e: DecodeError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reader that hands out at most a few bytes per call.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.0.len().min(buf.len()).min(3);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_splits_frames_across_reads() {
+        let capture = b"d1:y1:qeli1ei2ee4:spami-5e";
+        let frames: Vec<Frame> = Splitter::new(Trickle(capture))
+            .collect::<io::Result<_>>()
+            .unwrap();
+
+        let offsets: Vec<u64> = frames.iter().map(|f| f.offset).collect();
+        assert_eq!(offsets, vec![0, 8, 16, 22]);
+        assert_eq!(frames[1].bytes, b"li1ei2ee");
+        assert_eq!(frames[3].bytes, b"i-5e");
+    }
+
+    #[test]
+    fn test_truncated_and_corrupt_streams() {
+        let mut splitter = Splitter::new(&b"i1ei2"[..]);
+        assert!(splitter.next().unwrap().is_ok());
+        let err = splitter.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(splitter.next().is_none());
+
+        let mut splitter = Splitter::new(&b"i1ex"[..]);
+        assert!(splitter.next().unwrap().is_ok());
+        let err = splitter.next().unwrap().unwrap_err();
+        assert_eq!(
+            err.into_inner().unwrap().downcast_ref::<DecodeError>(),
+            Some(&DecodeError::UnexpectedByte {
+                offset: 3,
+                byte: b'x'
+            })
+        );
+    }
+
+    #[test]
+    fn test_dispatch_ordered_and_unordered() {
+        let capture: Vec<u8> = (0..50)
+            .flat_map(|i| format!("i{}e", i).into_bytes())
+            .collect();
+
+        let ordered: Vec<usize> = Splitter::new(io::Cursor::new(capture.clone()))
+            .dispatch(4, Delivery::Ordered, |frame| frame.bytes.len())
+            .map(|r| r.unwrap().1)
+            .collect();
+        let expected: Vec<usize> = (0..50).map(|i| format!("i{}e", i).len()).collect();
+        assert_eq!(ordered, expected);
+
+        let mut offsets: Vec<u64> = Splitter::new(io::Cursor::new(capture))
+            .dispatch(4, Delivery::Unordered, |frame| frame.offset)
+            .map(|r| r.unwrap().1)
+            .collect();
+        offsets.sort();
+        assert_eq!(offsets.len(), 50);
+        assert_eq!(offsets[1], 3);
+    }
+
+    #[test]
+    fn test_worker_panic_is_an_error() {
+        let results: Vec<Outcome<usize>> = Splitter::new(&b"i1ei2ei3e"[..])
+            .dispatch(2, Delivery::Ordered, |frame| {
+                assert_ne!(frame.offset, 3, "bad frame");
+                frame.bytes.len()
+            })
+            .collect();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap(), &(0, 3));
+        assert!(
+            results[1]
+                .as_ref()
+                .unwrap_err()
+                .to_string()
+                .contains("offset 3")
+        );
+        assert_eq!(results[2].as_ref().unwrap(), &(6, 3));
+    }
+
+    #[test]
+    fn test_large_frame_is_scanned_once() {
+        // Binary content and a large integer, split across many reads.
+        let mut capture = b"d1:xi5000000000e4:blob".to_vec();
+        capture.extend(format!("{}:", 3 * READ_CHUNK).into_bytes());
+        capture.extend((0..3 * READ_CHUNK).map(|i| i as u8));
+        capture.extend_from_slice(b"ei7e");
+        let frames: Vec<Frame> = Splitter::new(io::Cursor::new(&capture))
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].bytes.len(), capture.len() - 3);
+        assert_eq!(frames[1].offset as usize, capture.len() - 3);
+    }
+}
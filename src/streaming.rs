@@ -0,0 +1,233 @@
+use std::io::{self, Write};
+
+use crate::encode::{write_bytes, write_int};
+use crate::{BValue, DELIM_END, DICT_DELIM_BEGIN, LIST_DELIM_BEGIN};
+
+// Push-based encoder that writes as it goes. The states are types: a dict
+// hands out a value slot for each key, containers hand back their parent on
+// `end()`, and the top level finishes after one value. Two keys in a row,
+// a value where a key belongs, or an unbalanced `end()` do not compile.
+//
+//     let w = StreamingEncoder::new(Vec::new())
+//         .dict()?
+//         .key(b"length")?.int(42)?
+//         .key(b"path")?.list()?.str("a")?.end()?
+//         .end()?
+//         .finish();
+
+/// Access to the underlying writer through a chain of open containers.
+pub trait Sink {
+    fn writer(&mut self) -> &mut dyn Write;
+}
+
+/// A state that expects one value. Writing it moves to `Next`.
+pub trait ValueSlot: Sink + Sized {
+    type Next: Sink;
+
+    #[doc(hidden)]
+    fn filled(self) -> Self::Next;
+
+    fn int(mut self, n: i64) -> io::Result<Self::Next> {
+        write_int(self.writer(), n)?;
+        Ok(self.filled())
+    }
+
+    fn bytes(mut self, bytes: &[u8]) -> io::Result<Self::Next> {
+        write_bytes(self.writer(), bytes)?;
+        Ok(self.filled())
+    }
+
+    fn str(self, s: &str) -> io::Result<Self::Next> {
+        self.bytes(s.as_bytes())
+    }
+
+    /// Writes a whole decoded value.
+    fn value(mut self, value: &BValue) -> io::Result<Self::Next> {
+        crate::encode_to_writer(value, self.writer())?;
+        Ok(self.filled())
+    }
+
+    fn list(mut self) -> io::Result<ListEncoder<Self::Next>> {
+        self.writer().write_all(&[LIST_DELIM_BEGIN])?;
+        Ok(ListEncoder {
+            parent: self.filled(),
+        })
+    }
+
+    fn dict(mut self) -> io::Result<DictEncoder<Self::Next>> {
+        self.writer().write_all(&[DICT_DELIM_BEGIN])?;
+        Ok(DictEncoder {
+            parent: self.filled(),
+            last_key: None,
+        })
+    }
+}
+
+/// Top level, expecting exactly one value.
+pub struct StreamingEncoder<W> {
+    w: W,
+}
+
+impl<W: Write> StreamingEncoder<W> {
+    pub fn new(w: W) -> Self {
+        StreamingEncoder { w }
+    }
+}
+
+impl<W: Write> Sink for StreamingEncoder<W> {
+    fn writer(&mut self) -> &mut dyn Write {
+        &mut self.w
+    }
+}
+
+impl<W: Write> ValueSlot for StreamingEncoder<W> {
+    type Next = Finished<W>;
+
+    fn filled(self) -> Finished<W> {
+        Finished { w: self.w }
+    }
+}
+
+/// The top-level value has been started; only `finish` remains once all
+/// containers are closed.
+pub struct Finished<W> {
+    w: W,
+}
+
+impl<W: Write> Finished<W> {
+    pub fn finish(self) -> W {
+        self.w
+    }
+}
+
+impl<W: Write> Sink for Finished<W> {
+    fn writer(&mut self) -> &mut dyn Write {
+        &mut self.w
+    }
+}
+
+/// Inside a list: any number of values, then `end`.
+pub struct ListEncoder<P> {
+    parent: P,
+}
+
+impl<P: Sink> ListEncoder<P> {
+    pub fn end(mut self) -> io::Result<P> {
+        self.writer().write_all(&[DELIM_END])?;
+        Ok(self.parent)
+    }
+}
+
+impl<P: Sink> Sink for ListEncoder<P> {
+    fn writer(&mut self) -> &mut dyn Write {
+        self.parent.writer()
+    }
+}
+
+impl<P: Sink> ValueSlot for ListEncoder<P> {
+    type Next = ListEncoder<P>;
+
+    fn filled(self) -> Self {
+        self
+    }
+}
+
+/// Inside a dict, expecting a key or `end`.
+pub struct DictEncoder<P> {
+    parent: P,
+    last_key: Option<Vec<u8>>,
+}
+
+impl<P: Sink> DictEncoder<P> {
+    /// Starts an entry. Keys must come in strictly increasing byte order,
+    /// as canonical encoding requires; anything else is `InvalidInput`.
+    pub fn key(mut self, key: &[u8]) -> io::Result<DictValue<P>> {
+        if self.last_key.as_deref().is_some_and(|last| last >= key) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("dict key {:?} is out of order", String::from_utf8_lossy(key)),
+            ));
+        }
+        write_bytes(self.writer(), key)?;
+        self.last_key = Some(key.to_vec());
+        Ok(DictValue { dict: self })
+    }
+
+    pub fn end(mut self) -> io::Result<P> {
+        self.writer().write_all(&[DELIM_END])?;
+        Ok(self.parent)
+    }
+}
+
+impl<P: Sink> Sink for DictEncoder<P> {
+    fn writer(&mut self) -> &mut dyn Write {
+        self.parent.writer()
+    }
+}
+
+/// Inside a dict after a key, expecting its value.
+pub struct DictValue<P> {
+    dict: DictEncoder<P>,
+}
+
+impl<P: Sink> Sink for DictValue<P> {
+    fn writer(&mut self) -> &mut dyn Write {
+        self.dict.writer()
+    }
+}
+
+impl<P: Sink> ValueSlot for DictValue<P> {
+    type Next = DictEncoder<P>;
+
+    fn filled(self) -> DictEncoder<P> {
+        self.dict
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_output() -> io::Result<()> {
+        let out = StreamingEncoder::new(Vec::new())
+            .dict()?
+            .key(b"info")?
+            .dict()?
+            .key(b"length")?
+            .int(42)?
+            .key(b"path")?
+            .list()?
+            .str("a")?
+            .value(&BValue::Int(-1))?
+            .end()?
+            .end()?
+            .key(b"name")?
+            .bytes(b"x")?
+            .end()?
+            .finish();
+
+        assert_eq!(out, b"d4:infod6:lengthi42e4:pathl1:ai-1eee4:name1:xe");
+        Ok(())
+    }
+
+    #[test]
+    fn test_keys_must_be_sorted() -> io::Result<()> {
+        let result = StreamingEncoder::new(Vec::new())
+            .dict()
+            .and_then(|d| d.key(b"b")?.int(1)?.key(b"a"));
+        assert_eq!(result.err().unwrap().kind(), io::ErrorKind::InvalidInput);
+
+        // Binary keys, such as infohashes in a scrape reply, order by byte.
+        let out = StreamingEncoder::new(Vec::new())
+            .dict()?
+            .key(b"\x01\xff")?
+            .int(1)?
+            .key(b"\xfe")?
+            .int(2)?
+            .end()?
+            .finish();
+        assert_eq!(out, b"d2:\x01\xffi1e1:\xfei2ee");
+        Ok(())
+    }
+}
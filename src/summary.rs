@@ -0,0 +1,140 @@
+use crate::{Event, Events};
+
+// One-line description of an encoded message for request logging, e.g.
+// `dict{announce, info{…}} 14932 bytes`. Only the top level is described,
+// and the walk stops as soon as the description reaches the length budget.
+// Children are passed over as events, checking syntax only, so binary
+// strings and large integers are described like any others.
+
+const ELLIPSIS: &str = "…";
+
+/// Describes `input` in roughly `max_len` characters (the byte count is
+/// always appended). Nested containers are shown as `{…}` or `[…]`.
+pub fn summarize(input: &[u8], max_len: usize) -> String {
+    let mut out = String::new();
+    let mut events = Events::new(input);
+
+    match events.next() {
+        None => out.push_str("empty"),
+        Some(Ok(Event::DictStart)) => {
+            out.push_str("dict{");
+            children(&mut events, max_len, &mut out);
+            out.push('}');
+        }
+        Some(Ok(Event::ListStart)) => {
+            out.push_str("list[");
+            children(&mut events, max_len, &mut out);
+            out.push(']');
+        }
+        Some(Ok(event)) => out.push_str(&scalar(event)),
+        Some(Err(e)) => out.push_str(&format!("invalid ({})", e)),
+    }
+
+    format!("{} {} bytes", out, input.len())
+}
+
+/// Describes the children of the container just opened on `events`.
+fn children(events: &mut Events<'_>, max_len: usize, out: &mut String) {
+    let mut first = true;
+
+    loop {
+        let event = match events.next() {
+            Some(Ok(Event::End)) | None => return,
+            Some(Ok(event)) => event,
+            Some(Err(_)) => {
+                out.push_str(if first { "<invalid>" } else { ", <invalid>" });
+                return;
+            }
+        };
+        if out.chars().count() >= max_len {
+            out.push_str(", ");
+            out.push_str(ELLIPSIS);
+            return;
+        }
+        if !first {
+            out.push_str(", ");
+        }
+        first = false;
+
+        let is_key = matches!(event, Event::Key(_));
+        let value = match event {
+            Event::Key(key) => {
+                out.push_str(&String::from_utf8_lossy(key));
+                events.next().and_then(Result::ok)
+            }
+            value => Some(value),
+        };
+        let depth = events.depth();
+        let described = match value {
+            Some(Event::DictStart) => close(events, depth).map(|()| "{…}".to_string()),
+            Some(Event::ListStart) => close(events, depth).map(|()| "[…]".to_string()),
+            // Scalar dict values are left out.
+            Some(_) if is_key => Some(String::new()),
+            Some(value) => Some(scalar(value)),
+            None => None,
+        };
+        let Some(described) = described else {
+            out.push_str(if is_key { " <invalid>" } else { "<invalid>" });
+            return;
+        };
+        out.push_str(&described);
+    }
+}
+
+/// Consumes the rest of a container opened at `depth`.
+fn close(events: &mut Events<'_>, depth: usize) -> Option<()> {
+    while events.depth() >= depth {
+        events.expect_next().ok()?;
+    }
+    Some(())
+}
+
+fn scalar(event: Event<'_>) -> String {
+    match event {
+        Event::Int(n) => format!("int {}", n),
+        Event::Str(bytes) => format!("str({})", bytes.len()),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summaries() {
+        let input = b"d8:announce3:url4:infod6:lengthi42eee";
+        assert_eq!(summarize(input, 80), "dict{announce, info{…}} 37 bytes");
+        assert_eq!(
+            summarize(b"li1e3:abclee", 80),
+            "list[int 1, str(3), […]] 12 bytes"
+        );
+        assert_eq!(summarize(b"i-7e", 80), "int -7 4 bytes");
+        assert_eq!(summarize(b"", 80), "empty 0 bytes");
+    }
+
+    #[test]
+    fn test_budget_stops_the_walk() {
+        let input = b"d1:ai1e1:bi2e1:ci3e1:di4ee";
+        assert_eq!(summarize(input, 8), "dict{a, b, …} 26 bytes");
+    }
+
+    #[test]
+    fn test_malformed_input_is_described() {
+        assert_eq!(
+            summarize(b"d1:ai1e1:bie", 80),
+            "dict{a, b <invalid>} 12 bytes"
+        );
+        assert!(summarize(b"i4", 80).starts_with("invalid ("));
+    }
+
+    #[test]
+    fn test_real_torrent_values() {
+        let input = b"d4:infod6:pieces2:\xff\xfe12:piece lengthi262144ee5:ratioi99999ee";
+        assert_eq!(summarize(input, 80), "dict{info{…}, ratio} 59 bytes");
+        assert_eq!(
+            summarize(b"l2:\xff\xfei5000000000ee", 80),
+            "list[str(2), int 5000000000] 18 bytes"
+        );
+    }
+}
@@ -0,0 +1,58 @@
+use crate::BValue;
+
+// Moving subtrees out of a document, so parts of a message can be routed to
+// different owners without cloning.
+
+impl BValue {
+    /// Removes the value at `path` and returns it, leaving the rest of the
+    /// tree in place. Components are dict keys, or indices for lists
+    /// (later elements shift down). `None` if the path does not exist.
+    pub fn take_path(&mut self, path: &[&str]) -> Option<BValue> {
+        let (last, parents) = path.split_last()?;
+
+        let mut node = self;
+        for component in parents {
+            node = node.child_mut(component)?;
+        }
+
+        match node {
+            BValue::Dict(dict) => dict.remove(*last),
+            BValue::List(list) => {
+                let i = last.parse::<usize>().ok()?;
+                (i < list.len()).then(|| list.remove(i))
+            }
+            _ => None,
+        }
+    }
+
+    fn child_mut(&mut self, component: &str) -> Option<&mut BValue> {
+        match self {
+            BValue::Dict(dict) => dict.get_mut(component),
+            BValue::List(list) => list.get_mut(component.parse::<usize>().ok()?),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::decode;
+
+    #[test]
+    fn test_take_path() {
+        let (mut torrent, _) =
+            decode(b"d8:announce3:url4:infod5:filesld6:lengthi1eed6:lengthi2eee4:name1:xee").unwrap();
+
+        let (expected, _) = decode(b"d6:lengthi1ee").unwrap();
+        assert_eq!(torrent.take_path(&["info", "files", "0"]), Some(expected));
+
+        let info = torrent.take_path(&["info"]).unwrap();
+        let (expected, _) = decode(b"d5:filesld6:lengthi2eee4:name1:xe").unwrap();
+        assert_eq!(info, expected);
+        assert_eq!(torrent, decode(b"d8:announce3:urle").unwrap().0);
+
+        assert_eq!(torrent.take_path(&["info"]), None);
+        assert_eq!(torrent.take_path(&["announce", "x"]), None);
+        assert_eq!(torrent.take_path(&[]), None);
+    }
+}
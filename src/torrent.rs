@@ -0,0 +1,1923 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::slice::ChunksExact;
+use std::sync::LazyLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{Aliases, DELIM_END, DecodeError, Event, Events};
+
+// Typed view of a .torrent file (BEP 3). Real torrents do not fit `BValue`
+// as is: `pieces` is binary, lengths overflow `i16`, and clients add keys
+// of their own with binary or large values. So no tree is built: each dict
+// is scanned once into its keys and the raw bytes of their values, and
+// only the fields read here are interpreted. Unknown keys are never looked
+// at beyond their syntax.
+//
+// Field names in errors use `get_path` syntax, e.g. `info.piece length`.
+//
+// The infohash is the hash of the `info` dict exactly as it appears in the
+// file. Re-encoding the parsed dict could reorder keys or normalize other
+// details, so the original bytes are kept.
+//
+// v2 torrents (BEP 52, `meta version 2`) describe their files as a nested
+// `file tree` with binary `pieces root` hashes at arbitrary depth, and key
+// `piece layers` by those hashes; both are walked as events.
+//
+// A hybrid torrent carries both layouts for the same files. Its v1 `files`
+// list has padding entries (BEP 47) between files so each file starts on a
+// piece boundary; with those skipped, the two listings must agree.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetaInfo {
+    pub announce: Option<String>,
+    /// Tiers of tracker URLs (BEP 12); empty if absent.
+    pub announce_list: Vec<Vec<String>>,
+    /// Web seed URLs from `url-list` (BEP 19).
+    pub url_list: Vec<String>,
+    /// Seed URLs from `httpseeds` (BEP 17).
+    pub http_seeds: Vec<String>,
+    /// DHT bootstrap nodes for trackerless torrents (BEP 5).
+    pub nodes: Vec<Node>,
+    pub creation_date: Option<SystemTime>,
+    pub comment: Option<String>,
+    pub created_by: Option<String>,
+    pub info: Info,
+    /// v2 piece layers keyed by the `pieces root` of their file; empty for
+    /// v1 torrents.
+    pub piece_layers: HashMap<[u8; 32], Vec<[u8; 32]>>,
+    info_bytes: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Info {
+    pub name: String,
+    pub piece_length: u64,
+    /// `meta version`; 1 when absent.
+    pub meta_version: u64,
+    /// SHA-1 hash of each piece; empty in a v2-only torrent.
+    pub pieces: Vec<[u8; 20]>,
+    /// The v1 file layout; `None` in a v2-only torrent.
+    pub files: Option<Files>,
+    /// The v2 `file tree`, flattened in key order; empty for v1 torrents.
+    pub file_tree: Vec<TreeEntry>,
+    /// `private` is 1 (BEP 27): peers come only from the trackers.
+    pub private: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Files {
+    /// A single file named after `Info::name`.
+    Single { length: u64 },
+    /// Files inside a directory named after `Info::name`.
+    Multi(Vec<FileEntry>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileEntry {
+    pub length: u64,
+    /// Path components below the torrent's directory.
+    pub path: Vec<String>,
+    /// BEP 47 attribute flags: `p` padding, `l` symlink, `x` executable,
+    /// `h` hidden.
+    pub attr: Option<String>,
+    /// SHA-1 of the file's content, which some creators add (BEP 47).
+    pub sha1: Option<[u8; 20]>,
+    /// Target of a symlink (`l`), relative to the torrent's directory.
+    pub symlink_path: Option<Vec<String>>,
+}
+
+/// A `[host, port]` pair from `nodes`. The host may be a name or an IP
+/// address; `ToSocketAddrs` resolves either.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub host: String,
+    pub port: u16,
+}
+
+impl Node {
+    /// The address without a DNS lookup; `None` if the host is a name.
+    pub fn socket_addr(&self) -> Option<SocketAddr> {
+        let host = self.host.trim_start_matches('[').trim_end_matches(']');
+        let ip: IpAddr = host.parse().ok()?;
+        Some(SocketAddr::new(ip, self.port))
+    }
+}
+
+impl ToSocketAddrs for Node {
+    type Iter = std::vec::IntoIter<SocketAddr>;
+
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        match self.socket_addr() {
+            Some(addr) => Ok(vec![addr].into_iter()),
+            None => (self.host.as_str(), self.port).to_socket_addrs(),
+        }
+    }
+}
+
+/// Which file layouts a torrent carries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TorrentVersion {
+    V1,
+    V2,
+    Hybrid,
+}
+
+/// A disagreement between the v1 and v2 file listings of a hybrid torrent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileMismatch {
+    /// The files at `index` have different paths.
+    Path {
+        index: usize,
+        v1: Vec<String>,
+        v2: Vec<String>,
+    },
+    /// The same file has different lengths.
+    Length { path: Vec<String>, v1: u64, v2: u64 },
+    /// The listings have different numbers of files.
+    Count { v1: usize, v2: usize },
+}
+
+impl fmt::Display for FileMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileMismatch::Path { index, v1, v2 } => write!(
+                f,
+                "file {} is {:?} in v1 but {:?} in v2",
+                index,
+                v1.join("/"),
+                v2.join("/")
+            ),
+            FileMismatch::Length { path, v1, v2 } => write!(
+                f,
+                "{:?} is {} bytes in v1 but {} in v2",
+                path.join("/"),
+                v1,
+                v2
+            ),
+            FileMismatch::Count { v1, v2 } => {
+                write!(f, "{} files in v1 but {} in v2", v1, v2)
+            }
+        }
+    }
+}
+
+/// A problem found by `MetaInfo::validate`. Each names where in the
+/// torrent it is when displayed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inconsistency {
+    /// `pieces` does not hold one hash per piece of the v1 data.
+    PieceCount { expected: u64, found: usize },
+    /// The v1 file lengths add up to more than a `u64` holds.
+    TotalLengthOverflow,
+    /// Two files share a path.
+    DuplicatePath { path: Vec<String> },
+    /// A non-empty v2 file has no `pieces root`.
+    MissingPiecesRoot { path: Vec<String> },
+    /// A v2 file longer than one piece has no piece layer (`found` is
+    /// `None`) or one with the wrong number of hashes.
+    PieceLayer {
+        path: Vec<String>,
+        expected: u64,
+        found: Option<usize>,
+    },
+    /// The two layouts of a hybrid disagree.
+    Mismatch(FileMismatch),
+}
+
+impl fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Inconsistency::PieceCount { expected, found } => write!(
+                f,
+                "info.pieces: {} hashes for {} pieces of data",
+                found, expected
+            ),
+            Inconsistency::TotalLengthOverflow => {
+                write!(f, "info.files: total length does not fit in 64 bits")
+            }
+            Inconsistency::DuplicatePath { path } => {
+                write!(f, "info.files: {:?} is listed twice", path.join("/"))
+            }
+            Inconsistency::MissingPiecesRoot { path } => {
+                write!(f, "info.file tree/{}: no pieces root", path.join("/"))
+            }
+            Inconsistency::PieceLayer {
+                path,
+                expected,
+                found: None,
+            } => write!(
+                f,
+                "piece layers: no layer for {:?}, which has {} pieces",
+                path.join("/"),
+                expected
+            ),
+            Inconsistency::PieceLayer {
+                path,
+                expected,
+                found: Some(found),
+            } => write!(
+                f,
+                "piece layers: {} hashes for {:?}, which has {} pieces",
+                found,
+                path.join("/"),
+                expected
+            ),
+            Inconsistency::Mismatch(mismatch) => write!(f, "info.files: {}", mismatch),
+        }
+    }
+}
+
+/// Both infohashes of a torrent, each present if it has that layout.
+#[cfg(all(feature = "sha1", feature = "sha256"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InfoHashes {
+    pub v1: Option<[u8; 20]>,
+    pub v2: Option<[u8; 32]>,
+}
+
+/// A file in a v2 `file tree`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeEntry {
+    /// Path components from the root of the tree.
+    pub path: Vec<String>,
+    pub length: u64,
+    /// Merkle root of the file's piece hashes; absent for empty files.
+    pub pieces_root: Option<[u8; 32]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TorrentError {
+    Decode(DecodeError),
+    /// A required field is absent.
+    Missing(&'static str),
+    /// A field has the wrong type or an impossible value.
+    Invalid(&'static str),
+}
+
+impl fmt::Display for TorrentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TorrentError::Decode(e) => e.fmt(f),
+            TorrentError::Missing(field) => write!(f, "missing field {:?}", field),
+            TorrentError::Invalid(field) => write!(f, "invalid field {:?}", field),
+        }
+    }
+}
+
+impl std::error::Error for TorrentError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TorrentError::Decode(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<DecodeError> for TorrentError {
+    fn from(e: DecodeError) -> Self {
+        TorrentError::Decode(e)
+    }
+}
+
+/// Why `FileEntry::safe_path` refused a path. Each variant carries the
+/// offending component.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnsafePath {
+    /// The path has no components.
+    Empty,
+    /// An empty component, `.` or `..`.
+    Traversal(String),
+    /// A component holding a separator, a drive prefix or NUL, which could
+    /// make the path absolute or climb out of its directory.
+    Absolute(String),
+    /// A device name Windows reserves, such as `CON` or `lpt1.txt`.
+    Reserved(String),
+}
+
+impl fmt::Display for UnsafePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnsafePath::Empty => write!(f, "empty file path"),
+            UnsafePath::Traversal(part) => write!(f, "path component {:?} is not a name", part),
+            UnsafePath::Absolute(part) => {
+                write!(f, "path component {:?} could escape the directory", part)
+            }
+            UnsafePath::Reserved(part) => write!(f, "path component {:?} is reserved", part),
+        }
+    }
+}
+
+impl std::error::Error for UnsafePath {}
+
+static ALIASES: LazyLock<Aliases> = LazyLock::new(Aliases::torrent);
+
+/// A dict's keys with the raw encoded bytes of their values.
+type Dict<'a> = HashMap<&'a [u8], &'a [u8]>;
+
+impl MetaInfo {
+    /// Parses a complete .torrent file.
+    pub fn from_bytes(input: &[u8]) -> Result<MetaInfo, TorrentError> {
+        if input.is_empty() {
+            return Err(DecodeError::EmptyInput.into());
+        }
+        let (root, end) = dict_prefix(input, "")?;
+        if end < input.len() {
+            return Err(DecodeError::TrailingData { offset: end }.into());
+        }
+
+        let announce = match get(&root, "announce") {
+            Some(url) => Some(string(url, "announce")?),
+            None => None,
+        };
+        let announce_list = match get(&root, "announce-list") {
+            Some(tiers) => list(tiers, "announce-list")?
+                .into_iter()
+                .map(|tier| {
+                    list(tier, "announce-list")?
+                        .into_iter()
+                        .map(|url| string(url, "announce-list"))
+                        .collect()
+                })
+                .collect::<Result<_, _>>()?,
+            None => Vec::new(),
+        };
+        let url_list = match get(&root, "url-list") {
+            Some(urls) => urls_or_url(urls, "url-list")?,
+            None => Vec::new(),
+        };
+        let http_seeds = match get(&root, "httpseeds") {
+            Some(urls) => urls_or_url(urls, "httpseeds")?,
+            None => Vec::new(),
+        };
+        let nodes = match get(&root, "nodes") {
+            Some(nodes) => list(nodes, "nodes")?
+                .into_iter()
+                .map(Node::from_raw)
+                .collect::<Result<_, _>>()?,
+            None => Vec::new(),
+        };
+        let creation_date = match get(&root, "creation date") {
+            Some(date) => Some(
+                raw_int(date)
+                    .and_then(unix_time)
+                    .ok_or(TorrentError::Invalid("creation date"))?,
+            ),
+            None => None,
+        };
+        let comment = text(&root, "comment", "comment")?;
+        let created_by = text(&root, "created by", "created by")?;
+        let info_bytes = required(&root, "info", "info")?;
+        let info = Info::from_dict(&dict(info_bytes, "info")?)?;
+        let piece_layers = match get(&root, "piece layers") {
+            Some(layers) => {
+                raw_piece_layers(layers).ok_or(TorrentError::Invalid("piece layers"))?
+            }
+            None => HashMap::new(),
+        };
+
+        Ok(MetaInfo {
+            announce,
+            announce_list,
+            url_list,
+            http_seeds,
+            nodes,
+            creation_date,
+            comment,
+            created_by,
+            info,
+            piece_layers,
+            info_bytes: info_bytes.to_vec(),
+        })
+    }
+
+    /// Moves `info` out, so the file list and piece hashes can go to a
+    /// different owner than the tracker data without a clone. The rest is
+    /// returned with `Info::default()` in its place; it keeps `info_bytes`,
+    /// so `info_hash` still works on it.
+    pub fn split_info(mut self) -> (Info, MetaInfo) {
+        (std::mem::take(&mut self.info), self)
+    }
+
+    /// The `info` dict as encoded in the file.
+    pub fn info_bytes(&self) -> &[u8] {
+        &self.info_bytes
+    }
+
+    /// SHA-1 of the original `info` dict (the v1 infohash).
+    #[cfg(feature = "sha1")]
+    pub fn info_hash(&self) -> [u8; 20] {
+        crate::sha1(&self.info_bytes)
+    }
+
+    /// SHA-256 of the original `info` dict (the v2 infohash). Trackers and
+    /// the DHT use its first 20 bytes.
+    #[cfg(feature = "sha256")]
+    pub fn info_hash_v2(&self) -> [u8; 32] {
+        crate::sha256(&self.info_bytes)
+    }
+
+    /// The infohash of every layout the torrent carries; a hybrid has both.
+    #[cfg(all(feature = "sha1", feature = "sha256"))]
+    pub fn info_hashes(&self) -> InfoHashes {
+        InfoHashes {
+            v1: self.info.is_v1().then(|| self.info_hash()),
+            v2: self.info.is_v2().then(|| self.info_hash_v2()),
+        }
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.info.private
+    }
+
+    /// Cross-checks fields that parse fine on their own: the piece count
+    /// against the data length, piece layers against v2 file lengths, and
+    /// the two layouts of a hybrid against each other. Negative lengths
+    /// and a `pieces` string that is not whole hashes are already parse
+    /// errors. Every problem is reported, not just the first.
+    pub fn validate(&self) -> Result<(), Vec<Inconsistency>> {
+        let info = &self.info;
+        let mut problems = Vec::new();
+
+        if info.is_v1() {
+            let layout = info.v1_layout();
+            let total = layout
+                .iter()
+                .try_fold(0u64, |total, file| total.checked_add(file.length));
+            match total.map(|total| total.div_ceil(info.piece_length)) {
+                None => problems.push(Inconsistency::TotalLengthOverflow),
+                Some(expected) if expected != info.pieces.len() as u64 => {
+                    problems.push(Inconsistency::PieceCount {
+                        expected,
+                        found: info.pieces.len(),
+                    })
+                }
+                Some(_) => {}
+            }
+            let mut seen = HashSet::new();
+            for file in layout.iter().filter(|file| !file.is_padding()) {
+                if !seen.insert(&file.path) {
+                    problems.push(Inconsistency::DuplicatePath {
+                        path: file.path.clone(),
+                    });
+                }
+            }
+        }
+
+        for file in &info.file_tree {
+            if file.length == 0 {
+                continue;
+            }
+            if file.pieces_root.is_none() {
+                problems.push(Inconsistency::MissingPiecesRoot {
+                    path: file.path.clone(),
+                });
+                continue;
+            }
+            if file.length <= info.piece_length {
+                continue;
+            }
+            let expected = file.length.div_ceil(info.piece_length);
+            let found = self.piece_layer(file).map(<[_]>::len);
+            if found != Some(expected as usize) {
+                problems.push(Inconsistency::PieceLayer {
+                    path: file.path.clone(),
+                    expected,
+                    found,
+                });
+            }
+        }
+
+        problems.extend(
+            info.file_mismatches()
+                .into_iter()
+                .map(Inconsistency::Mismatch),
+        );
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+
+    /// Tracker tiers in failover order (BEP 12): `announce-list` without
+    /// blank or repeated URLs, then `announce` as a last tier if the list
+    /// does not name it. URLs within a tier stay in file order; clients are
+    /// expected to shuffle them.
+    pub fn tiers(&self) -> Vec<Vec<&str>> {
+        let mut seen = HashSet::new();
+        let mut tiers: Vec<Vec<&str>> = self
+            .announce_list
+            .iter()
+            .map(|tier| {
+                tier.iter()
+                    .map(|url| url.trim())
+                    .filter(|url| !url.is_empty() && seen.insert(*url))
+                    .collect()
+            })
+            .filter(|tier: &Vec<&str>| !tier.is_empty())
+            .collect();
+        if let Some(url) = self.announce.as_deref().map(str::trim)
+            && !url.is_empty()
+            && seen.insert(url)
+        {
+            tiers.push(vec![url]);
+        }
+        tiers
+    }
+
+    /// Each tracker URL once, in the order `tiers` gives.
+    pub fn trackers(&self) -> impl Iterator<Item = &str> {
+        self.tiers().into_iter().flatten()
+    }
+
+    /// The v1 piece hashes in order; empty for a v2-only torrent.
+    pub fn pieces(&self) -> Pieces<'_> {
+        Pieces {
+            chunks: self.info.pieces.as_flattened().chunks_exact(20),
+        }
+    }
+
+    /// The piece layer of a v2 file. Files no larger than one piece have
+    /// none: their `pieces_root` is the hash of the whole file.
+    pub fn piece_layer(&self, file: &TreeEntry) -> Option<&[[u8; 32]]> {
+        self.piece_layers
+            .get(file.pieces_root.as_ref()?)
+            .map(Vec::as_slice)
+    }
+}
+
+/// The v1 infohash of an encoded torrent, hashed from the original bytes
+/// of its `info` dict. Strings may hold any bytes.
+#[cfg(feature = "sha1")]
+pub fn info_hash(input: &[u8]) -> Result<[u8; 20], TorrentError> {
+    Ok(crate::sha1(&input[info_range(input)?]))
+}
+
+/// The v2 infohash of an encoded torrent; see `info_hash`.
+#[cfg(feature = "sha256")]
+pub fn info_hash_v2(input: &[u8]) -> Result<[u8; 32], TorrentError> {
+    Ok(crate::sha256(&input[info_range(input)?]))
+}
+
+/// Iterator over the 20-byte SHA-1 hashes packed into a `pieces` string.
+#[derive(Debug, Clone)]
+pub struct Pieces<'a> {
+    chunks: ChunksExact<'a, u8>,
+}
+
+/// Splits a raw `pieces` string into hashes; `None` unless its length is a
+/// multiple of 20.
+pub fn pieces(bytes: &[u8]) -> Option<Pieces<'_>> {
+    bytes.len().is_multiple_of(20).then(|| Pieces {
+        chunks: bytes.chunks_exact(20),
+    })
+}
+
+impl Iterator for Pieces<'_> {
+    type Item = [u8; 20];
+
+    fn next(&mut self) -> Option<[u8; 20]> {
+        self.chunks.next().and_then(|hash| hash.try_into().ok())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.chunks.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Pieces<'_> {
+    fn next_back(&mut self) -> Option<[u8; 20]> {
+        self.chunks
+            .next_back()
+            .and_then(|hash| hash.try_into().ok())
+    }
+}
+
+impl ExactSizeIterator for Pieces<'_> {}
+
+/// Byte range of the top-level `info` value, found by scanning events so
+/// that binary strings do not matter.
+#[cfg(any(feature = "sha1", feature = "sha256"))]
+fn info_range(input: &[u8]) -> Result<Range<usize>, TorrentError> {
+    crate::lazy::raw_range(input, "info")?.ok_or(TorrentError::Missing("info"))
+}
+
+impl Info {
+    fn from_dict(info: &Dict<'_>) -> Result<Info, TorrentError> {
+        let name = text(info, "name", "info.name")?.ok_or(TorrentError::Missing("info.name"))?;
+        let piece_length = int(
+            required(info, "piece length", "info.piece length")?,
+            "info.piece length",
+        )?;
+        let meta_version = match get(info, "meta version") {
+            Some(version) => int(version, "info.meta version")?,
+            None => 1,
+        };
+        if !matches!(meta_version, 1 | 2) {
+            return Err(TorrentError::Invalid("info.meta version"));
+        }
+        // v2 pieces must be a power of two of at least 16 KiB.
+        let valid_length = match meta_version {
+            2 => piece_length >= 16384 && piece_length.is_power_of_two(),
+            _ => piece_length > 0,
+        };
+        if !valid_length {
+            return Err(TorrentError::Invalid("info.piece length"));
+        }
+
+        let private = match get(info, "private") {
+            Some(flag) => raw_int(flag).ok_or(TorrentError::Invalid("info.private"))? == 1,
+            None => false,
+        };
+
+        let file_tree = match get(info, "file tree") {
+            Some(tree) => raw_file_tree(tree)
+                .filter(|entries| !entries.is_empty())
+                .ok_or(TorrentError::Invalid("info.file tree"))?,
+            None if meta_version == 2 => return Err(TorrentError::Missing("info.file tree")),
+            None => Vec::new(),
+        };
+
+        // A v2 torrent may also carry the v1 fields (a hybrid); without
+        // `pieces` it is v2 only.
+        if meta_version == 2 && get(info, "pieces").is_none() {
+            return Ok(Info {
+                name,
+                piece_length,
+                meta_version,
+                pieces: Vec::new(),
+                files: None,
+                file_tree,
+                private,
+            });
+        }
+
+        let pieces = bytes(required(info, "pieces", "info.pieces")?, "info.pieces")
+            .ok()
+            .and_then(pieces)
+            .map(Iterator::collect)
+            .ok_or(TorrentError::Invalid("info.pieces"))?;
+
+        let files = match (get(info, "length"), get(info, "files")) {
+            (Some(length), None) => Files::Single {
+                length: int(length, "info.length")?,
+            },
+            (None, Some(files)) => Files::Multi(
+                list(files, "info.files")?
+                    .into_iter()
+                    .map(FileEntry::from_raw)
+                    .collect::<Result<_, _>>()?,
+            ),
+            (None, None) => return Err(TorrentError::Missing("info.length")),
+            (Some(_), Some(_)) => return Err(TorrentError::Invalid("info.files")),
+        };
+
+        Ok(Info {
+            name,
+            piece_length,
+            meta_version,
+            pieces,
+            files: Some(files),
+            file_tree,
+            private,
+        })
+    }
+
+    /// Whether the v1 fields (`pieces` and `length` or `files`) are present.
+    pub fn is_v1(&self) -> bool {
+        self.files.is_some()
+    }
+
+    pub fn is_v2(&self) -> bool {
+        self.meta_version == 2
+    }
+
+    pub fn version(&self) -> TorrentVersion {
+        match (self.is_v1(), self.is_v2()) {
+            (true, true) => TorrentVersion::Hybrid,
+            (false, true) => TorrentVersion::V2,
+            _ => TorrentVersion::V1,
+        }
+    }
+
+    /// Every file with its length, from the `file tree` when there is one
+    /// and from the v1 fields otherwise, without padding files. Paths are
+    /// relative to the torrent directory; a single file's path is just its
+    /// name.
+    pub fn file_list(&self) -> Vec<FileEntry> {
+        if self.is_v2() {
+            self.file_tree
+                .iter()
+                .map(|entry| FileEntry {
+                    length: entry.length,
+                    path: entry.path.clone(),
+                    attr: None,
+                    sha1: None,
+                    symlink_path: None,
+                })
+                .collect()
+        } else {
+            self.v1_files()
+        }
+    }
+
+    /// Where the `files` list and the `file tree` of a hybrid disagree,
+    /// padding files aside. Always empty for a torrent with one layout.
+    pub fn file_mismatches(&self) -> Vec<FileMismatch> {
+        if self.version() != TorrentVersion::Hybrid {
+            return Vec::new();
+        }
+        let v1 = self.v1_files();
+        let mut mismatches = Vec::new();
+        for (index, (old, new)) in v1.iter().zip(&self.file_tree).enumerate() {
+            if old.path != new.path {
+                mismatches.push(FileMismatch::Path {
+                    index,
+                    v1: old.path.clone(),
+                    v2: new.path.clone(),
+                });
+            } else if old.length != new.length {
+                mismatches.push(FileMismatch::Length {
+                    path: new.path.clone(),
+                    v1: old.length,
+                    v2: new.length,
+                });
+            }
+        }
+        if v1.len() != self.file_tree.len() {
+            mismatches.push(FileMismatch::Count {
+                v1: v1.len(),
+                v2: self.file_tree.len(),
+            });
+        }
+        mismatches
+    }
+
+    /// Every real file of the v1 layout with the offset of its first byte
+    /// in the concatenated piece data. Padding files are skipped but still
+    /// move the offset, so pieces map onto the files around them.
+    pub fn file_offsets(&self) -> impl Iterator<Item = (u64, FileEntry)> + '_ {
+        self.layout_offsets()
+            .filter(|(_, file)| !file.is_padding())
+            .map(|(offset, file)| (offset, file.into_owned()))
+    }
+
+    /// The parts of real files that piece `index` covers, as byte ranges
+    /// within each file. Empty if there is no such piece.
+    pub fn piece_files(&self, index: usize) -> Vec<(FileEntry, Range<u64>)> {
+        if index >= self.pieces.len() {
+            return Vec::new();
+        }
+        let Some(start) = (index as u64).checked_mul(self.piece_length) else {
+            return Vec::new();
+        };
+        let end = start.saturating_add(self.piece_length);
+        self.layout_offsets()
+            .take_while(|(offset, _)| *offset < end)
+            .filter(|(offset, file)| {
+                !file.is_padding() && offset.saturating_add(file.length) > start
+            })
+            .map(|(offset, file)| {
+                let file_end = offset.saturating_add(file.length);
+                let range = start.max(offset) - offset..end.min(file_end) - offset;
+                (file.into_owned(), range)
+            })
+            .collect()
+    }
+
+    /// Each entry of the v1 layout, padding included, with its offset.
+    /// Ends at the first file that would start past the `u64` range.
+    fn layout_offsets(&self) -> impl Iterator<Item = (u64, Cow<'_, FileEntry>)> {
+        let mut next = Some(0u64);
+        self.v1_entries().map_while(move |file| {
+            let start = next?;
+            next = start.checked_add(file.length);
+            Some((start, file))
+        })
+    }
+
+    /// `v1_layout` without copying the files list.
+    fn v1_entries(&self) -> impl Iterator<Item = Cow<'_, FileEntry>> {
+        let single = match &self.files {
+            Some(Files::Single { length }) => Some(FileEntry {
+                length: *length,
+                path: vec![self.name.clone()],
+                attr: None,
+                sha1: None,
+                symlink_path: None,
+            }),
+            _ => None,
+        };
+        let multi = match &self.files {
+            Some(Files::Multi(files)) => files.as_slice(),
+            _ => &[],
+        };
+        single
+            .into_iter()
+            .map(Cow::Owned)
+            .chain(multi.iter().map(Cow::Borrowed))
+    }
+
+    /// The `files` list as stored, padding included.
+    pub(crate) fn v1_layout(&self) -> Vec<FileEntry> {
+        self.v1_entries().map(Cow::into_owned).collect()
+    }
+
+    fn v1_files(&self) -> Vec<FileEntry> {
+        let mut files = self.v1_layout();
+        files.retain(|file| !file.is_padding());
+        files
+    }
+
+    /// Sum of all file lengths; `None` if it does not fit in a `u64`,
+    /// which only a broken or hostile torrent declares.
+    pub fn total_length(&self) -> Option<u64> {
+        self.file_list()
+            .iter()
+            .try_fold(0u64, |total, file| total.checked_add(file.length))
+    }
+}
+
+impl FileEntry {
+    fn from_raw(raw: &[u8]) -> Result<FileEntry, TorrentError> {
+        let file = dict(raw, "info.files")?;
+        let length = int(
+            required(&file, "length", "info.files.length")?,
+            "info.files.length",
+        )?;
+        let path = components(&file, "path", "info.files.path")?
+            .ok_or(TorrentError::Missing("info.files.path"))?;
+        if path.is_empty() {
+            return Err(TorrentError::Invalid("info.files.path"));
+        }
+        let attr = match get(&file, "attr") {
+            Some(attr) => Some(string(attr, "info.files.attr")?),
+            None => None,
+        };
+        let sha1 = match get(&file, "sha1") {
+            Some(hash) => Some(
+                bytes(hash, "info.files.sha1")?
+                    .try_into()
+                    .map_err(|_| TorrentError::Invalid("info.files.sha1"))?,
+            ),
+            None => None,
+        };
+        let symlink_path = components(&file, "symlink path", "info.files.symlink path")?;
+        Ok(FileEntry {
+            length,
+            path,
+            attr,
+            sha1,
+            symlink_path,
+        })
+    }
+
+    pub fn has_attr(&self, flag: char) -> bool {
+        self.attr.as_deref().is_some_and(|attr| attr.contains(flag))
+    }
+
+    /// Filler that aligns the next file to a piece boundary; its content
+    /// is zeros and it is not written to disk.
+    pub fn is_padding(&self) -> bool {
+        self.has_attr('p')
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.has_attr('l')
+    }
+
+    pub fn is_executable(&self) -> bool {
+        self.has_attr('x')
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.has_attr('h')
+    }
+
+    /// The path as a relative `PathBuf` that stays inside the download
+    /// directory on any platform. Components must be plain names: no
+    /// separators, drive prefixes, `.`/`..`, or Windows device names.
+    pub fn safe_path(&self) -> Result<PathBuf, UnsafePath> {
+        if self.path.is_empty() {
+            return Err(UnsafePath::Empty);
+        }
+        for part in &self.path {
+            check_component(part)?;
+        }
+        Ok(self.path.iter().collect())
+    }
+}
+
+fn check_component(part: &str) -> Result<(), UnsafePath> {
+    const RESERVED: [&str; 4] = ["CON", "PRN", "AUX", "NUL"];
+
+    if matches!(part, "" | "." | "..") {
+        return Err(UnsafePath::Traversal(part.to_string()));
+    }
+    if part.contains(['/', '\\', ':', '\0']) {
+        return Err(UnsafePath::Absolute(part.to_string()));
+    }
+    // Windows ignores the extension and trailing dots or spaces here.
+    let stem = part.split('.').next().unwrap_or(part).trim_end_matches(' ');
+    let upper = stem.to_ascii_uppercase();
+    let numbered = (upper.starts_with("COM") || upper.starts_with("LPT"))
+        && upper.len() == 4
+        && matches!(upper.as_bytes()[3], b'1'..=b'9');
+    if RESERVED.contains(&upper.as_str()) || numbered {
+        return Err(UnsafePath::Reserved(part.to_string()));
+    }
+    Ok(())
+}
+
+impl Node {
+    fn from_raw(raw: &[u8]) -> Result<Node, TorrentError> {
+        match list(raw, "nodes")?[..] {
+            [host, port] => Ok(Node {
+                host: string(host, "nodes")?,
+                port: int(port, "nodes")?
+                    .try_into()
+                    .map_err(|_| TorrentError::Invalid("nodes"))?,
+            }),
+            _ => Err(TorrentError::Invalid("nodes")),
+        }
+    }
+}
+
+pub(crate) fn raw_int(raw: &[u8]) -> Option<i64> {
+    match Events::new(raw).next() {
+        Some(Ok(Event::Int(n))) => Some(n),
+        _ => None,
+    }
+}
+
+pub(crate) fn raw_bytes(raw: &[u8]) -> Option<Vec<u8>> {
+    match Events::new(raw).next() {
+        Some(Ok(Event::Str(bytes))) => Some(bytes.to_vec()),
+        _ => None,
+    }
+}
+
+/// The flattened `file tree`: one entry per `""` leaf, in key order.
+fn raw_file_tree(raw: &[u8]) -> Option<Vec<TreeEntry>> {
+    let mut events = Events::new(raw);
+    if events.next()?.ok()? != Event::DictStart {
+        return None;
+    }
+    let mut entries = Vec::new();
+    tree_dir(&mut events, &mut Vec::new(), &mut entries)?;
+    Some(entries)
+}
+
+/// Reads a directory whose `DictStart` was just consumed.
+fn tree_dir(
+    events: &mut Events<'_>,
+    path: &mut Vec<String>,
+    entries: &mut Vec<TreeEntry>,
+) -> Option<()> {
+    loop {
+        match events.next()?.ok()? {
+            Event::End => return Some(()),
+            // The tree root cannot itself be a file.
+            Event::Key(b"") if path.is_empty() => return None,
+            Event::Key(b"") => entries.push(tree_file(events, path.clone())?),
+            Event::Key(name) => {
+                path.push(std::str::from_utf8(name).ok()?.to_string());
+                if events.next()?.ok()? != Event::DictStart {
+                    return None;
+                }
+                tree_dir(events, path, entries)?;
+                path.pop();
+            }
+            _ => return None,
+        }
+    }
+}
+
+fn tree_file(events: &mut Events<'_>, path: Vec<String>) -> Option<TreeEntry> {
+    if events.next()?.ok()? != Event::DictStart {
+        return None;
+    }
+    let (mut length, mut pieces_root) = (None, None);
+    loop {
+        match events.next()?.ok()? {
+            Event::End => break,
+            Event::Key(b"length") => match events.next()?.ok()? {
+                Event::Int(n) => length = Some(u64::try_from(n).ok()?),
+                _ => return None,
+            },
+            Event::Key(b"pieces root") => match events.next()?.ok()? {
+                Event::Str(root) => pieces_root = Some(root.try_into().ok()?),
+                _ => return None,
+            },
+            Event::Key(_) => skip_events(events)?,
+            _ => return None,
+        }
+    }
+    Some(TreeEntry {
+        path,
+        length: length?,
+        pieces_root,
+    })
+}
+
+/// `piece layers`: binary 32-byte keys, each mapping to concatenated hashes.
+fn raw_piece_layers(raw: &[u8]) -> Option<HashMap<[u8; 32], Vec<[u8; 32]>>> {
+    let mut events = Events::new(raw);
+    if events.next()?.ok()? != Event::DictStart {
+        return None;
+    }
+    let mut layers = HashMap::new();
+    loop {
+        let root = match events.next()?.ok()? {
+            Event::End => return Some(layers),
+            Event::Key(key) => key.try_into().ok()?,
+            _ => return None,
+        };
+        let Event::Str(layer) = events.next()?.ok()? else {
+            return None;
+        };
+        layers.insert(root, hashes(layer)?);
+    }
+}
+
+/// Consumes one value, however deeply nested.
+pub(crate) fn skip_events(events: &mut Events<'_>) -> Option<()> {
+    let depth = events.depth();
+    loop {
+        events.next()?.ok()?;
+        if events.depth() == depth {
+            return Some(());
+        }
+    }
+}
+
+/// Splits concatenated `N`-byte hashes; `None` if the length is not a multiple.
+fn hashes<const N: usize>(bytes: &[u8]) -> Option<Vec<[u8; N]>> {
+    if !bytes.len().is_multiple_of(N) {
+        return None;
+    }
+    bytes
+        .chunks_exact(N)
+        .map(|hash| hash.try_into().ok())
+        .collect()
+}
+
+/// Scans the dict at the start of `input`, returning it and its end.
+fn dict_prefix<'a>(
+    input: &'a [u8],
+    field: &'static str,
+) -> Result<(Dict<'a>, usize), TorrentError> {
+    let mut events = Events::new(input);
+    if events.expect_next()? != Event::DictStart {
+        return Err(TorrentError::Invalid(field));
+    }
+    let mut dict = HashMap::new();
+    loop {
+        match events.expect_next()? {
+            Event::End => return Ok((dict, events.offset())),
+            Event::Key(key) => {
+                let start = events.offset();
+                events.skip_value()?;
+                dict.insert(key, &input[start..events.offset()]);
+            }
+            _ => return Err(TorrentError::Invalid(field)),
+        }
+    }
+}
+
+fn dict<'a>(raw: &'a [u8], field: &'static str) -> Result<Dict<'a>, TorrentError> {
+    Ok(dict_prefix(raw, field)?.0)
+}
+
+/// The raw bytes of each item of a list.
+fn list<'a>(raw: &'a [u8], field: &'static str) -> Result<Vec<&'a [u8]>, TorrentError> {
+    let mut events = Events::new(raw);
+    if events.expect_next()? != Event::ListStart {
+        return Err(TorrentError::Invalid(field));
+    }
+    let mut items = Vec::new();
+    loop {
+        let start = events.offset();
+        if raw.get(start) == Some(&DELIM_END) {
+            return Ok(items);
+        }
+        events.skip_value()?;
+        items.push(&raw[start..events.offset()]);
+    }
+}
+
+/// A list of path components, with spellings chosen as for `text`: the
+/// first list of valid UTF-8 (`path.utf-8` before `path`), else the first
+/// present decoded lossily.
+fn components(
+    dict: &Dict<'_>,
+    key: &'static str,
+    field: &'static str,
+) -> Result<Option<Vec<String>>, TorrentError> {
+    let mut variants = Vec::new();
+    for k in ALIASES.candidates(key) {
+        if let Some(raw) = get(dict, k) {
+            let parts = list(raw, field)?
+                .into_iter()
+                .map(|part| bytes(part, field))
+                .collect::<Result<Vec<_>, _>>()?;
+            variants.push(parts);
+        }
+    }
+    let utf8 = |parts: &&Vec<&[u8]>| parts.iter().all(|p| std::str::from_utf8(p).is_ok());
+    Ok(variants
+        .iter()
+        .find(utf8)
+        .or(variants.first())
+        .map(|parts| {
+            parts
+                .iter()
+                .map(|part| String::from_utf8_lossy(part).into_owned())
+                .collect()
+        }))
+}
+
+fn get<'a>(dict: &Dict<'a>, key: &str) -> Option<&'a [u8]> {
+    dict.get(key.as_bytes()).copied()
+}
+
+fn required<'a>(dict: &Dict<'a>, key: &str, field: &'static str) -> Result<&'a [u8], TorrentError> {
+    get(dict, key).ok_or(TorrentError::Missing(field))
+}
+
+fn bytes<'a>(raw: &'a [u8], field: &'static str) -> Result<&'a [u8], TorrentError> {
+    match Events::new(raw).next() {
+        Some(Ok(Event::Str(bytes))) => Ok(bytes),
+        _ => Err(TorrentError::Invalid(field)),
+    }
+}
+
+fn string(raw: &[u8], field: &'static str) -> Result<String, TorrentError> {
+    String::from_utf8(bytes(raw, field)?.to_vec()).map_err(|_| TorrentError::Invalid(field))
+}
+
+/// A non-negative integer.
+fn int(raw: &[u8], field: &'static str) -> Result<u64, TorrentError> {
+    raw_int(raw)
+        .and_then(|n| u64::try_from(n).ok())
+        .ok_or(TorrentError::Invalid(field))
+}
+
+/// Free text such as `comment`, which older tools wrote in the local
+/// encoding. Of the spellings `Aliases::torrent` knows for `key`, the first
+/// present that is valid UTF-8 wins (so `<key>.utf-8` is preferred), else
+/// the first present decoded lossily.
+fn text(
+    dict: &Dict<'_>,
+    key: &'static str,
+    field: &'static str,
+) -> Result<Option<String>, TorrentError> {
+    let mut variants = Vec::new();
+    for k in ALIASES.candidates(key) {
+        if let Some(raw) = get(dict, k) {
+            variants.push(bytes(raw, field)?);
+        }
+    }
+    if let Some(text) = variants.iter().find_map(|b| std::str::from_utf8(b).ok()) {
+        return Ok(Some(text.to_string()));
+    }
+    Ok(variants
+        .first()
+        .map(|bytes| String::from_utf8_lossy(bytes).into_owned()))
+}
+
+/// Seconds since the Unix epoch, which may be negative.
+fn unix_time(secs: i64) -> Option<SystemTime> {
+    let offset = Duration::from_secs(secs.unsigned_abs());
+    if secs < 0 {
+        UNIX_EPOCH.checked_sub(offset)
+    } else {
+        UNIX_EPOCH.checked_add(offset)
+    }
+}
+
+/// A list of URLs, or one URL given as a bare string as BEP 19 allows.
+/// Blank entries, such as the `url-list` of `""` some tools write, are
+/// dropped.
+fn urls_or_url(raw: &[u8], field: &'static str) -> Result<Vec<String>, TorrentError> {
+    let urls = match bytes(raw, field) {
+        Ok(_) => vec![string(raw, field)?],
+        Err(_) => list(raw, field)?
+            .into_iter()
+            .map(|url| string(url, field))
+            .collect::<Result<_, _>>()?,
+    };
+    Ok(urls
+        .into_iter()
+        .filter(|url: &String| !url.trim().is_empty())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn torrent(info: &[u8]) -> Vec<u8> {
+        let mut out =
+            b"d8:announce9:udp://t/113:announce-listll9:udp://t/1el9:udp://t/2ee4:info".to_vec();
+        out.extend_from_slice(info);
+        out.push(b'e');
+        out
+    }
+
+    #[test]
+    fn test_single_file() {
+        let mut info =
+            b"d6:lengthi5000000000e4:name5:a.iso12:piece lengthi262144e6:pieces40:".to_vec();
+        info.extend((0..40).map(|i| 0xff - i as u8));
+        info.push(b'e');
+
+        let meta = MetaInfo::from_bytes(&torrent(&info)).unwrap();
+        assert_eq!(meta.announce.as_deref(), Some("udp://t/1"));
+        assert_eq!(
+            meta.announce_list,
+            vec![vec!["udp://t/1"], vec!["udp://t/2"]]
+        );
+        assert_eq!(meta.info.name, "a.iso");
+        assert_eq!(meta.info.piece_length, 262144);
+        assert_eq!(meta.info.pieces.len(), 2);
+        assert_eq!(meta.info.pieces[1][0], 0xff - 20);
+        assert_eq!(
+            meta.info.files,
+            Some(Files::Single {
+                length: 5_000_000_000
+            })
+        );
+        assert!(meta.info.is_v1() && !meta.info.is_v2());
+        assert_eq!(meta.info.total_length(), Some(5_000_000_000));
+    }
+
+    #[test]
+    fn test_multi_file() {
+        let info = b"d5:filesld6:lengthi70000e4:pathl3:dir5:a.txteed6:lengthi1e4:pathl5:b.txteee\
+                     4:name4:root12:piece lengthi16384e6:pieces0:e";
+        let meta = MetaInfo::from_bytes(&torrent(info)).unwrap();
+        assert_eq!(
+            meta.info.files,
+            Some(Files::Multi(vec![
+                FileEntry {
+                    length: 70000,
+                    path: vec!["dir".to_string(), "a.txt".to_string()],
+                    attr: None,
+                    sha1: None,
+                    symlink_path: None,
+                },
+                FileEntry {
+                    length: 1,
+                    path: vec!["b.txt".to_string()],
+                    attr: None,
+                    sha1: None,
+                    symlink_path: None,
+                },
+            ]))
+        );
+        assert_eq!(meta.info.total_length(), Some(70001));
+        assert_eq!(meta.info.file_list().len(), 2);
+    }
+
+    #[test]
+    fn test_unknown_and_legacy_fields() {
+        // Keys this module does not read may hold anything.
+        let info = b"d5:filesld6:lengthi1e5:mtimei1700000000e4:pathl1:\xe9eee\
+                     4:name3:\xc4\xe3!12:piece lengthi1e6:pieces0:6:sourcei99999ee";
+        let mut input = torrent(info);
+        input.pop();
+        input.extend_from_slice(b"4:hash2:\x00\xff5:ratioi100000ee");
+        let meta = MetaInfo::from_bytes(&input).unwrap();
+        assert_eq!(meta.info.name, "\u{fffd}\u{fffd}!");
+        let Some(Files::Multi(files)) = &meta.info.files else {
+            panic!("{:?}", meta.info.files);
+        };
+        assert_eq!(files[0].path, ["\u{fffd}"]);
+        assert_eq!(meta.info_bytes(), info);
+
+        // The `.utf-8` spellings win over the legacy ones.
+        let info = b"d5:filesld6:lengthi1e4:pathl1:\xe9e10:path.utf-8l2:\xc3\xa9eee\
+                     4:name1:\xe910:name.utf-82:\xc3\xa912:piece lengthi1e6:pieces0:e";
+        let meta = MetaInfo::from_bytes(&torrent(info)).unwrap();
+        assert_eq!(meta.info.name, "\u{e9}");
+        assert_eq!(meta.info.file_list()[0].path, ["\u{e9}"]);
+    }
+
+    #[test]
+    fn test_split_info() {
+        let info = b"d6:lengthi1e4:name1:a12:piece lengthi1e6:pieces0:e";
+        let meta = MetaInfo::from_bytes(&torrent(info)).unwrap();
+        let (taken, rest) = meta.clone().split_info();
+        assert_eq!(taken, meta.info);
+        assert_eq!(rest.info, Info::default());
+        assert_eq!(rest.announce_list, meta.announce_list);
+        assert_eq!(rest.info_bytes(), info);
+    }
+
+    #[test]
+    fn test_tracker_tiers() {
+        let info = b"d6:lengthi1e4:name1:a12:piece lengthi1e6:pieces0:e";
+        let mut meta = MetaInfo::from_bytes(&torrent(info)).unwrap();
+        assert_eq!(meta.tiers(), [["udp://t/1"], ["udp://t/2"]]);
+
+        meta.announce = Some("http://a".to_string());
+        meta.announce_list = vec![
+            vec!["udp://b".to_string(), " udp://c ".to_string()],
+            vec!["udp://c".to_string(), String::new()],
+            vec!["udp://d".to_string(), "udp://b".to_string()],
+        ];
+        assert_eq!(
+            meta.tiers(),
+            [
+                vec!["udp://b", "udp://c"],
+                vec!["udp://d"],
+                vec!["http://a"]
+            ]
+        );
+        assert_eq!(
+            meta.trackers().collect::<Vec<_>>(),
+            ["udp://b", "udp://c", "udp://d", "http://a"]
+        );
+
+        meta.announce_list.clear();
+        assert_eq!(meta.trackers().collect::<Vec<_>>(), ["http://a"]);
+        meta.announce = None;
+        assert_eq!(meta.trackers().count(), 0);
+    }
+
+    #[test]
+    fn test_web_seeds() {
+        let info: &[u8] = b"d6:lengthi1e4:name1:a12:piece lengthi1e6:pieces0:e";
+        let with = |extra: &[u8]| {
+            let mut input = torrent(info);
+            input.pop();
+            input.extend_from_slice(extra);
+            input.push(b'e');
+            MetaInfo::from_bytes(&input)
+        };
+
+        let meta = with(b"").unwrap();
+        assert!(meta.url_list.is_empty() && meta.http_seeds.is_empty());
+
+        let meta = with(b"8:url-list8:http://w").unwrap();
+        assert_eq!(meta.url_list, ["http://w"]);
+        let meta = with(b"8:url-list0:").unwrap();
+        assert!(meta.url_list.is_empty());
+        let meta = with(b"9:httpseedsl8:http://a0:8:http://be").unwrap();
+        assert_eq!(meta.http_seeds, ["http://a", "http://b"]);
+
+        assert_eq!(
+            with(b"8:url-listi1e"),
+            Err(TorrentError::Invalid("url-list"))
+        );
+        assert_eq!(
+            with(b"8:url-listli1ee"),
+            Err(TorrentError::Invalid("url-list"))
+        );
+    }
+
+    #[test]
+    fn test_metadata() {
+        let mut input = b"d7:comment4:\xc4\xe3\xba\xc3".to_vec();
+        input.extend_from_slice(b"10:created by9:mktorrent13:creation datei1700000000e4:info");
+        input.extend_from_slice(b"d6:lengthi1e4:name1:a12:piece lengthi1e6:pieces0:7:privatei1eee");
+        let meta = MetaInfo::from_bytes(&input).unwrap();
+        assert_eq!(meta.comment.as_deref(), Some("\u{fffd}\u{fffd}\u{fffd}"));
+        assert_eq!(meta.created_by.as_deref(), Some("mktorrent"));
+        assert_eq!(
+            meta.creation_date,
+            Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+        assert!(meta.is_private());
+
+        // The UTF-8 variant wins over the legacy one.
+        let mut input = b"d7:comment2:\xc4\xe3".to_vec();
+        input.extend_from_slice(b"13:comment.utf-83:\xe4\xbd\xa013:creation datei-60e4:info");
+        input.extend_from_slice(b"d6:lengthi1e4:name1:a12:piece lengthi1e6:pieces0:7:privatei0eee");
+        let meta = MetaInfo::from_bytes(&input).unwrap();
+        assert_eq!(meta.comment.as_deref(), Some("\u{4f60}"));
+        assert_eq!(meta.created_by, None);
+        assert_eq!(
+            meta.creation_date,
+            Some(UNIX_EPOCH - Duration::from_secs(60))
+        );
+        assert!(!meta.is_private());
+
+        let mut input = b"d10:created-by4:tool4:info".to_vec();
+        input.extend_from_slice(b"d6:lengthi1e4:name1:a12:piece lengthi1e6:pieces0:ee");
+        let meta = MetaInfo::from_bytes(&input).unwrap();
+        assert_eq!(meta.created_by.as_deref(), Some("tool"));
+
+        let mut input = b"d13:creation date3:now4:info".to_vec();
+        input.extend_from_slice(b"d6:lengthi1e4:name1:a12:piece lengthi1e6:pieces0:ee");
+        assert_eq!(
+            MetaInfo::from_bytes(&input),
+            Err(TorrentError::Invalid("creation date"))
+        );
+    }
+
+    #[test]
+    fn test_nodes() {
+        let mut input = b"d4:info".to_vec();
+        input.extend_from_slice(b"d6:lengthi1e4:name1:a12:piece lengthi1e6:pieces0:e");
+        input.extend_from_slice(b"5:nodesll9:127.0.0.1i6881eel5:[::1]i80eel9:localhosti65535eeee");
+        let meta = MetaInfo::from_bytes(&input).unwrap();
+        assert_eq!(meta.nodes.len(), 3);
+        assert_eq!(
+            meta.nodes[0].socket_addr(),
+            Some("127.0.0.1:6881".parse().unwrap())
+        );
+        assert_eq!(
+            meta.nodes[1].socket_addr(),
+            Some("[::1]:80".parse().unwrap())
+        );
+        assert_eq!(
+            meta.nodes[2],
+            Node {
+                host: "localhost".to_string(),
+                port: 65535
+            }
+        );
+        assert_eq!(meta.nodes[2].socket_addr(), None);
+        let addrs: Vec<_> = meta.nodes[0].to_socket_addrs().unwrap().collect();
+        assert_eq!(addrs, ["127.0.0.1:6881".parse().unwrap()]);
+
+        for nodes in [&b"l9:127.0.0.1e"[..], b"l1:hi65536ee", b"l1:hi-1ee", b"i1e"] {
+            let mut input = b"d4:info".to_vec();
+            input.extend_from_slice(b"d6:lengthi1e4:name1:a12:piece lengthi1e6:pieces0:e");
+            input.extend_from_slice(b"5:nodesl");
+            input.extend_from_slice(nodes);
+            input.extend_from_slice(b"ee");
+            assert_eq!(
+                MetaInfo::from_bytes(&input),
+                Err(TorrentError::Invalid("nodes"))
+            );
+        }
+    }
+
+    #[test]
+    fn test_safe_path() {
+        let entry = |parts: &[&str]| FileEntry {
+            length: 0,
+            path: parts.iter().map(|p| p.to_string()).collect(),
+            attr: None,
+            sha1: None,
+            symlink_path: None,
+        };
+        assert_eq!(
+            entry(&["dir", "a.txt"]).safe_path(),
+            Ok(PathBuf::from("dir").join("a.txt"))
+        );
+        assert_eq!(
+            entry(&["cons", "COM10"]).safe_path(),
+            Ok(PathBuf::from("cons").join("COM10"))
+        );
+
+        let cases = [
+            (&[][..], UnsafePath::Empty),
+            (&["a", ".."], UnsafePath::Traversal("..".to_string())),
+            (&["", "a"], UnsafePath::Traversal(String::new())),
+            (&["/etc"], UnsafePath::Absolute("/etc".to_string())),
+            (&["a\\..\\b"], UnsafePath::Absolute("a\\..\\b".to_string())),
+            (&["C:x"], UnsafePath::Absolute("C:x".to_string())),
+            (&["aux.txt"], UnsafePath::Reserved("aux.txt".to_string())),
+            (&["Lpt3 "], UnsafePath::Reserved("Lpt3 ".to_string())),
+        ];
+        for (parts, expected) in cases {
+            assert_eq!(entry(parts).safe_path(), Err(expected));
+        }
+    }
+
+    #[test]
+    fn test_pieces() {
+        let mut blob = vec![0xaa; 20];
+        blob.extend([0xbb; 20]);
+        let mut iter = pieces(&blob).unwrap();
+        assert_eq!(iter.len(), 2);
+        assert_eq!(iter.next_back(), Some([0xbb; 20]));
+        assert_eq!(iter.next(), Some([0xaa; 20]));
+        assert_eq!(iter.next(), None);
+        assert!(pieces(&blob[..39]).is_none());
+        assert_eq!(pieces(&[]).map(|p| p.len()), Some(0));
+
+        let mut info = b"d6:lengthi1e4:name1:a12:piece lengthi1e6:pieces40:".to_vec();
+        info.extend_from_slice(&blob);
+        info.push(b'e');
+        let meta = MetaInfo::from_bytes(&torrent(&info)).unwrap();
+        assert!(meta.pieces().eq(pieces(&blob).unwrap()));
+        assert_eq!(meta.pieces().collect::<Vec<_>>(), meta.info.pieces);
+    }
+
+    #[test]
+    fn test_info_bytes_are_original() {
+        // Unsorted keys inside info, which re-encoding would reorder.
+        let info = b"d4:name1:a6:lengthi1e12:piece lengthi1e6:pieces0:e";
+        let meta = MetaInfo::from_bytes(&torrent(info)).unwrap();
+        assert_eq!(meta.info_bytes(), info);
+    }
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_info_hash() {
+        let mut info = b"d6:lengthi1e4:name1:a12:piece lengthi1e6:pieces20:".to_vec();
+        info.extend([0xff; 20]);
+        info.push(b'e');
+        let input = torrent(&info);
+
+        let expected = crate::sha1(&info);
+        assert_eq!(info_hash(&input), Ok(expected));
+        assert_eq!(info_range(b"d1:ai1e4:infoi7e1:zi0ee").unwrap(), 13..16);
+        assert_eq!(info_range(b"li1ee"), Err(TorrentError::Missing("info")));
+        assert_eq!(MetaInfo::from_bytes(&input).unwrap().info_hash(), expected);
+        assert_eq!(
+            info_hash(b"d4:infod"),
+            Err(TorrentError::Decode(DecodeError::UnexpectedEof {
+                offset: 8
+            }))
+        );
+    }
+
+    fn v2_info() -> Vec<u8> {
+        let mut info = b"d9:file treed3:dird1:ad0:d6:lengthi40000e11:pieces root32:".to_vec();
+        info.extend([1; 32]);
+        info.extend_from_slice(
+            b"eee5:emptyd0:d6:lengthi0eeee12:meta versioni2e4:name4:root12:piece lengthi16384ee",
+        );
+        info
+    }
+
+    #[test]
+    fn test_v2_file_tree_and_piece_layers() {
+        let mut input = torrent(&v2_info());
+        input.pop();
+        input.extend_from_slice(b"12:piece layersd32:");
+        input.extend([1; 32]);
+        input.extend_from_slice(b"96:");
+        input.extend([2; 32].iter().chain(&[3; 32]).chain(&[4; 32]));
+        input.extend_from_slice(b"ee");
+
+        let meta = MetaInfo::from_bytes(&input).unwrap();
+        assert!(meta.info.is_v2() && !meta.info.is_v1());
+        assert_eq!(meta.info.meta_version, 2);
+        assert_eq!(meta.info.files, None);
+        assert!(meta.info.pieces.is_empty());
+        assert_eq!(
+            meta.info.file_tree,
+            vec![
+                TreeEntry {
+                    path: vec!["dir".to_string(), "a".to_string()],
+                    length: 40000,
+                    pieces_root: Some([1; 32]),
+                },
+                TreeEntry {
+                    path: vec!["empty".to_string()],
+                    length: 0,
+                    pieces_root: None,
+                },
+            ]
+        );
+        assert_eq!(meta.info.total_length(), Some(40000));
+        assert_eq!(meta.info.file_list()[0].path, ["dir", "a"]);
+        assert_eq!(
+            meta.piece_layer(&meta.info.file_tree[0]),
+            Some(&[[2; 32], [3; 32], [4; 32]][..])
+        );
+        assert_eq!(meta.piece_layer(&meta.info.file_tree[1]), None);
+    }
+
+    #[cfg(feature = "sha256")]
+    #[test]
+    fn test_info_hash_v2() {
+        let info = v2_info();
+        let input = torrent(&info);
+        let expected = crate::sha256(&info);
+        assert_eq!(info_hash_v2(&input), Ok(expected));
+        assert_eq!(
+            MetaInfo::from_bytes(&input).unwrap().info_hash_v2(),
+            expected
+        );
+    }
+
+    /// A hybrid with `a` (40000 bytes), a padding file and `b` (1 byte).
+    fn hybrid_info(b_length: &[u8]) -> Vec<u8> {
+        let mut info = b"d9:file treed1:ad0:d6:lengthi40000e11:pieces root32:".to_vec();
+        info.extend([1; 32]);
+        info.extend_from_slice(b"ee1:bd0:d6:lengthi");
+        info.extend_from_slice(b_length);
+        info.extend_from_slice(b"e11:pieces root32:");
+        info.extend([2; 32]);
+        info.extend_from_slice(
+            b"eee5:filesld6:lengthi40000e4:pathl1:aeed4:attr1:p6:lengthi9152e\
+              4:pathl4:.pad4:9152eed6:lengthi1e4:pathl1:beee\
+              12:meta versioni2e4:name4:root12:piece lengthi16384e6:pieces80:",
+        );
+        info.extend([0; 80]);
+        info.push(b'e');
+        info
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut v2 = torrent(&v2_info());
+        v2.pop();
+        v2.extend_from_slice(b"12:piece layersd32:");
+        v2.extend([1; 32]);
+        v2.extend_from_slice(b"96:");
+        v2.extend([2; 96]);
+        v2.extend_from_slice(b"ee");
+        assert_eq!(MetaInfo::from_bytes(&v2).unwrap().validate(), Ok(()));
+
+        // `a` needs a three-hash layer and `b` differs between layouts.
+        let hybrid = MetaInfo::from_bytes(&torrent(&hybrid_info(b"2"))).unwrap();
+        let problems = hybrid.validate().unwrap_err();
+        assert_eq!(
+            problems,
+            [
+                Inconsistency::PieceLayer {
+                    path: vec!["a".to_string()],
+                    expected: 3,
+                    found: None,
+                },
+                Inconsistency::Mismatch(FileMismatch::Length {
+                    path: vec!["b".to_string()],
+                    v1: 1,
+                    v2: 2,
+                }),
+            ]
+        );
+        assert_eq!(
+            problems[0].to_string(),
+            "piece layers: no layer for \"a\", which has 3 pieces"
+        );
+
+        let mut info = b"d5:filesld6:lengthi10e4:pathl1:aeed6:lengthi1e4:pathl1:aeee\
+                         4:name1:n12:piece lengthi4e6:pieces20:"
+            .to_vec();
+        info.extend([0; 20]);
+        info.push(b'e');
+        let meta = MetaInfo::from_bytes(&torrent(&info)).unwrap();
+        assert_eq!(
+            meta.validate(),
+            Err(vec![
+                Inconsistency::PieceCount {
+                    expected: 3,
+                    found: 1,
+                },
+                Inconsistency::DuplicatePath {
+                    path: vec!["a".to_string()],
+                },
+            ])
+        );
+
+        let mut info = b"d5:filesl".to_vec();
+        for name in ["a", "b", "c"] {
+            let file = format!("d6:lengthi{}e4:pathl1:{}ee", i64::MAX, name);
+            info.extend_from_slice(file.as_bytes());
+        }
+        info.extend_from_slice(b"e4:name1:n12:piece lengthi4e6:pieces0:e");
+        let meta = MetaInfo::from_bytes(&torrent(&info)).unwrap();
+        let problems = meta.validate().unwrap_err();
+        assert_eq!(problems, [Inconsistency::TotalLengthOverflow]);
+        assert_eq!(
+            problems[0].to_string(),
+            "info.files: total length does not fit in 64 bits"
+        );
+    }
+
+    #[test]
+    fn test_pieces_map_past_padding() {
+        let meta = MetaInfo::from_bytes(&torrent(&hybrid_info(b"1"))).unwrap();
+        let offsets: Vec<(u64, Vec<String>)> = meta
+            .info
+            .file_offsets()
+            .map(|(offset, file)| (offset, file.path))
+            .collect();
+        assert_eq!(
+            offsets,
+            [(0, vec!["a".to_string()]), (49152, vec!["b".to_string()])]
+        );
+
+        let paths = |index| -> Vec<(String, Range<u64>)> {
+            meta.info
+                .piece_files(index)
+                .into_iter()
+                .map(|(file, range)| (file.path.join("/"), range))
+                .collect()
+        };
+        assert_eq!(paths(0), [("a".to_string(), 0..16384)]);
+        // The tail of `a`; the rest of the piece is padding.
+        assert_eq!(paths(2), [("a".to_string(), 32768..40000)]);
+        assert_eq!(paths(3), [("b".to_string(), 0..1)]);
+        assert_eq!(paths(4), []);
+
+        let file = FileEntry {
+            length: 0,
+            path: vec!["run".to_string()],
+            attr: Some("xh".to_string()),
+            sha1: None,
+            symlink_path: None,
+        };
+        assert!(file.is_executable() && file.is_hidden());
+        assert!(!file.is_symlink() && !file.is_padding());
+    }
+
+    #[test]
+    fn test_lengths_that_overflow() {
+        let mut info = b"d5:filesl".to_vec();
+        for name in ["a", "b", "c"] {
+            let file = format!("d6:lengthi{}e4:pathl1:{}ee", i64::MAX, name);
+            info.extend_from_slice(file.as_bytes());
+        }
+        info.extend_from_slice(b"e4:name1:n12:piece lengthi4e6:pieces20:");
+        info.extend([0; 20]);
+        info.push(b'e');
+        let meta = MetaInfo::from_bytes(&torrent(&info)).unwrap();
+        assert_eq!(meta.info.total_length(), None);
+        let offsets: Vec<u64> = meta.info.file_offsets().map(|(offset, _)| offset).collect();
+        assert_eq!(offsets, [0, i64::MAX as u64, u64::MAX - 1]);
+        let files: Vec<(Vec<String>, Range<u64>)> = meta
+            .info
+            .piece_files(0)
+            .into_iter()
+            .map(|(file, range)| (file.path, range))
+            .collect();
+        assert_eq!(files, [(vec!["a".to_string()], 0..4)]);
+        #[cfg(feature = "sha1")]
+        assert_eq!(meta.magnet().length, None);
+    }
+
+    #[test]
+    fn test_bep47_file_fields() {
+        let mut info = b"d5:filesld6:lengthi3e4:pathl1:ae4:sha120:".to_vec();
+        info.extend([0xff; 20]);
+        info.extend_from_slice(
+            b"ed4:attr1:l6:lengthi0e4:pathl4:linke12:symlink pathl1:aeee\
+              4:name1:r12:piece lengthi4e6:pieces0:e",
+        );
+        let meta = MetaInfo::from_bytes(&torrent(&info)).unwrap();
+        let Some(Files::Multi(files)) = &meta.info.files else {
+            panic!("{:?}", meta.info.files);
+        };
+        assert_eq!(files[0].sha1, Some([0xff; 20]));
+        assert_eq!(files[0].symlink_path, None);
+        assert!(files[1].is_symlink());
+        assert_eq!(
+            files[1].symlink_path.as_deref(),
+            Some(&["a".to_string()][..])
+        );
+
+        let info = b"d5:filesld6:lengthi3e4:pathl1:ae4:sha12:\xff\xffee\
+                     4:name1:r12:piece lengthi4e6:pieces0:e";
+        assert_eq!(
+            MetaInfo::from_bytes(&torrent(info)),
+            Err(TorrentError::Invalid("info.files.sha1"))
+        );
+    }
+
+    #[test]
+    fn test_hybrid() {
+        let meta = MetaInfo::from_bytes(&torrent(&hybrid_info(b"1"))).unwrap();
+        assert_eq!(meta.info.version(), TorrentVersion::Hybrid);
+        assert!(matches!(&meta.info.files, Some(Files::Multi(files)) if files[1].is_padding()));
+        assert_eq!(meta.info.file_mismatches(), []);
+
+        let files = meta.info.file_list();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[1].path, ["b"]);
+        assert_eq!(meta.info.total_length(), Some(40001));
+
+        let meta = MetaInfo::from_bytes(&torrent(&hybrid_info(b"2"))).unwrap();
+        let mismatches = meta.info.file_mismatches();
+        assert_eq!(
+            mismatches,
+            [FileMismatch::Length {
+                path: vec!["b".to_string()],
+                v1: 1,
+                v2: 2,
+            }]
+        );
+        assert_eq!(
+            mismatches[0].to_string(),
+            "\"b\" is 1 bytes in v1 but 2 in v2"
+        );
+    }
+
+    #[test]
+    fn test_hybrid_path_and_count_mismatches() {
+        let mut info = b"d9:file treed1:ad0:d6:lengthi1eee1:bd0:d6:lengthi1eeee".to_vec();
+        info.extend_from_slice(b"5:filesld6:lengthi1e4:pathl1:beee");
+        info.extend_from_slice(b"12:meta versioni2e4:name1:r12:piece lengthi16384e6:pieces0:e");
+        let meta = MetaInfo::from_bytes(&torrent(&info)).unwrap();
+        assert_eq!(
+            meta.info.file_mismatches(),
+            [
+                FileMismatch::Path {
+                    index: 0,
+                    v1: vec!["b".to_string()],
+                    v2: vec!["a".to_string()],
+                },
+                FileMismatch::Count { v1: 1, v2: 2 },
+            ]
+        );
+
+        let v1 = MetaInfo::from_bytes(&torrent(
+            b"d6:lengthi1e4:name1:a12:piece lengthi1e6:pieces0:e",
+        ))
+        .unwrap();
+        assert_eq!(v1.info.version(), TorrentVersion::V1);
+        assert_eq!(v1.info.file_mismatches(), []);
+    }
+
+    #[cfg(all(feature = "sha1", feature = "sha256"))]
+    #[test]
+    fn test_info_hashes() {
+        let info = hybrid_info(b"1");
+        let hashes = MetaInfo::from_bytes(&torrent(&info)).unwrap().info_hashes();
+        assert_eq!(hashes.v1, Some(crate::sha1(&info)));
+        assert_eq!(hashes.v2, Some(crate::sha256(&info)));
+
+        let hashes = MetaInfo::from_bytes(&torrent(&v2_info()))
+            .unwrap()
+            .info_hashes();
+        assert_eq!(hashes.v1, None);
+        assert!(hashes.v2.is_some());
+    }
+
+    #[test]
+    fn test_v2_errors() {
+        let cases: [(&[u8], TorrentError); 4] = [
+            (
+                b"d12:meta versioni2e4:name1:a12:piece lengthi16384ee",
+                TorrentError::Missing("info.file tree"),
+            ),
+            (
+                b"d9:file treed1:ad0:d6:lengthi1eeee12:meta versioni3e4:name1:a12:piece lengthi16384ee",
+                TorrentError::Invalid("info.meta version"),
+            ),
+            (
+                b"d9:file treed1:ad0:d6:lengthi1eeee12:meta versioni2e4:name1:a12:piece lengthi1000ee",
+                TorrentError::Invalid("info.piece length"),
+            ),
+            (
+                b"d9:file treed1:ad0:d6:lengthi1e11:pieces root3:abceee12:meta versioni2e4:name1:a12:piece lengthi16384ee",
+                TorrentError::Invalid("info.file tree"),
+            ),
+        ];
+        for (info, expected) in cases {
+            assert_eq!(MetaInfo::from_bytes(&torrent(info)), Err(expected));
+        }
+
+        let mut input = torrent(&v2_info());
+        input.pop();
+        input.extend_from_slice(b"12:piece layersd3:abc3:xyzee");
+        assert_eq!(
+            MetaInfo::from_bytes(&input),
+            Err(TorrentError::Invalid("piece layers"))
+        );
+    }
+
+    #[test]
+    fn test_errors_name_the_field() {
+        let cases: [(&[u8], TorrentError); 5] = [
+            (
+                b"d4:name1:a12:piece lengthi1e6:pieces0:e",
+                TorrentError::Missing("info.length"),
+            ),
+            (
+                b"d6:lengthi1e4:name1:a12:piece lengthi1e6:pieces3:abce",
+                TorrentError::Invalid("info.pieces"),
+            ),
+            (
+                b"d6:lengthi-1e4:name1:a12:piece lengthi1e6:pieces0:e",
+                TorrentError::Invalid("info.length"),
+            ),
+            (
+                b"d5:filesld6:lengthi1e4:pathleee4:name1:a12:piece lengthi1e6:pieces0:e",
+                TorrentError::Invalid("info.files.path"),
+            ),
+            (
+                b"d6:lengthi1e12:piece lengthi1e6:pieces0:e",
+                TorrentError::Missing("info.name"),
+            ),
+        ];
+        for (info, expected) in cases {
+            assert_eq!(MetaInfo::from_bytes(&torrent(info)), Err(expected));
+        }
+        assert_eq!(
+            MetaInfo::from_bytes(b"de"),
+            Err(TorrentError::Missing("info"))
+        );
+        assert_eq!(
+            MetaInfo::from_bytes(b"d4:infodee1:x"),
+            Err(TorrentError::Decode(DecodeError::TrailingData {
+                offset: 10
+            }))
+        );
+    }
+}
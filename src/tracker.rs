@@ -0,0 +1,629 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::time::Duration;
+
+use crate::torrent::{raw_bytes, raw_int, skip_events};
+use crate::{
+    DecodeError, Encoder, Event, Events, compact_peers_v4, compact_peers_v6,
+    encode_compact_peers_v4, encode_compact_peers_v6,
+};
+
+// Client side of the HTTP tracker protocol (BEP 3): typed views of the
+// bencoded bodies a tracker sends back. Peer lists come either as a list
+// of dicts or packed into one binary string (BEP 23); both end up as the
+// same `Peer` values. IPv6 peers may also arrive packed under `peers6`
+// (BEP 7). Scrape responses (BEP 48) are keyed by raw infohashes. The
+// top-level dict is walked as events and only known keys are read, from
+// their raw bytes, so extensions such as BEP 24's binary `external ip` or
+// large vendor counters never get in the way.
+//
+// A `failure reason` is returned as `TrackerError::Failure` rather than as
+// a response, so callers can tell a tracker that answered "no" from a body
+// that could not be read. A `warning message` comes with the response.
+
+/// A peer from an announce response. Only the dictionary model carries
+/// peer IDs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Peer {
+    pub addr: SocketAddr,
+    pub id: Option<[u8; 20]>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AnnounceResponse {
+    pub warning_message: Option<String>,
+    /// How long to wait before the next regular announce.
+    pub interval: Duration,
+    pub min_interval: Option<Duration>,
+    /// Seeders.
+    pub complete: Option<u64>,
+    /// Leechers.
+    pub incomplete: Option<u64>,
+    /// To be sent back on later announces.
+    pub tracker_id: Option<Vec<u8>>,
+    /// The `peers` key, in either model.
+    pub peers: Vec<Peer>,
+    /// The compact `peers6` key.
+    pub peers6: Vec<SocketAddrV6>,
+}
+
+/// Swarm counts for one torrent in a scrape response. Counts the tracker
+/// left out are zero.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScrapeStats {
+    /// Seeders.
+    pub complete: u64,
+    /// Completed downloads so far.
+    pub downloaded: u64,
+    /// Leechers.
+    pub incomplete: u64,
+    /// The torrent's name, which some trackers include.
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScrapeResponse {
+    pub warning_message: Option<String>,
+    /// Stats by infohash.
+    pub files: HashMap<[u8; 20], ScrapeStats>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrackerError {
+    /// The tracker refused the request with this `failure reason`.
+    Failure(String),
+    Decode(DecodeError),
+    /// A required field is absent.
+    Missing(&'static str),
+    /// A field has the wrong type or an impossible value.
+    Invalid(&'static str),
+}
+
+impl fmt::Display for TrackerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrackerError::Failure(reason) => write!(f, "tracker failure: {}", reason),
+            TrackerError::Decode(e) => e.fmt(f),
+            TrackerError::Missing(field) => write!(f, "missing field {:?}", field),
+            TrackerError::Invalid(field) => write!(f, "invalid field {:?}", field),
+        }
+    }
+}
+
+impl std::error::Error for TrackerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TrackerError::Decode(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<DecodeError> for TrackerError {
+    fn from(e: DecodeError) -> Self {
+        TrackerError::Decode(e)
+    }
+}
+
+impl AnnounceResponse {
+    /// Parses the body of an announce response.
+    pub fn from_bytes(input: &[u8]) -> Result<AnnounceResponse, TrackerError> {
+        let root = root_fields(
+            input,
+            &[
+                "failure reason",
+                "warning message",
+                "interval",
+                "min interval",
+                "complete",
+                "incomplete",
+                "tracker id",
+                "peers",
+                "peers6",
+            ],
+        )?;
+        let field = |key: &'static str| root.get(key).map(|&raw| (raw, key));
+
+        if let Some(reason) = field("failure reason") {
+            return Err(TrackerError::Failure(text(reason)?));
+        }
+        let mut response = AnnounceResponse {
+            warning_message: field("warning message").map(text).transpose()?,
+            ..AnnounceResponse::default()
+        };
+
+        let (interval, key) = field("interval").ok_or(TrackerError::Missing("interval"))?;
+        response.interval = Duration::from_secs(int((interval, key))?);
+        response.min_interval = field("min interval")
+            .map(int)
+            .transpose()?
+            .map(Duration::from_secs);
+        response.complete = field("complete").map(int).transpose()?;
+        response.incomplete = field("incomplete").map(int).transpose()?;
+        response.tracker_id = field("tracker id")
+            .map(|(raw, key)| raw_bytes(raw).ok_or(TrackerError::Invalid(key)))
+            .transpose()?;
+        if let Some((peers, key)) = field("peers") {
+            response.peers = raw_peers(peers).ok_or(TrackerError::Invalid(key))?;
+        }
+        if let Some((peers6, key)) = field("peers6") {
+            response.peers6 = raw_bytes(peers6)
+                .and_then(|bytes| compact_peers_v6(&bytes))
+                .ok_or(TrackerError::Invalid(key))?;
+        }
+        Ok(response)
+    }
+
+    /// Every peer from `peers` and then `peers6`.
+    pub fn all_peers(&self) -> impl Iterator<Item = Peer> + '_ {
+        let v6 = self.peers6.iter().map(|&addr| Peer {
+            addr: addr.into(),
+            id: None,
+        });
+        self.peers.iter().copied().chain(v6)
+    }
+
+    /// Encodes the response as a tracker would send it. Peers are packed
+    /// into the compact form unless one of them needs the dictionary model
+    /// for its peer ID or IPv6 address.
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        Encoder::to_vec(|enc| {
+            enc.begin_dict()?;
+            if let Some(complete) = self.complete {
+                enc.key(b"complete")?.int(complete as i64)?;
+            }
+            if let Some(incomplete) = self.incomplete {
+                enc.key(b"incomplete")?.int(incomplete as i64)?;
+            }
+            enc.key(b"interval")?.int(self.interval.as_secs() as i64)?;
+            if let Some(min_interval) = self.min_interval {
+                enc.key(b"min interval")?
+                    .int(min_interval.as_secs() as i64)?;
+            }
+            let compact: Option<Vec<SocketAddrV4>> = self
+                .peers
+                .iter()
+                .map(|peer| match (peer.addr, peer.id) {
+                    (SocketAddr::V4(addr), None) => Some(addr),
+                    _ => None,
+                })
+                .collect();
+            match compact {
+                Some(addrs) => {
+                    enc.key(b"peers")?.bytes(&encode_compact_peers_v4(&addrs))?;
+                }
+                None => {
+                    enc.key(b"peers")?.begin_list()?;
+                    for peer in &self.peers {
+                        enc.begin_dict()?;
+                        enc.key(b"ip")?.str(&peer.addr.ip().to_string())?;
+                        if let Some(id) = &peer.id {
+                            enc.key(b"peer id")?.bytes(id)?;
+                        }
+                        enc.key(b"port")?.int(i64::from(peer.addr.port()))?;
+                        enc.end()?;
+                    }
+                    enc.end()?;
+                }
+            }
+            if !self.peers6.is_empty() {
+                enc.key(b"peers6")?
+                    .bytes(&encode_compact_peers_v6(&self.peers6))?;
+            }
+            if let Some(id) = &self.tracker_id {
+                enc.key(b"tracker id")?.bytes(id)?;
+            }
+            if let Some(warning) = &self.warning_message {
+                enc.key(b"warning message")?.str(warning)?;
+            }
+            enc.end()?;
+            Ok(())
+        })
+    }
+}
+
+impl ScrapeResponse {
+    /// Parses the body of a scrape response.
+    pub fn from_bytes(input: &[u8]) -> Result<ScrapeResponse, TrackerError> {
+        let root = root_fields(input, &["failure reason", "warning message", "files"])?;
+        let field = |key: &'static str| root.get(key).map(|&raw| (raw, key));
+
+        if let Some(reason) = field("failure reason") {
+            return Err(TrackerError::Failure(text(reason)?));
+        }
+        let (files, key) = field("files").ok_or(TrackerError::Missing("files"))?;
+        Ok(ScrapeResponse {
+            warning_message: field("warning message").map(text).transpose()?,
+            files: raw_files(files).ok_or(TrackerError::Invalid(key))?,
+        })
+    }
+
+    pub fn get(&self, info_hash: &[u8; 20]) -> Option<&ScrapeStats> {
+        self.files.get(info_hash)
+    }
+}
+
+/// The body a tracker sends to refuse a request.
+pub fn encode_failure(reason: &str) -> io::Result<Vec<u8>> {
+    Encoder::to_vec(|enc| {
+        enc.begin_dict()?.key(b"failure reason")?.str(reason)?.end()?;
+        Ok(())
+    })
+}
+
+/// The raw value of each of `keys` in the top-level dict of `input`.
+/// Other values are skipped without being interpreted.
+fn root_fields<'a>(
+    input: &'a [u8],
+    keys: &[&'static str],
+) -> Result<HashMap<&'static str, &'a [u8]>, TrackerError> {
+    if input.is_empty() {
+        return Err(DecodeError::EmptyInput.into());
+    }
+    let mut events = Events::new(input);
+    if events.expect_next()? != Event::DictStart {
+        return Err(TrackerError::Invalid(""));
+    }
+    let mut fields = HashMap::new();
+    loop {
+        match events.expect_next()? {
+            Event::End => break,
+            Event::Key(key) => {
+                let start = events.offset();
+                events.skip_value()?;
+                if let Some(&known) = keys.iter().find(|known| known.as_bytes() == key) {
+                    fields.insert(known, &input[start..events.offset()]);
+                }
+            }
+            _ => return Err(TrackerError::Invalid("")),
+        }
+    }
+    if events.offset() < input.len() {
+        return Err(DecodeError::TrailingData {
+            offset: events.offset(),
+        }
+        .into());
+    }
+    Ok(fields)
+}
+
+/// Free text; trackers are not always careful about UTF-8.
+fn text((raw, key): (&[u8], &'static str)) -> Result<String, TrackerError> {
+    raw_bytes(raw)
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+        .ok_or(TrackerError::Invalid(key))
+}
+
+fn int((raw, key): (&[u8], &'static str)) -> Result<u64, TrackerError> {
+    raw_int(raw)
+        .and_then(|n| u64::try_from(n).ok())
+        .ok_or(TrackerError::Invalid(key))
+}
+
+/// `peers` in either model.
+fn raw_peers(raw: &[u8]) -> Option<Vec<Peer>> {
+    let mut events = Events::new(raw);
+    match events.next()?.ok()? {
+        Event::Str(compact) => Some(
+            compact_peers_v4(compact)?
+                .into_iter()
+                .map(|addr| Peer {
+                    addr: addr.into(),
+                    id: None,
+                })
+                .collect(),
+        ),
+        Event::ListStart => {
+            let mut peers = Vec::new();
+            loop {
+                match events.next()?.ok()? {
+                    Event::End => return Some(peers),
+                    Event::DictStart => peers.extend(dict_peer(&mut events)?),
+                    _ => return None,
+                }
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The scrape `files` dict. A key that is not a 20-byte hash makes the
+/// whole dict invalid.
+fn raw_files(raw: &[u8]) -> Option<HashMap<[u8; 20], ScrapeStats>> {
+    let mut events = Events::new(raw);
+    if events.next()?.ok()? != Event::DictStart {
+        return None;
+    }
+    let mut files = HashMap::new();
+    loop {
+        let hash: [u8; 20] = match events.next()?.ok()? {
+            Event::End => return Some(files),
+            Event::Key(key) => key.try_into().ok()?,
+            _ => return None,
+        };
+        if events.next()?.ok()? != Event::DictStart {
+            return None;
+        }
+        files.insert(hash, scrape_stats(&mut events)?);
+    }
+}
+
+/// One entry of `files` whose `DictStart` was just consumed.
+fn scrape_stats(events: &mut Events<'_>) -> Option<ScrapeStats> {
+    let mut stats = ScrapeStats::default();
+    loop {
+        let count = match events.next()?.ok()? {
+            Event::End => return Some(stats),
+            Event::Key(b"complete") => &mut stats.complete,
+            Event::Key(b"downloaded") => &mut stats.downloaded,
+            Event::Key(b"incomplete") => &mut stats.incomplete,
+            Event::Key(b"name") => {
+                match events.next()?.ok()? {
+                    Event::Str(name) => {
+                        stats.name = Some(String::from_utf8_lossy(name).into_owned())
+                    }
+                    _ => return None,
+                }
+                continue;
+            }
+            Event::Key(_) => {
+                skip_events(events)?;
+                continue;
+            }
+            _ => return None,
+        };
+        match events.next()?.ok()? {
+            Event::Int(n) => *count = u64::try_from(n).ok()?,
+            _ => return None,
+        }
+    }
+}
+
+/// One dictionary-model peer whose `DictStart` was just consumed.
+/// `Some(None)` if its `ip` is a host name rather than an address.
+fn dict_peer(events: &mut Events<'_>) -> Option<Option<Peer>> {
+    let (mut ip, mut port, mut id) = (None, None, None);
+    loop {
+        match events.next()?.ok()? {
+            Event::End => break,
+            Event::Key(b"ip") => match events.next()?.ok()? {
+                Event::Str(host) => ip = Some(std::str::from_utf8(host).ok()?.to_string()),
+                _ => return None,
+            },
+            Event::Key(b"port") => match events.next()?.ok()? {
+                Event::Int(n) => port = Some(u16::try_from(n).ok()?),
+                _ => return None,
+            },
+            // IDs of any other length are dropped, not rejected.
+            Event::Key(b"peer id") => match events.next()?.ok()? {
+                Event::Str(bytes) => id = bytes.try_into().ok(),
+                _ => return None,
+            },
+            Event::Key(_) => skip_events(events)?,
+            _ => return None,
+        }
+    }
+    let port = port?;
+    Some(ip?.parse::<IpAddr>().ok().map(|ip| Peer {
+        addr: SocketAddr::new(ip, port),
+        id,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compact_peers() {
+        let input = b"d8:completei5e10:incompletei3e8:intervali1800e12:min intervali60e\
+                      5:peers12:\x7f\x00\x00\x01\x1a\xe1\x0a\x00\x00\x02\x00\x50e";
+        let response = AnnounceResponse::from_bytes(input).unwrap();
+        assert_eq!(response.interval, Duration::from_secs(1800));
+        assert_eq!(response.min_interval, Some(Duration::from_secs(60)));
+        assert_eq!(response.complete, Some(5));
+        assert_eq!(response.incomplete, Some(3));
+        assert_eq!(
+            response.peers,
+            [
+                Peer {
+                    addr: "127.0.0.1:6881".parse().unwrap(),
+                    id: None,
+                },
+                Peer {
+                    addr: "10.0.0.2:80".parse().unwrap(),
+                    id: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dict_peers() {
+        let mut input = b"d8:intervali900e5:peersld2:ip9:127.0.0.17:peer id20:".to_vec();
+        input.extend([0xee; 20]);
+        input.extend_from_slice(
+            b"4:porti6881eed2:ip3:::14:porti1eed2:ip7:tracker4:porti2eee\
+              10:tracker id2:\xff\x0015:warning message4:slowe",
+        );
+        let response = AnnounceResponse::from_bytes(&input).unwrap();
+        assert_eq!(response.interval, Duration::from_secs(900));
+        assert_eq!(response.warning_message.as_deref(), Some("slow"));
+        assert_eq!(response.tracker_id, Some(vec![0xff, 0x00]));
+        // The host-name peer is skipped.
+        assert_eq!(
+            response.peers,
+            [
+                Peer {
+                    addr: "127.0.0.1:6881".parse().unwrap(),
+                    id: Some([0xee; 20]),
+                },
+                Peer {
+                    addr: "[::1]:1".parse().unwrap(),
+                    id: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_keys_are_skipped() {
+        // BEP 24 `external ip` is 4 raw bytes; `retry in` exceeds i16.
+        let input = b"d11:external ip4:\xc0\xa8\x00\xff8:intervali86400e5:peers0:\
+                      8:retry ini100000e6:vendorld1:xi-70000eeee";
+        let response = AnnounceResponse::from_bytes(input).unwrap();
+        assert_eq!(response.interval, Duration::from_secs(86400));
+        assert!(response.peers.is_empty());
+        assert_eq!(
+            AnnounceResponse::from_bytes(b"d8:intervali1e5:peers0:"),
+            Err(TrackerError::Decode(DecodeError::UnexpectedEof { offset: 23 }))
+        );
+    }
+
+    #[test]
+    fn test_peers6() {
+        let addrs: [SocketAddrV6; 1] = ["[2001:db8::1]:6881".parse().unwrap()];
+        let packed = encode_compact_peers_v6(&addrs);
+        let mut input = b"d8:intervali60e5:peers6:\x7f\0\0\x01\0\x506:peers618:".to_vec();
+        input.extend_from_slice(&packed);
+        input.push(b'e');
+        let response = AnnounceResponse::from_bytes(&input).unwrap();
+        assert_eq!(response.peers6, addrs);
+        let all: Vec<SocketAddr> = response.all_peers().map(|peer| peer.addr).collect();
+        assert_eq!(
+            all,
+            [
+                "127.0.0.1:80".parse().unwrap(),
+                "[2001:db8::1]:6881".parse().unwrap()
+            ]
+        );
+        assert_eq!(response.to_bytes().unwrap(), input);
+
+        assert_eq!(
+            AnnounceResponse::from_bytes(b"d8:intervali60e6:peers63:abce"),
+            Err(TrackerError::Invalid("peers6"))
+        );
+    }
+
+    #[test]
+    fn test_scrape_response() {
+        let mut input = b"d5:filesd20:".to_vec();
+        input.extend([0xaa; 20]);
+        input.extend_from_slice(b"d8:completei5e10:downloadedi50e10:incompletei10e4:name3:fooe20:");
+        input.extend([0x00; 20]);
+        input.extend_from_slice(
+            b"d8:completei1e5:extrali1eeee5:flagsd20:min_request_intervali86400eee",
+        );
+        let response = ScrapeResponse::from_bytes(&input).unwrap();
+        assert_eq!(response.files.len(), 2);
+        assert_eq!(
+            response.get(&[0xaa; 20]),
+            Some(&ScrapeStats {
+                complete: 5,
+                downloaded: 50,
+                incomplete: 10,
+                name: Some("foo".to_string()),
+            })
+        );
+        assert_eq!(
+            response.get(&[0x00; 20]),
+            Some(&ScrapeStats {
+                complete: 1,
+                ..ScrapeStats::default()
+            })
+        );
+
+        assert_eq!(
+            ScrapeResponse::from_bytes(b"d14:failure reason4:nopee"),
+            Err(TrackerError::Failure("nope".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_scrape_errors() {
+        assert_eq!(
+            ScrapeResponse::from_bytes(b"de"),
+            Err(TrackerError::Missing("files"))
+        );
+        // Keys must be 20-byte hashes and counts non-negative.
+        for bad in [
+            &b"d5:filesd4:abcdd8:completei1eeee"[..],
+            b"d5:filesd20:aaaaaaaaaaaaaaaaaaaad8:completei-1eeee",
+            b"d5:filesd20:aaaaaaaaaaaaaaaaaaaai1eee",
+            b"d5:fileslee",
+        ] {
+            assert_eq!(
+                ScrapeResponse::from_bytes(bad),
+                Err(TrackerError::Invalid("files"))
+            );
+        }
+    }
+
+    #[test]
+    fn test_to_bytes_round_trips() {
+        let mut response = AnnounceResponse {
+            interval: Duration::from_secs(1800),
+            complete: Some(1),
+            incomplete: Some(0),
+            peers: vec![Peer {
+                addr: "127.0.0.1:6881".parse().unwrap(),
+                id: None,
+            }],
+            ..AnnounceResponse::default()
+        };
+        let encoded = response.to_bytes().unwrap();
+        assert_eq!(
+            encoded,
+            b"d8:completei1e10:incompletei0e8:intervali1800e5:peers6:\x7f\x00\x00\x01\x1a\xe1e"
+        );
+        assert_eq!(AnnounceResponse::from_bytes(&encoded), Ok(response.clone()));
+
+        response.peers[0].id = Some([7; 20]);
+        response.peers.push(Peer {
+            addr: "[::1]:80".parse().unwrap(),
+            id: None,
+        });
+        response.min_interval = Some(Duration::from_secs(60));
+        response.tracker_id = Some(b"abc".to_vec());
+        response.warning_message = Some("slow".to_string());
+        let encoded = response.to_bytes().unwrap();
+        assert!(crate::is_canonical(&encoded));
+        assert_eq!(AnnounceResponse::from_bytes(&encoded), Ok(response));
+    }
+
+    #[test]
+    fn test_failure_and_errors() {
+        let failure = encode_failure("banned").unwrap();
+        assert_eq!(failure, b"d14:failure reason6:bannede");
+        let err = AnnounceResponse::from_bytes(&failure).unwrap_err();
+        assert_eq!(err, TrackerError::Failure("banned".to_string()));
+        assert_eq!(err.to_string(), "tracker failure: banned");
+        // A failure wins over whatever else the body holds.
+        assert_eq!(
+            AnnounceResponse::from_bytes(b"d14:failure reason1:x8:intervali-1ee"),
+            Err(TrackerError::Failure("x".to_string()))
+        );
+
+        let cases: [(&[u8], TrackerError); 4] = [
+            (b"d5:peers0:e", TrackerError::Missing("interval")),
+            (b"d8:intervali-1ee", TrackerError::Invalid("interval")),
+            (
+                b"d8:intervali1e5:peers5:abcdee",
+                TrackerError::Invalid("peers"),
+            ),
+            (
+                b"d8:intervali1e5:peersld2:ip1:x4:porti70000eeee",
+                TrackerError::Invalid("peers"),
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(AnnounceResponse::from_bytes(input), Err(expected));
+        }
+        assert_eq!(
+            AnnounceResponse::from_bytes(b"le"),
+            Err(TrackerError::Invalid(""))
+        );
+    }
+}
@@ -0,0 +1,441 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{Encoder, encode_compact_peers_v4, encode_compact_peers_v6, encode_failure};
+use crate::bytes::percent_decode;
+
+// Minimal HTTP tracker (BEP 3, with compact peers from BEP 23 and BEP 7 and
+// scrape). Swarm state lives in memory behind one lock and every response
+// is streamed through `Encoder`, so this doubles as an end-to-end exercise
+// of the encoding side of the crate.
+//
+//     let tracker = Arc::new(Tracker::new(Duration::from_secs(1800)));
+//     tracker.serve(TcpListener::bind("0.0.0.0:6969")?)?;
+
+const DEFAULT_NUMWANT: usize = 50;
+const MAX_NUMWANT: usize = 200;
+const MAX_REQUEST_LINE: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AnnounceEvent {
+    #[default]
+    None,
+    Started,
+    Completed,
+    Stopped,
+}
+
+/// A parsed `/announce` query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnounceRequest {
+    pub info_hash: [u8; 20],
+    pub peer_id: [u8; 20],
+    /// Where the request came from, with the announced port.
+    pub addr: SocketAddr,
+    /// The `ip` parameter, if sent. Only used by a `Tracker` that trusts
+    /// it; anyone could otherwise list third-party hosts in a swarm.
+    pub ip: Option<IpAddr>,
+    pub uploaded: u64,
+    pub downloaded: u64,
+    pub left: u64,
+    pub event: AnnounceEvent,
+    pub numwant: usize,
+    pub compact: bool,
+}
+
+impl AnnounceRequest {
+    /// Parses the query string of an announce sent from `remote`.
+    pub fn from_query(query: &str, remote: IpAddr) -> Result<Self, &'static str> {
+        let mut info_hash = None;
+        let mut peer_id = None;
+        let mut port = None;
+        let mut request = AnnounceRequest {
+            info_hash: [0; 20],
+            peer_id: [0; 20],
+            addr: SocketAddr::new(remote, 0),
+            ip: None,
+            uploaded: 0,
+            downloaded: 0,
+            left: 0,
+            event: AnnounceEvent::None,
+            numwant: DEFAULT_NUMWANT,
+            compact: true,
+        };
+
+        for (name, value) in query_pairs(query) {
+            let value = value.ok_or("malformed query")?;
+            match name {
+                "info_hash" => info_hash = Some(hash20(&value).ok_or("invalid info_hash")?),
+                "peer_id" => peer_id = Some(hash20(&value).ok_or("invalid peer_id")?),
+                "port" => port = Some(number(&value).ok_or("invalid port")?),
+                "uploaded" => request.uploaded = number(&value).ok_or("invalid uploaded")?,
+                "downloaded" => request.downloaded = number(&value).ok_or("invalid downloaded")?,
+                "left" => request.left = number(&value).ok_or("invalid left")?,
+                "numwant" => {
+                    request.numwant = number::<usize>(&value)
+                        .ok_or("invalid numwant")?
+                        .min(MAX_NUMWANT);
+                }
+                "compact" => request.compact = value != b"0",
+                "ip" => {
+                    let ip = std::str::from_utf8(&value)
+                        .ok()
+                        .and_then(|s| s.parse().ok())
+                        .ok_or("invalid ip")?;
+                    request.ip = Some(ip);
+                }
+                "event" => {
+                    request.event = match &value[..] {
+                        b"" => AnnounceEvent::None,
+                        b"started" => AnnounceEvent::Started,
+                        b"completed" => AnnounceEvent::Completed,
+                        b"stopped" => AnnounceEvent::Stopped,
+                        _ => return Err("invalid event"),
+                    };
+                }
+                _ => {}
+            }
+        }
+
+        request.info_hash = info_hash.ok_or("missing info_hash")?;
+        request.peer_id = peer_id.ok_or("missing peer_id")?;
+        request.addr.set_port(port.ok_or("missing port")?);
+        Ok(request)
+    }
+}
+
+#[derive(Debug)]
+struct Peer {
+    addr: SocketAddr,
+    seeding: bool,
+    last_seen: Instant,
+}
+
+#[derive(Debug, Default)]
+struct Swarm {
+    peers: HashMap<[u8; 20], Peer>,
+    /// Completed downloads reported over the swarm's lifetime.
+    downloaded: u64,
+}
+
+impl Swarm {
+    fn counts(&self) -> (usize, usize) {
+        let complete = self.peers.values().filter(|p| p.seeding).count();
+        (complete, self.peers.len() - complete)
+    }
+}
+
+/// In-memory swarm registry, shared between connection threads.
+#[derive(Debug)]
+pub struct Tracker {
+    swarms: Mutex<HashMap<[u8; 20], Swarm>>,
+    interval: Duration,
+    trust_client_ip: bool,
+}
+
+impl Tracker {
+    /// `interval` is what clients are told to wait between announces.
+    /// Peers silent for two intervals are dropped.
+    pub fn new(interval: Duration) -> Self {
+        Tracker {
+            swarms: Mutex::new(HashMap::new()),
+            interval,
+            trust_client_ip: false,
+        }
+    }
+
+    /// Lists peers under the `ip` they announce instead of the address
+    /// their request came from. Off by default, since it lets any client
+    /// point a swarm at a third party; turn it on only behind a proxy or on
+    /// a network where clients are trusted.
+    pub fn trust_client_ip(mut self, trust: bool) -> Self {
+        self.trust_client_ip = trust;
+        self
+    }
+
+    /// Records the announce and returns the bencoded response.
+    pub fn announce(&self, request: &AnnounceRequest) -> io::Result<Vec<u8>> {
+        let now = Instant::now();
+        let mut swarms = self.swarms.lock().unwrap_or_else(|e| e.into_inner());
+        let swarm = swarms.entry(request.info_hash).or_default();
+
+        let ttl = self.interval * 2;
+        swarm.peers.retain(|_, p| now.duration_since(p.last_seen) < ttl);
+        if request.event == AnnounceEvent::Stopped {
+            swarm.peers.remove(&request.peer_id);
+        } else {
+            let addr = match request.ip {
+                Some(ip) if self.trust_client_ip => SocketAddr::new(ip, request.addr.port()),
+                _ => request.addr,
+            };
+            swarm.peers.insert(
+                request.peer_id,
+                Peer {
+                    addr,
+                    seeding: request.left == 0,
+                    last_seen: now,
+                },
+            );
+        }
+        if request.event == AnnounceEvent::Completed {
+            swarm.downloaded += 1;
+        }
+
+        let peers: Vec<(&[u8; 20], &Peer)> = swarm
+            .peers
+            .iter()
+            .filter(|(id, _)| **id != request.peer_id)
+            .take(request.numwant)
+            .collect();
+        let (complete, incomplete) = swarm.counts();
+
+        Encoder::to_vec(|enc| {
+            enc.begin_dict()?;
+            enc.key(b"complete")?.int(complete as i64)?;
+            enc.key(b"incomplete")?.int(incomplete as i64)?;
+            enc.key(b"interval")?.int(self.interval.as_secs() as i64)?;
+            if request.compact {
+                let mut v4 = Vec::new();
+                let mut v6 = Vec::new();
+                for (_, peer) in &peers {
+                    match peer.addr {
+                        SocketAddr::V4(addr) => v4.push(addr),
+                        SocketAddr::V6(addr) => v6.push(addr),
+                    }
+                }
+                enc.key(b"peers")?.bytes(&encode_compact_peers_v4(&v4))?;
+                enc.key(b"peers6")?.bytes(&encode_compact_peers_v6(&v6))?;
+            } else {
+                enc.key(b"peers")?.begin_list()?;
+                for (id, peer) in &peers {
+                    enc.begin_dict()?;
+                    enc.key(b"ip")?.str(&peer.addr.ip().to_string())?;
+                    enc.key(b"peer id")?.bytes(&id[..])?;
+                    enc.key(b"port")?.int(peer.addr.port() as i64)?;
+                    enc.end()?;
+                }
+                enc.end()?;
+            }
+            enc.end()?;
+            Ok(())
+        })
+    }
+
+    /// Bencoded scrape response for `hashes`, or for every swarm if empty.
+    pub fn scrape(&self, hashes: &[[u8; 20]]) -> io::Result<Vec<u8>> {
+        let swarms = self.swarms.lock().unwrap_or_else(|e| e.into_inner());
+        let mut hashes: Vec<&[u8; 20]> = if hashes.is_empty() {
+            swarms.keys().collect()
+        } else {
+            hashes.iter().collect()
+        };
+        hashes.sort();
+        hashes.dedup();
+
+        Encoder::to_vec(|enc| {
+            enc.begin_dict()?.key(b"files")?.begin_dict()?;
+            for hash in hashes {
+                let Some(swarm) = swarms.get(hash) else { continue };
+                let (complete, incomplete) = swarm.counts();
+                enc.key(hash)?.begin_dict()?;
+                enc.key(b"complete")?.int(complete as i64)?;
+                enc.key(b"downloaded")?.int(swarm.downloaded as i64)?;
+                enc.key(b"incomplete")?.int(incomplete as i64)?;
+                enc.end()?;
+            }
+            enc.end()?.end()?;
+            Ok(())
+        })
+    }
+
+    /// Routes a request target such as `/announce?info_hash=...` and
+    /// returns the response body. Bad requests get a `failure reason`.
+    pub fn handle(&self, target: &str, remote: IpAddr) -> io::Result<Vec<u8>> {
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let result = match path {
+            "/announce" => AnnounceRequest::from_query(query, remote).map(|r| self.announce(&r)),
+            "/scrape" => query_pairs(query)
+                .filter(|(name, _)| *name == "info_hash")
+                .map(|(_, value)| value.as_deref().and_then(hash20).ok_or("invalid info_hash"))
+                .collect::<Result<Vec<_>, _>>()
+                .map(|hashes| self.scrape(&hashes)),
+            _ => Err("unknown path"),
+        };
+        result.unwrap_or_else(encode_failure)
+    }
+
+    /// Accepts HTTP connections on `listener`, one thread each, until
+    /// accepting fails.
+    pub fn serve(self: Arc<Self>, listener: TcpListener) -> io::Result<()> {
+        loop {
+            let (stream, remote) = listener.accept()?;
+            let tracker = Arc::clone(&self);
+            thread::spawn(move || {
+                // A client hanging up early is not the tracker's problem.
+                let _ = tracker.respond(stream, remote.ip());
+            });
+        }
+    }
+
+    fn respond(&self, stream: TcpStream, remote: IpAddr) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?).take(MAX_REQUEST_LINE as u64);
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+
+        let mut parts = line.split_whitespace();
+        let body = match (parts.next(), parts.next()) {
+            (Some("GET"), Some(target)) => self.handle(target, remote)?,
+            _ => encode_failure("malformed request")?,
+        };
+
+        let mut stream = stream;
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        )?;
+        stream.write_all(&body)
+    }
+}
+
+/// `name=value` pairs with values percent-decoded; `None` for a value with
+/// a bad escape.
+fn query_pairs(query: &str) -> impl Iterator<Item = (&str, Option<Vec<u8>>)> {
+    query.split('&').filter(|s| !s.is_empty()).map(|pair| {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        (name, percent_decode(value))
+    })
+}
+
+fn hash20(bytes: &[u8]) -> Option<[u8; 20]> {
+    bytes.try_into().ok()
+}
+
+fn number<T: std::str::FromStr>(bytes: &[u8]) -> Option<T> {
+    std::str::from_utf8(bytes).ok()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    const LOCALHOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+    fn escaped(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("%{:02X}", b)).collect()
+    }
+
+    fn announce_query(hash: u8, peer: u8, port: u16, left: u64) -> String {
+        format!(
+            "info_hash={}&peer_id={}&port={}&left={}&event=started",
+            escaped(&[hash; 20]),
+            escaped(&[peer; 20]),
+            port,
+            left
+        )
+    }
+
+    #[test]
+    fn test_announce_returns_compact_peers() {
+        let tracker = Tracker::new(Duration::from_secs(60));
+        tracker.handle(&format!("/announce?{}", announce_query(1, 1, 6881, 0)), LOCALHOST).unwrap();
+
+        let body = tracker.handle(&format!("/announce?{}", announce_query(1, 2, 6882, 5)), LOCALHOST).unwrap();
+        assert_eq!(
+            body,
+            b"d8:completei1e10:incompletei1e8:intervali60e5:peers6:\x7f\x00\x00\x01\x1a\xe16:peers60:e"
+        );
+
+        let query = format!("{}&compact=0&numwant=0", announce_query(1, 3, 1, 5));
+        let body = tracker.handle(&format!("/announce?{}", query), LOCALHOST).unwrap();
+        assert_eq!(body, b"d8:completei1e10:incompletei2e8:intervali60e5:peerslee");
+    }
+
+    #[test]
+    fn test_client_ip_needs_trust() {
+        let query = format!("{}&ip=10.0.0.1", announce_query(1, 1, 6881, 0));
+        let request = AnnounceRequest::from_query(&query, LOCALHOST).unwrap();
+        assert_eq!(request.addr, SocketAddr::new(LOCALHOST, 6881));
+        assert_eq!(request.ip, Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))));
+
+        let peers = |tracker: &Tracker| {
+            tracker.announce(&request).unwrap();
+            let other = announce_query(1, 2, 1, 5);
+            tracker.announce(&AnnounceRequest::from_query(&other, LOCALHOST).unwrap()).unwrap()
+        };
+        let ignored = peers(&Tracker::new(Duration::from_secs(60)));
+        assert!(ignored.ends_with(b"5:peers6:\x7f\x00\x00\x01\x1a\xe16:peers60:e"));
+        let trusted = peers(&Tracker::new(Duration::from_secs(60)).trust_client_ip(true));
+        assert!(trusted.ends_with(b"5:peers6:\x0a\x00\x00\x01\x1a\xe16:peers60:e"));
+    }
+
+    #[test]
+    fn test_failures_are_bencoded() {
+        let tracker = Tracker::new(Duration::from_secs(60));
+        assert_eq!(
+            tracker.handle("/announce?info_hash=%ZZ", LOCALHOST).unwrap(),
+            b"d14:failure reason15:malformed querye"
+        );
+        assert_eq!(
+            tracker.handle("/announce?port=1", LOCALHOST).unwrap(),
+            b"d14:failure reason17:missing info_hashe"
+        );
+        assert_eq!(tracker.handle("/", LOCALHOST).unwrap(), b"d14:failure reason12:unknown pathe");
+    }
+
+    #[test]
+    fn test_concurrent_announces_and_scrape() {
+        let tracker = Arc::new(Tracker::new(Duration::from_secs(60)));
+        let handles: Vec<_> = (0..8u8)
+            .map(|peer| {
+                let tracker = Arc::clone(&tracker);
+                thread::spawn(move || {
+                    for hash in 0..4u8 {
+                        let left = if peer % 2 == 0 { 0 } else { 10 };
+                        let query = announce_query(hash, peer, 7000 + peer as u16, left);
+                        tracker.handle(&format!("/announce?{}", query), LOCALHOST).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let body = tracker.handle(&format!("/scrape?info_hash={}", escaped(&[2; 20])), LOCALHOST).unwrap();
+        let mut expected = b"d5:filesd20:".to_vec();
+        expected.extend_from_slice(&[2; 20]);
+        expected.extend_from_slice(b"d8:completei4e10:downloadedi0e10:incompletei4eeee");
+        assert_eq!(body, expected);
+
+        let mut expected = b"d5:filesd".to_vec();
+        for hash in 0..4u8 {
+            expected.extend_from_slice(b"20:");
+            expected.extend_from_slice(&[hash; 20]);
+            expected.extend_from_slice(b"d8:completei4e10:downloadedi0e10:incompletei4ee");
+        }
+        expected.extend_from_slice(b"ee");
+        assert_eq!(tracker.scrape(&[]).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_serves_http() -> io::Result<()> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        let addr = listener.local_addr()?;
+        let tracker = Arc::new(Tracker::new(Duration::from_secs(60)));
+        thread::spawn(move || tracker.serve(listener));
+
+        let mut stream = TcpStream::connect(addr)?;
+        write!(stream, "GET /scrape HTTP/1.1\r\nHost: localhost\r\n\r\n")?;
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response)?;
+        assert!(response.starts_with(b"HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with(b"\r\n\r\nd5:filesdee"));
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+use crate::memchr::memchr;
+use crate::{COLON_DELIM, DELIM_END, DICT_DELIM_BEGIN, DecodeError, INT_DELIM_BEGIN, LIST_DELIM_BEGIN};
+
+// Syntax check that walks the input without building a `BValue`, and
+// never allocates. Strings may hold any bytes and integers anything that
+// fits in `i64`, as with `Events`. `decode` is stricter than this, since
+// a `BValue` holds UTF-8 text and `i16` integers, but real payloads carry
+// binary hashes and large counters and are still well-formed.
+
+pub fn validate(input: &[u8]) -> Result<(), DecodeError> {
+    if input.is_empty() {
+        return Err(DecodeError::EmptyInput);
+    }
+
+    let mut validator = Validator { input, pos: 0 };
+    validator.value()?;
+
+    if validator.pos < input.len() {
+        return Err(DecodeError::TrailingData {
+            offset: validator.pos,
+        });
+    }
+
+    Ok(())
+}
+
+/// Length of the value at the front of `input`, checked as `validate`
+/// checks it, without allocating. Bytes after the value are ignored, so
+/// back-to-back messages can be split with repeated calls.
+pub fn skip_value(input: &[u8]) -> Result<usize, DecodeError> {
+    if input.is_empty() {
+        return Err(DecodeError::EmptyInput);
+    }
+    skip(input, 0)
+}
+
+/// Checks the value starting at `pos` and returns the offset just past it.
+pub(crate) fn skip(input: &[u8], pos: usize) -> Result<usize, DecodeError> {
+    let mut validator = Validator { input, pos };
+    validator.value()?;
+    Ok(validator.pos)
+}
+
+struct Validator<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl Validator<'_> {
+    fn peek(&self) -> Result<u8, DecodeError> {
+        self.input
+            .get(self.pos)
+            .copied()
+            .ok_or(DecodeError::UnexpectedEof { offset: self.pos })
+    }
+
+    fn value(&mut self) -> Result<(), DecodeError> {
+        match self.peek()? {
+            INT_DELIM_BEGIN => self.int(),
+            LIST_DELIM_BEGIN => self.list(),
+            DICT_DELIM_BEGIN => self.dict(),
+            b'0'..=b'9' => self.string(),
+            DELIM_END => Err(DecodeError::UnexpectedEnd { offset: self.pos }),
+            byte => Err(DecodeError::UnexpectedByte {
+                offset: self.pos,
+                byte,
+            }),
+        }
+    }
+
+    fn int(&mut self) -> Result<(), DecodeError> {
+        let start = self.pos;
+        self.pos += 1;
+
+        let end = self.find(DELIM_END)?;
+        let digits = &self.input[self.pos..end];
+        if digits.is_empty() {
+            return Err(DecodeError::EmptyInteger { offset: start });
+        }
+
+        crate::parse_int(digits).ok_or(DecodeError::InvalidInteger { offset: start })?;
+
+        self.pos = end + 1;
+        Ok(())
+    }
+
+    fn string(&mut self) -> Result<(), DecodeError> {
+        let start = self.pos;
+        let colon = self.find(COLON_DELIM)?;
+
+        let len = crate::parse_len(&self.input[start..colon], start)?;
+
+        let data_start = colon + 1;
+        let end = data_start
+            .checked_add(len)
+            .filter(|&end| end <= self.input.len())
+            .ok_or(DecodeError::UnexpectedEof {
+                offset: self.input.len(),
+            })?;
+
+        self.pos = end;
+        Ok(())
+    }
+
+    fn list(&mut self) -> Result<(), DecodeError> {
+        self.pos += 1;
+        while self.peek()? != DELIM_END {
+            self.value()?;
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn dict(&mut self) -> Result<(), DecodeError> {
+        self.pos += 1;
+        while self.peek()? != DELIM_END {
+            if !self.peek()?.is_ascii_digit() {
+                return Err(DecodeError::NonStringKey { offset: self.pos });
+            }
+            self.string()?;
+            self.value()?;
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// Position of the next `delim` at or after the cursor.
+    fn find(&self, delim: u8) -> Result<usize, DecodeError> {
+        memchr(delim, &self.input[self.pos..])
+            .map(|n| self.pos + n)
+            .ok_or(DecodeError::UnexpectedEof {
+                offset: self.input.len(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accepts_well_formed_input() {
+        assert_eq!(validate(b"i42e"), Ok(()));
+        assert_eq!(validate(b"0:"), Ok(()));
+        assert_eq!(validate(b"le"), Ok(()));
+        assert_eq!(
+            validate(b"d8:announce3:url4:infod5:filesld6:lengthi42eeeee"),
+            Ok(())
+        );
+        // Binary strings and integers beyond what a `BValue` holds.
+        assert_eq!(validate(b"d6:pieces4:\xff\x00\xfe\x0112:piece lengthi262144ee"), Ok(()));
+        assert_eq!(validate(b"i-9223372036854775808e"), Ok(()));
+    }
+
+    #[test]
+    fn test_rejects_malformed_input() {
+        assert_eq!(validate(b""), Err(DecodeError::EmptyInput));
+        assert_eq!(validate(b"ie"), Err(DecodeError::EmptyInteger { offset: 0 }));
+        assert_eq!(
+            validate(b"li32bee"),
+            Err(DecodeError::InvalidInteger { offset: 1 })
+        );
+        assert_eq!(
+            validate(b"4:spa"),
+            Err(DecodeError::UnexpectedEof { offset: 5 })
+        );
+        assert_eq!(
+            validate(b"di1e3:fooe"),
+            Err(DecodeError::NonStringKey { offset: 1 })
+        );
+        assert_eq!(
+            validate(b"i1ei2e"),
+            Err(DecodeError::TrailingData { offset: 3 })
+        );
+        assert_eq!(validate(b"e"), Err(DecodeError::UnexpectedEnd { offset: 0 }));
+        assert_eq!(
+            validate(b"l4:spam"),
+            Err(DecodeError::UnexpectedEof { offset: 7 })
+        );
+        assert_eq!(
+            validate(b"i9223372036854775808e"),
+            Err(DecodeError::InvalidInteger { offset: 0 })
+        );
+    }
+
+    #[test]
+    fn test_skip_value_splits_messages() {
+        let mut input: &[u8] = b"d1:ai1ee4:spamli-3ee";
+        let mut lengths = Vec::new();
+        while !input.is_empty() {
+            let len = skip_value(input).unwrap();
+            lengths.push(len);
+            input = &input[len..];
+        }
+        assert_eq!(lengths, vec![8, 6, 6]);
+
+        // A tracker reply with compact peers and a large interval.
+        let reply = b"d8:intervali86400e5:peers6:\x7f\0\0\x01\x1a\xe1ei1e";
+        assert_eq!(skip_value(reply), Ok(reply.len() - 3));
+
+        assert_eq!(skip_value(b""), Err(DecodeError::EmptyInput));
+        assert_eq!(
+            skip_value(b"li1e"),
+            Err(DecodeError::UnexpectedEof { offset: 4 })
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::hashing::PieceHasher;
+use crate::{FileEntry, Files, MetaInfo};
+
+// Checking downloaded data against the v1 piece hashes. Files are streamed
+// in torrent order through one hasher, as `TorrentBuilder` does when
+// creating them; padding files are hashed as zeros and never opened. A file
+// that is missing or short fails every piece it touches rather than the
+// whole check, so a partial download reports what it already has.
+// v2-only torrents have no SHA-1 pieces and verify to an empty list.
+//
+// Declared lengths are not trusted to bound the work: only as many bytes
+// as the piece hashes cover are hashed or skipped, so a huge missing or
+// padding file in a small torrent costs nothing.
+
+/// Whether each piece of `meta` matches its hash, indexed by piece.
+/// `open` supplies the content of each real file; a `NotFound` error marks
+/// the file as absent, any other error is returned.
+pub fn verify_pieces<R, F>(meta: &MetaInfo, mut open: F) -> io::Result<Vec<bool>>
+where
+    R: Read,
+    F: FnMut(&FileEntry) -> io::Result<R>,
+{
+    let info = &meta.info;
+    if info.piece_length == 0 {
+        return Ok(vec![false; info.pieces.len()]);
+    }
+    let mut checker = Checker::new(&info.pieces, info.piece_length);
+    let mut buf = vec![0; 64 * 1024];
+
+    for file in info.v1_layout() {
+        if checker.is_full() {
+            break;
+        }
+        if file.is_padding() {
+            buf.fill(0);
+            let mut left = file.length;
+            while left > 0 && !checker.is_full() {
+                let take = left.min(buf.len() as u64) as usize;
+                checker.feed(&buf[..take]);
+                left -= take as u64;
+            }
+            continue;
+        }
+        let reader = match open(&file) {
+            Ok(reader) => reader,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                checker.skip(file.length);
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        // Anything past the declared length is not part of the torrent.
+        let mut reader = reader.take(file.length);
+        let mut read = 0;
+        while !checker.is_full() {
+            let n = match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            checker.feed(&buf[..n]);
+            read += n as u64;
+        }
+        checker.skip(file.length - read);
+    }
+    Ok(checker.finish())
+}
+
+/// `verify_pieces` over files saved below `dir` the usual way: a single
+/// file at `dir/name`, a multi-file torrent under `dir/name/`. Paths that
+/// would leave `dir` are an `InvalidData` error.
+pub fn verify_dir(meta: &MetaInfo, dir: impl AsRef<Path>) -> io::Result<Vec<bool>> {
+    let dir = dir.as_ref();
+    let single = matches!(meta.info.files, Some(Files::Single { .. }));
+    verify_pieces(meta, |file| {
+        let mut path = file.path.clone();
+        if !single {
+            path.insert(0, meta.info.name.clone());
+        }
+        let relative = FileEntry {
+            length: file.length,
+            path,
+            attr: None,
+            sha1: None,
+            symlink_path: None,
+        }
+        .safe_path()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        File::open(dir.join(relative))
+    })
+}
+
+/// Hashes a stream of piece data and records each piece as it completes.
+struct Checker<'a> {
+    expected: &'a [[u8; 20]],
+    pieces: PieceHasher,
+    /// Part of the current piece was missing.
+    damaged: bool,
+    /// Bytes the hashes still cover; anything past them is ignored.
+    left: u64,
+    results: Vec<bool>,
+}
+
+impl<'a> Checker<'a> {
+    fn new(expected: &'a [[u8; 20]], piece_length: u64) -> Self {
+        Checker {
+            expected,
+            pieces: PieceHasher::new(piece_length),
+            damaged: false,
+            left: (expected.len() as u64).saturating_mul(piece_length),
+            results: Vec::with_capacity(expected.len()),
+        }
+    }
+
+    /// Every piece has a result; more data cannot change them.
+    fn is_full(&self) -> bool {
+        self.results.len() == self.expected.len()
+    }
+
+    fn feed(&mut self, data: &[u8]) {
+        let len = data.len().min(usize::try_from(self.left).unwrap_or(usize::MAX));
+        let mut data = &data[..len];
+        self.left -= data.len() as u64;
+        while !data.is_empty() {
+            let (taken, done) = self.pieces.update(data);
+            data = &data[taken..];
+            if let Some(hash) = done {
+                self.end_piece(hash);
+            }
+        }
+    }
+
+    /// Stands in for `len` bytes that could not be read.
+    fn skip(&mut self, len: u64) {
+        let mut len = len.min(self.left);
+        self.left -= len;
+        while len > 0 {
+            let (taken, done) = self.pieces.skip(len);
+            self.damaged = true;
+            len -= taken;
+            if let Some(hash) = done {
+                self.end_piece(hash);
+            }
+        }
+    }
+
+    fn end_piece(&mut self, hash: [u8; 20]) {
+        let index = self.results.len();
+        let ok = !self.damaged && self.expected.get(index) == Some(&hash);
+        self.results.push(ok);
+        self.damaged = false;
+    }
+
+    fn finish(mut self) -> Vec<bool> {
+        if let Some(hash) = self.pieces.finish() {
+            self.end_piece(hash);
+        }
+        // A layout that disagrees with the piece count fails the rest.
+        self.results.resize(self.expected.len(), false);
+        self.results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::temp_path;
+    use crate::{TorrentBuilder, sha1};
+    use std::fs;
+
+    #[test]
+    fn test_verify_dir() {
+        let base = temp_path("verify-dir");
+        let root = base.join("root");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("a"), b"abcdef").unwrap();
+        fs::write(root.join("sub").join("b"), b"ghijk").unwrap();
+        let built = TorrentBuilder::new(&root).piece_length(4).build().unwrap();
+        let meta = MetaInfo::from_bytes(&built).unwrap();
+
+        let all = verify_dir(&meta, &base).unwrap();
+        fs::write(root.join("a"), b"abcXef").unwrap();
+        let corrupt = verify_dir(&meta, &base).unwrap();
+        fs::remove_file(root.join("sub").join("b")).unwrap();
+        let missing = verify_dir(&meta, &base).unwrap();
+        fs::remove_dir_all(&base).unwrap();
+
+        assert_eq!(all, [true, true, true]);
+        assert_eq!(corrupt, [false, true, true]);
+        // "efgh" spans both files, so losing `b` fails it too.
+        assert_eq!(missing, [false, false, false]);
+    }
+
+    #[test]
+    fn test_padding_and_short_files() {
+        let pieces = [sha1(b"abcd"), sha1(b"ef\0\0"), sha1(b"ghij")];
+        let mut input = b"d4:infod5:filesl\
+                          d6:lengthi6e4:pathl1:aee\
+                          d4:attr1:p6:lengthi2e4:pathl4:.pad1:2ee\
+                          d6:lengthi4e4:pathl1:bee\
+                          e4:name4:root12:piece lengthi4e6:pieces60:"
+            .to_vec();
+        input.extend(pieces.concat());
+        input.extend_from_slice(b"ee");
+        let meta = MetaInfo::from_bytes(&input).unwrap();
+
+        let content = |file: &FileEntry| match file.path[0].as_str() {
+            "a" => Ok(&b"abcdef"[..]),
+            "b" => Ok(&b"ghijEXTRA"[..]),
+            _ => panic!("opened {:?}", file.path),
+        };
+        assert_eq!(verify_pieces(&meta, content).unwrap(), [true, true, true]);
+
+        let short = |file: &FileEntry| match file.path[0].as_str() {
+            "a" => Ok(&b"abcde"[..]),
+            _ => Err(io::Error::from(io::ErrorKind::NotFound)),
+        };
+        assert_eq!(verify_pieces(&meta, short).unwrap(), [true, false, false]);
+
+        let failing = |_: &FileEntry| Err::<&[u8], _>(io::Error::other("boom"));
+        assert!(verify_pieces(&meta, failing).is_err());
+    }
+
+    #[test]
+    fn test_work_is_bounded_by_pieces() {
+        // One 16-byte piece, then a 1 TiB padding file and a 1 TiB file.
+        let mut input = b"d4:infod5:filesl\
+                          d6:lengthi16e4:pathl1:aee\
+                          d4:attr1:p6:lengthi1099511627776e4:pathl4:.pad1:0ee\
+                          d6:lengthi1099511627776e4:pathl1:bee\
+                          e4:name4:root12:piece lengthi16e6:pieces20:"
+            .to_vec();
+        input.extend(sha1(b"0123456789abcdef"));
+        input.extend_from_slice(b"ee");
+        let meta = MetaInfo::from_bytes(&input).unwrap();
+
+        let content = |file: &FileEntry| match file.path[0].as_str() {
+            "a" => Ok(&b"0123456789abcdef"[..]),
+            _ => panic!("opened {:?}", file.path),
+        };
+        assert_eq!(verify_pieces(&meta, content).unwrap(), [true]);
+
+        let missing = |_: &FileEntry| Err::<&[u8], _>(io::Error::from(io::ErrorKind::NotFound));
+        assert_eq!(verify_pieces(&meta, missing).unwrap(), [false]);
+    }
+}
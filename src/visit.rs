@@ -0,0 +1,137 @@
+use crate::{BValue, External};
+
+// Structured traversal: `accept` walks the tree depth-first and reports each
+// node to a `BVisitor`, dict entries in sorted key order. Every method has
+// an empty default, so a pass only implements what it cares about.
+
+pub trait BVisitor {
+    fn visit_int(&mut self, _n: i16) {}
+    fn visit_str(&mut self, _s: &str) {}
+    fn visit_none(&mut self) {}
+    fn visit_external(&mut self, _external: &External) {}
+
+    fn visit_list_start(&mut self, _len: usize) {}
+    fn visit_list_end(&mut self) {}
+
+    fn visit_dict_start(&mut self, _len: usize) {}
+    /// Called before the entry's value is visited.
+    fn visit_dict_key(&mut self, _key: &str) {}
+    fn visit_dict_end(&mut self) {}
+}
+
+impl BValue {
+    pub fn accept<V: BVisitor + ?Sized>(&self, visitor: &mut V) {
+        match self {
+            BValue::Int(n) => visitor.visit_int(*n),
+            BValue::Str(s) => visitor.visit_str(s),
+            BValue::None => visitor.visit_none(),
+            BValue::External(external) => visitor.visit_external(external),
+            BValue::List(list) => {
+                visitor.visit_list_start(list.len());
+                for item in list {
+                    item.accept(visitor);
+                }
+                visitor.visit_list_end();
+            }
+            BValue::Dict(dict) => {
+                visitor.visit_dict_start(dict.len());
+                for (key, value) in self.dict_iter_sorted() {
+                    visitor.visit_dict_key(key);
+                    value.accept(visitor);
+                }
+                visitor.visit_dict_end();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode_exact;
+
+    #[derive(Default)]
+    struct Stats {
+        ints: usize,
+        string_bytes: usize,
+        depth: usize,
+        max_depth: usize,
+    }
+
+    impl BVisitor for Stats {
+        fn visit_int(&mut self, _n: i16) {
+            self.ints += 1;
+        }
+
+        fn visit_str(&mut self, s: &str) {
+            self.string_bytes += s.len();
+        }
+
+        fn visit_list_start(&mut self, _len: usize) {
+            self.depth += 1;
+            self.max_depth = self.max_depth.max(self.depth);
+        }
+
+        fn visit_list_end(&mut self) {
+            self.depth -= 1;
+        }
+
+        fn visit_dict_start(&mut self, len: usize) {
+            self.visit_list_start(len);
+        }
+
+        fn visit_dict_end(&mut self) {
+            self.visit_list_end();
+        }
+    }
+
+    /// Renders the tree as a compact trace, to check call order.
+    struct Trace(String);
+
+    impl BVisitor for Trace {
+        fn visit_int(&mut self, n: i16) {
+            self.0 += &format!("{} ", n);
+        }
+
+        fn visit_str(&mut self, s: &str) {
+            self.0 += &format!("{:?} ", s);
+        }
+
+        fn visit_list_start(&mut self, len: usize) {
+            self.0 += &format!("[{} ", len);
+        }
+
+        fn visit_list_end(&mut self) {
+            self.0 += "] ";
+        }
+
+        fn visit_dict_start(&mut self, len: usize) {
+            self.0 += &format!("{{{} ", len);
+        }
+
+        fn visit_dict_key(&mut self, key: &str) {
+            self.0 += &format!("{}: ", key);
+        }
+
+        fn visit_dict_end(&mut self) {
+            self.0 += "} ";
+        }
+    }
+
+    #[test]
+    fn test_statistics_visitor() {
+        let value = decode_exact(b"d1:ali1eli2e3:fooee1:b2:xye").unwrap();
+        let mut stats = Stats::default();
+        value.accept(&mut stats);
+        assert_eq!((stats.ints, stats.string_bytes), (2, 5));
+        assert_eq!((stats.depth, stats.max_depth), (0, 3));
+    }
+
+    #[test]
+    fn test_visit_order() {
+        let value = decode_exact(b"d1:bli1e1:xe1:ad0:i0eee").unwrap();
+        let mut trace = Trace(String::new());
+        value.accept(&mut trace as &mut dyn BVisitor);
+        assert_eq!(trace.0, r#"{2 a: {1 : 0 } b: [2 1 "x" ] } "#);
+    }
+}
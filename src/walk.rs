@@ -0,0 +1,85 @@
+use crate::{BValue, PathSegment};
+
+// Depth-first, pre-order traversal of a value tree. Each node comes with the
+// path leading to it; dict entries are visited in sorted key order. Use
+// `format_path` to turn a path back into the `get_path` syntax.
+//
+//     let big = value
+//         .walk()
+//         .filter(|(_, v)| v.as_bytes().is_some_and(|b| b.len() > 1024));
+
+pub struct Walk<'a> {
+    stack: Vec<(Vec<PathSegment>, &'a BValue)>,
+}
+
+impl<'a> Iterator for Walk<'a> {
+    type Item = (Vec<PathSegment>, &'a BValue);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, value) = self.stack.pop()?;
+        // Children go on in reverse so the first one is visited next.
+        match value {
+            BValue::List(list) => {
+                for (i, item) in list.iter().enumerate().rev() {
+                    self.stack.push((child(&path, PathSegment::Index(i)), item));
+                }
+            }
+            BValue::Dict(_) => {
+                for (key, item) in value.dict_iter_sorted().rev() {
+                    self.stack.push((child(&path, PathSegment::Key(key.clone())), item));
+                }
+            }
+            _ => {}
+        }
+        Some((path, value))
+    }
+}
+
+fn child(path: &[PathSegment], segment: PathSegment) -> Vec<PathSegment> {
+    let mut path = path.to_vec();
+    path.push(segment);
+    path
+}
+
+impl BValue {
+    /// Every node in the tree, starting with this one at the empty path.
+    pub fn walk(&self) -> Walk<'_> {
+        Walk {
+            stack: vec![(Vec::new(), self)],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decode_exact, format_path};
+
+    #[test]
+    fn test_walk_order_and_paths() {
+        let value = decode_exact(b"d1:bli1e2:xxe1:ad1:ci2eee").unwrap();
+        let visited: Vec<(String, &BValue)> = value
+            .walk()
+            .map(|(path, node)| (format_path(&path), node))
+            .collect();
+        let paths: Vec<&str> = visited.iter().map(|(p, _)| p.as_str()).collect();
+        assert_eq!(paths, vec!["", "a", "a.c", "b", "b[0]", "b[1]"]);
+        assert_eq!(visited[2].1, &BValue::Int(2));
+
+        for (path, node) in value.walk() {
+            assert_eq!(value.get_path(&format_path(&path)), Some(node));
+        }
+    }
+
+    #[test]
+    fn test_walk_filters() {
+        let value = decode_exact(b"d4:infod6:lengthi9e6:pieces3:abce4:porti80ee").unwrap();
+        let ints: Vec<String> = value
+            .walk()
+            .filter(|(_, node)| node.is_int())
+            .map(|(path, _)| format_path(&path))
+            .collect();
+        assert_eq!(ints, vec!["info.length", "port"]);
+        assert_eq!(BValue::Int(1).walk().count(), 1);
+    }
+}